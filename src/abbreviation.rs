@@ -0,0 +1,154 @@
+use std::{collections::BTreeMap, ops::Range};
+
+// Characters after which a new command starts
+const COMMAND_SEPARATORS: [char; 5] = ['\n', ';', '|', '&', '('];
+
+/// Table of abbreviations that are expanded when typed as a command, like the
+/// abbreviations of the fish shell
+///
+/// An abbreviation is expanded when it is the word before the cursor in the position
+/// of a command and space or enter is pressed. [`ReedlineEvent::SkipAbbreviation`](crate::ReedlineEvent::SkipAbbreviation),
+/// bound to `Ctrl+Space` by default, leaves the next one as it was typed.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{Abbreviations, Reedline};
+///
+/// let abbreviations = Abbreviations::new()
+///     .with_abbreviation("gco", "git checkout")
+///     .with_abbreviation("gst", "git status");
+/// let line_editor = Reedline::create()?.with_abbreviations(abbreviations);
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Abbreviations {
+    table: BTreeMap<String, String>,
+}
+
+impl Abbreviations {
+    /// Creates an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A builder that adds an abbreviation to the table
+    pub fn with_abbreviation(mut self, name: &str, expansion: &str) -> Self {
+        self.add(name, expansion);
+        self
+    }
+
+    /// Adds an abbreviation, returning the expansion it replaced
+    pub fn add(&mut self, name: &str, expansion: &str) -> Option<String> {
+        self.table.insert(name.to_string(), expansion.to_string())
+    }
+
+    /// Removes an abbreviation, returning its expansion
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.table.remove(name)
+    }
+
+    /// The expansion of an abbreviation
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.table.get(name).map(String::as_str)
+    }
+
+    /// The abbreviations with their expansions, sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.table
+            .iter()
+            .map(|(name, expansion)| (name.as_str(), expansion.as_str()))
+    }
+
+    /// Number of abbreviations in the table
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Returns true if there are no abbreviations
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Finds the abbreviation that ends at `offset` in the buffer, returning the range
+    /// it takes and its expansion
+    pub(crate) fn find(&self, buffer: &str, offset: usize) -> Option<(Range<usize>, &str)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        // The cursor has to be at the end of the word
+        let after = &buffer[offset..];
+        if after
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_whitespace() && !COMMAND_SEPARATORS.contains(&c))
+        {
+            return None;
+        }
+
+        let before = &buffer[..offset];
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace() || COMMAND_SEPARATORS.contains(c))
+            .map_or(0, |(index, c)| index + c.len_utf8());
+
+        let preceding = before[..start].trim_end_matches([' ', '\t']);
+        let command_position = preceding.is_empty() || preceding.ends_with(COMMAND_SEPARATORS);
+        if !command_position {
+            return None;
+        }
+
+        self.get(&before[start..])
+            .map(|expansion| (start..offset, expansion))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn abbreviations() -> Abbreviations {
+        Abbreviations::new()
+            .with_abbreviation("gco", "git checkout")
+            .with_abbreviation("l", "ls -la")
+    }
+
+    #[rstest]
+    #[case("gco", 3, Some((0..3, "git checkout")))]
+    #[case("  gco", 5, Some((2..5, "git checkout")))]
+    #[case("cd src; gco", 11, Some((8..11, "git checkout")))]
+    #[case("cat x |l", 8, Some((7..8, "ls -la")))]
+    #[case("echo\ngco", 8, Some((5..8, "git checkout")))]
+    #[case("gco main", 3, Some((0..3, "git checkout")))]
+    #[case("echo gco", 8, None)]
+    #[case("gcoo", 4, None)]
+    #[case("gcoo", 3, None)]
+    #[case("", 0, None)]
+    fn abbreviations_expand_as_commands(
+        #[case] buffer: &str,
+        #[case] offset: usize,
+        #[case] expected: Option<(Range<usize>, &str)>,
+    ) {
+        assert_eq!(abbreviations().find(buffer, offset), expected);
+    }
+
+    #[test]
+    fn abbreviations_can_change_at_runtime() {
+        let mut abbreviations = abbreviations();
+
+        assert_eq!(
+            abbreviations.add("gco", "git switch"),
+            Some("git checkout".to_string())
+        );
+        assert_eq!(abbreviations.remove("l"), Some("ls -la".to_string()));
+        assert_eq!(abbreviations.remove("l"), None);
+        assert_eq!(
+            abbreviations.iter().collect::<Vec<_>>(),
+            vec![("gco", "git switch")]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use {std::ops::Range, unicode_segmentation::UnicodeSegmentation};
+
+/// Direction of a grapheme, a simplified form of the bidirectional classes of Unicode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Left,
+    Right,
+    Number,
+    Neutral,
+}
+
+/// Whether the character is written from right to left, like Hebrew or Arabic
+pub(crate) fn is_rtl(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200f}'
+            | '\u{0590}'..='\u{065f}'
+            | '\u{066a}'..='\u{06ef}'
+            | '\u{06fa}'..='\u{08ff}'
+            | '\u{fb1d}'..='\u{fdff}'
+            | '\u{fe70}'..='\u{feff}'
+            | '\u{10800}'..='\u{10fff}'
+            | '\u{1e800}'..='\u{1efff}'
+    )
+}
+
+/// Whether the text has right-to-left characters, the rest needs no reordering
+pub(crate) fn has_rtl(text: &str) -> bool {
+    text.chars().any(is_rtl)
+}
+
+fn class(grapheme: &str) -> Class {
+    match grapheme.chars().next() {
+        Some(c) if is_rtl(c) => Class::Right,
+        Some(c) if c.is_numeric() => Class::Number,
+        Some(c) if c.is_alphabetic() => Class::Left,
+        _ => Class::Neutral,
+    }
+}
+
+/// Byte ranges of the graphemes of `line` in the order they are shown from left to
+/// right, for a line that reads from left to right like a command does
+///
+/// A run of right-to-left graphemes is reversed together with the spaces and the
+/// punctuation between them. Numbers in the run keep reading from left to right, and
+/// so do the separators between their digits.
+pub(crate) fn visual_order(line: &str) -> Vec<Range<usize>> {
+    let graphemes: Vec<(Range<usize>, &str)> = line
+        .grapheme_indices(true)
+        .map(|(index, grapheme)| (index..index + grapheme.len(), grapheme))
+        .collect();
+    let mut classes: Vec<Class> = graphemes
+        .iter()
+        .map(|(_, grapheme)| class(grapheme))
+        .collect();
+    for index in 1..classes.len().saturating_sub(1) {
+        let separator = matches!(graphemes[index].1, "." | "," | ":" | "/");
+        if separator && classes[index - 1] == Class::Number && classes[index + 1] == Class::Number {
+            classes[index] = Class::Number;
+        }
+    }
+
+    let mut order = Vec::with_capacity(graphemes.len());
+    let mut start = 0;
+    while start < graphemes.len() {
+        if classes[start] != Class::Right {
+            order.push(start);
+            start += 1;
+            continue;
+        }
+
+        // The run ends at the last grapheme with a direction before one reading
+        // from left to right
+        let mut end = start + 1;
+        for (index, class) in classes.iter().enumerate().skip(start + 1) {
+            match class {
+                Class::Left => break,
+                Class::Right | Class::Number => end = index + 1,
+                Class::Neutral => {}
+            }
+        }
+
+        let mut index = end;
+        while index > start {
+            let mut number_start = index;
+            while number_start > start && classes[number_start - 1] == Class::Number {
+                number_start -= 1;
+            }
+            if number_start < index {
+                order.extend(number_start..index);
+                index = number_start;
+            } else {
+                order.push(index - 1);
+                index -= 1;
+            }
+        }
+        start = end;
+    }
+
+    order
+        .into_iter()
+        .map(|index| graphemes[index].0.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn visual(line: &str) -> String {
+        visual_order(line)
+            .into_iter()
+            .map(|range| &line[range])
+            .collect()
+    }
+
+    #[rstest]
+    #[case("ls -la", "ls -la")]
+    #[case("echo שלום", "echo םולש")]
+    #[case("echo שלום עולם", "echo םלוע םולש")]
+    #[case("echo שלום 12.5 abc", "echo 12.5 םולש abc")]
+    #[case("echo \"سلام\" ok", "echo \"مالس\" ok")]
+    #[case("עד 2022", "2022 דע")]
+    fn right_to_left_runs_are_reversed(#[case] line: &str, #[case] expected: &str) {
+        assert_eq!(visual(line), expected);
+    }
+
+    #[test]
+    fn marks_stay_with_their_letter() {
+        // Kasra below the first letter
+        assert_eq!(visual("بِت"), "تبِ");
+    }
+
+    #[test]
+    fn visual_order_covers_every_grapheme() {
+        let line = "cp ملف.txt נתיב/2";
+        let mut ranges = visual_order(line);
+        ranges.sort_by_key(|range| range.start);
+
+        let graphemes: Vec<_> = line
+            .grapheme_indices(true)
+            .map(|(index, grapheme)| index..index + grapheme.len())
+            .collect();
+        assert_eq!(ranges, graphemes);
+    }
+}
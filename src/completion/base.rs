@@ -1,3 +1,5 @@
+use std::task::Poll;
+
 /// A span of source code, with positions in bytes
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub struct Span {
@@ -31,4 +33,70 @@ pub trait Completer: Send {
     /// the action that will take the line and position and convert it to a vector of completions, which include the
     /// span to replace and the contents of that replacement
     fn complete(&self, line: &str, pos: usize) -> Vec<(Span, String)>;
+
+    /// Returns only a window of the completions, skipping the first `start` values and
+    /// taking at most `len` values.
+    ///
+    /// Menus only use it when `total_completions` gives the number of completions.
+    /// Completers that produce a large number of completions should override both
+    /// to avoid building the whole list
+    fn partial_complete(
+        &self,
+        line: &str,
+        pos: usize,
+        start: usize,
+        len: usize,
+    ) -> Vec<(Span, String)> {
+        partial_values(&self.complete(line, pos), start, len)
+    }
+
+    /// The total number of completions available for the line and position, for the
+    /// completers that collect them a window at a time with `partial_complete`.
+    ///
+    /// By default it is `None` and menus collect all the completions at once with
+    /// `complete`, taking the windows from them
+    fn total_completions(&self, _line: &str, _pos: usize) -> Option<usize> {
+        None
+    }
+
+    /// Whether the completion leaves more to complete after it is accepted, like a
+    /// directory whose entries come next. The menu then stays open and collects the
+    /// completions for the longer line. By default every completion is final
+    fn keeps_menu_open(&self, _span: Span, _value: &str) -> bool {
+        false
+    }
+
+    /// Non-blocking check that the completions for the line and position are available,
+    /// for completers that take long, like the ones asking a language server.
+    ///
+    /// While it returns [`Poll::Pending`] the menu shows that the completions are
+    /// loading and asks again after a short wait, so the implementation should start
+    /// the work in the background the first time. Once it returns [`Poll::Ready`] the
+    /// results are collected with the other methods, which should then answer from
+    /// the finished work. By default the completions are available right away
+    fn poll_completions(&self, _line: &str, _pos: usize) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    /// The completions in flight are no longer needed, the menu waiting for them was
+    /// closed. Their results should be dropped when they arrive
+    fn cancel_completions(&self) {}
+
+    /// The completions split in groups, each one with a label like "Flags" or "Files".
+    ///
+    /// Menus that show grouped completions display the label as a header before the
+    /// completions of the group. By default all the completions belong to a single group
+    /// without label, which is shown without header
+    fn complete_grouped(&self, line: &str, pos: usize) -> Vec<(String, Vec<(Span, String)>)> {
+        vec![(String::new(), self.complete(line, pos))]
+    }
+}
+
+/// The window of `values` that skips the first `start` values and takes at most `len`
+pub(crate) fn partial_values(
+    values: &[(Span, String)],
+    start: usize,
+    len: usize,
+) -> Vec<(Span, String)> {
+    values.iter().skip(start).take(len).cloned().collect()
 }
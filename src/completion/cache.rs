@@ -0,0 +1,247 @@
+use {
+    super::{partial_values, Completer, Span},
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex, MutexGuard},
+        task::Poll,
+    },
+};
+
+type Completions = Vec<(Span, String)>;
+
+/// Results of the completer kept while the text around the completed span stays the
+/// same, shared by the engine and the completer it wraps
+#[derive(Clone, Default)]
+pub(crate) struct CompletionCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    enabled: bool,
+    // Text before the completed span and after the cursor of the cached results
+    context: Option<(String, String)>,
+    // Completions by the buffer up to the cursor
+    results: HashMap<String, Completions>,
+    grouped: HashMap<String, Vec<(String, Completions)>>,
+}
+
+impl CompletionCache {
+    /// Wraps the completer so its results are kept while the cache is enabled
+    pub(crate) fn wrap(&self, completer: Box<dyn Completer>) -> Box<dyn Completer> {
+        Box::new(CachedCompleter {
+            completer,
+            cache: self.clone(),
+        })
+    }
+
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        let mut state = self.state();
+        state.enabled = enabled;
+        state.clear();
+    }
+
+    /// Drops the kept results, for example when a new line is read and the completer
+    /// may answer differently
+    pub(crate) fn clear(&self) {
+        self.state().clear();
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl CacheState {
+    fn clear(&mut self) {
+        self.context = None;
+        self.results.clear();
+        self.grouped.clear();
+    }
+
+    /// Drops the results when the text around the completed span changed, and keeps
+    /// the new context
+    fn check_context(&mut self, line: &str, pos: usize, span_start: usize) {
+        let context = (
+            line[..span_start.min(pos)].to_string(),
+            line[pos..].to_string(),
+        );
+        if self.context.as_ref() != Some(&context) {
+            self.results.clear();
+            self.grouped.clear();
+            self.context = Some(context);
+        }
+    }
+
+    /// Whether the line is still in the context of the cached results
+    fn in_context(&self, line: &str, pos: usize) -> bool {
+        self.context.as_ref().is_some_and(|(before, after)| {
+            before.len() <= pos && line[..pos].starts_with(before.as_str()) && line[pos..] == *after
+        })
+    }
+}
+
+/// The start of the span the completions replace, the cursor without completions
+fn span_start(completions: &[(Span, String)], pos: usize) -> usize {
+    completions
+        .iter()
+        .map(|(span, _)| span.start)
+        .min()
+        .unwrap_or(pos)
+}
+
+struct CachedCompleter {
+    completer: Box<dyn Completer>,
+    cache: CompletionCache,
+}
+
+impl CachedCompleter {
+    fn cached_complete(&self, line: &str, pos: usize) -> Completions {
+        let key = line[..pos].to_string();
+        {
+            let state = self.cache.state();
+            if let Some(results) = state
+                .results
+                .get(&key)
+                .filter(|_| state.in_context(line, pos))
+            {
+                return results.clone();
+            }
+        }
+
+        let results = self.completer.complete(line, pos);
+        let mut state = self.cache.state();
+        state.check_context(line, pos, span_start(&results, pos));
+        state.results.insert(key, results.clone());
+        results
+    }
+
+    fn enabled(&self) -> bool {
+        self.cache.state().enabled
+    }
+}
+
+impl Completer for CachedCompleter {
+    fn complete(&self, line: &str, pos: usize) -> Vec<(Span, String)> {
+        if !self.enabled() {
+            return self.completer.complete(line, pos);
+        }
+        self.cached_complete(line, pos)
+    }
+
+    fn partial_complete(
+        &self,
+        line: &str,
+        pos: usize,
+        start: usize,
+        len: usize,
+    ) -> Vec<(Span, String)> {
+        if !self.enabled() {
+            return self.completer.partial_complete(line, pos, start, len);
+        }
+        partial_values(&self.cached_complete(line, pos), start, len)
+    }
+
+    fn total_completions(&self, line: &str, pos: usize) -> Option<usize> {
+        if !self.enabled() {
+            return self.completer.total_completions(line, pos);
+        }
+        // The cached results are complete, menus take the windows from them
+        None
+    }
+
+    fn complete_grouped(&self, line: &str, pos: usize) -> Vec<(String, Vec<(Span, String)>)> {
+        if !self.enabled() {
+            return self.completer.complete_grouped(line, pos);
+        }
+
+        let key = line[..pos].to_string();
+        {
+            let state = self.cache.state();
+            if let Some(groups) = state
+                .grouped
+                .get(&key)
+                .filter(|_| state.in_context(line, pos))
+            {
+                return groups.clone();
+            }
+        }
+
+        let groups = self.completer.complete_grouped(line, pos);
+        let all: Completions = groups
+            .iter()
+            .flat_map(|(_, values)| values.iter().cloned())
+            .collect();
+        let mut state = self.cache.state();
+        state.check_context(line, pos, span_start(&all, pos));
+        state.grouped.insert(key, groups.clone());
+        groups
+    }
+
+    fn keeps_menu_open(&self, span: Span, value: &str) -> bool {
+        self.completer.keeps_menu_open(span, value)
+    }
+
+    fn poll_completions(&self, line: &str, pos: usize) -> Poll<()> {
+        self.completer.poll_completions(line, pos)
+    }
+
+    fn cancel_completions(&self) {
+        self.completer.cancel_completions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Completes the word before the cursor with the words of the list, counting the calls
+    struct CountingCompleter(Arc<AtomicUsize>);
+
+    impl Completer for CountingCompleter {
+        fn complete(&self, line: &str, pos: usize) -> Vec<(Span, String)> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let start = line[..pos].rfind(' ').map_or(0, |space| space + 1);
+            ["hello", "help", "world"]
+                .iter()
+                .filter(|word| word.starts_with(&line[start..pos]))
+                .map(|word| (Span::new(start, pos), word.to_string()))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn results_are_kept_while_the_text_around_the_span_stays() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = CompletionCache::default();
+        cache.set_enabled(true);
+        let completer = cache.wrap(Box::new(CountingCompleter(calls.clone())));
+        let complete = |line: &str| completer.complete(line, line.len()).len();
+
+        assert_eq!(complete("echo he"), 2);
+        assert_eq!(complete("echo hel"), 2);
+        // Backspacing within the word
+        assert_eq!(complete("echo he"), 2);
+        assert_eq!(complete("echo h"), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // The text before the word changed
+        assert_eq!(complete("ech he"), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(complete("echo he"), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        cache.clear();
+        assert_eq!(complete("echo he"), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+
+        cache.set_enabled(false);
+        assert_eq!(complete("echo he"), 2);
+        assert_eq!(complete("echo he"), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 8);
+    }
+}
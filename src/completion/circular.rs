@@ -1,6 +1,7 @@
 use crate::{core_editor::LineBuffer, Completer};
 
 /// A simple handler that will do a cycle-based rotation through the options given by the Completer
+#[derive(Default)]
 pub struct CircularCompletionHandler {
     initial_line: LineBuffer,
     index: usize,
@@ -8,16 +9,6 @@ pub struct CircularCompletionHandler {
     last_buffer: Option<LineBuffer>,
 }
 
-impl Default for CircularCompletionHandler {
-    fn default() -> Self {
-        CircularCompletionHandler {
-            initial_line: LineBuffer::new(),
-            index: 0,
-            last_buffer: None,
-        }
-    }
-}
-
 impl CircularCompletionHandler {
     fn reset_index(&mut self) {
         self.index = 0;
@@ -28,7 +19,13 @@ impl CircularCompletionHandler {
     //  in the line_buffer only the specified range of characters.
     // If internal index is 0 it means that is the first tab event pressed.
     // If internal index is greater than completions vector, we bring it back to 0.
-    pub(crate) fn handle(&mut self, completer: &dyn Completer, present_buffer: &mut LineBuffer) {
+    //
+    // Returns false when there are no completions
+    pub(crate) fn handle(
+        &mut self,
+        completer: &dyn Completer,
+        present_buffer: &mut LineBuffer,
+    ) -> bool {
         if let Some(last_buffer) = &self.last_buffer {
             if last_buffer != present_buffer {
                 self.reset_index();
@@ -64,6 +61,8 @@ impl CircularCompletionHandler {
             }
         }
         self.last_buffer = Some(present_buffer.clone());
+
+        !completions.is_empty()
     }
 }
 
@@ -1,7 +1,15 @@
 mod base;
+mod cache;
 mod circular;
 mod default;
+mod quoting;
+mod tokenizer;
 
+pub(crate) use base::partial_values;
 pub use base::{Completer, Span};
+pub(crate) use cache::CompletionCache;
 pub use circular::CircularCompletionHandler;
 pub use default::DefaultCompleter;
+pub(crate) use quoting::quoted_value;
+pub use quoting::{CompletionQuoting, PosixQuoting};
+pub use tokenizer::Tokenizer;
@@ -0,0 +1,152 @@
+use super::Span;
+
+/// How a completion is written into the buffer, so the shell of the host reads it
+/// back as the value it stands for
+///
+/// The menu passes the quote the completed span sits in, if any. A span that starts
+/// with an opening quote, like `"My Doc` completed to `My Documents`, sits in that
+/// quote and the menu keeps the quote in front of the inserted text. Values that
+/// start with a quote are taken as quoted by the completer and are inserted as they are.
+pub trait CompletionQuoting: Send {
+    /// The text inserted for `value`, inside the quote `quote` or unquoted for `None`
+    fn quote(&self, value: &str, quote: Option<char>) -> String;
+
+    /// Whether the character opens a quoted part of the line
+    fn is_quote(&self, c: char) -> bool {
+        c == '"' || c == '\''
+    }
+}
+
+/// Quoting of POSIX shells: spaces and special characters are escaped with a backslash
+/// outside quotes, inside double quotes only `"`, `\`, `$` and `` ` `` are escaped, and
+/// a single quote inside single quotes closes the quote, is escaped, and opens it again
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{CompletionQuoting, PosixQuoting};
+///
+/// assert_eq!(PosixQuoting.quote("My Documents", None), "My\\ Documents");
+/// assert_eq!(PosixQuoting.quote("a \"b\"", Some('"')), "a \\\"b\\\"");
+/// assert_eq!(PosixQuoting.quote("it's", Some('\'')), "it'\\''s");
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PosixQuoting;
+
+impl CompletionQuoting for PosixQuoting {
+    fn quote(&self, value: &str, quote: Option<char>) -> String {
+        let mut quoted = String::with_capacity(value.len());
+        for c in value.chars() {
+            match quote {
+                Some('\'') if c == '\'' => quoted.push_str("'\\''"),
+                Some('\'') => quoted.push(c),
+                Some(_) if matches!(c, '"' | '\\' | '$' | '`') => {
+                    quoted.push('\\');
+                    quoted.push(c);
+                }
+                Some(_) => quoted.push(c),
+                None if c.is_whitespace() || "\"'\\$`&|;<>()*?[]#~!{}".contains(c) => {
+                    quoted.push('\\');
+                    quoted.push(c);
+                }
+                None => quoted.push(c),
+            }
+        }
+        quoted
+    }
+}
+
+/// The quote the position of the line sits in, skipping the escaped characters
+pub(crate) fn quote_at(quoting: &dyn CompletionQuoting, line: &str, pos: usize) -> Option<char> {
+    let mut quote = None;
+    let mut escaped = false;
+    for c in line[..pos].chars() {
+        match quote {
+            _ if escaped => escaped = false,
+            Some('\'') if c == '\'' => quote = None,
+            Some('\'') => {}
+            _ if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if quoting.is_quote(c) => quote = Some(c),
+            None => {}
+        }
+    }
+    quote
+}
+
+/// The text that replaces the span of the line for the completion `value`
+pub(crate) fn quoted_value(
+    quoting: &dyn CompletionQuoting,
+    line: &str,
+    span: Span,
+    value: &str,
+) -> String {
+    if value.starts_with(|c| quoting.is_quote(c)) {
+        return value.to_string();
+    }
+
+    match quote_at(quoting, line, span.start) {
+        Some(quote) => quoting.quote(value, Some(quote)),
+        None => {
+            let opening = line
+                .get(span.start..span.end)
+                .and_then(|spanned| spanned.chars().next())
+                .filter(|c| quoting.is_quote(*c));
+            match opening {
+                Some(quote) => format!("{}{}", quote, quoting.quote(value, Some(quote))),
+                None => quoting.quote(value, None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("cat ", None)]
+    #[case("cat \"My ", Some('"'))]
+    #[case("cat 'a \" ", Some('\''))]
+    #[case("cat \"a\" ", None)]
+    #[case("cat \\\"", None)]
+    #[case("cat 'a\\' ", None)]
+    fn quote_of_the_position(#[case] line: &str, #[case] expected: Option<char>) {
+        assert_eq!(quote_at(&PosixQuoting, line, line.len()), expected);
+    }
+
+    #[rstest]
+    #[case("plain.txt", None, "plain.txt")]
+    #[case("a b&c", None, "a\\ b\\&c")]
+    #[case("$HOME \\", Some('"'), "\\$HOME \\\\")]
+    #[case("a $b", Some('\''), "a $b")]
+    fn values_are_escaped_for_their_quote(
+        #[case] value: &str,
+        #[case] quote: Option<char>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(PosixQuoting.quote(value, quote), expected);
+    }
+
+    #[rstest]
+    #[case("cat My", Span::new(4, 6), "My Doc", "cat My\\ Doc")]
+    #[case("cat \"My", Span::new(4, 7), "My \"Doc\"", "cat \"My \\\"Doc\\\"")]
+    #[case("cat \"My", Span::new(5, 7), "My Doc", "cat \"My Doc")]
+    #[case("cat My", Span::new(4, 6), "'My Doc'", "cat 'My Doc'")]
+    fn completions_are_quoted_where_they_go(
+        #[case] line: &str,
+        #[case] span: Span,
+        #[case] value: &str,
+        #[case] expected: &str,
+    ) {
+        let mut completed = line.to_string();
+        completed.replace_range(
+            span.start..span.end,
+            &quoted_value(&PosixQuoting, line, span, value),
+        );
+        assert_eq!(completed, expected);
+    }
+}
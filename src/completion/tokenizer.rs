@@ -0,0 +1,170 @@
+use super::Span;
+
+/// Splits a line in tokens the way a shell would, to find the span a completer replaces
+///
+/// Tokens are separated by the separator characters. A separator in quotes or after a
+/// backslash is part of the token, so `cat "My Documents"/a\ b` has two tokens.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{Span, Tokenizer};
+///
+/// let tokenizer = Tokenizer::default();
+/// let line = "cat \"My Doc";
+///
+/// // The text typed of the token under the cursor
+/// assert_eq!(tokenizer.span_to_cursor(line, line.len()), Span::new(4, 11));
+/// assert_eq!(
+///     tokenizer.tokens("ls | grep a\\ b"),
+///     vec![Span::new(0, 2), Span::new(5, 9), Span::new(10, 14)]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tokenizer {
+    separators: Vec<char>,
+    quotes: Vec<char>,
+}
+
+impl Default for Tokenizer {
+    /// Whitespace and the operators of a shell separate the tokens, double and single
+    /// quotes group them
+    fn default() -> Self {
+        Self {
+            separators: vec![' ', '\t', '\n', '|', ';', '&', '<', '>', '(', ')'],
+            quotes: vec!['"', '\''],
+        }
+    }
+}
+
+impl Tokenizer {
+    /// A builder that sets the characters that separate the tokens
+    pub fn with_separators(mut self, separators: Vec<char>) -> Self {
+        self.separators = separators;
+        self
+    }
+
+    /// A builder that sets the characters that start and end a quoted part of a token
+    pub fn with_quotes(mut self, quotes: Vec<char>) -> Self {
+        self.quotes = quotes;
+        self
+    }
+
+    /// The spans of all the tokens of the line
+    pub fn tokens(&self, line: &str) -> Vec<Span> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        for (index, _, separates) in self.scan(line) {
+            match (start, separates) {
+                (None, false) => start = Some(index),
+                (Some(token_start), true) => {
+                    tokens.push(Span::new(token_start, index));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(token_start) = start {
+            tokens.push(Span::new(token_start, line.len()));
+        }
+        tokens
+    }
+
+    /// The span from the start of the token under the cursor to the cursor, the text
+    /// a completion replaces. It is empty at the cursor when the cursor follows a
+    /// separator
+    pub fn span_to_cursor(&self, line: &str, pos: usize) -> Span {
+        let start = self
+            .scan(&line[..pos])
+            .filter(|(_, _, separates)| *separates)
+            .last()
+            .map_or(0, |(index, c, _)| index + c.len_utf8());
+        Span::new(start, pos)
+    }
+
+    /// The span of the whole token under the cursor, also the part after the cursor
+    pub fn token_at(&self, line: &str, pos: usize) -> Span {
+        let start = self.span_to_cursor(line, pos).start;
+        let end = self
+            .scan(line)
+            .find(|(index, _, separates)| *index >= pos && *separates)
+            .map_or(line.len(), |(index, _, _)| index);
+        Span::new(start, end)
+    }
+
+    /// The characters of the line with their index and whether they separate tokens
+    fn scan<'a>(&'a self, line: &'a str) -> impl Iterator<Item = (usize, char, bool)> + 'a {
+        let mut quote = None;
+        let mut escaped = false;
+        line.char_indices().map(move |(index, c)| {
+            let separates = match quote {
+                _ if escaped => {
+                    escaped = false;
+                    false
+                }
+                Some(q) => {
+                    if c == q {
+                        quote = None;
+                    } else if c == '\\' && q != '\'' {
+                        escaped = true;
+                    }
+                    false
+                }
+                None if c == '\\' => {
+                    escaped = true;
+                    false
+                }
+                None if self.quotes.contains(&c) => {
+                    quote = Some(c);
+                    false
+                }
+                None => self.separators.contains(&c),
+            };
+            (index, c, separates)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("git chec", 8, Span::new(4, 8), Span::new(4, 8))]
+    #[case("git ", 4, Span::new(4, 4), Span::new(4, 4))]
+    #[case("cat \"My Doc\" x", 9, Span::new(4, 9), Span::new(4, 12))]
+    #[case("cat a\\ b", 8, Span::new(4, 8), Span::new(4, 8))]
+    #[case("ls|gr", 5, Span::new(3, 5), Span::new(3, 5))]
+    #[case("echo 'a\\' b", 11, Span::new(10, 11), Span::new(10, 11))]
+    #[case("vim src/ma.rs", 10, Span::new(4, 10), Span::new(4, 13))]
+    fn token_under_the_cursor(
+        #[case] line: &str,
+        #[case] pos: usize,
+        #[case] to_cursor: Span,
+        #[case] token: Span,
+    ) {
+        let tokenizer = Tokenizer::default();
+
+        assert_eq!(tokenizer.span_to_cursor(line, pos), to_cursor);
+        assert_eq!(tokenizer.token_at(line, pos), token);
+    }
+
+    #[test]
+    fn separators_can_be_configured() {
+        let tokenizer = Tokenizer::default()
+            .with_separators(vec![' ', '.'])
+            .with_quotes(vec!['`']);
+
+        assert_eq!(
+            tokenizer.tokens("a.b `c.d` e"),
+            vec![
+                Span::new(0, 1),
+                Span::new(2, 3),
+                Span::new(4, 9),
+                Span::new(10, 11)
+            ]
+        );
+    }
+}
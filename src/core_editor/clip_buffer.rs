@@ -6,34 +6,32 @@ pub trait Clipboard: Send {
 
     fn get(&mut self) -> (String, ClipboardMode);
 
+    #[allow(dead_code)]
     fn clear(&mut self) {
         self.set("", ClipboardMode::Normal);
     }
 
+    #[allow(dead_code)]
     fn len(&mut self) -> usize {
         self.get().0.len()
     }
 
+    #[allow(dead_code)]
     fn is_empty(&mut self) -> bool {
         self.get().0.is_empty()
     }
 }
 
 /// Determines how the content in the clipboard should be inserted
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default)]
 pub enum ClipboardMode {
     /// As direct content at the current cursor position
+    #[default]
     Normal,
     /// As new lines below or above
     Lines,
 }
 
-impl Default for ClipboardMode {
-    fn default() -> Self {
-        ClipboardMode::Normal
-    }
-}
-
 /// Simple buffer that provides a clipboard only usable within the application/library.
 #[derive(Default)]
 pub struct LocalClipboard {
@@ -1,5 +1,9 @@
-use super::{Clipboard, ClipboardMode, LineBuffer};
+use super::{
+    snippet::{Placeholders, Snippet},
+    Clipboard, ClipboardMode, LineBuffer,
+};
 use crate::{core_editor::get_default_clipboard, EditCommand, UndoBehavior};
+use std::ops::Range;
 
 pub struct Editor {
     line_buffer: LineBuffer,
@@ -7,6 +11,20 @@ pub struct Editor {
 
     edits: Vec<LineBuffer>,
     index_undo: usize,
+    // Words in the last entry of `edits`, to coalesce typing without counting again
+    last_edit_word_count: usize,
+
+    // Characters overwritten by the undo group of the vi replace mode, given back when
+    // backspacing over them
+    replaced: Option<Vec<String>>,
+
+    // Placeholders of the last inserted snippet while it is being edited
+    placeholders: Option<Placeholders>,
+
+    // Inserted text is brought to the Unicode normalization form C
+    normalize: bool,
+    // The arrows move over the text as it is shown, with the right-to-left runs reversed
+    visual_movement: bool,
 }
 
 impl Default for Editor {
@@ -17,7 +35,12 @@ impl Default for Editor {
 
             // Note: Using list-zipper we can reduce these to one field
             edits: vec![LineBuffer::new()],
+            last_edit_word_count: 0,
             index_undo: 2,
+            replaced: None,
+            placeholders: None,
+            normalize: false,
+            visual_movement: false,
         }
     }
 }
@@ -31,12 +54,47 @@ impl Editor {
         self.line_buffer = line_buffer;
     }
 
+    pub(crate) fn set_normalization(&mut self, normalize: bool) {
+        self.normalize = normalize;
+    }
+
+    pub(crate) fn set_visual_movement(&mut self, visual_movement: bool) {
+        self.visual_movement = visual_movement;
+    }
+
     pub fn run_edit_command(&mut self, command: &EditCommand) {
+        let old_len = self.line_buffer.len();
+        let in_group = self.replaced.is_some();
+        if !matches!(command.undo_behavior(), UndoBehavior::Group) {
+            self.replaced = None;
+        }
+        if let Some(placeholders) = &mut self.placeholders {
+            // Typing over a placeholder replaces its default text
+            let inserts = matches!(
+                command,
+                EditCommand::InsertChar(_)
+                    | EditCommand::InsertString(_)
+                    | EditCommand::PasteCutBufferBefore
+            );
+            if placeholders.take_replace_pending() && inserts {
+                let current = placeholders.current();
+                self.line_buffer.set_insertion_point(current.start);
+                self.line_buffer.clear_range(current);
+            }
+        }
+
         match command {
             EditCommand::MoveToStart => self.line_buffer.move_to_start(),
             EditCommand::MoveToLineStart => self.line_buffer.move_to_line_start(),
             EditCommand::MoveToEnd => self.line_buffer.move_to_end(),
             EditCommand::MoveToLineEnd => self.line_buffer.move_to_line_end(),
+            EditCommand::MoveToPosition { line, column } => {
+                self.line_buffer.move_to_position(*line, *column)
+            }
+            EditCommand::MoveLeft if self.visual_movement => self.line_buffer.move_left_visually(),
+            EditCommand::MoveRight if self.visual_movement => {
+                self.line_buffer.move_right_visually()
+            }
             EditCommand::MoveLeft => self.line_buffer.move_left(),
             EditCommand::MoveRight => self.line_buffer.move_right(),
             EditCommand::MoveWordLeft => self.line_buffer.move_word_left(),
@@ -61,8 +119,28 @@ impl Editor {
             EditCommand::UppercaseWord => self.line_buffer.uppercase_word(),
             EditCommand::LowercaseWord => self.line_buffer.lowercase_word(),
             EditCommand::CapitalizeChar => self.line_buffer.capitalize_char(),
+            EditCommand::CapitalizeWord => self.line_buffer.capitalize_word(),
+            EditCommand::SwitchcaseChar => self.line_buffer.switchcase_char(),
+            EditCommand::SwapLineUp => self.line_buffer.swap_line_up(),
+            EditCommand::SwapLineDown => self.line_buffer.swap_line_down(),
+            EditCommand::DuplicateLine => self.line_buffer.duplicate_line(),
+            EditCommand::ToggleComment(prefix) => self.line_buffer.toggle_comment(prefix),
+            EditCommand::IncrementNumber(amount) => self.line_buffer.increment_number(*amount),
+            EditCommand::UppercaseLine => self.line_buffer.uppercase_line(),
+            EditCommand::LowercaseLine => self.line_buffer.lowercase_line(),
             EditCommand::SwapWords => self.line_buffer.swap_words(),
             EditCommand::SwapGraphemes => self.line_buffer.swap_graphemes(),
+            EditCommand::ReplaceChar(c) => self.replace_char(*c),
+            EditCommand::ReplaceBackspace => self.replace_backspace(),
+            EditCommand::ReplaceChars(count, c) => self.line_buffer.replace_graphemes(*count, *c),
+            EditCommand::Substitute {
+                pattern,
+                replacement,
+                all_lines,
+                every_match,
+            } => self
+                .line_buffer
+                .substitute(pattern, replacement, *all_lines, *every_match),
             EditCommand::Undo => self.undo(),
             EditCommand::Redo => self.redo(),
             EditCommand::CutRightUntil(c) => self.cut_right_until_char(*c, false, true),
@@ -73,6 +151,33 @@ impl Editor {
             EditCommand::CutLeftBefore(c) => self.cut_left_until_char(*c, true, true),
             EditCommand::MoveLeftUntil(c) => self.move_left_until_char(*c, false, true),
             EditCommand::MoveLeftBefore(c) => self.move_left_until_char(*c, true, true),
+            EditCommand::InsertSnippet(snippet) => self.insert_snippet(snippet),
+        }
+
+        // Accents typed or pasted after their letter are composed with it
+        if self.normalize
+            && matches!(
+                command,
+                EditCommand::InsertChar(_)
+                    | EditCommand::InsertString(_)
+                    | EditCommand::PasteCutBufferBefore
+                    | EditCommand::PasteCutBufferAfter
+            )
+        {
+            self.line_buffer.normalize();
+        }
+
+        if !matches!(command, EditCommand::InsertSnippet(_)) {
+            let new_len = self.line_buffer.len();
+            let offset = self.line_buffer.offset();
+            let keep = match (&mut self.placeholders, command) {
+                (_, EditCommand::Undo | EditCommand::Redo) => false,
+                (Some(placeholders), _) => placeholders.update(old_len, new_len, offset),
+                (None, _) => false,
+            };
+            if !keep {
+                self.placeholders = None;
+            }
         }
         match command.undo_behavior() {
             UndoBehavior::Ignore => {}
@@ -82,9 +187,74 @@ impl Editor {
             UndoBehavior::Coalesce => {
                 self.remember_undo_state(false);
             }
+            UndoBehavior::Group => {
+                if in_group && self.edits.len() > 1 {
+                    self.edits.pop();
+                }
+                self.remember_undo_state(true);
+            }
+        }
+    }
+
+    /// Ends the undo group of the vi replace mode, so the next replaced characters
+    /// are undone on their own
+    pub(crate) fn end_undo_group(&mut self) {
+        self.replaced = None;
+    }
+
+    fn replace_char(&mut self, c: char) {
+        let overwritten = self.line_buffer.overwrite_char(c);
+        self.replaced.get_or_insert_with(Vec::new).push(overwritten);
+    }
+
+    /// Moves left over the last replaced character and gives back the one it overwrote.
+    /// Before the characters of the group it only moves left
+    fn replace_backspace(&mut self) {
+        match self.replaced.as_mut().and_then(Vec::pop) {
+            Some(overwritten) => {
+                let offset = self.line_buffer.offset();
+                let left = self.line_buffer.grapheme_left_index();
+                self.line_buffer.replace(left..offset, &overwritten);
+                self.line_buffer.set_insertion_point(left);
+            }
+            None => self.line_buffer.move_left(),
+        }
+    }
+
+    /// Inserts the text of a snippet and moves to its first placeholder
+    fn insert_snippet(&mut self, snippet: &str) {
+        let snippet = Snippet::parse(snippet);
+        let offset = self.line_buffer.offset();
+        self.line_buffer.insert_str(&snippet.text);
+
+        let placeholders = Placeholders::new(&snippet, offset);
+        self.line_buffer
+            .set_insertion_point(placeholders.current().start);
+        self.placeholders = (!placeholders.is_last()).then_some(placeholders);
+    }
+
+    /// Moves to the next placeholder of the snippet being edited. Returns false if
+    /// there is no snippet
+    pub(crate) fn next_placeholder(&mut self) -> bool {
+        match &mut self.placeholders {
+            Some(placeholders) => {
+                placeholders.advance();
+                self.line_buffer
+                    .set_insertion_point(placeholders.current().start);
+                if placeholders.is_last() {
+                    self.placeholders = None;
+                }
+                true
+            }
+            None => false,
         }
     }
 
+    /// The placeholder of the snippet that is being edited
+    pub(crate) fn current_placeholder(&self) -> Option<Range<usize>> {
+        self.placeholders.as_ref().map(Placeholders::current)
+    }
+
     pub fn move_line_up(&mut self) {
         self.line_buffer.move_line_up();
     }
@@ -150,15 +320,13 @@ impl Editor {
 
     pub fn reset_undo_stack(&mut self) {
         self.edits = vec![LineBuffer::new()];
+        self.replaced = None;
+        self.last_edit_word_count = 0;
         self.index_undo = 2;
     }
 
     fn get_index_undo(&self) -> usize {
-        if let Some(c) = self.edits.len().checked_sub(self.index_undo) {
-            c
-        } else {
-            0
-        }
+        self.edits.len().saturating_sub(self.index_undo)
     }
 
     fn undo(&mut self) {
@@ -192,13 +360,12 @@ impl Editor {
     pub fn remember_undo_state(&mut self, is_after_action: bool) -> Option<()> {
         self.reset_index_undo();
 
-        if self.edits.len() > 1
-            && self.edits.last()?.word_count() == self.line_buffer.word_count()
-            && !is_after_action
-        {
+        let word_count = self.line_buffer.word_count();
+        if self.edits.len() > 1 && self.last_edit_word_count == word_count && !is_after_action {
             self.edits.pop();
         }
         self.edits.push(self.line_buffer.clone());
+        self.last_edit_word_count = word_count;
 
         Some(())
     }
@@ -403,4 +570,134 @@ mod test {
             editor.edits
         );
     }
+
+    fn editor_with_snippet(snippet: &str) -> Editor {
+        let mut editor = Editor::default();
+        editor.run_edit_command(&EditCommand::InsertSnippet(snippet.to_string()));
+        editor
+    }
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.run_edit_command(&EditCommand::InsertChar(c));
+        }
+    }
+
+    #[test]
+    fn snippet_placeholders_are_edited_in_sequence() {
+        let mut editor = editor_with_snippet("cp ${1:from} ${2:to}");
+        assert_eq!(editor.get_buffer(), "cp from to");
+        assert_eq!(editor.current_placeholder(), Some(3..7));
+
+        type_text(&mut editor, "a.txt");
+        assert!(editor.next_placeholder());
+        type_text(&mut editor, "b.txt");
+
+        assert_eq!(editor.get_buffer(), "cp a.txt b.txt");
+        assert!(editor.next_placeholder());
+        assert_eq!(editor.offset(), 14);
+        assert!(!editor.next_placeholder());
+    }
+
+    #[test]
+    fn placeholder_keeps_default_when_skipped() {
+        let mut editor = editor_with_snippet("git checkout -b ${1:branch} ${2:origin/main}");
+
+        assert!(editor.next_placeholder());
+        type_text(&mut editor, "upstream/dev");
+
+        assert_eq!(editor.get_buffer(), "git checkout -b branch upstream/dev");
+    }
+
+    #[test]
+    fn leaving_the_placeholder_ends_the_snippet() {
+        let mut editor = editor_with_snippet("cp ${1:from} ${2:to}");
+
+        editor.run_edit_command(&EditCommand::MoveToStart);
+
+        assert_eq!(editor.current_placeholder(), None);
+        assert!(!editor.next_placeholder());
+    }
+
+    #[test]
+    fn snippet_without_placeholders_is_plain_text() {
+        let mut editor = editor_with_snippet("ls -la");
+
+        assert_eq!(editor.get_buffer(), "ls -la");
+        assert_eq!(editor.offset(), 6);
+        assert!(!editor.next_placeholder());
+    }
+
+    #[test]
+    fn typed_accents_are_composed_when_normalizing() {
+        let mut editor = Editor::default();
+        editor.set_normalization(true);
+
+        type_text(&mut editor, "cafe\u{301}");
+        editor.run_edit_command(&EditCommand::InsertString("\u{301}".into()));
+
+        assert_eq!(editor.get_buffer(), "café\u{301}");
+        assert_eq!(editor.offset(), editor.get_buffer().len());
+
+        let mut editor = Editor::default();
+        type_text(&mut editor, "cafe\u{301}");
+        assert_eq!(editor.get_buffer(), "cafe\u{301}");
+    }
+
+    fn replace_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.run_edit_command(&EditCommand::ReplaceChar(c));
+        }
+    }
+
+    #[test]
+    fn replaced_characters_are_undone_together() {
+        let mut editor = Editor::default();
+        type_text(&mut editor, "echo hello");
+        editor.run_edit_command(&EditCommand::MoveToLineStart);
+
+        replace_text(&mut editor, "ls  world !");
+        assert_eq!(editor.get_buffer(), "ls  world !");
+
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), "echo hello");
+    }
+
+    #[test]
+    fn replace_groups_end_with_other_edits() {
+        let mut editor = Editor::default();
+        type_text(&mut editor, "abcd");
+        editor.run_edit_command(&EditCommand::MoveToLineStart);
+
+        replace_text(&mut editor, "x");
+        editor.end_undo_group();
+        replace_text(&mut editor, "y");
+        editor.run_edit_command(&EditCommand::MoveRight);
+        replace_text(&mut editor, "z");
+        assert_eq!(editor.get_buffer(), "xycz");
+
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), "xycd");
+        // Past the move right
+        editor.run_edit_command(&EditCommand::Undo);
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), "xbcd");
+    }
+
+    #[test]
+    fn backspace_gives_back_the_replaced_characters() {
+        let mut editor = Editor::default();
+        type_text(&mut editor, "ab");
+        editor.run_edit_command(&EditCommand::MoveToLineStart);
+        editor.run_edit_command(&EditCommand::MoveRight);
+
+        replace_text(&mut editor, "xyz");
+        assert_eq!(editor.get_buffer(), "axyz");
+
+        for _ in 0..4 {
+            editor.run_edit_command(&EditCommand::ReplaceBackspace);
+        }
+        assert_eq!(editor.get_buffer(), "ab");
+        assert_eq!(editor.offset(), 0);
+    }
 }
@@ -1,5 +1,7 @@
 use {
-    std::{convert::From, ops::Range},
+    super::nfc::nfc,
+    crate::bidi,
+    std::{borrow::Cow, convert::From, ops::Range},
     unicode_segmentation::UnicodeSegmentation,
 };
 
@@ -22,6 +24,11 @@ impl Default for InsertionPoint {
 }
 
 /// In memory representation of the entered line(s) to facilitate cursor based editing.
+///
+/// The text is kept in a single `String`, as highlighters, hinters, completers and
+/// validators all work on a `&str` of the whole buffer. Inserting in the middle of a
+/// buffer of a few hundred kilobytes takes microseconds, the cost of large buffers is in
+/// the passes over the whole text when repainting.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct LineBuffer {
     lines: String,
@@ -191,6 +198,25 @@ impl LineBuffer {
         self.insertion_point.offset = self.find_current_line_end();
     }
 
+    /// Move the cursor to the grapheme `column` of the line `line`, both counted from zero
+    ///
+    /// Past the last line the cursor goes to the last line, past the end of the line
+    /// to its end
+    pub fn move_to_position(&mut self, line: usize, column: usize) {
+        let line_start = self
+            .lines
+            .match_indices('\n')
+            .take(line)
+            .last()
+            .map_or(0, |(index, _)| index + 1);
+        let content = line_content_range(&self.lines, line_start);
+
+        self.insertion_point.offset = self.lines[content.clone()]
+            .grapheme_indices(true)
+            .nth(column)
+            .map_or(content.end, |(index, _)| content.start + index);
+    }
+
     /// Cursor position *behind* the next unicode grapheme to the right
     pub fn grapheme_right_index(&self) -> usize {
         self.lines[self.insertion_point.offset..]
@@ -204,7 +230,7 @@ impl LineBuffer {
     pub fn grapheme_left_index(&self) -> usize {
         self.lines[..self.insertion_point.offset]
             .grapheme_indices(true)
-            .last()
+            .next_back()
             .map(|(i, _)| i)
             .unwrap_or(0)
     }
@@ -222,8 +248,7 @@ impl LineBuffer {
     pub fn word_left_index(&self) -> usize {
         self.lines[..self.insertion_point.offset]
             .split_word_bound_indices()
-            .filter(|(_, word)| !is_word_boundary(word))
-            .last()
+            .rfind(|(_, word)| !is_word_boundary(word))
             .map(|(i, _)| i)
             .unwrap_or(0)
     }
@@ -238,6 +263,55 @@ impl LineBuffer {
         self.insertion_point.offset = self.grapheme_left_index();
     }
 
+    /// Move cursor position to the grapheme shown left of the current one, when the
+    /// right-to-left runs of the line are shown reversed
+    ///
+    /// Leaves the line like [`LineBuffer::move_left`] once at its left end
+    pub fn move_left_visually(&mut self) {
+        self.move_visually(false);
+    }
+
+    /// Move cursor position to the grapheme shown right of the current one, when the
+    /// right-to-left runs of the line are shown reversed
+    ///
+    /// Leaves the line like [`LineBuffer::move_right`] once at its right end
+    pub fn move_right_visually(&mut self) {
+        self.move_visually(true);
+    }
+
+    fn move_visually(&mut self, right: bool) {
+        let line_range = self.current_line_range();
+        let line = self.lines[line_range.clone()]
+            .trim_end_matches('\n')
+            .trim_end_matches('\r');
+        if !bidi::has_rtl(line) {
+            if right {
+                self.move_right();
+            } else {
+                self.move_left();
+            }
+            return;
+        }
+
+        // The end of the line comes after the last grapheme shown
+        let positions: Vec<usize> = bidi::visual_order(line)
+            .into_iter()
+            .map(|range| line_range.start + range.start)
+            .chain(std::iter::once(line_range.start + line.len()))
+            .collect();
+        let current = positions
+            .iter()
+            .position(|&position| position == self.insertion_point.offset)
+            .unwrap_or(positions.len() - 1);
+
+        match (right, current) {
+            (false, 0) => self.move_left(),
+            (false, current) => self.insertion_point.offset = positions[current - 1],
+            (true, current) if current + 1 == positions.len() => self.move_right(),
+            (true, current) => self.insertion_point.offset = positions[current + 1],
+        }
+    }
+
     /// Move cursor position *in front of* the next word to the left
     pub fn move_word_left(&mut self) {
         self.insertion_point.offset = self.word_left_index();
@@ -266,6 +340,19 @@ impl LineBuffer {
         self.insertion_point.offset = self.offset() + string.len();
     }
 
+    /// Brings the buffer to the Unicode normalization form C. The cursor stays after
+    /// the text it was after
+    pub fn normalize(&mut self) {
+        if let Cow::Owned(lines) = nfc(&self.lines) {
+            let mut offset = nfc(&self.lines[..self.offset()]).len().min(lines.len());
+            while !lines.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            self.lines = lines;
+            self.insertion_point.offset = offset;
+        }
+    }
+
     /// Empty buffer and reset cursor
     pub fn clear(&mut self) {
         self.lines = String::new();
@@ -325,8 +412,7 @@ impl LineBuffer {
         let right_index = self.word_right_index();
         let left_index = self.lines[..right_index]
             .split_word_bound_indices()
-            .filter(|(_, word)| !is_word_boundary(word))
-            .last()
+            .rfind(|(_, word)| !is_word_boundary(word))
             .map(|(i, _)| i)
             .unwrap_or(0);
 
@@ -365,9 +451,79 @@ impl LineBuffer {
         self.move_word_right();
     }
 
+    /// Capitalizes the current word: its first grapheme in upper case and the rest
+    /// in lower case
+    pub fn capitalize_word(&mut self) {
+        let change_range = self.current_word_range();
+        let word = &self.get_buffer()[change_range.clone()];
+        let mut graphemes = word.graphemes(true);
+        let capitalized = match graphemes.next() {
+            Some(first) => first.to_uppercase() + &graphemes.as_str().to_lowercase(),
+            None => return,
+        };
+        self.replace_range(change_range, &capitalized);
+        self.move_word_right();
+    }
+
+    /// Switches the case of the grapheme at the insertion point and moves right
+    pub fn switchcase_char(&mut self) {
+        let insertion_offset = self.insertion_point().offset;
+        let right_index = self.grapheme_right_index();
+
+        if right_index > insertion_offset {
+            let change_range = insertion_offset..right_index;
+            let switched: String = self.get_buffer()[change_range.clone()]
+                .chars()
+                .map(|c| {
+                    if c.is_uppercase() {
+                        c.to_lowercase().to_string()
+                    } else {
+                        c.to_uppercase().to_string()
+                    }
+                })
+                .collect();
+            self.replace_range(change_range, &switched);
+            self.set_insertion_point(insertion_offset + switched.len());
+        }
+    }
+
+    /// Uppercases the current line, keeping the insertion point
+    pub fn uppercase_line(&mut self) {
+        let change_range = self.current_line_range();
+        let uppercased = self.get_buffer()[change_range.clone()].to_uppercase();
+        self.replace_line_keeping_offset(change_range, &uppercased);
+    }
+
+    /// Lowercases the current line, keeping the insertion point
+    pub fn lowercase_line(&mut self) {
+        let change_range = self.current_line_range();
+        let lowercased = self.get_buffer()[change_range.clone()].to_lowercase();
+        self.replace_line_keeping_offset(change_range, &lowercased);
+    }
+
+    // The case change can change the length of the line, e.g. `ß` becomes `SS`,
+    // so the insertion point is kept on a grapheme boundary inside the line
+    fn replace_line_keeping_offset(&mut self, range: Range<usize>, replace_with: &str) {
+        let relative = self.insertion_point().offset - range.start;
+        let at_end = range.start + relative == range.end;
+        self.replace_range(range.clone(), replace_with);
+
+        let new_offset = if at_end {
+            replace_with.len()
+        } else {
+            replace_with
+                .grapheme_indices(true)
+                .map(|(index, _)| index)
+                .take_while(|index| *index <= relative)
+                .last()
+                .unwrap_or(0)
+        };
+        self.set_insertion_point(range.start + new_offset);
+    }
+
     /// Counts the number of words in the buffer
     pub fn word_count(&self) -> usize {
-        self.lines.trim().split_whitespace().count()
+        self.lines.split_whitespace().count()
     }
 
     /// Capitalize the character at insertion point (or the first character
@@ -399,6 +555,84 @@ impl LineBuffer {
         }
     }
 
+    /// Overwrites the grapheme under the cursor with `c` and moves behind it, like
+    /// typing in the vi replace mode. At the end of the line `c` is inserted.
+    ///
+    /// Returns the overwritten grapheme, empty when `c` was inserted
+    pub fn overwrite_char(&mut self, c: char) -> String {
+        let offset = self.insertion_point.offset;
+        let content_end = line_content_range(&self.lines, offset).end;
+        let end = self.grapheme_right_index().min(content_end).max(offset);
+
+        let overwritten = self.lines[offset..end].to_string();
+        self.lines
+            .replace_range(offset..end, c.encode_utf8(&mut [0; 4]));
+        self.insertion_point.offset = offset + c.len_utf8();
+        overwritten
+    }
+
+    /// Replaces the `count` graphemes from the cursor with `c` and moves onto the
+    /// last of them (vi `r`). Nothing is replaced when fewer graphemes are left in
+    /// the line
+    pub fn replace_graphemes(&mut self, count: usize, c: char) {
+        let offset = self.insertion_point.offset;
+        let content_end = line_content_range(&self.lines, offset).end;
+        if count == 0 || content_end <= offset {
+            return;
+        }
+
+        let end = match self.lines[offset..content_end]
+            .grapheme_indices(true)
+            .map(|(index, grapheme)| offset + index + grapheme.len())
+            .nth(count - 1)
+        {
+            Some(end) => end,
+            None => return,
+        };
+
+        let replacement = c.to_string().repeat(count);
+        self.lines.replace_range(offset..end, &replacement);
+        self.insertion_point.offset = offset + replacement.len() - c.len_utf8();
+    }
+
+    /// Replaces the first match of `pattern` in the current line, or in every line with
+    /// `all_lines`, and all the matches of the line with `every_match`. The pattern is
+    /// matched literally. The cursor goes to the start of the last line that changed
+    pub fn substitute(
+        &mut self,
+        pattern: &str,
+        replacement: &str,
+        all_lines: bool,
+        every_match: bool,
+    ) {
+        if pattern.is_empty() {
+            return;
+        }
+
+        let current = line_content_range(&self.lines, self.offset()).start;
+        let mut substituted = String::with_capacity(self.lines.len());
+        let mut last_changed = None;
+        let mut start = 0;
+        for line in self.lines.split_inclusive('\n') {
+            if (all_lines || start == current) && line.contains(pattern) {
+                last_changed = Some(substituted.len());
+                if every_match {
+                    substituted.push_str(&line.replace(pattern, replacement));
+                } else {
+                    substituted.push_str(&line.replacen(pattern, replacement, 1));
+                }
+            } else {
+                substituted.push_str(line);
+            }
+            start += line.len();
+        }
+
+        if let Some(line_start) = last_changed {
+            self.lines = substituted;
+            self.insertion_point.offset = line_start;
+        }
+    }
+
     /// Deletes one grapheme to the right
     pub fn delete_right_grapheme(&mut self) {
         let right_index = self.grapheme_right_index();
@@ -437,6 +671,160 @@ impl LineBuffer {
         }
     }
 
+    /// Swaps the current line with the line above, keeping the cursor on it
+    pub fn swap_line_up(&mut self) {
+        let current = self.current_line_content_range();
+        if current.start == 0 {
+            return;
+        }
+
+        let previous = line_content_range(&self.lines, current.start - 1);
+        let column = self.offset() - current.start;
+        self.swap_lines(previous.clone(), current.clone());
+        self.insertion_point.offset = previous.start + column;
+    }
+
+    /// Swaps the current line with the line below, keeping the cursor on it
+    pub fn swap_line_down(&mut self) {
+        let current = self.current_line_content_range();
+        let current_line_end = self.current_line_range().end;
+        if current_line_end == current.end {
+            return;
+        }
+
+        let next = line_content_range(&self.lines, current_line_end);
+        let column = self.offset() - current.start;
+        self.swap_lines(current.clone(), next.clone());
+        self.insertion_point.offset = next.end - current.len() + column;
+    }
+
+    /// Duplicates the current line below it and moves the cursor to the copy
+    pub fn duplicate_line(&mut self) {
+        let current = self.current_line_content_range();
+        let column = self.offset() - current.start;
+        let line_ending = if self.lines.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+
+        let copy = format!("{}{}", line_ending, &self.lines[current.clone()]);
+        self.lines.insert_str(current.end, &copy);
+        self.insertion_point.offset = current.end + line_ending.len() + column;
+    }
+
+    /// Comments out the current line with `prefix`, or removes the prefix if the
+    /// line is already commented out
+    ///
+    /// A prefix without its trailing whitespace, like `#` for `# `, is removed as well
+    pub fn toggle_comment(&mut self, prefix: &str) {
+        let line_start = self.current_line_content_range().start;
+        let offset = self.offset();
+        let line = &self.lines[line_start..];
+
+        let comment = [prefix, prefix.trim_end()]
+            .iter()
+            .copied()
+            .find(|comment| !comment.is_empty() && line.starts_with(comment));
+        match comment {
+            Some(comment) => {
+                let comment_len = comment.len();
+                self.replace_range(line_start..line_start + comment_len, "");
+                self.insertion_point.offset =
+                    line_start + (offset - line_start).saturating_sub(comment_len);
+            }
+            None => {
+                self.lines.insert_str(line_start, prefix);
+                self.insertion_point.offset = offset + prefix.len();
+            }
+        }
+    }
+
+    /// Comments out all the lines of the buffer that are not already commented out
+    pub fn comment_lines(&mut self, prefix: &str) {
+        let trimmed_prefix = prefix.trim_end();
+        self.lines = self
+            .lines
+            .split('\n')
+            .map(|line| {
+                if !trimmed_prefix.is_empty() && line.starts_with(trimmed_prefix) {
+                    line.to_string()
+                } else {
+                    format!("{}{}", prefix, line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.insertion_point.offset = self.lines.len();
+    }
+
+    /// Adds `amount` to the number at or after the insertion point in the current line,
+    /// keeping the leading zeros, and leaves the insertion point on its last digit
+    ///
+    /// A `-` is taken as the sign unless it follows a letter or digit, so dates like
+    /// `2021-10-15` count up. Nothing changes if there is no number or it would overflow.
+    pub fn increment_number(&mut self, amount: i64) {
+        let line = self.current_line_content_range();
+        let offset = self.offset();
+        let bytes = self.lines.as_bytes();
+
+        let start = if bytes.get(offset).is_some_and(u8::is_ascii_digit) {
+            self.lines[line.start..offset]
+                .rfind(|c: char| !c.is_ascii_digit())
+                .map_or(line.start, |index| line.start + index + 1)
+        } else {
+            match self.lines[offset..line.end].find(|c: char| c.is_ascii_digit()) {
+                Some(index) => offset + index,
+                None => return,
+            }
+        };
+        let end = self.lines[start..line.end]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(line.end, |index| start + index);
+
+        let negative = start > line.start
+            && bytes[start - 1] == b'-'
+            && !(start - 1 > line.start && bytes[start - 2].is_ascii_alphanumeric());
+        let digits = &self.lines[start..end];
+        let number = match digits.parse::<i64>() {
+            Ok(number) if negative => -number,
+            Ok(number) => number,
+            Err(_) => return,
+        };
+        let number = match number.checked_add(amount) {
+            Some(number) => number,
+            None => return,
+        };
+
+        let width = if digits.starts_with('0') {
+            digits.len()
+        } else {
+            0
+        };
+        let sign = if number < 0 { "-" } else { "" };
+        let replacement = format!("{}{:0width$}", sign, number.unsigned_abs(), width = width);
+
+        let start = if negative { start - 1 } else { start };
+        self.replace_range(start..end, &replacement);
+        self.insertion_point.offset = start + replacement.len() - 1;
+    }
+
+    /// Range of the current line without its line ending
+    fn current_line_content_range(&self) -> Range<usize> {
+        line_content_range(&self.lines, self.offset())
+    }
+
+    // The lines keep their line endings
+    fn swap_lines(&mut self, upper: Range<usize>, lower: Range<usize>) {
+        let swapped = format!(
+            "{}{}{}",
+            &self.lines[lower.clone()],
+            &self.lines[upper.end..lower.start],
+            &self.lines[upper.clone()]
+        );
+        self.replace_range(upper.start..lower.end, &swapped);
+    }
+
     /// Swaps current grapheme with grapheme on right
     pub fn swap_graphemes(&mut self) {
         let initial_offset = self.insertion_point().offset;
@@ -621,6 +1009,21 @@ fn is_word_boundary(s: &str) -> bool {
     !s.chars().any(char::is_alphanumeric)
 }
 
+/// Range of the line containing `offset`, without its line ending
+fn line_content_range(lines: &str, offset: usize) -> Range<usize> {
+    let start = lines[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let end = lines[offset..]
+        .find('\n')
+        .map_or(lines.len(), |index| offset + index);
+    let end = if lines[start..end].ends_with('\r') {
+        end - 1
+    } else {
+        end
+    };
+
+    start..end
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -696,6 +1099,62 @@ mod test {
         line_buffer.assert_valid();
     }
 
+    #[test]
+    fn visual_movement_follows_the_shown_order() {
+        // Shown as "ab גבא"
+        let mut line_buffer = buffer_with("ab אבג");
+
+        let mut positions = vec![];
+        for _ in 0..7 {
+            line_buffer.move_left_visually();
+            positions.push(line_buffer.offset());
+        }
+        assert_eq!(positions, vec![3, 5, 7, 2, 1, 0, 0]);
+
+        positions.clear();
+        for _ in 0..7 {
+            line_buffer.move_right_visually();
+            positions.push(line_buffer.offset());
+        }
+        assert_eq!(positions, vec![1, 2, 7, 5, 3, 9, 9]);
+    }
+
+    #[test]
+    fn visual_movement_leaves_the_line_at_its_ends() {
+        let mut line_buffer = buffer_with("אב\nab");
+        line_buffer.set_insertion_point(6);
+
+        line_buffer.move_left_visually();
+        assert_eq!(line_buffer.offset(), 5);
+        line_buffer.move_left_visually();
+        assert_eq!(line_buffer.offset(), 4);
+        line_buffer.move_left_visually();
+        assert_eq!(line_buffer.offset(), 0);
+        line_buffer.move_left_visually();
+        assert_eq!(line_buffer.offset(), 2);
+    }
+
+    #[rstest]
+    #[case("cafe\u{301}", 6, "café", 5)]
+    #[case("cafe\u{301} au lait", 6, "café au lait", 5)]
+    #[case("a\u{308}ha\u{308}", 7, "ähä", 5)]
+    #[case("ls", 1, "ls", 1)]
+    fn normalize_keeps_the_cursor_after_the_same_text(
+        #[case] input: &str,
+        #[case] offset: usize,
+        #[case] expected: &str,
+        #[case] expected_offset: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(offset);
+
+        line_buffer.normalize();
+
+        assert_eq!(line_buffer.get_buffer(), expected);
+        assert_eq!(line_buffer.offset(), expected_offset);
+        line_buffer.assert_valid();
+    }
+
     #[rstest]
     #[case("This is a test", "This is a tes")]
     #[case("This is a test 😊", "This is a test ")]
@@ -803,6 +1262,94 @@ mod test {
         line_buffer.assert_valid();
     }
 
+    #[rstest]
+    #[case("this is a TEST", 11, "this is a Test", 14)]
+    #[case("this is a test", 0, "This is a test", 4)]
+    #[case("élan", 2, "Élan", 5)]
+    #[case("e\u{301}LAN", 0, "E\u{301}lan", 6)]
+    #[case("", 0, "", 0)]
+    fn capitalize_word_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.capitalize_word();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("abc", 0, "Abc", 1)]
+    #[case("ABC", 2, "ABc", 3)]
+    #[case("e\u{301}", 0, "E\u{301}", 3)]
+    #[case("ß", 0, "SS", 2)]
+    #[case("a b", 1, "a b", 2)]
+    #[case("abc", 3, "abc", 3)]
+    fn switchcase_char_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.switchcase_char();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("ls\nstraße\nls", 5, "ls\nSTRASSE\nls", 5)]
+    #[case("ls\nstraße", 10, "ls\nSTRASSE", 10)]
+    #[case("Echo", 2, "ECHO", 2)]
+    fn uppercase_line_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.uppercase_line();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("LS\nECHO", 4, "LS\necho", 4)]
+    #[case("ECHO\nLS", 4, "echo\nLS", 4)]
+    fn lowercase_line_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.lowercase_line();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
     #[rstest]
     #[case("This is a TEST", 13, "This is a test", 14)]
     #[case("This is a TEST", 10, "This is a test", 14)]
@@ -1279,4 +1826,229 @@ mod test {
         assert_eq!(expected, line_buffer);
         line_buffer.assert_valid();
     }
+
+    #[rstest]
+    #[case("a\nbc", 3, "bc\na", 1)]
+    #[case("a\nbc\nd", 4, "bc\na\nd", 2)]
+    #[case("a\n\nb", 2, "\na\nb", 0)]
+    #[case("a\r\nbc", 4, "bc\r\na", 1)]
+    #[case("a\nbc", 1, "a\nbc", 1)]
+    fn swap_line_up_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.swap_line_up();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("ab\nc", 1, "c\nab", 3)]
+    #[case("d\nab\nc", 3, "d\nc\nab", 5)]
+    #[case("ab\r\nc", 2, "c\r\nab", 5)]
+    #[case("ab\nc", 4, "ab\nc", 4)]
+    fn swap_line_down_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.swap_line_down();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("ab", 1, "ab\nab", 4)]
+    #[case("ab\ncd", 4, "ab\ncd\ncd", 7)]
+    #[case("ab\r\ncd", 1, "ab\r\nab\r\ncd", 5)]
+    #[case("", 0, "\n", 1)]
+    fn duplicate_line_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.duplicate_line();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("ls", 1, "# ls", 3)]
+    #[case("# ls", 3, "ls", 1)]
+    #[case("# ls", 1, "ls", 0)]
+    #[case("#ls", 2, "ls", 1)]
+    #[case("cd\nls", 4, "cd\n# ls", 6)]
+    #[case("cd\r\n# ls", 7, "cd\r\nls", 5)]
+    #[case("", 0, "# ", 2)]
+    fn toggle_comment_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.toggle_comment("# ");
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("ls", "# ls")]
+    #[case("cd\n# ls\r\npwd", "# cd\n# ls\r\n# pwd")]
+    #[case("#ls", "#ls")]
+    fn comment_lines_works(#[case] input: &str, #[case] output: &str) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.comment_lines("# ");
+
+        assert_eq!(buffer_with(output), line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("port 8080", 0, 1, "port 8081", 8)]
+    #[case("port 8080", 6, 1, "port 8081", 8)]
+    #[case("port 8080 9090", 9, 1, "port 8080 9091", 13)]
+    #[case("x 9", 0, 5, "x 14", 3)]
+    #[case("x 1", 0, -3, "x -2", 3)]
+    #[case("x -2", 3, 3, "x 1", 2)]
+    #[case("2021-10-15", 9, 1, "2021-10-16", 9)]
+    #[case("file007", 0, 1, "file008", 6)]
+    #[case("file009", 0, 1, "file010", 6)]
+    #[case("no number", 0, 1, "no number", 0)]
+    #[case("1\nab", 2, 1, "1\nab", 2)]
+    #[case("9223372036854775807", 0, 1, "9223372036854775807", 0)]
+    fn increment_number_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] amount: i64,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.increment_number(amount);
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("ab\ncd\nef", 1, 1, 4)]
+    #[case("ab\ncd\nef", 0, 0, 0)]
+    #[case("ab\ncd\nef", 2, 9, 8)]
+    #[case("ab\ncd\nef", 7, 1, 7)]
+    #[case("ab\r\ncd", 0, 5, 2)]
+    #[case("ab\n\ncd", 1, 3, 3)]
+    #[case("ab\nçd", 1, 1, 5)]
+    fn move_to_position_works(
+        #[case] input: &str,
+        #[case] line: usize,
+        #[case] column: usize,
+        #[case] expected: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.move_to_position(line, column);
+
+        assert_eq!(line_buffer.offset(), expected);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("abc", 1, 'x', "axc", 2, "b")]
+    #[case("abc", 3, 'x', "abcx", 4, "")]
+    #[case("ab\ncd", 2, 'x', "abx\ncd", 3, "")]
+    #[case("ab\r\ncd", 2, 'x', "abx\r\ncd", 3, "")]
+    #[case("çé", 0, 'x', "xé", 1, "ç")]
+    fn overwrite_char_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] c: char,
+        #[case] output: &str,
+        #[case] out_location: usize,
+        #[case] overwritten: &str,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+
+        assert_eq!(line_buffer.overwrite_char(c), overwritten);
+        assert_eq!(line_buffer.get_buffer(), output);
+        assert_eq!(line_buffer.offset(), out_location);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("abcd", 1, 2, 'x', "axxd", 2)]
+    #[case("abcd", 0, 1, 'x', "xbcd", 0)]
+    #[case("abcd", 2, 3, 'x', "abcd", 2)]
+    #[case("ab\ncd", 1, 2, 'x', "ab\ncd", 1)]
+    #[case("éé", 0, 2, 'x', "xx", 1)]
+    fn replace_graphemes_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] count: usize,
+        #[case] c: char,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.replace_graphemes(count, c);
+
+        assert_eq!(line_buffer.get_buffer(), output);
+        assert_eq!(line_buffer.offset(), out_location);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("a a\na a", 0, false, false, "b a\na a", 0)]
+    #[case("a a\na a", 5, false, true, "a a\nb b", 4)]
+    #[case("a a\na a\nc", 9, true, false, "b a\nb a\nc", 4)]
+    #[case("a a\r\na a", 0, true, true, "b b\r\nb b", 5)]
+    #[case("c\nc", 3, true, true, "c\nc", 3)]
+    fn substitute_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] all_lines: bool,
+        #[case] every_match: bool,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.substitute("a", "b", all_lines, every_match);
+
+        assert_eq!(line_buffer.get_buffer(), output);
+        assert_eq!(line_buffer.offset(), out_location);
+        line_buffer.assert_valid();
+    }
 }
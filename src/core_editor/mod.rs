@@ -1,6 +1,8 @@
 mod clip_buffer;
 mod editor;
 mod line_buffer;
+mod nfc;
+mod snippet;
 
 pub(crate) use clip_buffer::{get_default_clipboard, Clipboard, ClipboardMode};
 pub use editor::Editor;
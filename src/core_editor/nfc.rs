@@ -0,0 +1,55 @@
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// Brings `text` to the Unicode normalization form C, where a letter and the accents
+/// following it are stored as one precomposed character where there is one
+///
+/// Decomposed text, as pasted from the file names of macOS, looks the same as the
+/// composed one but compares differently. Borrows `text` if it is already composed.
+pub(crate) fn nfc(text: &str) -> Cow<'_, str> {
+    if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+        return Cow::Borrowed(text);
+    }
+
+    let composed: String = text.nfc().collect();
+    if composed == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(composed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("ls -la", "ls -la")]
+    #[case("cafe\u{301}", "café")]
+    #[case("A\u{30A}ngstro\u{308}m", "Ångström")]
+    // The dot below comes before the acute, whatever order they were typed in
+    #[case("e\u{301}\u{323}", "\u{1EB9}\u{301}")]
+    #[case("\u{E9}\u{323}", "\u{1EB9}\u{301}")]
+    #[case("\u{1112}\u{1161}\u{11AB}", "한")]
+    #[case("\u{304B}\u{3099}", "が")]
+    #[case("a\u{338}", "a\u{338}")]
+    #[case("=\u{338}", "≠")]
+    // Singletons and composition exclusions are decomposed
+    #[case("\u{340}", "\u{300}")]
+    #[case("a\u{37E}", "a;")]
+    #[case("\u{958}", "\u{915}\u{93C}")]
+    #[case("\u{F900}", "\u{8C48}")]
+    #[case("\u{2F800}", "\u{4E3D}")]
+    fn composes_the_text(#[case] text: &str, #[case] expected: &str) {
+        assert_eq!(nfc(text), expected);
+    }
+
+    #[test]
+    fn composed_text_is_borrowed() {
+        assert!(matches!(nfc("ls -la"), Cow::Borrowed(_)));
+        assert!(matches!(nfc("Ångström 한"), Cow::Borrowed(_)));
+        assert!(matches!(nfc("cafe\u{301}"), Cow::Owned(_)));
+    }
+}
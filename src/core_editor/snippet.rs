@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+/// Text with the placeholders to visit after inserting it, parsed from a snippet
+/// like `git checkout -b ${1:branch} ${2:origin/main}`
+///
+/// `${N:text}` is a placeholder with a default text and `${N}` an empty one. They are
+/// visited by their number, `${0}` last. Without `${0}` the cursor ends after the text.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Snippet {
+    pub text: String,
+    // Ranges of the placeholders in the text, in the order they are visited
+    pub stops: Vec<Range<usize>>,
+}
+
+impl Snippet {
+    pub fn parse(snippet: &str) -> Self {
+        let mut text = String::with_capacity(snippet.len());
+        let mut numbered_stops = Vec::new();
+        let mut rest = snippet;
+
+        while let Some(start) = rest.find("${") {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            match parse_placeholder(rest) {
+                Some((number, default, length)) => {
+                    numbered_stops.push((number, text.len()..text.len() + default.len()));
+                    text.push_str(default);
+                    rest = &rest[length..];
+                }
+                None => {
+                    text.push('$');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        text.push_str(rest);
+
+        // The final stop goes after the others
+        if !numbered_stops.iter().any(|(number, _)| *number == 0) {
+            numbered_stops.push((0, text.len()..text.len()));
+        }
+        numbered_stops.sort_by_key(|(number, _)| if *number == 0 { u32::MAX } else { *number });
+
+        Self {
+            text,
+            stops: numbered_stops.into_iter().map(|(_, range)| range).collect(),
+        }
+    }
+}
+
+/// Parses `${N:default}` or `${N}` at the start of `text`, returning the number, the
+/// default text and the length of the placeholder
+fn parse_placeholder(text: &str) -> Option<(u32, &str, usize)> {
+    let inner = text.strip_prefix("${")?;
+    let end = inner.find('}')?;
+    let inner = &inner[..end];
+    let (number, default) = inner.split_once(':').unwrap_or((inner, ""));
+    if number.is_empty() || !number.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((number.parse().ok()?, default, end + 3))
+}
+
+/// Placeholders of an inserted snippet that are still to be edited
+///
+/// The ranges are kept up to date while the current placeholder is edited. Leaving it
+/// or editing the buffer elsewhere ends the snippet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Placeholders {
+    stops: Vec<Range<usize>>,
+    current: usize,
+    // The default text of the current placeholder is replaced by the next insertion
+    replace_pending: bool,
+}
+
+impl Placeholders {
+    /// Placeholders of a snippet inserted at `offset`
+    pub fn new(snippet: &Snippet, offset: usize) -> Self {
+        let stops: Vec<Range<usize>> = snippet
+            .stops
+            .iter()
+            .map(|stop| stop.start + offset..stop.end + offset)
+            .collect();
+        let replace_pending = !stops[0].is_empty();
+
+        Self {
+            stops,
+            current: 0,
+            replace_pending,
+        }
+    }
+
+    pub fn current(&self) -> Range<usize> {
+        self.stops[self.current].clone()
+    }
+
+    /// Whether the current placeholder is the last one, so the snippet is done
+    pub fn is_last(&self) -> bool {
+        self.current + 1 == self.stops.len()
+    }
+
+    pub fn take_replace_pending(&mut self) -> bool {
+        std::mem::take(&mut self.replace_pending)
+    }
+
+    /// Moves to the next placeholder
+    pub fn advance(&mut self) {
+        self.current = (self.current + 1).min(self.stops.len() - 1);
+        self.replace_pending = !self.current().is_empty();
+    }
+
+    /// Updates the ranges after the buffer changed its length from `old_len` to
+    /// `new_len`, leaving the cursor at `cursor`
+    ///
+    /// Returns false if the change or the cursor left the current placeholder
+    pub fn update(&mut self, old_len: usize, new_len: usize, cursor: usize) -> bool {
+        let current = self.current();
+        let new_end = (current.end + new_len).checked_sub(old_len);
+
+        match new_end {
+            Some(new_end) if current.start <= cursor && cursor <= new_end => {
+                for (index, stop) in self.stops.iter_mut().enumerate() {
+                    if index != self.current && stop.start >= current.end {
+                        stop.start = stop.start + new_len - old_len;
+                        stop.end = stop.end + new_len - old_len;
+                    }
+                }
+                self.stops[self.current].end = new_end;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("ls", "ls", vec![2..2])]
+    #[case("git checkout ${1:branch}", "git checkout branch", vec![13..19, 19..19])]
+    #[case("cp ${2:to} ${1:from}", "cp to from", vec![6..10, 3..5, 10..10])]
+    #[case("cd ${0} && ${1}", "cd  && ", vec![7..7, 3..3])]
+    #[case("echo ${HOME} $${1:x", "echo ${HOME} $${1:x", vec![19..19])]
+    fn snippets_are_parsed(
+        #[case] snippet: &str,
+        #[case] text: &str,
+        #[case] stops: Vec<Range<usize>>,
+    ) {
+        assert_eq!(
+            Snippet::parse(snippet),
+            Snippet {
+                text: text.to_string(),
+                stops,
+            }
+        );
+    }
+
+    #[test]
+    fn editing_a_placeholder_moves_the_next_ones() {
+        let snippet = Snippet::parse("cp ${1:from} ${2:to}");
+        let mut placeholders = Placeholders::new(&snippet, 2);
+        assert_eq!(placeholders.current(), 5..9);
+
+        // "from" replaced by "source.txt"
+        assert!(placeholders.update(12, 18, 15));
+        assert_eq!(placeholders.current(), 5..15);
+
+        placeholders.advance();
+        assert_eq!(placeholders.current(), 16..18);
+        assert!(!placeholders.is_last());
+
+        placeholders.advance();
+        assert_eq!(placeholders.current(), 18..18);
+        assert!(placeholders.is_last());
+    }
+
+    #[test]
+    fn leaving_the_placeholder_ends_the_snippet() {
+        let snippet = Snippet::parse("cp ${1:from} ${2:to}");
+        let mut placeholders = Placeholders::new(&snippet, 0);
+
+        assert!(placeholders.update(10, 10, 4));
+        assert!(!placeholders.update(10, 10, 9));
+    }
+}
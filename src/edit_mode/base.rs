@@ -11,4 +11,16 @@ pub trait EditMode: Send {
 
     /// What to display in the prompt indicator
     fn edit_mode(&self) -> PromptEditMode;
+
+    /// Whether the last keys started a command that isn't complete yet, so
+    /// [`ReedlineEvent::None`] doesn't mean that the key has no binding
+    fn has_pending_keys(&self) -> bool {
+        false
+    }
+
+    /// The command being typed in a command line of the edit mode, like `:%s/a/b/` in
+    /// vi, shown below the buffer
+    fn command_line(&self) -> Option<String> {
+        None
+    }
 }
@@ -0,0 +1,295 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+/// A character typed by its code point, as a digraph or literally, for the characters
+/// the keyboard layout lacks and the control characters
+///
+/// - `ctrl-v` or `ctrl-q` and a key type the key literally, as `^[` for ESC or `^M`
+///   for enter
+/// - `ctrl-v u` and up to four hexadecimal digits, or `ctrl-v U` and up to eight, type
+///   the code point. A key that isn't a digit ends it early and is handled on its own
+/// - `ctrl-k` and two characters type the digraph, as `ctrl-k e '` for `é`. When the
+///   two characters aren't a digraph the second one is typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CharEntry {
+    /// The key of the quoted insert, `v` or `q`, was pressed with `ctrl`
+    Quoted(char),
+    Codepoint {
+        digits: String,
+        max_digits: usize,
+    },
+    Digraph(Option<char>),
+}
+
+/// The outcome of a key of a [`CharEntry`]
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Entered {
+    Pending(CharEntry),
+    /// The character typed, if any, and whether the key still has to be handled
+    Done(Option<char>, bool),
+}
+
+impl CharEntry {
+    /// The entry a key starts, `ctrl-v`, `ctrl-q` or `ctrl-k`
+    pub fn start(modifiers: KeyModifiers, code: KeyCode) -> Option<Self> {
+        match (modifiers, code) {
+            (KeyModifiers::CONTROL, KeyCode::Char(c @ ('v' | 'q'))) => Some(Self::Quoted(c)),
+            (KeyModifiers::CONTROL, KeyCode::Char('k')) => Some(Self::Digraph(None)),
+            _ => None,
+        }
+    }
+
+    pub fn push_key(self, modifiers: KeyModifiers, code: KeyCode) -> Entered {
+        let typed = match (modifiers, code) {
+            (KeyModifiers::NONE, KeyCode::Char(c)) => Some(c),
+            (KeyModifiers::SHIFT, KeyCode::Char(c)) => Some(c.to_ascii_uppercase()),
+            _ => None,
+        };
+
+        match (self, typed) {
+            (Self::Quoted(_), Some('u')) => Entered::Pending(Self::Codepoint {
+                digits: String::new(),
+                max_digits: 4,
+            }),
+            (Self::Quoted(_), Some('U')) => Entered::Pending(Self::Codepoint {
+                digits: String::new(),
+                max_digits: 8,
+            }),
+            (Self::Quoted(_), _) => Entered::Done(literal(modifiers, code), false),
+            (
+                Self::Codepoint {
+                    mut digits,
+                    max_digits,
+                },
+                Some(c),
+            ) if c.is_ascii_hexdigit() => {
+                digits.push(c);
+                if digits.len() == max_digits {
+                    Entered::Done(codepoint(&digits), false)
+                } else {
+                    Entered::Pending(Self::Codepoint { digits, max_digits })
+                }
+            }
+            (Self::Codepoint { digits, .. }, _) => Entered::Done(codepoint(&digits), true),
+            (Self::Digraph(None), Some(c)) => Entered::Pending(Self::Digraph(Some(c))),
+            (Self::Digraph(Some(first)), Some(c)) => {
+                Entered::Done(Some(digraph(first, c).unwrap_or(c)), false)
+            }
+            (Self::Digraph(_), None) => Entered::Done(None, false),
+        }
+    }
+
+    /// The keys typed so far, shown below the buffer
+    pub fn display(&self) -> String {
+        match self {
+            Self::Quoted(c) => format!("^{}", c.to_ascii_uppercase()),
+            Self::Codepoint { digits, .. } => format!("U+{}", digits.to_ascii_uppercase()),
+            Self::Digraph(None) => "^K".to_string(),
+            Self::Digraph(Some(first)) => format!("^K {}", first),
+        }
+    }
+}
+
+/// The character a key sends to the terminal, with `ctrl` for the control characters
+fn literal(modifiers: KeyModifiers, code: KeyCode) -> Option<char> {
+    match (modifiers, code) {
+        (KeyModifiers::NONE, KeyCode::Char(c)) => Some(c),
+        (KeyModifiers::SHIFT, KeyCode::Char(c)) => Some(c.to_ascii_uppercase()),
+        (KeyModifiers::CONTROL, KeyCode::Char(' ')) => Some('\0'),
+        (KeyModifiers::CONTROL, KeyCode::Char('?')) => Some('\x7f'),
+        (KeyModifiers::CONTROL, KeyCode::Char(c)) => match c.to_ascii_uppercase() {
+            c @ '@'..='_' => Some(char::from(c as u8 - 0x40)),
+            _ => None,
+        },
+        (KeyModifiers::NONE, KeyCode::Esc) => Some('\x1b'),
+        (KeyModifiers::NONE, KeyCode::Enter) => Some('\r'),
+        (KeyModifiers::NONE, KeyCode::Tab) => Some('\t'),
+        (KeyModifiers::NONE, KeyCode::Backspace) => Some('\x7f'),
+        _ => None,
+    }
+}
+
+fn codepoint(digits: &str) -> Option<char> {
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+/// The character of a digraph, in either order. The uppercase version of a letter
+/// is also typed with uppercase keys, as `A:` for `Ä`
+fn digraph(first: char, second: char) -> Option<char> {
+    let find = |first: char, second: char| {
+        DIGRAPHS
+            .iter()
+            .find(|(a, b, _)| (*a, *b) == (first, second) || (*a, *b) == (second, first))
+            .map(|(_, _, c)| *c)
+    };
+
+    find(first, second).or_else(|| {
+        if !first.is_uppercase() && !second.is_uppercase() {
+            return None;
+        }
+        let lowercase = find(first.to_ascii_lowercase(), second.to_ascii_lowercase())?;
+        let mut uppercase = lowercase.to_uppercase();
+        match (uppercase.next(), uppercase.next()) {
+            (Some(c), None) if c != lowercase => Some(c),
+            _ => None,
+        }
+    })
+}
+
+/// A part of the digraphs of RFC 1345, as vim types them
+const DIGRAPHS: &[(char, char, char)] = &[
+    ('a', '!', 'à'),
+    ('a', '\'', 'á'),
+    ('a', '>', 'â'),
+    ('a', '?', 'ã'),
+    ('a', ':', 'ä'),
+    ('a', 'a', 'å'),
+    ('a', 'e', 'æ'),
+    ('c', ',', 'ç'),
+    ('e', '!', 'è'),
+    ('e', '\'', 'é'),
+    ('e', '>', 'ê'),
+    ('e', ':', 'ë'),
+    ('i', '!', 'ì'),
+    ('i', '\'', 'í'),
+    ('i', '>', 'î'),
+    ('i', ':', 'ï'),
+    ('n', '?', 'ñ'),
+    ('o', '!', 'ò'),
+    ('o', '\'', 'ó'),
+    ('o', '>', 'ô'),
+    ('o', '?', 'õ'),
+    ('o', ':', 'ö'),
+    ('o', '/', 'ø'),
+    ('u', '!', 'ù'),
+    ('u', '\'', 'ú'),
+    ('u', '>', 'û'),
+    ('u', ':', 'ü'),
+    ('y', '\'', 'ý'),
+    ('y', ':', 'ÿ'),
+    ('s', 's', 'ß'),
+    ('a', '*', 'α'),
+    ('b', '*', 'β'),
+    ('g', '*', 'γ'),
+    ('d', '*', 'δ'),
+    ('e', '*', 'ε'),
+    ('l', '*', 'λ'),
+    ('m', '*', 'μ'),
+    ('p', '*', 'π'),
+    ('s', '*', 'σ'),
+    ('w', '*', 'ω'),
+    ('E', 'u', '€'),
+    ('P', 'd', '£'),
+    ('C', 't', '¢'),
+    ('Y', 'e', '¥'),
+    ('C', 'o', '©'),
+    ('R', 'g', '®'),
+    ('T', 'M', '™'),
+    ('S', 'E', '§'),
+    ('P', 'I', '¶'),
+    ('D', 'G', '°'),
+    ('M', 'y', 'µ'),
+    ('+', '-', '±'),
+    ('*', 'X', '×'),
+    ('-', ':', '÷'),
+    ('!', '=', '≠'),
+    ('=', '<', '≤'),
+    ('>', '=', '≥'),
+    ('0', '0', '∞'),
+    ('1', '2', '½'),
+    ('1', '4', '¼'),
+    ('3', '4', '¾'),
+    ('1', 'S', '¹'),
+    ('2', 'S', '²'),
+    ('3', 'S', '³'),
+    ('<', '<', '«'),
+    ('>', '>', '»'),
+    ('!', 'I', '¡'),
+    ('?', 'I', '¿'),
+    ('-', '>', '→'),
+    ('<', '-', '←'),
+    ('-', '!', '↑'),
+    ('-', 'v', '↓'),
+    ('=', '>', '⇒'),
+    ('=', '=', '⇔'),
+    ('-', 'N', '–'),
+    ('-', 'M', '—'),
+    ('.', 'M', '·'),
+    ('\'', '6', '‘'),
+    ('\'', '9', '’'),
+    ('"', '6', '“'),
+    ('"', '9', '”'),
+    ('O', 'K', '✓'),
+    ('X', 'X', '✗'),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn enter(start: char, typed: &str) -> Entered {
+        let mut entry = CharEntry::start(KeyModifiers::CONTROL, KeyCode::Char(start)).unwrap();
+        let mut keys = typed.chars().peekable();
+        while let Some(c) = keys.next() {
+            match entry.push_key(KeyModifiers::NONE, KeyCode::Char(c)) {
+                Entered::Pending(pending) => entry = pending,
+                done => {
+                    assert!(keys.peek().is_none(), "keys left after {:?}", done);
+                    return done;
+                }
+            }
+        }
+        Entered::Pending(entry)
+    }
+
+    #[rstest]
+    #[case('v', "u00e9", Entered::Done(Some('é'), false))]
+    #[case('v', "U0001f600", Entered::Done(Some('😀'), false))]
+    #[case('v', "U1F600 ", Entered::Done(Some('😀'), true))]
+    #[case('v', "ud800", Entered::Done(None, false))]
+    #[case('v', "x", Entered::Done(Some('x'), false))]
+    #[case('q', "u", Entered::Pending(CharEntry::Codepoint { digits: String::new(), max_digits: 4 }))]
+    #[case('k', "e'", Entered::Done(Some('é'), false))]
+    #[case('k', "'e", Entered::Done(Some('é'), false))]
+    #[case('k', "O:", Entered::Done(Some('Ö'), false))]
+    #[case('k', "Eu", Entered::Done(Some('€'), false))]
+    #[case('k', "a*", Entered::Done(Some('α'), false))]
+    #[case('k', "qz", Entered::Done(Some('z'), false))]
+    fn characters_are_entered(#[case] start: char, #[case] typed: &str, #[case] expected: Entered) {
+        assert_eq!(enter(start, typed), expected);
+    }
+
+    #[rstest]
+    #[case(KeyModifiers::NONE, KeyCode::Esc, Some('\x1b'))]
+    #[case(KeyModifiers::NONE, KeyCode::Enter, Some('\r'))]
+    #[case(KeyModifiers::CONTROL, KeyCode::Char('a'), Some('\x01'))]
+    #[case(KeyModifiers::CONTROL, KeyCode::Char('['), Some('\x1b'))]
+    #[case(KeyModifiers::CONTROL, KeyCode::Char('?'), Some('\x7f'))]
+    #[case(KeyModifiers::SHIFT, KeyCode::Char('x'), Some('X'))]
+    #[case(KeyModifiers::CONTROL, KeyCode::Char('1'), None)]
+    #[case(KeyModifiers::NONE, KeyCode::Left, None)]
+    fn quoted_keys_are_typed_literally(
+        #[case] modifiers: KeyModifiers,
+        #[case] code: KeyCode,
+        #[case] expected: Option<char>,
+    ) {
+        let entry = CharEntry::start(KeyModifiers::CONTROL, KeyCode::Char('q')).unwrap();
+        assert_eq!(entry.display(), "^Q");
+        assert_eq!(
+            entry.push_key(modifiers, code),
+            Entered::Done(expected, false)
+        );
+    }
+
+    #[test]
+    fn entry_in_progress_is_displayed() {
+        let entry = match enter('v', "u1f") {
+            Entered::Pending(entry) => entry,
+            done => panic!("entry ended with {:?}", done),
+        };
+        assert_eq!(entry.display(), "U+1F");
+    }
+}
@@ -1,9 +1,11 @@
 use super::{
+    char_entry::{CharEntry, Entered},
     keybindings::{add_common_keybindings, edit_bind, Keybindings},
     EditMode,
 };
 use crate::{
     enums::{EditCommand, ReedlineEvent},
+    macros::EMACS_REGISTER,
     PromptEditMode,
 };
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
@@ -23,7 +25,7 @@ pub fn default_emacs_keybindings() -> Keybindings {
     kb.add_binding(KM::CONTROL, KC::Char('a'), edit_bind(EC::MoveToLineStart));
     kb.add_binding(KM::CONTROL, KC::Char('e'), edit_bind(EC::MoveToLineEnd));
     kb.add_binding(KM::CONTROL, KC::Char('k'), edit_bind(EC::CutToEnd));
-    kb.add_binding(KM::CONTROL, KC::Char('u'), edit_bind(EC::CutFromStart));
+    kb.add_binding(KM::CONTROL, KC::Char('u'), ReedlineEvent::UniversalArgument);
     kb.add_binding(
         KM::CONTROL,
         KC::Char('y'),
@@ -34,22 +36,32 @@ pub fn default_emacs_keybindings() -> Keybindings {
     kb.add_binding(KM::CONTROL, KC::Char('t'), edit_bind(EC::SwapGraphemes));
 
     // ALT
-    kb.add_binding(KM::ALT, KC::Left, edit_bind(EC::MoveWordLeft));
     kb.add_binding(KM::ALT, KC::Delete, edit_bind(EC::DeleteWord));
     kb.add_binding(KM::ALT, KC::Backspace, edit_bind(EC::BackspaceWord));
+    kb.add_binding(KM::ALT, KC::Char('b'), edit_bind(EC::MoveWordLeft));
     kb.add_binding(
         KM::ALT,
-        KC::Right,
+        KC::Char('f'),
         ReedlineEvent::UntilFound(vec![
             ReedlineEvent::HistoryHintWordComplete,
             edit_bind(EC::MoveWordRight),
         ]),
     );
-    kb.add_binding(KM::ALT, KC::Char('b'), edit_bind(EC::MoveWordLeft));
     kb.add_binding(KM::ALT, KC::Char('d'), edit_bind(EC::CutWordRight));
     kb.add_binding(KM::ALT, KC::Char('u'), edit_bind(EC::UppercaseWord));
     kb.add_binding(KM::ALT, KC::Char('l'), edit_bind(EC::LowercaseWord));
-    kb.add_binding(KM::ALT, KC::Char('c'), edit_bind(EC::CapitalizeChar));
+    kb.add_binding(KM::ALT, KC::Char('c'), edit_bind(EC::CapitalizeWord));
+    // Depending on the terminal `#` arrives with or without shift
+    for modifiers in [KM::ALT, KM::ALT | KM::SHIFT] {
+        kb.add_binding(
+            modifiers,
+            KC::Char('#'),
+            ReedlineEvent::CommentAndAccept("# ".to_string()),
+        );
+    }
+    for modifiers in [KM::ALT, KM::ALT | KM::SHIFT] {
+        kb.add_binding(modifiers, KC::Char('%'), ReedlineEvent::SearchReplace);
+    }
     kb.add_binding(
         KM::ALT,
         KC::Char('m'),
@@ -61,15 +73,49 @@ pub fn default_emacs_keybindings() -> Keybindings {
     kb
 }
 
+// Larger numeric arguments are cut down, a repeated `ctrl-u` grows them quickly
+const MAX_ARGUMENT: usize = 10_000;
+
 /// This parses the incoming Events like a emacs style-editor
+///
+/// A numeric argument repeats the next command: `alt-3 ctrl-d` deletes three
+/// characters. The digits are typed with `alt` or after `ctrl-u`, which on its own
+/// stands for four and multiplies the argument by four each time it is pressed.
+///
+/// Unless `ctrl-x` is bound, `ctrl-x (` starts recording a keyboard macro, `ctrl-x )`
+/// stops it and `ctrl-x e` replays it. The macro is kept in the register `"` of the
+/// vi macros.
+///
+/// Unless they are bound, `ctrl-v` and `ctrl-q` type the next key literally, as `^[`
+/// for ESC, and `ctrl-v u 1F600` types a character by its code point.
 pub struct Emacs {
     keybindings: Keybindings,
+    argument: Option<NumericArgument>,
+    // `ctrl-x` was pressed and the key of the macro command follows
+    macro_prefix: bool,
+    char_entry: Option<CharEntry>,
+}
+
+/// The numeric argument being typed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NumericArgument {
+    digits: Option<usize>,
+    universal: usize,
+}
+
+impl NumericArgument {
+    fn count(&self) -> usize {
+        self.digits.unwrap_or(self.universal)
+    }
 }
 
 impl Default for Emacs {
     fn default() -> Self {
         Emacs {
             keybindings: default_emacs_keybindings(),
+            argument: None,
+            macro_prefix: false,
+            char_entry: None,
         }
     }
 }
@@ -77,37 +123,48 @@ impl Default for Emacs {
 impl EditMode for Emacs {
     fn parse_event(&mut self, event: Event) -> ReedlineEvent {
         match event {
-            Event::Key(KeyEvent { code, modifiers }) => match (modifiers, code) {
-                (modifier, KeyCode::Char(c)) => {
-                    // Note. The modifier can also be a combination of modifiers, for
-                    // example:
-                    //     KeyModifiers::CONTROL | KeyModifiers::ALT
-                    //     KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
-                    //
-                    // Mixed modifiers are used by non american keyboards that have extra
-                    // keys like 'alt gr'. Keep this in mind if in the future there are
-                    // cases where an event is not being captured
-                    if modifier == KeyModifiers::SHIFT {
-                        let char = c.to_ascii_uppercase();
-                        ReedlineEvent::Edit(vec![EditCommand::InsertChar(char)])
-                    } else if modifier == KeyModifiers::NONE
-                        || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT
-                        || modifier
-                            == KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
-                    {
-                        ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)])
-                    } else {
-                        self.keybindings
-                            .find_binding(modifier, code)
-                            .unwrap_or(ReedlineEvent::None)
+            Event::Key(KeyEvent { code, modifiers }) => {
+                if let Some(entry) = self.char_entry.take() {
+                    return self.enter_char(entry, modifiers, code);
+                }
+                if std::mem::take(&mut self.macro_prefix) {
+                    let event = macro_event(modifiers, code);
+                    return match self.argument.take() {
+                        Some(argument) => repeated(event, argument.count()),
+                        None => event,
+                    };
+                }
+                if self.push_digit(modifiers, code) {
+                    return ReedlineEvent::None;
+                }
+                if (modifiers, code) == (KeyModifiers::CONTROL, KeyCode::Char('x'))
+                    && self.keybindings.find_binding(modifiers, code).is_none()
+                {
+                    self.macro_prefix = true;
+                    return ReedlineEvent::None;
+                }
+                if self.keybindings.find_binding(modifiers, code).is_none() {
+                    if let Some(entry) = CharEntry::start(modifiers, code) {
+                        self.char_entry = Some(entry);
+                        return ReedlineEvent::None;
                     }
                 }
-                (KeyModifiers::NONE, KeyCode::Enter) => ReedlineEvent::Enter,
-                _ => self
-                    .keybindings
-                    .find_binding(modifiers, code)
-                    .unwrap_or(ReedlineEvent::None),
-            },
+
+                match self.parse_key(modifiers, code) {
+                    ReedlineEvent::UniversalArgument => {
+                        let argument = self.argument.get_or_insert(NumericArgument {
+                            digits: None,
+                            universal: 1,
+                        });
+                        argument.universal = (argument.universal * 4).min(MAX_ARGUMENT);
+                        ReedlineEvent::None
+                    }
+                    event => match self.argument.take() {
+                        Some(argument) => repeated(event, argument.count()),
+                        None => event,
+                    },
+                }
+            }
 
             Event::Mouse(_) => ReedlineEvent::Mouse,
             Event::Resize(width, height) => ReedlineEvent::Resize(width, height),
@@ -117,12 +174,158 @@ impl EditMode for Emacs {
     fn edit_mode(&self) -> PromptEditMode {
         PromptEditMode::Emacs
     }
+
+    fn has_pending_keys(&self) -> bool {
+        self.argument.is_some() || self.macro_prefix || self.char_entry.is_some()
+    }
+
+    fn command_line(&self) -> Option<String> {
+        match &self.char_entry {
+            Some(entry) => Some(entry.display()),
+            None => self
+                .argument
+                .map(|argument| format!("(arg: {})", argument.count())),
+        }
+    }
 }
 
 impl Emacs {
     /// Emacs style input parsing constructor if you want to use custom keybindings
     pub fn new(keybindings: Keybindings) -> Self {
-        Emacs { keybindings }
+        Emacs {
+            keybindings,
+            argument: None,
+            macro_prefix: false,
+            char_entry: None,
+        }
+    }
+
+    /// Adds the digit to the numeric argument, for `alt` and a digit without a binding
+    /// or a digit after `ctrl-u`. Returns false if the key isn't part of the argument
+    fn push_digit(&mut self, modifiers: KeyModifiers, code: KeyCode) -> bool {
+        let digit = match code {
+            KeyCode::Char(c) => c.to_digit(10),
+            _ => None,
+        };
+        let digit = match digit {
+            Some(digit) => digit as usize,
+            None => return false,
+        };
+        let is_argument = match modifiers {
+            KeyModifiers::ALT => self.keybindings.find_binding(modifiers, code).is_none(),
+            KeyModifiers::NONE => self.argument.is_some(),
+            _ => false,
+        };
+        if !is_argument {
+            return false;
+        }
+
+        let argument = self.argument.get_or_insert(NumericArgument {
+            digits: None,
+            universal: 1,
+        });
+        let digits = argument.digits.unwrap_or(0);
+        argument.digits = Some((digits * 10 + digit).min(MAX_ARGUMENT));
+        true
+    }
+
+    /// Passes the key to the character being typed by its code point. The numeric
+    /// argument repeats the character
+    fn enter_char(
+        &mut self,
+        entry: CharEntry,
+        modifiers: KeyModifiers,
+        code: KeyCode,
+    ) -> ReedlineEvent {
+        let (c, handle_key) = match entry.push_key(modifiers, code) {
+            Entered::Pending(entry) => {
+                self.char_entry = Some(entry);
+                return ReedlineEvent::None;
+            }
+            Entered::Done(c, handle_key) => (c, handle_key),
+        };
+
+        let count = self.argument.take().map_or(1, |argument| argument.count());
+        let mut events: Vec<ReedlineEvent> = c
+            .map(|c| ReedlineEvent::Edit(vec![EditCommand::InsertChar(c); count]))
+            .into_iter()
+            .collect();
+        if handle_key {
+            events.push(self.parse_event(Event::Key(KeyEvent::new(code, modifiers))));
+        }
+        match events.len() {
+            0 => ReedlineEvent::None,
+            1 => events.remove(0),
+            _ => ReedlineEvent::Multiple(events),
+        }
+    }
+
+    fn parse_key(&self, modifiers: KeyModifiers, code: KeyCode) -> ReedlineEvent {
+        match (modifiers, code) {
+            (modifier, KeyCode::Char(c)) => {
+                // Note. The modifier can also be a combination of modifiers, for
+                // example:
+                //     KeyModifiers::CONTROL | KeyModifiers::ALT
+                //     KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+                //
+                // Mixed modifiers are used by non american keyboards that have extra
+                // keys like 'alt gr'. Keep this in mind if in the future there are
+                // cases where an event is not being captured
+                if modifier == KeyModifiers::SHIFT {
+                    let char = c.to_ascii_uppercase();
+                    ReedlineEvent::Edit(vec![EditCommand::InsertChar(char)])
+                } else if modifier == KeyModifiers::NONE
+                    || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT
+                    || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+                {
+                    ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)])
+                } else {
+                    self.keybindings
+                        .find_binding(modifier, code)
+                        .unwrap_or(ReedlineEvent::None)
+                }
+            }
+            (KeyModifiers::NONE, KeyCode::Enter) => ReedlineEvent::Enter,
+            _ => self
+                .keybindings
+                .find_binding(modifiers, code)
+                .unwrap_or(ReedlineEvent::None),
+        }
+    }
+}
+
+/// The macro command of the key after `ctrl-x`
+fn macro_event(modifiers: KeyModifiers, code: KeyCode) -> ReedlineEvent {
+    if modifiers != KeyModifiers::NONE && modifiers != KeyModifiers::SHIFT {
+        return ReedlineEvent::None;
+    }
+
+    match code {
+        KeyCode::Char('(') => ReedlineEvent::StartMacro(EMACS_REGISTER),
+        KeyCode::Char(')') => ReedlineEvent::EndMacro,
+        KeyCode::Char('e') => ReedlineEvent::ReplayMacro(EMACS_REGISTER),
+        _ => ReedlineEvent::None,
+    }
+}
+
+/// The event repeated for a numeric argument. Edits and moves are repeated, other
+/// events like enter happen once
+fn repeated(event: ReedlineEvent, count: usize) -> ReedlineEvent {
+    match event {
+        ReedlineEvent::Edit(commands) => {
+            let len = commands.len() * count;
+            ReedlineEvent::Edit(commands.into_iter().cycle().take(len).collect())
+        }
+        // Deleting stops at the end of the buffer instead of exiting
+        ReedlineEvent::CtrlD => ReedlineEvent::Edit(vec![EditCommand::Delete; count]),
+        event @ (ReedlineEvent::Left
+        | ReedlineEvent::Right
+        | ReedlineEvent::Up
+        | ReedlineEvent::Down
+        | ReedlineEvent::UntilFound(_)
+        | ReedlineEvent::Multiple(_)
+        | ReedlineEvent::ReplayMacro(_)) => ReedlineEvent::Multiple(vec![event; count]),
+        event => event,
     }
 }
 
@@ -130,6 +333,7 @@ impl Emacs {
 mod test {
     use super::*;
     use pretty_assertions::assert_eq;
+    use rstest::rstest;
 
     #[test]
     fn ctrl_l_leads_to_clear_screen_event() {
@@ -143,6 +347,17 @@ mod test {
         assert_eq!(result, ReedlineEvent::ClearScreen);
     }
 
+    #[test]
+    fn backtab_selects_the_previous_menu_value() {
+        let mut emacs = Emacs::default();
+        let backtab = Event::Key(KeyEvent {
+            modifiers: KeyModifiers::SHIFT,
+            code: KeyCode::BackTab,
+        });
+
+        assert_eq!(emacs.parse_event(backtab), ReedlineEvent::MenuPrevious);
+    }
+
     #[test]
     fn overriding_default_keybindings_works() {
         let mut keybindings = default_emacs_keybindings();
@@ -162,6 +377,23 @@ mod test {
         assert_eq!(result, ReedlineEvent::HistoryHintComplete);
     }
 
+    #[rstest]
+    #[case(KeyModifiers::ALT, KeyCode::Right)]
+    #[case(KeyModifiers::ALT, KeyCode::Char('f'))]
+    #[case(KeyModifiers::CONTROL, KeyCode::Right)]
+    fn word_right_accepts_hint_word(#[case] modifiers: KeyModifiers, #[case] code: KeyCode) {
+        let mut emacs = Emacs::default();
+        let result = emacs.parse_event(Event::Key(KeyEvent { modifiers, code }));
+
+        assert_eq!(
+            result,
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::HistoryHintWordComplete,
+                ReedlineEvent::Edit(vec![EditCommand::MoveWordRight]),
+            ])
+        );
+    }
+
     #[test]
     fn inserting_character_works() {
         let mut emacs = Emacs::default();
@@ -222,4 +454,129 @@ mod test {
             ReedlineEvent::Edit(vec![EditCommand::InsertChar('😀')])
         );
     }
+
+    fn key(modifiers: KeyModifiers, c: char) -> Event {
+        Event::Key(KeyEvent {
+            modifiers,
+            code: KeyCode::Char(c),
+        })
+    }
+
+    #[rstest]
+    #[case(&[(KeyModifiers::ALT, '3')], 3)]
+    #[case(&[(KeyModifiers::ALT, '1'), (KeyModifiers::ALT, '2')], 12)]
+    #[case(&[(KeyModifiers::ALT, '1'), (KeyModifiers::NONE, '5')], 15)]
+    #[case(&[(KeyModifiers::CONTROL, 'u')], 4)]
+    #[case(&[(KeyModifiers::CONTROL, 'u'), (KeyModifiers::CONTROL, 'u')], 16)]
+    #[case(&[(KeyModifiers::CONTROL, 'u'), (KeyModifiers::NONE, '7')], 7)]
+    fn numeric_argument_repeats_deletion(
+        #[case] keys: &[(KeyModifiers, char)],
+        #[case] count: usize,
+    ) {
+        let mut emacs = Emacs::default();
+        for (modifiers, c) in keys {
+            assert_eq!(emacs.parse_event(key(*modifiers, *c)), ReedlineEvent::None);
+        }
+        assert!(emacs.has_pending_keys());
+        assert_eq!(emacs.command_line(), Some(format!("(arg: {})", count)));
+
+        let result = emacs.parse_event(key(KeyModifiers::CONTROL, 'd'));
+
+        assert_eq!(
+            result,
+            ReedlineEvent::Edit(vec![EditCommand::Delete; count])
+        );
+        assert!(!emacs.has_pending_keys());
+        assert_eq!(emacs.command_line(), None);
+    }
+
+    #[test]
+    fn numeric_argument_repeats_moves_and_typing() {
+        let left = Event::Key(KeyEvent {
+            modifiers: KeyModifiers::NONE,
+            code: KeyCode::Left,
+        });
+        let mut emacs = Emacs::default();
+        let move_left = emacs.parse_event(left);
+
+        emacs.parse_event(key(KeyModifiers::ALT, '2'));
+        let result = emacs.parse_event(left);
+        assert_eq!(
+            result,
+            ReedlineEvent::Multiple(vec![move_left.clone(), move_left])
+        );
+
+        emacs.parse_event(key(KeyModifiers::CONTROL, 'u'));
+        let result = emacs.parse_event(key(KeyModifiers::NONE, 'x'));
+        assert_eq!(
+            result,
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('x'); 4])
+        );
+
+        emacs.parse_event(key(KeyModifiers::ALT, '2'));
+        let result = emacs.parse_event(Event::Key(KeyEvent {
+            modifiers: KeyModifiers::NONE,
+            code: KeyCode::Enter,
+        }));
+        assert_eq!(result, ReedlineEvent::Enter);
+    }
+
+    #[test]
+    fn bound_alt_digit_is_not_an_argument() {
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::ALT,
+            KeyCode::Char('1'),
+            ReedlineEvent::ClearScreen,
+        );
+        let mut emacs = Emacs::new(keybindings);
+
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::ALT, '1')),
+            ReedlineEvent::ClearScreen
+        );
+        assert!(!emacs.has_pending_keys());
+    }
+
+    #[rstest]
+    #[case('(', ReedlineEvent::StartMacro('"'))]
+    #[case(')', ReedlineEvent::EndMacro)]
+    #[case('e', ReedlineEvent::ReplayMacro('"'))]
+    #[case('z', ReedlineEvent::None)]
+    fn ctrl_x_starts_macro_commands(#[case] c: char, #[case] expected: ReedlineEvent) {
+        let mut emacs = Emacs::default();
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::CONTROL, 'x')),
+            ReedlineEvent::None
+        );
+        assert!(emacs.has_pending_keys());
+
+        assert_eq!(emacs.parse_event(key(KeyModifiers::SHIFT, c)), expected);
+        assert!(!emacs.has_pending_keys());
+    }
+
+    #[test]
+    fn ctrl_v_u_types_a_code_point() {
+        let mut emacs = Emacs::default();
+        let keys = [
+            key(KeyModifiers::CONTROL, 'v'),
+            key(KeyModifiers::NONE, 'u'),
+            key(KeyModifiers::NONE, 'e'),
+            key(KeyModifiers::NONE, '9'),
+        ];
+        for key in keys {
+            assert_eq!(emacs.parse_event(key), ReedlineEvent::None);
+        }
+        assert_eq!(emacs.command_line(), Some("U+E9".to_string()));
+
+        // A key that isn't a digit ends the code point and is typed too
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::NONE, 'x')),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('é')]),
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('x')]),
+            ])
+        );
+        assert!(!emacs.has_pending_keys());
+    }
 }
@@ -1,16 +1,154 @@
 use {
-    crate::{enums::ReedlineEvent, EditCommand},
+    crate::{enums::ReedlineEvent, EditCommand, ReedlineError},
     crossterm::event::{KeyCode, KeyModifiers},
-    serde::{Deserialize, Serialize},
-    std::collections::HashMap,
+    serde::{de, Deserialize, Deserializer, Serialize, Serializer},
+    std::{collections::HashMap, fmt::Display, str::FromStr},
 };
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+// Names of the modifiers in the config format, in the order they are written
+const MODIFIER_NAMES: [(KeyModifiers, &str); 3] = [
+    (KeyModifiers::CONTROL, "ctrl"),
+    (KeyModifiers::ALT, "alt"),
+    (KeyModifiers::SHIFT, "shift"),
+];
+
+// Names of the keys that are not a character in the config format
+const KEY_NAMES: [(KeyCode, &str); 16] = [
+    (KeyCode::Backspace, "backspace"),
+    (KeyCode::Enter, "enter"),
+    (KeyCode::Left, "left"),
+    (KeyCode::Right, "right"),
+    (KeyCode::Up, "up"),
+    (KeyCode::Down, "down"),
+    (KeyCode::Home, "home"),
+    (KeyCode::End, "end"),
+    (KeyCode::PageUp, "pageup"),
+    (KeyCode::PageDown, "pagedown"),
+    (KeyCode::Tab, "tab"),
+    (KeyCode::BackTab, "backtab"),
+    (KeyCode::Delete, "delete"),
+    (KeyCode::Insert, "insert"),
+    (KeyCode::Esc, "esc"),
+    (KeyCode::Null, "null"),
+];
+
+// Highest function key that can be bound
+const MAX_FUNCTION_KEY: u8 = 24;
+
+/// Key with its modifiers that triggers a binding
+///
+/// In the config format it is written as the modifiers followed by the key, joined
+/// by `-`, for example `ctrl-alt-f5`, `shift-insert`, `alt-b` or `space`. The keys
+/// that are not characters use their lowercase names and the function keys go from
+/// `f1` to `f24`. The keys of the keypad arrive as the navigation keys or the
+/// characters they stand for, so they are bound with those names.
+///
+/// ## Example
+///
+/// ```rust
+/// use crossterm::event::{KeyCode, KeyModifiers};
+/// use reedline::KeyCombination;
+///
+/// let combination: KeyCombination = "ctrl-f5".parse()?;
+/// assert_eq!(combination.modifier, KeyModifiers::CONTROL);
+/// assert_eq!(combination.key_code, KeyCode::F(5));
+/// assert_eq!(combination.to_string(), "ctrl-f5");
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct KeyCombination {
+    /// Modifiers held with the key
     pub modifier: KeyModifiers,
+    /// Key that is pressed
     pub key_code: KeyCode,
 }
 
+impl Display for KeyCombination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in MODIFIER_NAMES {
+            if self.modifier.contains(modifier) {
+                write!(f, "{}-", name)?;
+            }
+        }
+
+        match self.key_code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            key_code => {
+                let name = KEY_NAMES
+                    .iter()
+                    .find(|(code, _)| *code == key_code)
+                    .map_or("null", |(_, name)| name);
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl FromStr for KeyCombination {
+    type Err = ReedlineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ReedlineError::Config(format!("invalid key combination: {:?}", s));
+
+        let mut modifier = KeyModifiers::NONE;
+        let mut key = s;
+        // The key itself can be `-`, so only the names before a `-` are modifiers
+        while let Some((name, rest)) = key.split_once('-') {
+            let name = name.to_ascii_lowercase();
+            let found = match name.as_str() {
+                "control" => Some(KeyModifiers::CONTROL),
+                name => MODIFIER_NAMES
+                    .iter()
+                    .find(|(_, modifier_name)| *modifier_name == name)
+                    .map(|(modifier, _)| *modifier),
+            };
+            match found {
+                Some(found) if !rest.is_empty() => {
+                    modifier |= found;
+                    key = rest;
+                }
+                _ => break,
+            }
+        }
+
+        let mut chars = key.chars();
+        let key_code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let name = key.to_ascii_lowercase();
+                match name.strip_prefix('f').map(str::parse::<u8>) {
+                    Some(Ok(n)) if (1..=MAX_FUNCTION_KEY).contains(&n) => KeyCode::F(n),
+                    Some(Ok(_)) => return Err(invalid()),
+                    _ if name == "space" => KeyCode::Char(' '),
+                    _ => KEY_NAMES
+                        .iter()
+                        .find(|(_, key_name)| *key_name == name)
+                        .map(|(code, _)| *code)
+                        .ok_or_else(invalid)?,
+                }
+            }
+        };
+
+        Ok(Self { modifier, key_code })
+    }
+}
+
+// Written as a string so the combinations can be the keys of a map in the config
+impl Serialize for KeyCombination {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyCombination {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let combination = String::deserialize(deserializer)?;
+        combination.parse().map_err(de::Error::custom)
+    }
+}
+
 /// Main definition of editor keybindings
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Keybindings {
@@ -79,12 +217,36 @@ pub fn add_common_keybindings(kb: &mut Keybindings) {
     kb.add_binding(KM::NONE, KC::Esc, ReedlineEvent::Esc);
     kb.add_binding(KM::NONE, KC::Backspace, edit_bind(EC::Backspace));
     kb.add_binding(KM::NONE, KC::Delete, edit_bind(EC::Delete));
-    kb.add_binding(KM::NONE, KC::End, edit_bind(EC::MoveToLineEnd));
-    kb.add_binding(KM::NONE, KC::Home, edit_bind(EC::MoveToLineStart));
+    kb.add_binding(
+        KM::NONE,
+        KC::End,
+        ReedlineEvent::UntilFound(vec![ReedlineEvent::MenuLast, edit_bind(EC::MoveToLineEnd)]),
+    );
+    kb.add_binding(
+        KM::NONE,
+        KC::Home,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::MenuFirst,
+            edit_bind(EC::MoveToLineStart),
+        ]),
+    );
+
+    // Terminals send shift-tab with or without the shift modifier
+    kb.add_binding(KM::SHIFT, KC::BackTab, ReedlineEvent::MenuPrevious);
+    kb.add_binding(KM::NONE, KC::BackTab, ReedlineEvent::MenuPrevious);
 
     kb.add_binding(KM::CONTROL, KC::Char('c'), ReedlineEvent::CtrlC);
     kb.add_binding(KM::CONTROL, KC::Char('l'), ReedlineEvent::ClearScreen);
     kb.add_binding(KM::CONTROL, KC::Char('r'), ReedlineEvent::SearchHistory);
+    kb.add_binding(KM::ALT, KC::Char('p'), ReedlineEvent::HistoryTogglePin);
+    kb.add_binding(
+        KM::CONTROL,
+        KC::Char(' '),
+        ReedlineEvent::Multiple(vec![
+            ReedlineEvent::SkipAbbreviation,
+            edit_bind(EC::InsertChar(' ')),
+        ]),
+    );
 
     kb.add_binding(
         KM::CONTROL,
@@ -95,6 +257,15 @@ pub fn add_common_keybindings(kb: &mut Keybindings) {
         ]),
     );
     kb.add_binding(KM::CONTROL, KC::Left, edit_bind(EC::MoveWordLeft));
+    kb.add_binding(
+        KM::ALT,
+        KC::Right,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::HistoryHintWordComplete,
+            edit_bind(EC::MoveWordRight),
+        ]),
+    );
+    kb.add_binding(KM::ALT, KC::Left, edit_bind(EC::MoveWordLeft));
 
     kb.add_binding(
         KM::NONE,
@@ -146,3 +317,83 @@ pub fn add_common_keybindings(kb: &mut Keybindings) {
         ReedlineEvent::UntilFound(vec![ReedlineEvent::MenuDown, ReedlineEvent::Down]),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use serde::de::{value::StrDeserializer, IntoDeserializer};
+
+    #[rstest]
+    #[case("f1", KeyModifiers::NONE, KeyCode::F(1))]
+    #[case("f24", KeyModifiers::NONE, KeyCode::F(24))]
+    #[case("ctrl-alt-f5", KeyModifiers::CONTROL | KeyModifiers::ALT, KeyCode::F(5))]
+    #[case("shift-insert", KeyModifiers::SHIFT, KeyCode::Insert)]
+    #[case("pageup", KeyModifiers::NONE, KeyCode::PageUp)]
+    #[case("alt-b", KeyModifiers::ALT, KeyCode::Char('b'))]
+    #[case("ctrl--", KeyModifiers::CONTROL, KeyCode::Char('-'))]
+    #[case("-", KeyModifiers::NONE, KeyCode::Char('-'))]
+    #[case("space", KeyModifiers::NONE, KeyCode::Char(' '))]
+    fn combinations_round_trip(
+        #[case] input: &str,
+        #[case] modifier: KeyModifiers,
+        #[case] key_code: KeyCode,
+    ) {
+        let combination: KeyCombination = input.parse().unwrap();
+
+        assert_eq!(combination, KeyCombination { modifier, key_code });
+        assert_eq!(combination.to_string(), input);
+    }
+
+    #[rstest]
+    #[case("Control-F5", "ctrl-f5")]
+    #[case("ALT-Insert", "alt-insert")]
+    fn names_ignore_case(#[case] input: &str, #[case] expected: &str) {
+        let combination: KeyCombination = input.parse().unwrap();
+        assert_eq!(combination.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("f0")]
+    #[case("f25")]
+    #[case("ctrl-")]
+    #[case("hyper-a")]
+    #[case("keypad")]
+    fn invalid_combinations_are_rejected(#[case] input: &str) {
+        assert!(matches!(
+            input.parse::<KeyCombination>(),
+            Err(ReedlineError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn combinations_deserialize_from_strings() {
+        let deserializer: StrDeserializer<'_, de::value::Error> = "ctrl-f12".into_deserializer();
+
+        assert_eq!(
+            KeyCombination::deserialize(deserializer).unwrap(),
+            KeyCombination {
+                modifier: KeyModifiers::CONTROL,
+                key_code: KeyCode::F(12),
+            }
+        );
+    }
+
+    #[test]
+    fn function_keys_are_unbound_by_default() {
+        let keybindings = crate::default_emacs_keybindings();
+
+        for n in 1..=MAX_FUNCTION_KEY {
+            assert_eq!(
+                keybindings.find_binding(KeyModifiers::NONE, KeyCode::F(n)),
+                None
+            );
+        }
+        assert_eq!(
+            keybindings.find_binding(KeyModifiers::NONE, KeyCode::Insert),
+            None
+        );
+    }
+}
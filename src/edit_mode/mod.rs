@@ -1,9 +1,10 @@
 mod base;
+mod char_entry;
 mod emacs;
 mod keybindings;
 mod vi;
 
 pub use base::EditMode;
 pub use emacs::{default_emacs_keybindings, Emacs};
-pub use keybindings::Keybindings;
+pub use keybindings::{KeyCombination, Keybindings};
 pub use vi::{default_vi_insert_keybindings, default_vi_normal_keybindings, Vi};
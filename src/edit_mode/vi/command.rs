@@ -1,6 +1,6 @@
 use super::motion::Motion;
 use super::parser::ReedlineOption;
-use crate::{EditCommand, ReedlineEvent};
+use crate::{macros::LAST_REGISTER, EditCommand, ReedlineEvent};
 use std::iter::Peekable;
 
 pub fn parse_command<'iter, I>(input: &mut Peekable<I>) -> Option<Command>
@@ -84,6 +84,75 @@ where
             let _ = input.next();
             Some(Command::AppendToEnd)
         }
+        Some('~') => {
+            let _ = input.next();
+            Some(Command::SwitchCase)
+        }
+        Some('R') => {
+            let _ = input.next();
+            Some(Command::EnterViReplace)
+        }
+        Some('r') => {
+            let _ = input.next();
+            match input.peek() {
+                Some(c) => Some(Command::ReplaceChar(**c)),
+                None => Some(Command::Incomplete),
+            }
+        }
+        Some('q') => {
+            let _ = input.next();
+            match input.next() {
+                Some(c) if is_register(*c) => Some(Command::RecordMacro(*c)),
+                Some(_) => None,
+                None => Some(Command::Incomplete),
+            }
+        }
+        Some('@') => {
+            let _ = input.next();
+            match input.next() {
+                Some(c) if is_register(*c) || *c == LAST_REGISTER => Some(Command::ReplayMacro(*c)),
+                Some(_) => None,
+                None => Some(Command::Incomplete),
+            }
+        }
+        Some(';') => {
+            let _ = input.next();
+            Some(Command::RepeatFind)
+        }
+        Some(',') => {
+            let _ = input.next();
+            Some(Command::ReverseFind)
+        }
+        Some(':') => {
+            let _ = input.next();
+            // The command line after `:` is run when enter is pressed
+            while input.next().is_some() {}
+            Some(Command::Incomplete)
+        }
+        Some('g') => {
+            let _ = input.next();
+            match input.peek() {
+                Some('U') => {
+                    let _ = input.next();
+                    // `gUU` changes the whole line, otherwise a motion follows
+                    if input.next_if_eq(&&'U').is_some() {
+                        Some(Command::UppercaseLine)
+                    } else {
+                        Some(Command::Uppercase)
+                    }
+                }
+                Some('u') => {
+                    let _ = input.next();
+                    if input.next_if_eq(&&'u').is_some() {
+                        Some(Command::LowercaseLine)
+                    } else {
+                        Some(Command::Lowercase)
+                    }
+                }
+                Some(_) => None,
+                None => Some(Command::Incomplete),
+            }
+        }
         Some('f') => {
             let _ = input.next();
             match input.peek() {
@@ -116,6 +185,11 @@ where
     }
 }
 
+/// The registers of `q` and `@`: letters, digits and the unnamed register `"`
+fn is_register(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '"'
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Command {
     Incomplete,
@@ -133,6 +207,8 @@ pub enum Command {
     MoveToLineEnd,
     EnterViAppend,
     EnterViInsert,
+    EnterViReplace,
+    ReplaceChar(char),
     Undo,
     DeleteToEnd,
     AppendToEnd,
@@ -141,10 +217,42 @@ pub enum Command {
     MoveRightBefore(char),
     MoveLeftUntil(char),
     MoveLeftBefore(char),
+    // `;` and `,` stand for the last character find until the editor resolves them
+    RepeatFind,
+    ReverseFind,
+    RecordMacro(char),
+    ReplayMacro(char),
     HistorySearch,
+    SwitchCase,
+    Uppercase,
+    Lowercase,
+    UppercaseLine,
+    LowercaseLine,
 }
 
 impl Command {
+    /// The move of a character find motion
+    pub fn from_char_find(motion: Motion) -> Option<Self> {
+        match motion {
+            Motion::RightUntil(c) => Some(Self::MoveRightUntil(c)),
+            Motion::RightBefore(c) => Some(Self::MoveRightBefore(c)),
+            Motion::LeftUntil(c) => Some(Self::MoveLeftUntil(c)),
+            Motion::LeftBefore(c) => Some(Self::MoveLeftBefore(c)),
+            _ => None,
+        }
+    }
+
+    /// The character find of a move command with `f`, `t`, `F` or `T`
+    pub fn char_find(&self) -> Option<Motion> {
+        match self {
+            Self::MoveRightUntil(c) => Some(Motion::RightUntil(*c)),
+            Self::MoveRightBefore(c) => Some(Motion::RightBefore(*c)),
+            Self::MoveLeftUntil(c) => Some(Motion::LeftUntil(*c)),
+            Self::MoveLeftBefore(c) => Some(Motion::LeftBefore(*c)),
+            _ => None,
+        }
+    }
+
     pub fn to_reedline(&self) -> Vec<ReedlineOption> {
         match self {
             Self::MoveUp => vec![ReedlineOption::Event(ReedlineEvent::Up)],
@@ -155,7 +263,10 @@ impl Command {
             Self::MoveToLineEnd => vec![ReedlineOption::Edit(EditCommand::MoveToLineEnd)],
             Self::MoveWordLeft => vec![ReedlineOption::Edit(EditCommand::MoveWordLeft)],
             Self::MoveWordRight => vec![ReedlineOption::Edit(EditCommand::MoveWordRight)],
-            Self::EnterViInsert => vec![ReedlineOption::Event(ReedlineEvent::Repaint)],
+            Self::EnterViInsert | Self::EnterViReplace => {
+                vec![ReedlineOption::Event(ReedlineEvent::Repaint)]
+            }
+            Self::ReplaceChar(c) => vec![ReedlineOption::Edit(EditCommand::ReplaceChars(1, *c))],
             Self::EnterViAppend => vec![ReedlineOption::Edit(EditCommand::MoveRight)],
             Self::PasteAfter => vec![ReedlineOption::Edit(EditCommand::PasteCutBufferAfter)],
             Self::PasteBefore => vec![ReedlineOption::Edit(EditCommand::PasteCutBufferBefore)],
@@ -170,8 +281,19 @@ impl Command {
             Self::MoveLeftBefore(c) => vec![ReedlineOption::Edit(EditCommand::MoveLeftBefore(*c))],
             Self::DeleteChar => vec![ReedlineOption::Edit(EditCommand::Delete)],
             Self::HistorySearch => vec![ReedlineOption::Event(ReedlineEvent::SearchHistory)],
+            Self::RecordMacro(c) => vec![ReedlineOption::Event(ReedlineEvent::StartMacro(*c))],
+            Self::ReplayMacro(c) => vec![ReedlineOption::Event(ReedlineEvent::ReplayMacro(*c))],
+            Self::SwitchCase => vec![ReedlineOption::Edit(EditCommand::SwitchcaseChar)],
+            Self::UppercaseLine => vec![ReedlineOption::Edit(EditCommand::UppercaseLine)],
+            Self::LowercaseLine => vec![ReedlineOption::Edit(EditCommand::LowercaseLine)],
             // Mark a command as incomplete whenever a motion is required to finish the command
-            Self::Delete | Self::Change | Self::Incomplete => vec![ReedlineOption::Incomplete],
+            Self::Delete
+            | Self::Change
+            | Self::Uppercase
+            | Self::Lowercase
+            | Self::RepeatFind
+            | Self::ReverseFind
+            | Self::Incomplete => vec![ReedlineOption::Incomplete],
         }
     }
 
@@ -197,7 +319,7 @@ impl Command {
                 Motion::LeftBefore(c) => {
                     Some(vec![ReedlineOption::Edit(EditCommand::CutLeftBefore(*c))])
                 }
-                Motion::Start => None,
+                Motion::Start | Motion::RepeatFind | Motion::ReverseFind => None,
             },
             Self::Change => match motion {
                 Motion::End => Some(vec![
@@ -229,7 +351,15 @@ impl Command {
                     ReedlineOption::Edit(EditCommand::CutLeftBefore(*c)),
                     ReedlineOption::Event(ReedlineEvent::Repaint),
                 ]),
-                Motion::Start => None,
+                Motion::Start | Motion::RepeatFind | Motion::ReverseFind => None,
+            },
+            Self::Uppercase => match motion {
+                Motion::Word => Some(vec![ReedlineOption::Edit(EditCommand::UppercaseWord)]),
+                _ => None,
+            },
+            Self::Lowercase => match motion {
+                Motion::Word => Some(vec![ReedlineOption::Edit(EditCommand::LowercaseWord)]),
+                _ => None,
             },
             _ => None,
         };
@@ -0,0 +1,118 @@
+use crate::{EditCommand, ReedlineEvent};
+
+/// The event of a command typed after `:`, run when enter is pressed
+///
+/// - `:w` hands the buffer to the hooks
+/// - `:q` and `:q!` cancel the line
+/// - `:s/pattern/replacement/` and `:%s/pattern/replacement/g` substitute in the current
+///   line or in all of them, with any character that isn't a letter, a digit or a
+///   backslash as separator
+/// - `:{n}` goes to the line n
+pub fn parse_command_line(command: &str) -> Option<ReedlineEvent> {
+    match command {
+        "w" => Some(ReedlineEvent::Write),
+        "q" | "q!" => Some(ReedlineEvent::CtrlC),
+        _ if command.starts_with(|c: char| c.is_ascii_digit()) => {
+            let line = command.parse::<usize>().ok()?;
+            Some(ReedlineEvent::Edit(vec![EditCommand::MoveToPosition {
+                line: line.saturating_sub(1),
+                column: 0,
+            }]))
+        }
+        _ => {
+            let (all_lines, command) = match command.strip_prefix('%') {
+                Some(command) => (true, command),
+                None => (false, command),
+            };
+            let substitute = parse_substitute(command.strip_prefix('s')?, all_lines)?;
+            Some(ReedlineEvent::Edit(vec![substitute]))
+        }
+    }
+}
+
+/// The substitution of `/pattern/replacement/flags`, where a backslash escapes the
+/// separator
+fn parse_substitute(arguments: &str, all_lines: bool) -> Option<EditCommand> {
+    let mut chars = arguments.chars();
+    let separator = chars
+        .next()
+        .filter(|c| !c.is_alphanumeric() && *c != '\\' && !c.is_whitespace())?;
+
+    let mut parts = vec![String::new()];
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == separator => parts.last_mut()?.push(next),
+                Some(next) => {
+                    parts.last_mut()?.push(c);
+                    parts.last_mut()?.push(next);
+                }
+                None => parts.last_mut()?.push(c),
+            },
+            _ if c == separator => parts.push(String::new()),
+            _ => parts.last_mut()?.push(c),
+        }
+    }
+
+    let mut parts = parts.into_iter();
+    let pattern = parts.next().filter(|pattern| !pattern.is_empty())?;
+    let replacement = parts.next().unwrap_or_default();
+    let every_match = match parts.next().as_deref() {
+        None | Some("") => false,
+        Some("g") => true,
+        Some(_) => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(EditCommand::Substitute {
+        pattern,
+        replacement,
+        all_lines,
+        every_match,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn substitute(
+        pattern: &str,
+        replacement: &str,
+        all_lines: bool,
+        every_match: bool,
+    ) -> ReedlineEvent {
+        ReedlineEvent::Edit(vec![EditCommand::Substitute {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            all_lines,
+            every_match,
+        }])
+    }
+
+    #[rstest]
+    #[case("w", Some(ReedlineEvent::Write))]
+    #[case("q", Some(ReedlineEvent::CtrlC))]
+    #[case("q!", Some(ReedlineEvent::CtrlC))]
+    #[case("3", Some(ReedlineEvent::Edit(vec![EditCommand::MoveToPosition { line: 2, column: 0 }])))]
+    #[case("3x", None)]
+    #[case("s/a/b/", Some(substitute("a", "b", false, false)))]
+    #[case("s/a/b", Some(substitute("a", "b", false, false)))]
+    #[case("%s/a/b/g", Some(substitute("a", "b", true, true)))]
+    #[case("%s#/usr#/opt#g", Some(substitute("/usr", "/opt", true, true)))]
+    #[case("s/a\\/b/c\\n/", Some(substitute("a/b", "c\\n", false, false)))]
+    #[case("s/a/", Some(substitute("a", "", false, false)))]
+    #[case("s/a/b/x", None)]
+    #[case("s/a/b/g/", None)]
+    #[case("s//b/", None)]
+    #[case("sxaxbx", None)]
+    #[case("", None)]
+    #[case("wq", None)]
+    fn commands_are_parsed(#[case] command: &str, #[case] expected: Option<ReedlineEvent>) {
+        assert_eq!(parse_command_line(command), expected);
+    }
+}
@@ -1,14 +1,20 @@
 mod command;
+mod command_line;
 mod motion;
 mod parser;
 mod vi_keybindings;
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::time::{Duration, Instant};
 pub use vi_keybindings::{default_vi_insert_keybindings, default_vi_normal_keybindings};
 
 use super::EditMode;
 use crate::{
-    edit_mode::{keybindings::Keybindings, vi::parser::parse},
+    edit_mode::{
+        char_entry::{CharEntry, Entered},
+        keybindings::Keybindings,
+        vi::{command_line::parse_command_line, motion::Motion, parser::parse},
+    },
     enums::{EditCommand, ReedlineEvent},
     PromptEditMode, PromptViMode,
 };
@@ -17,6 +23,7 @@ use crate::{
 enum Mode {
     Normal,
     Insert,
+    Replace,
 }
 
 /// This parses incoming input `Event`s like a Vi-Style editor
@@ -26,6 +33,15 @@ pub struct Vi {
     normal_keybindings: Keybindings,
     mode: Mode,
     previous: Option<ReedlineEvent>,
+    // The last `f`, `t`, `F` or `T`, repeated by `;` and reversed by `,`
+    last_find: Option<Motion>,
+    // A macro is recorded since `q{register}` and `q` stops it
+    recording: bool,
+    // A character typed by its code point or as a digraph in insert mode
+    char_entry: Option<CharEntry>,
+    // A key following a bare ESC this quickly is read as a meta combination
+    esc_timeout: Duration,
+    last_esc: Option<Instant>,
 }
 
 impl Default for Vi {
@@ -36,6 +52,11 @@ impl Default for Vi {
             cache: Vec::new(),
             mode: Mode::Insert,
             previous: None,
+            last_find: None,
+            recording: false,
+            char_entry: None,
+            esc_timeout: Duration::ZERO,
+            last_esc: None,
         }
     }
 }
@@ -49,100 +70,244 @@ impl Vi {
             cache: Vec::new(),
             mode: Mode::Insert,
             previous: None,
+            last_find: None,
+            recording: false,
+            char_entry: None,
+            esc_timeout: Duration::ZERO,
+            last_esc: None,
         }
     }
-}
 
-impl EditMode for Vi {
-    fn parse_event(&mut self, event: Event) -> ReedlineEvent {
-        match event {
-            Event::Key(KeyEvent { code, modifiers }) => match (self.mode, modifiers, code) {
-                (Mode::Normal, modifier, KeyCode::Char(c)) => {
-                    // The repeat character is the only character that is not managed
-                    // by the parser since the last event is stored in the editor
-                    if c == '.' {
-                        if let Some(event) = &self.previous {
-                            return event.clone();
-                        }
+    /// Vi builder that sets how long after a bare ESC a key is still read as the
+    /// meta combination with that key, when the insert mode has a binding for it.
+    ///
+    /// Slow connections can split the ESC prefix of a meta combination from its key.
+    /// By default the timeout is zero and ESC always switches to normal mode.
+    pub fn with_esc_timeout(mut self, esc_timeout: Duration) -> Self {
+        self.esc_timeout = esc_timeout;
+        self
+    }
+
+    /// The binding of the meta combination when the key follows a bare ESC within
+    /// the timeout
+    fn split_meta_binding(&self, esc_at: Instant, code: KeyCode) -> Option<ReedlineEvent> {
+        if esc_at.elapsed() > self.esc_timeout {
+            return None;
+        }
+
+        self.insert_keybindings
+            .find_binding(KeyModifiers::ALT, code)
+    }
+
+    /// Takes the count typed before a key that the parser does not handle, like `5`
+    /// before `ctrl-a`
+    fn take_count(&mut self) -> Option<i64> {
+        if self.cache.is_empty() || !self.cache.iter().all(char::is_ascii_digit) {
+            return None;
+        }
+
+        let count = self.cache.iter().collect::<String>().parse().ok();
+        self.cache.clear();
+        count
+    }
+
+    /// Passes the key to the character being typed by its code point or as a digraph,
+    /// which replaces a character in replace mode
+    fn enter_char(
+        &mut self,
+        entry: CharEntry,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> ReedlineEvent {
+        let (c, handle_key) = match entry.push_key(modifiers, code) {
+            Entered::Pending(entry) => {
+                self.char_entry = Some(entry);
+                return ReedlineEvent::None;
+            }
+            Entered::Done(c, handle_key) => (c, handle_key),
+        };
+
+        let mut events: Vec<ReedlineEvent> = c
+            .map(|c| match self.mode {
+                Mode::Replace => ReedlineEvent::Edit(vec![EditCommand::ReplaceChar(c)]),
+                _ => ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)]),
+            })
+            .into_iter()
+            .collect();
+        if handle_key {
+            events.push(self.parse_key(code, modifiers));
+        }
+        match events.len() {
+            0 => ReedlineEvent::None,
+            1 => events.remove(0),
+            _ => ReedlineEvent::Multiple(events),
+        }
+    }
+
+    fn parse_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> ReedlineEvent {
+        match (self.mode, modifiers, code) {
+            (Mode::Normal, modifier, KeyCode::Char(c)) => {
+                // The repeat character is the only character that is not managed
+                // by the parser since the last event is stored in the editor
+                if c == '.' {
+                    if let Some(event) = &self.previous {
+                        return event.clone();
                     }
+                }
+                if c == 'q' && self.recording && self.cache.is_empty() {
+                    self.recording = false;
+                    return ReedlineEvent::EndMacro;
+                }
 
-                    if modifier == KeyModifiers::NONE || modifier == KeyModifiers::SHIFT {
-                        let char = if let KeyModifiers::SHIFT = modifier {
-                            c.to_ascii_uppercase()
-                        } else {
-                            c
-                        };
-                        self.cache.push(char);
+                if modifier == KeyModifiers::NONE || modifier == KeyModifiers::SHIFT {
+                    let char = if let KeyModifiers::SHIFT = modifier {
+                        c.to_ascii_uppercase()
+                    } else {
+                        c
+                    };
+                    self.cache.push(char);
 
-                        let res = parse(&mut self.cache.iter().peekable());
+                    let mut res = parse(&mut self.cache.iter().peekable());
+                    if let Some(find) = res.char_find() {
+                        self.last_find = Some(find);
+                    }
+                    res.resolve_repeat_find(self.last_find);
 
-                        if res.enter_insert_mode() {
-                            self.mode = Mode::Insert;
-                        }
+                    if res.enter_insert_mode() {
+                        self.mode = Mode::Insert;
+                    } else if res.enter_replace_mode() {
+                        self.mode = Mode::Replace;
+                    } else if res.starts_recording() {
+                        self.recording = true;
+                    }
 
-                        let event = res.to_reedline_event();
-                        match event {
-                            ReedlineEvent::None => {
-                                if !res.is_valid() {
-                                    self.cache.clear();
-                                }
-                            }
-                            _ => {
+                    let event = res.to_reedline_event();
+                    match event {
+                        ReedlineEvent::None => {
+                            if !res.is_valid() {
                                 self.cache.clear();
                             }
-                        };
+                        }
+                        _ => {
+                            self.cache.clear();
+                        }
+                    };
 
-                        self.previous = Some(event.clone());
+                    self.previous = Some(event.clone());
 
-                        event
-                    } else {
-                        self.normal_keybindings
-                            .find_binding(modifiers, code)
-                            .unwrap_or(ReedlineEvent::None)
-                    }
+                    event
+                } else {
+                    let count = self.take_count();
+                    self.normal_keybindings
+                        .find_binding(modifiers, code)
+                        .map_or(ReedlineEvent::None, |event| with_count(event, count))
+                }
+            }
+            (Mode::Insert, modifier, KeyCode::Char(c)) => {
+                // Note. The modifier can also be a combination of modifiers, for
+                // example:
+                //     KeyModifiers::CONTROL | KeyModifiers::ALT
+                //     KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+                //
+                // Mixed modifiers are used by non american keyboards that have extra
+                // keys like 'alt gr'. Keep this in mind if in the future there are
+                // cases where an event is not being captured
+                if modifier == KeyModifiers::SHIFT {
+                    let char = c.to_ascii_uppercase();
+                    ReedlineEvent::Edit(vec![EditCommand::InsertChar(char)])
+                } else if modifier == KeyModifiers::NONE
+                    || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT
+                    || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+                {
+                    ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)])
+                } else {
+                    self.insert_keybindings
+                        .find_binding(modifier, code)
+                        .unwrap_or(ReedlineEvent::None)
+                }
+            }
+            (Mode::Replace, modifier, KeyCode::Char(c))
+                if modifier == KeyModifiers::NONE || modifier == KeyModifiers::SHIFT =>
+            {
+                let char = if modifier == KeyModifiers::SHIFT {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                };
+                ReedlineEvent::Edit(vec![EditCommand::ReplaceChar(char)])
+            }
+            (Mode::Replace, KeyModifiers::NONE, KeyCode::Backspace) => {
+                ReedlineEvent::Edit(vec![EditCommand::ReplaceBackspace])
+            }
+            (_, KeyModifiers::NONE, KeyCode::Esc) => {
+                if self.mode == Mode::Insert && !self.esc_timeout.is_zero() {
+                    self.last_esc = Some(Instant::now());
                 }
-                (Mode::Insert, modifier, KeyCode::Char(c)) => {
-                    // Note. The modifier can also be a combination of modifiers, for
-                    // example:
-                    //     KeyModifiers::CONTROL | KeyModifiers::ALT
-                    //     KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
-                    //
-                    // Mixed modifiers are used by non american keyboards that have extra
-                    // keys like 'alt gr'. Keep this in mind if in the future there are
-                    // cases where an event is not being captured
-                    if modifier == KeyModifiers::SHIFT {
-                        let char = c.to_ascii_uppercase();
-                        ReedlineEvent::Edit(vec![EditCommand::InsertChar(char)])
-                    } else if modifier == KeyModifiers::NONE
-                        || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT
-                        || modifier
-                            == KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+                self.cache.clear();
+                self.mode = Mode::Normal;
+                ReedlineEvent::Multiple(vec![ReedlineEvent::Esc, ReedlineEvent::Repaint])
+            }
+            (Mode::Normal, KeyModifiers::NONE, KeyCode::Enter)
+                if self.cache.first() == Some(&':') =>
+            {
+                let command = self.cache[1..].iter().collect::<String>();
+                self.cache.clear();
+                parse_command_line(&command).unwrap_or(ReedlineEvent::None)
+            }
+            (Mode::Normal, KeyModifiers::NONE, KeyCode::Backspace)
+                if self.cache.first() == Some(&':') =>
+            {
+                self.cache.pop();
+                ReedlineEvent::Repaint
+            }
+            (_, KeyModifiers::NONE, KeyCode::Enter) => {
+                self.mode = Mode::Insert;
+                ReedlineEvent::Enter
+            }
+            (Mode::Normal, _, _) => self
+                .normal_keybindings
+                .find_binding(modifiers, code)
+                .unwrap_or(ReedlineEvent::None),
+            (Mode::Insert | Mode::Replace, _, _) => self
+                .insert_keybindings
+                .find_binding(modifiers, code)
+                .unwrap_or(ReedlineEvent::None),
+        }
+    }
+}
+
+impl EditMode for Vi {
+    fn parse_event(&mut self, event: Event) -> ReedlineEvent {
+        match event {
+            Event::Key(KeyEvent { code, modifiers }) => {
+                // The ESC prefix of a meta combination arrived on its own
+                let last_esc = self.last_esc.take();
+                if self.mode == Mode::Normal && modifiers == KeyModifiers::NONE {
+                    if let Some(event) =
+                        last_esc.and_then(|esc_at| self.split_meta_binding(esc_at, code))
                     {
-                        ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)])
-                    } else {
-                        self.insert_keybindings
-                            .find_binding(modifier, code)
-                            .unwrap_or(ReedlineEvent::None)
+                        self.mode = Mode::Insert;
+                        return event;
                     }
                 }
-                (_, KeyModifiers::NONE, KeyCode::Esc) => {
-                    self.cache.clear();
-                    self.mode = Mode::Normal;
-                    ReedlineEvent::Multiple(vec![ReedlineEvent::Esc, ReedlineEvent::Repaint])
+
+                if let Some(entry) = self.char_entry.take() {
+                    return self.enter_char(entry, code, modifiers);
                 }
-                (_, KeyModifiers::NONE, KeyCode::Enter) => {
-                    self.mode = Mode::Insert;
-                    ReedlineEvent::Enter
+                if self.mode != Mode::Normal
+                    && self
+                        .insert_keybindings
+                        .find_binding(modifiers, code)
+                        .is_none()
+                {
+                    if let Some(entry) = CharEntry::start(modifiers, code) {
+                        self.char_entry = Some(entry);
+                        return ReedlineEvent::None;
+                    }
                 }
-                (Mode::Normal, _, _) => self
-                    .normal_keybindings
-                    .find_binding(modifiers, code)
-                    .unwrap_or(ReedlineEvent::None),
-                (Mode::Insert, _, _) => self
-                    .insert_keybindings
-                    .find_binding(modifiers, code)
-                    .unwrap_or(ReedlineEvent::None),
-            },
+
+                self.parse_key(code, modifiers)
+            }
 
             Event::Mouse(_) => ReedlineEvent::Mouse,
             Event::Resize(width, height) => ReedlineEvent::Resize(width, height),
@@ -153,6 +318,324 @@ impl EditMode for Vi {
         match self.mode {
             Mode::Normal => PromptEditMode::Vi(PromptViMode::Normal),
             Mode::Insert => PromptEditMode::Vi(PromptViMode::Insert),
+            Mode::Replace => PromptEditMode::Vi(PromptViMode::Replace),
+        }
+    }
+
+    fn has_pending_keys(&self) -> bool {
+        !self.cache.is_empty() || self.char_entry.is_some()
+    }
+
+    fn command_line(&self) -> Option<String> {
+        if let Some(entry) = &self.char_entry {
+            return Some(entry.display());
+        }
+        (self.cache.first() == Some(&':')).then(|| self.cache.iter().collect())
+    }
+}
+
+/// Applies a count to the number increments of a bound event
+fn with_count(event: ReedlineEvent, count: Option<i64>) -> ReedlineEvent {
+    let count = match count {
+        Some(count) => count,
+        None => return event,
+    };
+
+    match event {
+        ReedlineEvent::Edit(commands) => ReedlineEvent::Edit(
+            commands
+                .into_iter()
+                .map(|command| match command {
+                    EditCommand::IncrementNumber(amount) => {
+                        EditCommand::IncrementNumber(amount.saturating_mul(count))
+                    }
+                    command => command,
+                })
+                .collect(),
+        ),
+        event => event,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[test]
+    fn alt_right_accepts_hint_word_in_insert_mode() {
+        let mut vi = Vi::default();
+        let alt_right = Event::Key(KeyEvent {
+            modifiers: KeyModifiers::ALT,
+            code: KeyCode::Right,
+        });
+        let result = vi.parse_event(alt_right);
+
+        assert_eq!(
+            result,
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::HistoryHintWordComplete,
+                ReedlineEvent::Edit(vec![EditCommand::MoveWordRight]),
+            ])
+        );
+    }
+
+    #[test]
+    fn incomplete_command_has_pending_keys() {
+        let mut vi = Vi::default();
+        let key = |c| {
+            Event::Key(KeyEvent {
+                modifiers: KeyModifiers::NONE,
+                code: KeyCode::Char(c),
+            })
+        };
+        vi.parse_event(Event::Key(KeyEvent {
+            modifiers: KeyModifiers::NONE,
+            code: KeyCode::Esc,
+        }));
+
+        assert_eq!(vi.parse_event(key('d')), ReedlineEvent::None);
+        assert!(vi.has_pending_keys());
+
+        vi.parse_event(key('w'));
+        assert!(!vi.has_pending_keys());
+    }
+
+    fn key_event(modifiers: KeyModifiers, code: KeyCode) -> Event {
+        Event::Key(KeyEvent { modifiers, code })
+    }
+
+    fn vi_with_meta_b() -> Vi {
+        let mut insert_keybindings = default_vi_insert_keybindings();
+        insert_keybindings.add_binding(
+            KeyModifiers::ALT,
+            KeyCode::Char('b'),
+            ReedlineEvent::Edit(vec![EditCommand::MoveWordLeft]),
+        );
+
+        Vi::new(insert_keybindings, default_vi_normal_keybindings())
+    }
+
+    #[test]
+    fn backtab_selects_the_previous_menu_value_in_both_modes() {
+        let mut vi = Vi::default();
+        let backtab = key_event(KeyModifiers::SHIFT, KeyCode::BackTab);
+        assert_eq!(vi.parse_event(backtab), ReedlineEvent::MenuPrevious);
+
+        vi.mode = Mode::Normal;
+        assert_eq!(vi.parse_event(backtab), ReedlineEvent::MenuPrevious);
+    }
+
+    #[test]
+    fn bound_meta_combination_stays_in_insert_mode() {
+        let mut vi = vi_with_meta_b();
+        let result = vi.parse_event(key_event(KeyModifiers::ALT, KeyCode::Char('b')));
+
+        assert_eq!(result, ReedlineEvent::Edit(vec![EditCommand::MoveWordLeft]));
+        assert_eq!(vi.mode, Mode::Insert);
+    }
+
+    #[test]
+    fn unbound_meta_combination_is_ignored() {
+        let mut vi = vi_with_meta_b();
+        let result = vi.parse_event(key_event(KeyModifiers::ALT, KeyCode::Char('x')));
+
+        assert_eq!(result, ReedlineEvent::None);
+        assert_eq!(vi.mode, Mode::Insert);
+    }
+
+    #[rstest]
+    #[case(Duration::ZERO, Mode::Normal)]
+    #[case(Duration::from_secs(60), Mode::Insert)]
+    fn split_meta_combination_within_esc_timeout(
+        #[case] esc_timeout: Duration,
+        #[case] expected_mode: Mode,
+    ) {
+        let mut vi = vi_with_meta_b().with_esc_timeout(esc_timeout);
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Char('b')));
+
+        assert_eq!(vi.mode, expected_mode);
+    }
+
+    #[rstest]
+    #[case(&[], 'a', 1)]
+    #[case(&['5'], 'a', 5)]
+    #[case(&['1', '0'], 'x', -10)]
+    fn count_scales_number_increments(
+        #[case] count: &[char],
+        #[case] c: char,
+        #[case] expected: i64,
+    ) {
+        let mut vi = Vi::default();
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        for digit in count {
+            vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Char(*digit)));
         }
+        let result = vi.parse_event(key_event(KeyModifiers::CONTROL, KeyCode::Char(c)));
+
+        assert_eq!(
+            result,
+            ReedlineEvent::Edit(vec![EditCommand::IncrementNumber(expected)])
+        );
+        assert!(!vi.has_pending_keys());
+    }
+
+    #[rstest]
+    #[case(":12", ReedlineEvent::Edit(vec![EditCommand::MoveToPosition { line: 11, column: 0 }]))]
+    #[case(":0", ReedlineEvent::Edit(vec![EditCommand::MoveToPosition { line: 0, column: 0 }]))]
+    #[case(":", ReedlineEvent::None)]
+    fn colon_line_number_goes_to_the_line(#[case] typed: &str, #[case] expected: ReedlineEvent) {
+        let mut vi = Vi::default();
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        for c in typed.chars() {
+            assert_eq!(
+                vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Char(c))),
+                ReedlineEvent::None
+            );
+        }
+        let result = vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Enter));
+
+        assert_eq!(result, expected);
+        assert_eq!(vi.mode, Mode::Normal);
+        assert!(!vi.has_pending_keys());
+    }
+
+    fn normal_keys(vi: &mut Vi, typed: &str) -> Vec<ReedlineEvent> {
+        typed
+            .chars()
+            .map(|c| vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Char(c))))
+            .collect()
+    }
+
+    #[test]
+    fn ctrl_k_types_a_digraph() {
+        let mut vi = Vi::default();
+        let ctrl_k = key_event(KeyModifiers::CONTROL, KeyCode::Char('k'));
+
+        assert_eq!(vi.parse_event(ctrl_k), ReedlineEvent::None);
+        assert_eq!(
+            vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Char('e'))),
+            ReedlineEvent::None
+        );
+        assert_eq!(vi.command_line(), Some("^K e".to_string()));
+        assert_eq!(
+            vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Char(':'))),
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('ë')])
+        );
+        assert!(!vi.has_pending_keys());
+
+        // Normal mode keeps its meaning of the key
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        vi.parse_event(ctrl_k);
+        assert!(!vi.has_pending_keys());
+    }
+
+    #[test]
+    fn q_records_a_macro_that_at_replays() {
+        let mut vi = Vi::default();
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+
+        let events = normal_keys(&mut vi, "qaxq@a");
+        assert_eq!(
+            events,
+            vec![
+                ReedlineEvent::None,
+                ReedlineEvent::Multiple(vec![ReedlineEvent::StartMacro('a')]),
+                ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::Delete])]),
+                ReedlineEvent::EndMacro,
+                ReedlineEvent::None,
+                ReedlineEvent::Multiple(vec![ReedlineEvent::ReplayMacro('a')]),
+            ]
+        );
+
+        // Without a recording `q` waits for a register
+        assert_eq!(normal_keys(&mut vi, "q"), vec![ReedlineEvent::None]);
+        assert!(vi.has_pending_keys());
+    }
+
+    #[test]
+    fn last_find_is_repeated_and_reversed() {
+        let mut vi = Vi::default();
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        let move_event =
+            |command| ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![command])]);
+
+        let events = normal_keys(&mut vi, "tx;,dw;");
+        assert_eq!(
+            events,
+            vec![
+                ReedlineEvent::None,
+                move_event(EditCommand::MoveRightBefore('x')),
+                move_event(EditCommand::MoveRightBefore('x')),
+                move_event(EditCommand::MoveLeftBefore('x')),
+                ReedlineEvent::None,
+                move_event(EditCommand::CutWordRight),
+                move_event(EditCommand::MoveRightBefore('x')),
+            ]
+        );
+
+        // An operator finds the character too, and its find is repeated afterwards
+        let events = normal_keys(&mut vi, "dFy,");
+        assert_eq!(events[2], move_event(EditCommand::CutLeftUntil('y')));
+        assert_eq!(events[3], move_event(EditCommand::MoveRightUntil('y')));
+        assert!(!vi.has_pending_keys());
+    }
+
+    #[test]
+    fn repeat_without_a_find_does_nothing() {
+        let mut vi = Vi::default();
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+
+        assert_eq!(normal_keys(&mut vi, ";"), vec![ReedlineEvent::None]);
+        assert!(!vi.has_pending_keys());
+        assert_eq!(normal_keys(&mut vi, "d,"), vec![ReedlineEvent::None; 2]);
+        assert!(!vi.has_pending_keys());
+    }
+
+    #[test]
+    fn replace_mode_overwrites_until_esc() {
+        let mut vi = Vi::default();
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        normal_keys(&mut vi, "R");
+        assert!(matches!(
+            vi.edit_mode(),
+            PromptEditMode::Vi(PromptViMode::Replace)
+        ));
+
+        assert_eq!(
+            normal_keys(&mut vi, "a"),
+            vec![ReedlineEvent::Edit(vec![EditCommand::ReplaceChar('a')])]
+        );
+        assert_eq!(
+            vi.parse_event(key_event(KeyModifiers::SHIFT, KeyCode::Char('b'))),
+            ReedlineEvent::Edit(vec![EditCommand::ReplaceChar('B')])
+        );
+        assert_eq!(
+            vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Backspace)),
+            ReedlineEvent::Edit(vec![EditCommand::ReplaceBackspace])
+        );
+
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        assert_eq!(vi.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn command_line_is_shown_while_typed() {
+        let mut vi = Vi::default();
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        assert_eq!(vi.command_line(), None);
+
+        normal_keys(&mut vi, ":s/a b");
+        assert_eq!(vi.command_line(), Some(":s/a b".to_string()));
+
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Backspace));
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Backspace));
+        assert_eq!(vi.command_line(), Some(":s/a".to_string()));
+        assert!(vi.has_pending_keys());
+
+        vi.parse_event(key_event(KeyModifiers::NONE, KeyCode::Esc));
+        assert_eq!(vi.command_line(), None);
     }
 }
@@ -37,11 +37,19 @@ where
             let _ = input.next();
             input.peek().map(|c| Motion::LeftBefore(**c))
         }
+        Some(';') => {
+            let _ = input.next();
+            Some(Motion::RepeatFind)
+        }
+        Some(',') => {
+            let _ = input.next();
+            Some(Motion::ReverseFind)
+        }
         _ => None,
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Motion {
     Word,
     Line,
@@ -51,4 +59,31 @@ pub enum Motion {
     RightBefore(char),
     LeftUntil(char),
     LeftBefore(char),
+    // `;` and `,` stand for the last character find until the editor resolves them
+    RepeatFind,
+    ReverseFind,
+}
+
+impl Motion {
+    /// Whether the motion finds a character with `f`, `t`, `F` or `T`
+    pub fn is_char_find(&self) -> bool {
+        matches!(
+            self,
+            Motion::RightUntil(_)
+                | Motion::RightBefore(_)
+                | Motion::LeftUntil(_)
+                | Motion::LeftBefore(_)
+        )
+    }
+
+    /// The character find in the other direction, which `,` repeats
+    pub fn reversed(self) -> Self {
+        match self {
+            Motion::RightUntil(c) => Motion::LeftUntil(c),
+            Motion::RightBefore(c) => Motion::LeftBefore(c),
+            Motion::LeftUntil(c) => Motion::RightUntil(c),
+            Motion::LeftBefore(c) => Motion::RightBefore(c),
+            motion => motion,
+        }
+    }
 }
@@ -24,6 +24,42 @@ impl ParseResult {
         self.valid
     }
 
+    /// The character find of the command, either as a move or as the motion of an
+    /// operator, that `;` and `,` repeat afterwards
+    pub fn char_find(&self) -> Option<Motion> {
+        match (&self.command, &self.motion) {
+            (_, Some(motion)) if motion.is_char_find() => Some(*motion),
+            (Some(command), None) => command.char_find(),
+            _ => None,
+        }
+    }
+
+    /// Replaces `;` and `,` with the last character find or its reverse. Without a
+    /// last find the command is invalid
+    pub fn resolve_repeat_find(&mut self, last_find: Option<Motion>) {
+        let resolve = |repeat: Motion| match (repeat, last_find) {
+            (Motion::RepeatFind, Some(find)) => Some(find),
+            (Motion::ReverseFind, Some(find)) => Some(find.reversed()),
+            _ => None,
+        };
+
+        match (&self.command, self.motion) {
+            (Some(Command::RepeatFind), None) => {
+                self.command = resolve(Motion::RepeatFind).and_then(Command::from_char_find);
+                self.valid &= self.command.is_some();
+            }
+            (Some(Command::ReverseFind), None) => {
+                self.command = resolve(Motion::ReverseFind).and_then(Command::from_char_find);
+                self.valid &= self.command.is_some();
+            }
+            (_, Some(motion @ (Motion::RepeatFind | Motion::ReverseFind))) => {
+                self.motion = resolve(motion);
+                self.valid &= self.motion.is_some();
+            }
+            _ => {}
+        }
+    }
+
     pub fn enter_insert_mode(&self) -> bool {
         matches!(
             (&self.command, &self.motion),
@@ -35,8 +71,30 @@ impl ParseResult {
         )
     }
 
+    pub fn enter_replace_mode(&self) -> bool {
+        matches!(
+            (&self.command, &self.motion),
+            (Some(Command::EnterViReplace), None)
+        )
+    }
+
+    pub fn starts_recording(&self) -> bool {
+        matches!(
+            (&self.command, &self.motion),
+            (Some(Command::RecordMacro(_)), None)
+        )
+    }
+
     pub fn to_reedline_event(&self) -> ReedlineEvent {
         match (&self.multiplier, &self.command, &self.count, &self.motion) {
+            // The count of `r` is the number of characters replaced at once, which is
+            // a single change
+            (multiplier, Some(Command::ReplaceChar(c)), None, None) => {
+                ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::ReplaceChars(
+                    multiplier.unwrap_or(1),
+                    *c,
+                )])])
+            }
             // Movements with h,j,k,l are always single char or a number followed
             // by a single command (char)
             (multiplier, Some(command), None, None) => {
@@ -297,10 +355,75 @@ mod tests {
     #[case(&['d', 'd'], ReedlineEvent::Multiple(vec![
         ReedlineEvent::Edit(vec![EditCommand::CutCurrentLine])]))]
     #[case(&['d', 'w'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CutWordRight])]))]
+    #[case(&['~'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::SwitchcaseChar])]))]
+    #[case(&['3', '~'], ReedlineEvent::Multiple(vec![
+        ReedlineEvent::Edit(vec![EditCommand::SwitchcaseChar]),
+        ReedlineEvent::Edit(vec![EditCommand::SwitchcaseChar]),
+        ReedlineEvent::Edit(vec![EditCommand::SwitchcaseChar])
+        ]))]
+    #[case(&['g', 'U', 'w'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::UppercaseWord])]))]
+    #[case(&['g', 'u', 'w'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::LowercaseWord])]))]
+    #[case(&['g', 'U', 'U'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::UppercaseLine])]))]
+    #[case(&['g', 'u', 'u'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::LowercaseLine])]))]
+    #[case(&['g'], ReedlineEvent::None)]
+    #[case(&['g', 'U'], ReedlineEvent::None)]
+    #[case(&['r', 'x'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::ReplaceChars(1, 'x')])]))]
+    #[case(&['3', 'r', 'x'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::ReplaceChars(3, 'x')])]))]
+    #[case(&['r'], ReedlineEvent::None)]
+    #[case(&['R'], ReedlineEvent::Multiple(vec![ReedlineEvent::Repaint]))]
+    #[case(&['q', 'w'], ReedlineEvent::Multiple(vec![ReedlineEvent::StartMacro('w')]))]
+    #[case(&['q'], ReedlineEvent::None)]
+    #[case(&['2', '@', 'a'], ReedlineEvent::Multiple(vec![
+        ReedlineEvent::ReplayMacro('a'),
+        ReedlineEvent::ReplayMacro('a')
+        ]))]
+    #[case(&['@', '@'], ReedlineEvent::Multiple(vec![ReedlineEvent::ReplayMacro('@')]))]
+    #[case(&['@', '!'], ReedlineEvent::None)]
     fn test_reedline_move(#[case] input: &[char], #[case] expected: ReedlineEvent) {
         let res = vi_parse(input);
         let output = res.to_reedline_event();
 
         assert_eq!(output, expected);
     }
+
+    #[rstest]
+    #[case(&['g'], true)]
+    #[case(&['g', 'U'], true)]
+    #[case(&['g', 'x'], false)]
+    #[case(&['g', 'U', 'x'], false)]
+    fn test_incomplete_case_commands(#[case] input: &[char], #[case] valid: bool) {
+        assert_eq!(vi_parse(input).is_valid(), valid);
+    }
+
+    #[rstest]
+    #[case(&[';'], Some(Motion::RightUntil('x')), Some(Command::MoveRightUntil('x')), None, true)]
+    #[case(&[','], Some(Motion::RightBefore('x')), Some(Command::MoveLeftBefore('x')), None, true)]
+    #[case(&['d', ';'], Some(Motion::LeftUntil('x')), Some(Command::Delete), Some(Motion::LeftUntil('x')), true)]
+    #[case(&['c', ','], Some(Motion::LeftUntil('x')), Some(Command::Change), Some(Motion::RightUntil('x')), true)]
+    #[case(&[';'], None, None, None, false)]
+    #[case(&['d', ';'], None, Some(Command::Delete), None, false)]
+    fn repeated_find_is_resolved(
+        #[case] input: &[char],
+        #[case] last_find: Option<Motion>,
+        #[case] command: Option<Command>,
+        #[case] motion: Option<Motion>,
+        #[case] valid: bool,
+    ) {
+        let mut res = vi_parse(input);
+        res.resolve_repeat_find(last_find);
+
+        assert_eq!(res.command, command);
+        assert_eq!(res.motion, motion);
+        assert_eq!(res.is_valid(), valid);
+    }
+
+    #[rstest]
+    #[case(&['f', 'x'], Some(Motion::RightUntil('x')))]
+    #[case(&['2', 'T', 'x'], Some(Motion::LeftBefore('x')))]
+    #[case(&['d', 't', 'x'], Some(Motion::RightBefore('x')))]
+    #[case(&[';'], None)]
+    #[case(&['d', 'w'], None)]
+    fn char_find_is_recorded(#[case] input: &[char], #[case] expected: Option<Motion>) {
+        assert_eq!(vi_parse(input).char_find(), expected);
+    }
 }
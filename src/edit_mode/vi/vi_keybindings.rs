@@ -1,6 +1,6 @@
 use crate::{
     edit_mode::{keybindings::add_common_keybindings, Keybindings},
-    ReedlineEvent,
+    EditCommand, ReedlineEvent,
 };
 
 use crossterm::event::{KeyCode as KC, KeyModifiers as KM};
@@ -11,6 +11,18 @@ pub fn default_vi_normal_keybindings() -> Keybindings {
 
     kb.add_binding(KM::CONTROL, KC::Char('c'), ReedlineEvent::CtrlC);
     kb.add_binding(KM::CONTROL, KC::Char('l'), ReedlineEvent::ClearScreen);
+    kb.add_binding(KM::SHIFT, KC::BackTab, ReedlineEvent::MenuPrevious);
+    kb.add_binding(KM::NONE, KC::BackTab, ReedlineEvent::MenuPrevious);
+    kb.add_binding(
+        KM::CONTROL,
+        KC::Char('a'),
+        ReedlineEvent::Edit(vec![EditCommand::IncrementNumber(1)]),
+    );
+    kb.add_binding(
+        KM::CONTROL,
+        KC::Char('x'),
+        ReedlineEvent::Edit(vec![EditCommand::IncrementNumber(-1)]),
+    );
     kb.add_binding(
         KM::NONE,
         KC::Up,
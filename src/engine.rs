@@ -1,24 +1,45 @@
 use {
     crate::{
-        completion::{CircularCompletionHandler, Completer, DefaultCompleter},
+        abbreviation::Abbreviations,
+        completion::{
+            CircularCompletionHandler, Completer, CompletionCache, DefaultCompleter, Span,
+        },
         core_editor::Editor,
-        edit_mode::{EditMode, Emacs},
-        enums::{EventStatus, ReedlineEvent},
-        highlighter::SimpleMatchHighlighter,
+        edit_mode::{EditMode, Emacs, KeyCombination},
+        enums::{BellStyle, EventStatus, LineMetadata, ReedlineEvent, StatusPosition, TitleOnDrop},
+        external_printer::ExternalPrinter,
+        highlighter::HighlightContext,
         hinter::{DefaultHinter, Hinter},
-        history::{FileBackedHistory, History, HistoryNavigationQuery},
-        menu::{Menu, MenuEvent},
-        painter::{Painter, PromptLines},
-        prompt::{PromptEditMode, PromptHistorySearchStatus},
-        text_manipulation, DefaultValidator, EditCommand, ExampleHighlighter, Highlighter, Prompt,
+        history::{FileBackedHistory, History, HistoryEntryMetadata, HistoryNavigationQuery},
+        hook::Hook,
+        ime::ImeComposition,
+        macros::Macros,
+        menu::{
+            Menu, MenuEvent, MenuNavigation, CONFIRM_DELETION, DELETION_ANSWERED, NEXT_TIME_RANGE,
+        },
+        painter::{Painter, PromptLayout, PromptLines},
+        paste::{sanitize_paste, PastePolicy},
+        prompt::{PromptEditMode, PromptHistorySearchStatus, PromptViMode},
+        repaint_handle::RepaintHandle,
+        result::{ReedlineError, Result},
+        search_replace::{SearchReplace, Stage},
+        styled_text::{strip_ansi, StyledText, TabDisplay, Underline, UnderlineStyle},
+        terminal_backend::{CrosstermBackend, TerminalBackend},
+        terminal_manager::TerminalManager,
+        text_manipulation,
+        theme::Theme,
+        BindingCondition, DefaultValidator, EditCommand, ExampleHighlighter, Highlighter, Prompt,
         PromptHistorySearch, Signal, ValidationResult, Validator,
     },
-    crossterm::{
-        event,
-        event::{Event, KeyCode, KeyEvent, KeyModifiers},
-        terminal, Result,
+    crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    nu_ansi_term::{Color, Style},
+    std::{
+        borrow::Borrow,
+        io,
+        ops::Range,
+        panic::{self, AssertUnwindSafe},
+        time::{Duration, Instant},
     },
-    std::{borrow::Borrow, io, time::Duration},
 };
 
 // The POLL_WAIT is used to specify for how long the POLL should wait for
@@ -32,6 +53,22 @@ const POLL_WAIT: u64 = 10;
 // a paste. 10 events in 10 milliseconds is conservative enough (unlikely somebody
 // will type more than 10 characters in 10 milliseconds)
 const EVENTS_THRESHOLD: usize = 10;
+// While a menu waits for values from a slow source, such as a remote history, the
+// engine asks for them again after this many milliseconds without input
+const PENDING_POLL_WAIT: u64 = 50;
+// How many milliseconds the prompt stays in reverse video for the visual bell
+const BELL_FLASH_WAIT: u64 = 100;
+// How long the buffer has to stay unchanged before the hooks are told about a change
+const DEFAULT_CHANGE_DEBOUNCE: Duration = Duration::from_millis(100);
+// How long the engine has to wait for input before it saves the new history entries
+const DEFAULT_HISTORY_SYNC_DELAY: Duration = Duration::from_secs(1);
+// After this many failed saves in a row the wait between them stops growing
+const MAX_HISTORY_SYNC_BACKOFF: u32 = 6;
+// Name of the history set with `with_history`, until the host switches to another one
+const DEFAULT_HISTORY_NAME: &str = "default";
+// How many milliseconds the lines of an external printer, or a repaint requested from
+// another thread, can wait to be painted
+const EXTERNAL_WAIT: u64 = 100;
 
 /// Determines if inputs should be used to extend the regular line buffer,
 /// traverse the history in the standard prompt or edit the search string in the
@@ -49,6 +86,9 @@ enum InputMode {
     /// Either bash style up/down history or fish style prefix search,
     /// Edits directly switch to [`InputMode::Regular`]
     HistoryTraversal,
+    /// Typing edits the pattern and the replacement of the search-and-replace,
+    /// then confirms the replacements in the line buffer
+    SearchReplace,
 }
 
 /// Line editor engine
@@ -77,7 +117,14 @@ pub struct Reedline {
 
     // History
     history: Box<dyn History>,
+    // Name of the history in use, and the other histories the host can switch to
+    history_name: String,
+    other_histories: Vec<(String, Box<dyn History>)>,
     input_mode: InputMode,
+    search_replace: SearchReplace,
+
+    // Keyboard macros of the edit modes
+    macros: Macros,
 
     // Validator
     validator: Box<dyn Validator>,
@@ -90,6 +137,8 @@ pub struct Reedline {
 
     // Provides the tab completions
     completer: Box<dyn Completer>,
+    // Shared with the completer it wraps once enabled
+    completion_cache: Option<CompletionCache>,
     quick_completions: bool,
 
     // Performs bash style circular rotation through the available completions
@@ -97,10 +146,24 @@ pub struct Reedline {
 
     // Highlight the edit buffer
     highlighter: Box<dyn Highlighter>,
+    // What the host knows about the line, passed to the highlighter
+    highlight_context: HighlightContext,
 
     // Showcase hints based on various strategies (history, language-completion, spellcheck, etc)
     hinter: Box<dyn Hinter>,
     hide_hints: bool,
+    hint_min_chars: usize,
+    hints_on_paste: bool,
+    paste_in_progress: bool,
+
+    // Handling of the control characters and the final newlines of pasted text
+    paste_policy: PastePolicy,
+    trim_paste_newlines: bool,
+    // Pasted newlines are inserted instead of submitting the buffer
+    confirm_multiline_paste: bool,
+    // The text inserted by the last paste, highlighted until the next key
+    highlight_paste: bool,
+    pasted: Option<Range<usize>>,
 
     // Is Some(n) read_line() should repaint prompt every `n` milliseconds
     animate: bool,
@@ -108,23 +171,126 @@ pub struct Reedline {
     // Use ansi coloring or not
     use_ansi_coloring: bool,
 
+    // Expanded when typed as a command, unless the next expansion is skipped
+    abbreviations: Abbreviations,
+    skip_abbreviation: bool,
+
+    // How the tabs in the buffer are painted
+    tab_display: TabDisplay,
+
+    // Highlight the trailing whitespace and the non-breaking spaces in the buffer
+    visible_whitespace: bool,
+
+    // Style of the matches of a history search or a menu filter in the buffer, in
+    // place of the reversed style of the text
+    search_match_style: Option<Style>,
+
+    // Paint the right-to-left runs of the buffer reversed, for terminals that don't
+    rtl_reordering: bool,
+
+    // Styles of the prompt, the hints and the menus in place of their own
+    theme: Option<Theme>,
+
+    // Keep the output readable by screen readers: no hints or animations
+    screen_reader: bool,
+    // The menu value the hooks were last told about
+    announced_selection: Option<String>,
+
     // Engine Menus
     menus: Vec<Box<dyn Menu>>,
+    // Keys that activate a menu, with the name of the menu, checked before the edit mode
+    menu_triggers: Vec<(KeyCombination, String)>,
+    // Keys that move through a menu while it is open, by menu name
+    menu_navigation: Vec<(String, MenuNavigation)>,
+
+    // Callbacks of the host around each read, and the preview they return for the
+    // buffer, shown below it
+    hooks: Vec<Box<dyn Hook>>,
+    change_debounce: Duration,
+
+    // How long the buffer has to stay unchanged before an open menu collects its
+    // values again, and when the edit waiting for it was made
+    menu_refresh_delay: Duration,
+    menu_refresh_at: Option<Instant>,
+    preview: Option<String>,
+
+    // How long there has to be no input before the history is saved, and when the
+    // last input came since the history changed
+    history_sync_delay: Duration,
+    history_sync_at: Option<Instant>,
+    // The failed saves in a row, which make the next one wait longer, and the last error
+    history_sync_failures: u32,
+    history_sync_error: Option<ReedlineError>,
+    // The history entry selected for deletion, until the next key confirms it
+    pending_deletion: Option<String>,
+
+    // Lines of the host shown below the buffer, until they are cleared
+    status: Vec<String>,
+
+    // Lines printed above the prompt by other threads while reading
+    external_printer: Option<ExternalPrinter>,
+    // Repaints requested by other threads
+    repaint_handle: RepaintHandle,
+    // Text composed with an input method of the host
+    ime: Option<ImeComposition>,
+
+    // Repaints closer together than the frame interval are put off and coalesced
+    frame_interval: Duration,
+    last_repaint: Instant,
+
+    // The bell and the state of the visual bell: requested for the next repaint or
+    // shown until it times out
+    bell: BellStyle,
+    bell_flash: bool,
+    flash_shown: bool,
+    // Set when a menu is activated, to ring the bell if it has no values
+    menu_activated: bool,
+
+    // How the line being read came to be, and the metadata of the last line accepted
+    recalled: bool,
+    completions_accepted: usize,
+    last_line_metadata: Option<LineMetadata>,
+    // The buffer dropped by the last `Signal::CtrlC`
+    dismissed: Option<String>,
+
+    // The terminal shared with other instances, the id of this one in it, and the
+    // handovers seen when this instance last painted
+    terminal_manager: Option<(TerminalManager, usize)>,
+    handovers_seen: u64,
+
+    // Terminal title handling when the engine is dropped
+    title_on_drop: TitleOnDrop,
+    title_set: bool,
 }
 
 impl Drop for Reedline {
     fn drop(&mut self) {
         // Ensures that the terminal is in a good state if we panic semigracefully
-        // Calling `disable_raw_mode()` twice is fine with Linux
-        let _ = terminal::disable_raw_mode();
+        // Calling `disable_raw_mode()` twice is fine with Linux. Another instance
+        // sharing the terminal may still be reading
+        let shared_read = self
+            .terminal_manager
+            .as_ref()
+            .is_some_and(|(manager, _)| manager.is_reading());
+        if !shared_read {
+            let _ = self.painter.backend().disable_raw_mode();
+        }
+
+        if self.title_set {
+            let _ = match self.title_on_drop {
+                TitleOnDrop::Keep => Ok(()),
+                TitleOnDrop::Clear => self.painter.set_title(""),
+                TitleOnDrop::Restore => self.painter.pop_title(),
+            };
+        }
     }
 }
 
 impl Reedline {
     /// Create a new [`Reedline`] engine with a local [`History`] that is not synchronized to a file.
-    pub fn create() -> io::Result<Reedline> {
+    pub fn create() -> Result<Reedline> {
         let history = Box::new(FileBackedHistory::default());
-        let painter = Painter::new(std::io::BufWriter::new(std::io::stderr()));
+        let painter = Painter::new(Box::new(CrosstermBackend::default()));
         let buffer_highlighter = Box::new(ExampleHighlighter::default());
         let completer = Box::new(DefaultCompleter::default());
         let hinter = Box::new(DefaultHinter::default());
@@ -134,19 +300,72 @@ impl Reedline {
         let reedline = Reedline {
             editor: Editor::default(),
             history,
+            history_name: DEFAULT_HISTORY_NAME.to_string(),
+            other_histories: Vec::new(),
             input_mode: InputMode::Regular,
+            search_replace: SearchReplace::default(),
+            macros: Macros::default(),
             painter,
             edit_mode,
             completer,
+            completion_cache: None,
             quick_completions: false,
             circular_completion_handler: CircularCompletionHandler::default(),
             highlighter: buffer_highlighter,
+            highlight_context: HighlightContext::default(),
             hinter,
             hide_hints: false,
+            hint_min_chars: 0,
+            hints_on_paste: false,
+            paste_in_progress: false,
+            paste_policy: PastePolicy::default(),
+            trim_paste_newlines: false,
+            confirm_multiline_paste: false,
+            highlight_paste: false,
+            pasted: None,
             validator,
             animate: false,
             use_ansi_coloring: true,
+            abbreviations: Abbreviations::new(),
+            skip_abbreviation: false,
+            tab_display: TabDisplay::default(),
+            visible_whitespace: false,
+            search_match_style: None,
+            rtl_reordering: false,
+            theme: None,
+            screen_reader: false,
+            announced_selection: None,
             menus: Vec::new(),
+            menu_triggers: Vec::new(),
+            menu_navigation: Vec::new(),
+            hooks: Vec::new(),
+            change_debounce: DEFAULT_CHANGE_DEBOUNCE,
+            menu_refresh_delay: Duration::ZERO,
+            history_sync_delay: DEFAULT_HISTORY_SYNC_DELAY,
+            history_sync_at: None,
+            history_sync_failures: 0,
+            history_sync_error: None,
+            pending_deletion: None,
+            menu_refresh_at: None,
+            preview: None,
+            status: Vec::new(),
+            external_printer: None,
+            repaint_handle: RepaintHandle::default(),
+            ime: None,
+            frame_interval: Duration::ZERO,
+            last_repaint: Instant::now(),
+            bell: BellStyle::None,
+            bell_flash: false,
+            flash_shown: false,
+            menu_activated: false,
+            recalled: false,
+            completions_accepted: 0,
+            last_line_metadata: None,
+            dismissed: None,
+            terminal_manager: None,
+            handovers_seen: 0,
+            title_on_drop: TitleOnDrop::Keep,
+            title_set: false,
         };
 
         Ok(reedline)
@@ -175,6 +394,63 @@ impl Reedline {
         self
     }
 
+    /// A builder that sets the number of characters the buffer needs before any hint is shown,
+    /// regardless of the configured hinter
+    pub fn with_hint_min_chars(mut self, min_chars: usize) -> Reedline {
+        self.hint_min_chars = min_chars;
+        self
+    }
+
+    /// A builder which enables or disables hints while text is being pasted.
+    /// Hints are hidden during a paste by default until the next key is typed
+    ///
+    /// The paste is not reported by the terminal, it is guessed from the keys arriving
+    /// faster than they can be typed: more than a few in one batch, or more still waiting
+    /// after it. Keys typed very quickly can count as a paste too, and a paste short
+    /// enough to arrive in a small batch doesn't hide the hints
+    pub fn with_hints_on_paste(mut self, hints_on_paste: bool) -> Reedline {
+        self.hints_on_paste = hints_on_paste;
+        self
+    }
+
+    /// A builder that sets what happens to the control characters of pasted text.
+    /// They run their keybindings like typed ones by default
+    ///
+    /// # Example
+    /// ```rust
+    /// use reedline::{PastePolicy, Reedline};
+    ///
+    /// let line_editor = Reedline::create()?
+    ///     .with_paste_policy(PastePolicy::Strip)
+    ///     .with_trim_paste_newlines(true);
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn with_paste_policy(mut self, paste_policy: PastePolicy) -> Reedline {
+        self.paste_policy = paste_policy;
+        self
+    }
+
+    /// A builder which drops the newlines at the end of pasted text, so it is not
+    /// submitted by the paste itself
+    pub fn with_trim_paste_newlines(mut self, trim_paste_newlines: bool) -> Reedline {
+        self.trim_paste_newlines = trim_paste_newlines;
+        self
+    }
+
+    /// A builder which inserts all the lines of pasted text into the buffer, so they
+    /// are only submitted by an explicit Enter instead of running one by one as pasted
+    pub fn with_confirm_multiline_paste(mut self, confirm_multiline_paste: bool) -> Reedline {
+        self.confirm_multiline_paste = confirm_multiline_paste;
+        self
+    }
+
+    /// A builder which shows the text inserted by a paste in reverse video, like a
+    /// selection, until the next key is pressed
+    pub fn with_highlight_paste(mut self, highlight_paste: bool) -> Reedline {
+        self.highlight_paste = highlight_paste;
+        self
+    }
+
     /// A builder to configure the tab completion
     /// # Example
     /// ```rust,no_run
@@ -195,7 +471,30 @@ impl Reedline {
     /// # Ok::<(), io::Error>(())
     /// ```
     pub fn with_completer(mut self, completer: Box<dyn Completer>) -> Reedline {
-        self.completer = completer;
+        self.completer = match &self.completion_cache {
+            Some(cache) => cache.wrap(completer),
+            None => completer,
+        };
+        self
+    }
+
+    /// A builder that keeps the results of the completer while the text before the
+    /// completed word and after the cursor stays the same, so editing the word, like
+    /// backspacing over it, doesn't run an expensive completer again for text it
+    /// already completed. The results are dropped when a new line is read
+    ///
+    /// Completers whose results change while a line is edited shouldn't be cached
+    pub fn with_completion_cache(mut self, enabled: bool) -> Reedline {
+        if self.completion_cache.is_none() && enabled {
+            let cache = CompletionCache::default();
+            let completer =
+                std::mem::replace(&mut self.completer, Box::new(DefaultCompleter::default()));
+            self.completer = cache.wrap(completer);
+            self.completion_cache = Some(cache);
+        }
+        if let Some(cache) = &self.completion_cache {
+            cache.set_enabled(enabled);
+        }
         self
     }
 
@@ -213,6 +512,98 @@ impl Reedline {
         self
     }
 
+    /// A builder that sets the abbreviations expanded when they are typed as a command
+    pub fn with_abbreviations(mut self, abbreviations: Abbreviations) -> Reedline {
+        self.abbreviations = abbreviations;
+        self
+    }
+
+    /// The abbreviations expanded when they are typed as a command
+    pub fn abbreviations(&self) -> &Abbreviations {
+        &self.abbreviations
+    }
+
+    /// Changes the abbreviations between reading lines
+    ///
+    /// ```rust
+    /// use reedline::Reedline;
+    ///
+    /// let mut line_editor = Reedline::create()?;
+    /// line_editor.abbreviations_mut().add("gco", "git checkout");
+    /// assert_eq!(line_editor.abbreviations().get("gco"), Some("git checkout"));
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn abbreviations_mut(&mut self) -> &mut Abbreviations {
+        &mut self.abbreviations
+    }
+
+    /// A builder that sets how the tab characters in the buffer are painted
+    ///
+    /// By default a tab is painted as four spaces
+    ///
+    /// ```rust
+    /// use reedline::{Reedline, TabDisplay};
+    ///
+    /// let line_editor = Reedline::create()?.with_tab_display(TabDisplay::Arrow(4));
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn with_tab_display(mut self, tab_display: TabDisplay) -> Reedline {
+        self.tab_display = tab_display;
+        self
+    }
+
+    /// A builder that sets the style of the occurrences of the search term in the
+    /// buffer, while the history is searched or an open menu filters its values by
+    /// the text of the buffer
+    ///
+    /// By default the matches keep the style of the highlighter in reverse video
+    pub fn with_search_match_style(mut self, style: Style) -> Reedline {
+        self.search_match_style = Some(style);
+        self
+    }
+
+    /// A builder which enables or disables highlighting the trailing whitespace and
+    /// the spaces that look like a normal one, like the non-breaking space, in the buffer
+    ///
+    /// These characters are often pasted from web pages and make commands fail
+    /// in ways that are hard to see
+    pub fn with_visible_whitespace(mut self, visible_whitespace: bool) -> Reedline {
+        self.visible_whitespace = visible_whitespace;
+        self
+    }
+
+    /// A builder that paints the right-to-left runs of the buffer, like Hebrew or Arabic
+    /// words, reversed, for terminals that lay out every line from left to right.
+    /// The left and right arrows then move over the text as it is shown
+    ///
+    /// Terminals that reorder right-to-left text themselves need it off, the default
+    pub fn with_rtl_reordering(mut self, rtl_reordering: bool) -> Reedline {
+        self.rtl_reordering = rtl_reordering;
+        self.editor.set_visual_movement(rtl_reordering);
+        self
+    }
+
+    /// A builder that paints the prompt, the hints and the menus with the styles of
+    /// the [`Theme`], like one of the built-in ones picked with [`Theme::by_name`]
+    pub fn with_theme(mut self, theme: Theme) -> Reedline {
+        self.theme = Some(theme);
+        self.painter.set_theme(Some(theme));
+        self
+    }
+
+    /// A builder that makes the editor usable with a screen reader.
+    ///
+    /// Text typed at the end of the buffer is printed on its own instead of repainting
+    /// the line, so the reader speaks the typed text and not the whole line again.
+    /// The cursor stays visible while painting, and the hints and the animations,
+    /// which the reader would read out on every change, are turned off. The value
+    /// selected in a menu is told to the [`Hook::on_menu_selection`] of the hooks
+    pub fn with_screen_reader_mode(mut self, screen_reader: bool) -> Reedline {
+        self.screen_reader = screen_reader;
+        self.painter.set_screen_reader(screen_reader);
+        self
+    }
+
     /// A builder which enables or disables animations/automatic repainting of prompt.
     /// If `repaint` is true, every second the prompt will be repainted and the clock updates
     pub fn with_animation(mut self, repaint: bool) -> Reedline {
@@ -260,12 +651,40 @@ impl Reedline {
     ///     .expect("Error configuring reedline with history");
     /// # Ok::<(), io::Error>(())
     /// ```
-    pub fn with_history(mut self, history: Box<dyn History>) -> std::io::Result<Reedline> {
+    pub fn with_history(mut self, history: Box<dyn History>) -> Result<Reedline> {
         self.history = history;
 
         Ok(self)
     }
 
+    /// A builder that adds a history the engine can switch to with
+    /// [`Reedline::switch_history`], for prompts that keep their lines apart, like a
+    /// shell and a REPL embedded in it. The history set with [`Reedline::with_history`]
+    /// is named `"default"`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use reedline::{FileBackedHistory, Reedline};
+    ///
+    /// let mut line_editor = Reedline::create()?
+    ///     .with_named_history("python", Box::new(FileBackedHistory::default()));
+    ///
+    /// line_editor.switch_history("python")?;
+    /// assert_eq!(line_editor.history_name(), "python");
+    /// line_editor.switch_history("default")?;
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn with_named_history(mut self, name: &str, history: Box<dyn History>) -> Reedline {
+        if name == self.history_name {
+            self.history = history;
+        } else {
+            self.other_histories.retain(|(other, _)| other != name);
+            self.other_histories.push((name.to_string(), history));
+        }
+        self
+    }
+
     /// A builder that configures the validator for your instance of the Reedline engine
     /// # Example
     /// ```rust,no_run
@@ -283,6 +702,14 @@ impl Reedline {
         self
     }
 
+    /// A builder that shares the terminal with the other instances using the same
+    /// [`TerminalManager`], so one can read while another one waits for it
+    pub fn with_terminal_manager(mut self, manager: TerminalManager) -> Reedline {
+        let id = manager.register();
+        self.terminal_manager = Some((manager, id));
+        self
+    }
+
     /// A builder which configures the edit mode for your instance of the Reedline engine
     pub fn with_edit_mode(mut self, edit_mode: Box<dyn EditMode>) -> Reedline {
         self.edit_mode = edit_mode;
@@ -295,18 +722,254 @@ impl Reedline {
         self
     }
 
+    /// A builder that appends a menu activated by its own key, whatever the edit mode
+    /// binds to it. Pressing the key again while the menu is open selects its next value
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use reedline::{CompletionMenu, HistoryMenu, Reedline};
+    ///
+    /// let line_editor = Reedline::create()?
+    ///     .with_menu_trigger(Box::new(CompletionMenu::default()), "tab".parse()?)
+    ///     .with_menu_trigger(Box::new(HistoryMenu::default()), "ctrl-x".parse()?);
+    ///
+    /// assert_eq!(line_editor.menu_names(), vec!["completion_menu", "history_menu"]);
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn with_menu_trigger(mut self, menu: Box<dyn Menu>, trigger: KeyCombination) -> Reedline {
+        self.set_menu_trigger(menu.name(), Some(trigger));
+        self.replace_menu(menu);
+        self
+    }
+
+    /// A builder that adds a [`Hook`] called around each read. Hooks are called in the
+    /// order they were added
+    pub fn with_hook(mut self, hook: Box<dyn Hook>) -> Reedline {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// A builder that sets how long the buffer has to stay unchanged before
+    /// [`Hook::on_change`] is called. It is 100 milliseconds by default
+    pub fn with_change_debounce(mut self, change_debounce: Duration) -> Reedline {
+        self.change_debounce = change_debounce;
+        self
+    }
+
+    /// A builder that sets how long the buffer has to stay unchanged before an open
+    /// menu collects its values again, for example 80 milliseconds, so typing fast
+    /// with a slow completer doesn't run it for every key. The menu keeps showing
+    /// its values until then, and a key used on the menu, like Enter, collects them
+    /// right away. Quick completions aren't accepted by the late refresh
+    ///
+    /// By default the values are collected again after every edit
+    pub fn with_menu_refresh_delay(mut self, delay: Duration) -> Reedline {
+        self.menu_refresh_delay = delay;
+        self
+    }
+
+    /// A builder that sets how long there has to be no input before the new history
+    /// entries are saved with [`History::sync`], so a session that crashes or is
+    /// killed doesn't lose its lines. It is one second by default
+    ///
+    /// A save that fails is tried again after the next pause, which doubles with
+    /// every failure in a row. See [`Reedline::history_sync_error`]
+    pub fn with_history_sync_delay(mut self, delay: Duration) -> Reedline {
+        self.history_sync_delay = delay;
+        self
+    }
+
+    /// The error of the last history save made while waiting for input, until a
+    /// save succeeds again
+    pub fn history_sync_error(&self) -> Option<&ReedlineError> {
+        self.history_sync_error.as_ref()
+    }
+
+    /// A builder that sets the shortest time between two repaints. When keys arrive
+    /// faster, for example from key repeat, the changes are painted together once
+    /// the interval has passed
+    ///
+    /// Every change is painted right away by default.
+    ///
+    /// # Example
+    /// ```rust
+    /// use reedline::Reedline;
+    /// use std::time::Duration;
+    ///
+    /// // At most 60 repaints per second
+    /// let line_editor = Reedline::create()?.with_frame_interval(Duration::from_millis(16));
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn with_frame_interval(mut self, frame_interval: Duration) -> Reedline {
+        self.frame_interval = frame_interval;
+        self
+    }
+
+    /// A builder that sets where the status lines go while a menu is shown. They
+    /// are below the menu by default
+    pub fn with_status_position(mut self, status_position: StatusPosition) -> Reedline {
+        self.painter.set_status_position(status_position);
+        self
+    }
+
+    /// A builder that keeps the prompt on the last rows of the terminal, like the input
+    /// line of a chat client. The output printed between reads and the lines of the
+    /// [`ExternalPrinter`] scroll up above it
+    pub fn with_prompt_at_bottom(mut self, prompt_at_bottom: bool) -> Reedline {
+        self.painter.set_prompt_at_bottom(prompt_at_bottom);
+        self
+    }
+
+    /// A builder that prints the lines of the [`ExternalPrinter`] above the prompt
+    /// while reading. They are checked every 100 milliseconds
+    pub fn with_external_printer(mut self, external_printer: ExternalPrinter) -> Reedline {
+        self.external_printer = Some(external_printer);
+        self
+    }
+
+    /// A builder that shows the text composed with the input method of the host at
+    /// the cursor and inserts the text picked from it. See [`ImeComposition`]
+    pub fn with_ime_composition(mut self, composition: ImeComposition) -> Reedline {
+        self.ime = Some(composition);
+        self
+    }
+
+    /// A builder that brings the inserted text to the Unicode normalization form C.
+    ///
+    /// Letters followed by combining accents, like in the file names pasted from macOS,
+    /// are stored as the precomposed characters, so the accepted lines and the history
+    /// don't hold visually equal but different entries. Off by default
+    pub fn with_unicode_normalization(mut self, normalize: bool) -> Reedline {
+        self.editor.set_normalization(normalize);
+        self
+    }
+
     /// A builder which configures the painter for debug mode
     pub fn with_debug_mode(mut self) -> Reedline {
-        self.painter = Painter::new_with_debug(std::io::BufWriter::new(std::io::stderr()));
+        self.painter.set_debug_mode(true);
+
+        self
+    }
+
+    /// A builder that collects each repaint to write it to the terminal at once.
+    ///
+    /// Repaints that didn't change are skipped and the cursor isn't hidden while
+    /// painting, which reduces the output over slow connections like SSH or serial
+    /// consoles.
+    pub fn with_batched_output(mut self, batched_output: bool) -> Reedline {
+        self.painter.set_batched_output(batched_output);
+
+        self
+    }
 
+    /// A builder that sets how the bell is rung: when completing finds no candidates,
+    /// when the history search fails or when a key without binding is pressed
+    pub fn with_bell(mut self, bell: BellStyle) -> Reedline {
+        self.bell = bell;
         self
     }
 
+    /// A builder that sets what happens to the terminal title set with
+    /// [`Reedline::set_title()`] when the engine is dropped
+    pub fn with_title_on_drop(mut self, title_on_drop: TitleOnDrop) -> Reedline {
+        self.title_on_drop = title_on_drop;
+        self
+    }
+
+    /// A builder that replaces the terminal the engine reads events from and paints on.
+    ///
+    /// By default the terminal of the process is used through a [`CrosstermBackend`].
+    pub fn with_backend(mut self, backend: Box<dyn TerminalBackend>) -> Reedline {
+        self.painter.set_backend(backend);
+
+        self
+    }
+
+    /// Where the prompt of the last read was painted and where it left the cursor, so
+    /// the host can keep drawing below it. `None` before the first read ends and on
+    /// terminals that can't move the cursor
+    pub fn last_prompt_layout(&self) -> Option<PromptLayout> {
+        self.painter.last_layout()
+    }
+
+    /// How the line returned by the last [`Signal::Success`] came to be. `None` if the
+    /// last read ended otherwise
+    pub fn last_line_metadata(&self) -> Option<LineMetadata> {
+        self.last_line_metadata
+    }
+
+    /// Records what the host learned running the line of the last [`Signal::Success`],
+    /// like its exit status, with the entry added to the history for it
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use reedline::{DefaultPrompt, HistoryEntryMetadata, Reedline, Signal};
+    /// use std::time::Instant;
+    ///
+    /// let mut line_editor = Reedline::create()?;
+    /// if let Ok(Signal::Success(line)) = line_editor.read_line(&DefaultPrompt::default()) {
+    ///     let started = Instant::now();
+    ///     let status = std::process::Command::new("sh").args(["-c", &line]).status()?;
+    ///     line_editor.update_last_history_entry(HistoryEntryMetadata {
+    ///         exit_status: status.code(),
+    ///         duration: Some(started.elapsed()),
+    ///     });
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn update_last_history_entry(&mut self, metadata: HistoryEntryMetadata) {
+        self.history.update_last(metadata);
+    }
+
+    /// The text that was in the buffer when the last read ended with [`Signal::CtrlC`],
+    /// for hosts that keep dismissed lines to recover them later. `None` if the buffer
+    /// was empty or the last read ended otherwise
+    pub fn dismissed_buffer(&self) -> Option<&str> {
+        self.dismissed.as_deref()
+    }
+
+    /// Name of the history the lines are read with
+    pub fn history_name(&self) -> &str {
+        &self.history_name
+    }
+
+    /// Reads the next lines with the history of the given name, added with
+    /// [`Reedline::with_named_history`]. The history in use is kept to switch back to it
+    pub fn switch_history(&mut self, name: &str) -> Result<()> {
+        if name == self.history_name {
+            return Ok(());
+        }
+        let index = self
+            .other_histories
+            .iter()
+            .position(|(other, _)| other == name)
+            .ok_or_else(|| ReedlineError::Config(format!("no history named {}", name)))?;
+
+        let (name, history) = self.other_histories.remove(index);
+        let mut previous = std::mem::replace(&mut self.history, history);
+        if self.history_sync_at.take().is_some() {
+            // The history is saved again when it is dropped
+            let _ = previous.sync();
+        }
+        let previous_name = std::mem::replace(&mut self.history_name, name);
+        self.other_histories.push((previous_name, previous));
+        self.input_mode = InputMode::Regular;
+        Ok(())
+    }
+
     /// Returns the corresponding expected prompt style for the given edit mode
     pub fn prompt_edit_mode(&self) -> PromptEditMode {
         self.edit_mode.edit_mode()
     }
 
+    /// A handle to repaint the prompt from other threads while a line is read, for
+    /// example when the state shown in the prompt changed
+    pub fn repaint_handle(&self) -> RepaintHandle {
+        self.repaint_handle.clone()
+    }
+
     /// Output the complete [`History`] chronologically with numbering to the terminal
     pub fn print_history(&mut self) -> Result<()> {
         let history: Vec<_> = self
@@ -317,61 +980,358 @@ impl Reedline {
             .collect();
 
         for (i, entry) in history {
-            self.print_line(&format!("{}\t{}", i + 1, entry))?;
+            self.print_line(&format!("{}\t{}", i + 1, entry))
+                .map_err(ReedlineError::terminal)?;
         }
         Ok(())
     }
 
     /// Wait for input and provide the user with a specified [`Prompt`].
     ///
-    /// Returns a [`crossterm::Result`] in which the `Err` type is [`crossterm::ErrorKind`]
-    /// to distinguish I/O errors and the `Ok` variant wraps a [`Signal`] which
-    /// handles user inputs.
+    /// Returns a [`ReedlineError`] when the terminal fails or the read is interrupted,
+    /// otherwise the `Ok` variant wraps a [`Signal`] which handles user inputs.
     pub fn read_line(&mut self, prompt: &dyn Prompt) -> Result<Signal> {
-        terminal::enable_raw_mode()?;
+        // Only the outermost of the reads sharing the terminal switches the raw mode
+        let outermost = self
+            .terminal_manager
+            .as_ref()
+            .map_or(true, |(manager, id)| manager.begin_read(*id));
+        if outermost {
+            if let Err(error) = self.painter.backend().enable_raw_mode() {
+                self.end_shared_read();
+                return Err(ReedlineError::terminal(error));
+            }
+        }
 
-        let result = self.read_line_helper(prompt);
+        // Restores the terminal even if a completer, hinter or other callback panics
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.read_line_helper(prompt)));
+        // A menu on the alternate screen doesn't outlive the read, even after an error
+        let left = self.painter.leave_alternate_screen();
+        let restored = if self.end_shared_read() {
+            self.painter.backend().disable_raw_mode()
+        } else {
+            Ok(())
+        };
+        let result = result.unwrap_or_else(|panic| panic::resume_unwind(panic));
 
-        terminal::disable_raw_mode()?;
+        restored.map_err(ReedlineError::terminal)?;
+        left.map_err(ReedlineError::terminal)?;
 
         result
     }
 
+    /// Ends the read in the shared terminal. Returns whether the raw mode is to be disabled
+    fn end_shared_read(&mut self) -> bool {
+        self.terminal_manager
+            .as_ref()
+            .map_or(true, |(manager, id)| manager.end_read(*id))
+    }
+
     /// Writes `msg` to the terminal with a following carriage return and newline
-    fn print_line(&mut self, msg: &str) -> Result<()> {
+    fn print_line(&mut self, msg: &str) -> io::Result<()> {
         self.painter.paint_line(msg)
     }
 
+    /// Sets the title of the terminal window.
+    ///
+    /// The title is written through the same output as the prompt, so it can be
+    /// changed between or during reads without interfering with the repaints.
+    pub fn set_title(&mut self, title: &str) -> Result<()> {
+        if !self.title_set && self.title_on_drop == TitleOnDrop::Restore {
+            self.painter.push_title().map_err(ReedlineError::terminal)?;
+        }
+        self.title_set = true;
+
+        self.painter
+            .set_title(title)
+            .map_err(ReedlineError::terminal)
+    }
+
+    /// Sets the lines shown below the buffer, like a live preview, lint messages or
+    /// contextual help. They can be styled with ANSI escape sequences and stay
+    /// across reads until they are changed or cleared
+    ///
+    /// The lines are hidden on the last repaint of a submitted buffer, and aren't
+    /// shown on terminals without cursor movement.
+    ///
+    /// # Example
+    /// ```rust
+    /// use nu_ansi_term::Color;
+    /// use reedline::Reedline;
+    ///
+    /// let mut line_editor = Reedline::create()?;
+    /// line_editor.set_status(vec![
+    ///     Color::Yellow.paint("3 uncommitted changes").to_string(),
+    ///     "on branch main".to_string(),
+    /// ]);
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn set_status(&mut self, lines: Vec<String>) {
+        self.status = lines;
+    }
+
+    /// Removes the lines shown below the buffer
+    pub fn clear_status(&mut self) {
+        self.status.clear();
+    }
+
+    /// Sets what the host knows about the line for the highlighter, like the commands
+    /// that exist or the directory they run in. It can change between or during reads
+    pub fn set_highlight_context(&mut self, context: HighlightContext) {
+        self.highlight_context = context;
+    }
+
+    /// The context passed to the highlighter, to update a part of it
+    pub fn highlight_context_mut(&mut self) -> &mut HighlightContext {
+        &mut self.highlight_context
+    }
+
+    /// The names of the menus of the engine, in the order they were added
+    pub fn menu_names(&self) -> Vec<&str> {
+        self.menus.iter().map(|menu| menu.name()).collect()
+    }
+
+    /// The key that activates the menu with the given name, if it has one
+    pub fn menu_trigger(&self, name: &str) -> Option<&KeyCombination> {
+        self.menu_triggers
+            .iter()
+            .find(|(_, menu)| menu == name)
+            .map(|(trigger, _)| trigger)
+    }
+
+    /// Sets the key that activates the menu with the given name, or removes it with
+    /// `None`. A key only triggers one menu, so it is taken from any other menu
+    pub fn set_menu_trigger(&mut self, name: &str, trigger: Option<KeyCombination>) {
+        self.menu_triggers
+            .retain(|(key, menu)| menu != name && Some(key) != trigger.as_ref());
+        if let Some(trigger) = trigger {
+            self.menu_triggers.push((trigger, name.to_string()));
+        }
+    }
+
+    /// The keys that move through the menu with the given name, if it has its own
+    pub fn menu_navigation(&self, name: &str) -> Option<&MenuNavigation> {
+        self.menu_navigation
+            .iter()
+            .find(|(menu, _)| menu == name)
+            .map(|(_, navigation)| navigation)
+    }
+
+    /// Sets the keys that move through the menu with the given name while it is open.
+    /// They are used in place of the bindings of the edit mode, which still handle the
+    /// keys the menu leaves unset
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use reedline::{CompletionMenu, MenuNavigation, Reedline};
+    ///
+    /// let mut line_editor =
+    ///     Reedline::create()?.with_menu(Box::new(CompletionMenu::default()));
+    /// line_editor.set_menu_navigation(
+    ///     "completion_menu",
+    ///     MenuNavigation {
+    ///         next: Some("ctrl-n".parse()?),
+    ///         previous: Some("ctrl-p".parse()?),
+    ///     },
+    /// );
+    /// # Ok::<(), reedline::ReedlineError>(())
+    /// ```
+    pub fn set_menu_navigation(&mut self, name: &str, navigation: MenuNavigation) {
+        self.menu_navigation.retain(|(menu, _)| menu != name);
+        self.menu_navigation.push((name.to_string(), navigation));
+    }
+
+    /// Puts the menu in place of the menu with the same name, keeping its trigger key,
+    /// and returns the menu it replaced. A menu with a new name is appended
+    pub fn replace_menu(&mut self, menu: Box<dyn Menu>) -> Option<Box<dyn Menu>> {
+        match self.menus.iter_mut().find(|old| old.name() == menu.name()) {
+            Some(old) => Some(std::mem::replace(old, menu)),
+            None => {
+                self.menus.push(menu);
+                None
+            }
+        }
+    }
+
+    /// Removes the menu with the given name, together with its keys
+    pub fn remove_menu(&mut self, name: &str) -> Option<Box<dyn Menu>> {
+        self.set_menu_trigger(name, None);
+        self.menu_navigation.retain(|(menu, _)| menu != name);
+        let index = self.menus.iter().position(|menu| menu.name() == name)?;
+        Some(self.menus.remove(index))
+    }
+
     /// Clear the screen by printing enough whitespace to start the prompt or
     /// other output back at the first line of the terminal.
     pub fn clear_screen(&mut self) -> Result<()> {
-        self.painter.clear_screen()?;
+        self.painter
+            .clear_screen()
+            .map_err(ReedlineError::terminal)?;
 
         Ok(())
     }
 
+    /// Clears the screen, and the scrollback if asked, while reading. The prompt is
+    /// painted again on the first line with the same buffer and an open menu is closed
+    fn clear_terminal(&mut self, scrollback: bool) -> io::Result<EventStatus> {
+        self.menus
+            .iter_mut()
+            .for_each(|menu| menu.menu_event(MenuEvent::Deactivate));
+
+        if scrollback {
+            self.painter.clear_scrollback()?;
+        } else {
+            self.painter.clear_screen()?;
+        }
+
+        Ok(EventStatus::Handled)
+    }
+
     /// Helper implementing the logic for [`Reedline::read_line()`] to be wrapped
     /// in a `raw_mode` context.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn read_line_helper(&mut self, prompt: &dyn Prompt) -> Result<Signal> {
-        self.painter.initialize_prompt_position()?;
+        for hook in self.hooks.iter_mut() {
+            hook.before_read();
+        }
+
+        self.painter
+            .initialize_prompt_position()
+            .map_err(ReedlineError::terminal)?;
+        if let Some((manager, _)) = &self.terminal_manager {
+            self.handovers_seen = manager.handovers();
+        }
         self.hide_hints = false;
+        self.paste_in_progress = false;
+        self.pasted = None;
+        self.preview = None;
+        self.recalled = false;
+        self.completions_accepted = 0;
+        self.menu_refresh_at = None;
+        self.last_line_metadata = None;
+        self.dismissed = None;
+        self.pending_deletion = None;
+        if let Some(cache) = &self.completion_cache {
+            cache.clear();
+        }
 
-        self.repaint(prompt)?;
+        self.repaint(prompt).map_err(ReedlineError::terminal)?;
+        let started = Instant::now();
+        // The buffer the hooks were last told about, and when it last changed since
+        let mut last_buffer = self.editor.get_buffer().to_string();
+        let mut changed_at: Option<Instant> = None;
 
         let mut crossterm_events: Vec<Event> = vec![];
         let mut reedline_events: Vec<ReedlineEvent> = vec![];
+        // The lines of a paste after the first arrive in separate batches
+        let mut paste_continues = false;
+        let mut paste_rejected = false;
+        // A change is waiting for the frame interval to pass to be painted
+        let mut repaint_pending = false;
 
         loop {
             let mut paste_enter_state = false;
 
-            if event::poll(Duration::from_millis(1000))? {
-                let mut latest_resize = None;
-
+            let printed = self
+                .external_printer
+                .as_ref()
+                .map(ExternalPrinter::take)
+                .unwrap_or_default();
+            if !printed.is_empty() {
+                self.painter
+                    .print_above(&printed)
+                    .map_err(ReedlineError::terminal)?;
+            }
+            let (committed, composing) = self
+                .ime
+                .as_ref()
+                .map(ImeComposition::take_changes)
+                .unwrap_or_default();
+            // The picked text is typed at once, as a single edit
+            for text in committed {
+                self.handle_event(
+                    prompt,
+                    ReedlineEvent::Edit(vec![EditCommand::InsertString(text)]),
+                )
+                .map_err(ReedlineError::terminal)?;
+            }
+            if self.repaint_handle.take_request() || !printed.is_empty() || composing {
+                self.repaint(prompt).map_err(ReedlineError::terminal)?;
+            }
+
+            let pending_menu = self.active_menu().is_some_and(|menu| menu.is_pending());
+            let pending_hint = self.hints_active() && self.hinter.is_pending();
+            let wait = if self.flash_shown {
+                BELL_FLASH_WAIT
+            } else if pending_menu || pending_hint {
+                PENDING_POLL_WAIT
+            } else if self.external_printer.is_some()
+                || self.ime.is_some()
+                || self.repaint_handle.is_shared()
+            {
+                EXTERNAL_WAIT
+            } else {
+                1000
+            };
+            let wait = match changed_at {
+                Some(at) => {
+                    let remaining = self.change_debounce.saturating_sub(at.elapsed());
+                    wait.min(remaining.as_millis() as u64)
+                }
+                None => wait,
+            };
+            let wait = match self.menu_refresh_at {
+                Some(at) => {
+                    let remaining = self.menu_refresh_delay.saturating_sub(at.elapsed());
+                    wait.min(remaining.as_millis() as u64)
+                }
+                None => wait,
+            };
+            let wait = match self.history_sync_at {
+                Some(at) => {
+                    let remaining = self.history_sync_wait().saturating_sub(at.elapsed());
+                    wait.min(remaining.as_millis() as u64)
+                }
+                None => wait,
+            };
+            let wait = if repaint_pending {
+                let remaining = self
+                    .frame_interval
+                    .saturating_sub(self.last_repaint.elapsed());
+                wait.min(remaining.as_millis() as u64)
+            } else {
+                wait
+            };
+
+            if !crossterm_events.is_empty()
+                || self
+                    .painter
+                    .backend()
+                    .poll_event(Duration::from_millis(wait))
+                    .map_err(ReedlineError::terminal)?
+            {
+                let mut latest_resize = None;
+                if self.history_sync_at.is_some() {
+                    // The history waits for the input to pause
+                    self.history_sync_at = Some(Instant::now());
+                }
+
                 // There could be multiple events queued up!
                 // pasting text, resizes, blocking this thread (e.g. during debugging)
                 // We should be able to handle all of them as quickly as possible without causing unnecessary output steps.
-                while event::poll(Duration::from_millis(POLL_WAIT))? {
-                    match event::read()? {
+                while self
+                    .painter
+                    .backend()
+                    .poll_event(Duration::from_millis(POLL_WAIT))
+                    .map_err(ReedlineError::terminal)?
+                {
+                    match self
+                        .painter
+                        .backend()
+                        .read_event()
+                        .map_err(ReedlineError::terminal)?
+                    {
                         Event::Resize(x, y) => {
                             latest_resize = Some((x, y));
                         }
@@ -398,12 +1358,58 @@ impl Reedline {
                     reedline_events.push(ReedlineEvent::Resize(x, y));
                 }
 
+                // A large batch of events or events still waiting to be read after
+                // an early break point to text being pasted rather than typed
+                let events_waiting = self
+                    .painter
+                    .backend()
+                    .poll_event(Duration::from_millis(0))
+                    .map_err(ReedlineError::terminal)?;
+                self.paste_in_progress =
+                    crossterm_events.len() > EVENTS_THRESHOLD || events_waiting || paste_continues;
+                if paste_rejected {
+                    // The rest of a rejected paste
+                    crossterm_events.clear();
+                } else if self.paste_in_progress
+                    && !sanitize_paste(
+                        &mut crossterm_events,
+                        self.paste_policy,
+                        self.trim_paste_newlines,
+                        !events_waiting,
+                    )
+                {
+                    paste_rejected = true;
+                    self.ring_bell().map_err(ReedlineError::terminal)?;
+                }
+                paste_continues = self.paste_in_progress && events_waiting;
+                paste_rejected = paste_rejected && paste_continues;
+                #[cfg(feature = "tracing")]
+                tracing::trace!(
+                    events = crossterm_events.len(),
+                    paste = self.paste_in_progress,
+                    "read events"
+                );
+
                 // Accelerate pasted text by fusing `EditCommand`s
                 //
                 // (Text should only be `EditCommand::InsertChar`s)
                 let mut last_edit_commands = None;
+                // A key moving through a menu waits for the events before it to be
+                // handled, as they may open or close the menu
+                let waiting = crossterm_events
+                    .iter()
+                    .skip(1)
+                    .position(|event| self.is_menu_navigation_key(event))
+                    .map_or(Vec::new(), |index| crossterm_events.split_off(index + 1));
+                let mut unbound_key = false;
                 for event in crossterm_events.drain(..) {
-                    match (&mut last_edit_commands, self.edit_mode.parse_event(event)) {
+                    let reedline_event = self.parse_key_event(event);
+                    self.macros
+                        .record(event, &reedline_event, self.edit_mode.has_pending_keys());
+                    unbound_key |=
+                        reedline_event == ReedlineEvent::None && !self.edit_mode.has_pending_keys();
+
+                    match (&mut last_edit_commands, reedline_event) {
                         (None, ReedlineEvent::Edit(ec)) => {
                             last_edit_commands = Some(ec);
                         }
@@ -423,20 +1429,72 @@ impl Reedline {
                 if let Some(ec) = last_edit_commands {
                     reedline_events.push(ReedlineEvent::Edit(ec));
                 }
-            } else if self.animate && !self.painter.exceeds_screen_size() {
+                // The bell rings once for the batch, and not for the keys of a paste
+                if unbound_key && !self.paste_in_progress {
+                    self.ring_bell().map_err(ReedlineError::terminal)?;
+                }
+                crossterm_events = waiting;
+            } else if self
+                .menu_refresh_at
+                .is_some_and(|at| at.elapsed() >= self.menu_refresh_delay)
+            {
+                self.refresh_menu();
+                reedline_events.push(ReedlineEvent::Repaint);
+            } else if changed_at.is_some_and(|at| at.elapsed() >= self.change_debounce) {
+                changed_at = None;
+                self.notify_change();
+                reedline_events.push(ReedlineEvent::Repaint);
+            } else if self.flash_shown {
+                // Ends the visual bell
+                reedline_events.push(ReedlineEvent::Repaint);
+            } else if pending_menu {
+                if let Some(menu) = self.active_menu() {
+                    menu.menu_event(MenuEvent::Edit(false));
+                }
+                reedline_events.push(ReedlineEvent::Repaint);
+            } else if pending_hint {
+                reedline_events.push(ReedlineEvent::Repaint);
+            } else if self
+                .history_sync_at
+                .is_some_and(|at| at.elapsed() >= self.history_sync_wait())
+            {
+                self.sync_history();
+            } else if self.animate && !self.screen_reader && !self.painter.exceeds_screen_size() {
                 reedline_events.push(ReedlineEvent::Repaint);
             };
 
+            // Pasted text is painted once, after all its events are handled
+            let mut paste_repaint = false;
             for event in reedline_events.drain(..) {
-                match self.handle_event(prompt, event)? {
+                match self
+                    .handle_event(prompt, event)
+                    .map_err(ReedlineError::terminal)?
+                {
                     EventStatus::Exits(signal) => {
                         // Move the cursor below the input area, for external commands or new read_line call
-                        self.painter.move_cursor_to_end()?;
+                        self.painter
+                            .move_cursor_to_end()
+                            .map_err(ReedlineError::terminal)?;
+                        if let Signal::Success(line) = &signal {
+                            let elapsed = started.elapsed();
+                            self.last_line_metadata = Some(LineMetadata {
+                                editing_time: elapsed,
+                                from_history: self.recalled,
+                                completions_accepted: self.completions_accepted,
+                            });
+                            for hook in self.hooks.iter_mut() {
+                                hook.after_submit(line, elapsed);
+                            }
+                        }
                         return Ok(signal);
                     }
                     EventStatus::Handled => {
-                        if !paste_enter_state {
-                            self.repaint(prompt)?;
+                        if self.paste_in_progress {
+                            paste_repaint = true;
+                        } else if !self.frame_interval.is_zero() {
+                            repaint_pending = true;
+                        } else if !paste_enter_state {
+                            self.repaint(prompt).map_err(ReedlineError::terminal)?;
                         }
                     }
                     EventStatus::Inapplicable => {
@@ -444,26 +1502,131 @@ impl Reedline {
                     }
                 }
             }
+            if paste_repaint && !self.frame_interval.is_zero() {
+                repaint_pending = true;
+            } else if paste_repaint
+                && !paste_enter_state
+                && !self
+                    .painter
+                    .backend()
+                    .poll_event(Duration::ZERO)
+                    .map_err(ReedlineError::terminal)?
+            {
+                self.repaint(prompt).map_err(ReedlineError::terminal)?;
+            }
+            if repaint_pending && self.last_repaint.elapsed() >= self.frame_interval {
+                repaint_pending = false;
+                self.repaint(prompt).map_err(ReedlineError::terminal)?;
+            }
+
+            // The visual bell needs a repaint even if nothing else changed
+            if self.bell_flash {
+                self.repaint(prompt).map_err(ReedlineError::terminal)?;
+            }
+
+            if !self.hooks.is_empty() && self.editor.get_buffer() != last_buffer {
+                last_buffer = self.editor.get_buffer().to_string();
+                changed_at = Some(Instant::now());
+            }
         }
     }
 
-    fn handle_event(&mut self, prompt: &dyn Prompt, event: ReedlineEvent) -> Result<EventStatus> {
-        if self.input_mode == InputMode::HistorySearch {
-            self.handle_history_search_event(prompt, event)
+    /// Passes the buffer to the hooks and keeps the preview they return
+    fn notify_change(&mut self) {
+        let line = self.editor.get_buffer();
+        let pos = self.editor.offset();
+        let previews: Vec<String> = self
+            .hooks
+            .iter_mut()
+            .filter_map(|hook| hook.on_change(line, pos))
+            .collect();
+
+        self.preview = if previews.is_empty() {
+            None
         } else {
-            self.handle_editor_event(prompt, event)
+            Some(previews.join("\n"))
+        };
+    }
+
+    /// Rings the bell with the configured [`BellStyle`]
+    fn ring_bell(&mut self) -> io::Result<()> {
+        match self.bell {
+            BellStyle::None => Ok(()),
+            BellStyle::Audible => self.painter.ring_bell(),
+            BellStyle::Visual => {
+                self.bell_flash = true;
+                Ok(())
+            }
         }
     }
 
-    fn handle_history_search_event(
+    /// Whether the history search has a search string without matches
+    fn history_search_failing(&self) -> bool {
+        matches!(
+            self.history.get_navigation(),
+            HistoryNavigationQuery::SubstringSearch(substring) if !substring.is_empty()
+        ) && self.history.string_at_cursor().is_none()
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, prompt), ret, err)
+    )]
+    fn handle_event(
         &mut self,
         prompt: &dyn Prompt,
         event: ReedlineEvent,
     ) -> io::Result<EventStatus> {
+        if !self.paste_in_progress
+            && !matches!(
+                event,
+                ReedlineEvent::None
+                    | ReedlineEvent::Repaint
+                    | ReedlineEvent::Resize(..)
+                    | ReedlineEvent::Mouse
+            )
+        {
+            self.pasted = None;
+        }
+
+        if let Some(entry) = self.pending_deletion.take() {
+            let confirmed = match &event {
+                ReedlineEvent::None
+                | ReedlineEvent::Repaint
+                | ReedlineEvent::Resize(..)
+                | ReedlineEvent::Mouse => None,
+                ReedlineEvent::Edit(commands) => {
+                    Some(matches!(commands[..], [EditCommand::InsertChar('y' | 'Y')]))
+                }
+                // Any other key keeps the entry
+                _ => Some(false),
+            };
+            match confirmed {
+                None => self.pending_deletion = Some(entry),
+                Some(confirmed) => {
+                    if let Some(menu) = self.active_menu() {
+                        menu.menu_event(MenuEvent::Custom(DELETION_ANSWERED.to_string()));
+                    }
+                    if confirmed {
+                        self.delete_history_entry(&entry);
+                    }
+                    return Ok(EventStatus::Handled);
+                }
+            }
+        }
+
+        match self.input_mode {
+            InputMode::HistorySearch => self.handle_history_search_event(event),
+            InputMode::SearchReplace => self.handle_search_replace_event(event),
+            _ => self.handle_editor_event(prompt, event),
+        }
+    }
+
+    fn handle_history_search_event(&mut self, event: ReedlineEvent) -> io::Result<EventStatus> {
         match event {
             ReedlineEvent::UntilFound(events) => {
                 for event in events {
-                    match self.handle_history_search_event(prompt, event)? {
+                    match self.handle_history_search_event(event)? {
                         EventStatus::Inapplicable => {
                             // Try again with the next event handler
                         }
@@ -472,8 +1635,15 @@ impl Reedline {
                         }
                     }
                 }
-                // Exhausting the event handlers is still considered handled
-                Ok(EventStatus::Handled)
+                // None of the events applies
+                Ok(EventStatus::Inapplicable)
+            }
+            ReedlineEvent::When(conditions, event) => {
+                if self.conditions_hold(&conditions) {
+                    self.handle_history_search_event(*event)
+                } else {
+                    Ok(EventStatus::Inapplicable)
+                }
             }
             ReedlineEvent::CtrlD => {
                 if self.editor.is_empty() {
@@ -487,13 +1657,16 @@ impl Reedline {
             }
             ReedlineEvent::CtrlC => {
                 self.input_mode = InputMode::Regular;
+                self.dismiss_buffer();
                 Ok(EventStatus::Exits(Signal::CtrlC))
             }
-            ReedlineEvent::ClearScreen => Ok(EventStatus::Exits(Signal::CtrlL)),
+            ReedlineEvent::ClearScreen => self.clear_terminal(false),
+            ReedlineEvent::ClearScrollback => self.clear_terminal(true),
             ReedlineEvent::Enter | ReedlineEvent::HistoryHintComplete => {
                 if let Some(string) = self.history.string_at_cursor() {
                     self.editor.set_buffer(string);
                     self.editor.remember_undo_state(true);
+                    self.recalled = true;
                 }
 
                 self.input_mode = InputMode::Regular;
@@ -501,6 +1674,9 @@ impl Reedline {
             }
             ReedlineEvent::Edit(commands) => {
                 self.run_history_commands(&commands);
+                if self.history_search_failing() {
+                    self.ring_bell()?;
+                }
                 Ok(EventStatus::Handled)
             }
             ReedlineEvent::Mouse => Ok(EventStatus::Handled),
@@ -524,14 +1700,32 @@ impl Reedline {
                 }
                 Ok(EventStatus::Handled)
             }
+            ReedlineEvent::HistoryTogglePin => match self.history.string_at_cursor() {
+                Some(entry) => {
+                    self.toggle_pin(&entry);
+                    Ok(EventStatus::Handled)
+                }
+                None => Ok(EventStatus::Inapplicable),
+            },
             // TODO: Check if events should be handled
             ReedlineEvent::Right
             | ReedlineEvent::Left
             | ReedlineEvent::ActionHandler
+            | ReedlineEvent::SkipAbbreviation
+            | ReedlineEvent::NextPlaceholder
+            | ReedlineEvent::CommentAndAccept(_)
+            | ReedlineEvent::SearchReplace
+            | ReedlineEvent::Write
+            | ReedlineEvent::UniversalArgument
+            | ReedlineEvent::StartMacro(_)
+            | ReedlineEvent::EndMacro
+            | ReedlineEvent::ReplayMacro(_)
             | ReedlineEvent::Multiple(_)
             | ReedlineEvent::None
             | ReedlineEvent::Esc
             | ReedlineEvent::HistoryHintWordComplete
+            | ReedlineEvent::HistoryTimeRange
+            | ReedlineEvent::HistoryDelete
             | ReedlineEvent::Menu(_)
             | ReedlineEvent::MenuNext
             | ReedlineEvent::MenuPrevious
@@ -540,38 +1734,150 @@ impl Reedline {
             | ReedlineEvent::MenuLeft
             | ReedlineEvent::MenuRight
             | ReedlineEvent::MenuPageNext
-            | ReedlineEvent::MenuPagePrevious => Ok(EventStatus::Inapplicable),
+            | ReedlineEvent::MenuPagePrevious
+            | ReedlineEvent::MenuFirst
+            | ReedlineEvent::MenuLast
+            | ReedlineEvent::MenuPartialComplete
+            | ReedlineEvent::MenuCustom(_) => Ok(EventStatus::Inapplicable),
         }
     }
 
-    fn handle_editor_event(
-        &mut self,
-        prompt: &dyn Prompt,
-        event: ReedlineEvent,
-    ) -> io::Result<EventStatus> {
+    fn handle_search_replace_event(&mut self, event: ReedlineEvent) -> io::Result<EventStatus> {
         match event {
-            ReedlineEvent::Menu(name) => {
-                if self.active_menu().is_none() {
-                    if let Some(menu) = self.menus.iter_mut().find(|menu| menu.name() == name) {
-                        if self.quick_completions {
-                            menu.update_values(
-                                self.editor.line_buffer(),
-                                self.history.as_ref(),
-                                self.completer.as_ref(),
-                            );
-
-                            if menu.get_values().len() == 1 {
-                                menu.replace_in_buffer(self.editor.line_buffer());
-                                return Ok(EventStatus::Handled);
-                            }
+            ReedlineEvent::UntilFound(events) => {
+                for event in events {
+                    match self.handle_search_replace_event(event)? {
+                        EventStatus::Inapplicable => {
+                            // Try again with the next event handler
+                        }
+                        success => {
+                            return Ok(success);
                         }
-
-                        menu.menu_event(MenuEvent::Activate(self.quick_completions));
-                        return Ok(EventStatus::Handled);
                     }
                 }
+                // None of the events applies
                 Ok(EventStatus::Inapplicable)
             }
+            ReedlineEvent::When(conditions, event) => {
+                if self.conditions_hold(&conditions) {
+                    self.handle_search_replace_event(*event)
+                } else {
+                    Ok(EventStatus::Inapplicable)
+                }
+            }
+            ReedlineEvent::Multiple(events) => {
+                let mut latest_signal = EventStatus::Inapplicable;
+                for event in events {
+                    match self.handle_search_replace_event(event)? {
+                        EventStatus::Handled => {
+                            latest_signal = EventStatus::Handled;
+                        }
+                        EventStatus::Inapplicable => {
+                            // NO OP
+                        }
+                        EventStatus::Exits(signal) => {
+                            return Ok(EventStatus::Exits(signal));
+                        }
+                    }
+                }
+                Ok(latest_signal)
+            }
+            ReedlineEvent::Edit(commands) => {
+                self.run_search_replace_commands(&commands)?;
+                Ok(EventStatus::Handled)
+            }
+            ReedlineEvent::Enter => {
+                if self.search_replace.stage() == Stage::Confirm {
+                    self.input_mode = InputMode::Regular;
+                } else if !self.search_replace.accept(self.editor.get_buffer()) {
+                    self.input_mode = InputMode::Regular;
+                    if self.search_replace.stage() == Stage::Confirm {
+                        // The pattern is not in the buffer
+                        self.ring_bell()?;
+                    }
+                } else if self.search_replace.stage() == Stage::Confirm {
+                    self.search_replace
+                        .move_to_current(self.editor.line_buffer());
+                }
+                Ok(EventStatus::Handled)
+            }
+            ReedlineEvent::Esc => {
+                self.input_mode = InputMode::Regular;
+                Ok(EventStatus::Handled)
+            }
+            ReedlineEvent::CtrlC => {
+                self.input_mode = InputMode::Regular;
+                self.dismiss_buffer();
+                Ok(EventStatus::Exits(Signal::CtrlC))
+            }
+            ReedlineEvent::ClearScreen => self.clear_terminal(false),
+            ReedlineEvent::ClearScrollback => self.clear_terminal(true),
+            ReedlineEvent::Mouse | ReedlineEvent::Repaint => Ok(EventStatus::Handled),
+            ReedlineEvent::Resize(width, height) => {
+                self.painter.handle_resize(width, height);
+                Ok(EventStatus::Handled)
+            }
+            ReedlineEvent::None
+            | ReedlineEvent::CtrlD
+            | ReedlineEvent::HistoryHintComplete
+            | ReedlineEvent::HistoryHintWordComplete
+            | ReedlineEvent::ActionHandler
+            | ReedlineEvent::Up
+            | ReedlineEvent::Down
+            | ReedlineEvent::Right
+            | ReedlineEvent::Left
+            | ReedlineEvent::NextHistory
+            | ReedlineEvent::PreviousHistory
+            | ReedlineEvent::SearchHistory
+            | ReedlineEvent::HistoryTogglePin
+            | ReedlineEvent::HistoryTimeRange
+            | ReedlineEvent::HistoryDelete
+            | ReedlineEvent::SkipAbbreviation
+            | ReedlineEvent::NextPlaceholder
+            | ReedlineEvent::SearchReplace
+            | ReedlineEvent::Write
+            | ReedlineEvent::UniversalArgument
+            | ReedlineEvent::StartMacro(_)
+            | ReedlineEvent::EndMacro
+            | ReedlineEvent::ReplayMacro(_)
+            | ReedlineEvent::CommentAndAccept(_)
+            | ReedlineEvent::Menu(_)
+            | ReedlineEvent::MenuNext
+            | ReedlineEvent::MenuPrevious
+            | ReedlineEvent::MenuUp
+            | ReedlineEvent::MenuDown
+            | ReedlineEvent::MenuLeft
+            | ReedlineEvent::MenuRight
+            | ReedlineEvent::MenuPageNext
+            | ReedlineEvent::MenuPagePrevious
+            | ReedlineEvent::MenuFirst
+            | ReedlineEvent::MenuLast
+            | ReedlineEvent::MenuPartialComplete
+            | ReedlineEvent::MenuCustom(_) => Ok(EventStatus::Inapplicable),
+        }
+    }
+
+    fn handle_editor_event(
+        &mut self,
+        prompt: &dyn Prompt,
+        event: ReedlineEvent,
+    ) -> io::Result<EventStatus> {
+        // The menu is brought up to date before it is used
+        if !matches!(
+            event,
+            ReedlineEvent::Edit(_)
+                | ReedlineEvent::Repaint
+                | ReedlineEvent::Resize(..)
+                | ReedlineEvent::Mouse
+                | ReedlineEvent::None
+                | ReedlineEvent::Esc
+                | ReedlineEvent::CtrlC
+        ) {
+            self.refresh_menu();
+        }
+
+        match event {
+            ReedlineEvent::Menu(name) => Ok(self.open_menu(&name, false)),
             ReedlineEvent::MenuNext => {
                 self.active_menu()
                     .map_or(Ok(EventStatus::Inapplicable), |menu| {
@@ -628,6 +1934,20 @@ impl Reedline {
                         Ok(EventStatus::Handled)
                     })
             }
+            ReedlineEvent::MenuFirst => {
+                self.active_menu()
+                    .map_or(Ok(EventStatus::Inapplicable), |menu| {
+                        menu.menu_event(MenuEvent::MoveToStart);
+                        Ok(EventStatus::Handled)
+                    })
+            }
+            ReedlineEvent::MenuLast => {
+                self.active_menu()
+                    .map_or(Ok(EventStatus::Inapplicable), |menu| {
+                        menu.menu_event(MenuEvent::MoveToEnd);
+                        Ok(EventStatus::Handled)
+                    })
+            }
             ReedlineEvent::HistoryHintComplete => {
                 let current_hint = self.hinter.complete_hint();
                 if self.hints_active()
@@ -636,6 +1956,7 @@ impl Reedline {
                     && self.active_menu().is_none()
                 {
                     self.run_edit_commands(&[EditCommand::InsertString(current_hint)]);
+                    self.completions_accepted += 1;
                     Ok(EventStatus::Handled)
                 } else {
                     Ok(EventStatus::Inapplicable)
@@ -649,6 +1970,7 @@ impl Reedline {
                     && self.active_menu().is_none()
                 {
                     self.run_edit_commands(&[EditCommand::InsertString(current_hint_part)]);
+                    self.completions_accepted += 1;
                     Ok(EventStatus::Handled)
                 } else {
                     Ok(EventStatus::Inapplicable)
@@ -656,11 +1978,19 @@ impl Reedline {
             }
             ReedlineEvent::ActionHandler => {
                 let line_buffer = self.editor.line_buffer();
-                self.circular_completion_handler
+                let found = self
+                    .circular_completion_handler
                     .handle(self.completer.as_ref(), line_buffer);
+                if found {
+                    self.completions_accepted += 1;
+                } else {
+                    self.ring_bell()?;
+                }
                 Ok(EventStatus::Handled)
             }
             ReedlineEvent::Esc => {
+                self.cancel_pending_menu();
+                self.editor.end_undo_group();
                 self.menus
                     .iter_mut()
                     .for_each(|menu| menu.menu_event(MenuEvent::Deactivate));
@@ -676,50 +2006,62 @@ impl Reedline {
                 }
             }
             ReedlineEvent::CtrlC => {
+                self.cancel_pending_menu();
                 self.menus
                     .iter_mut()
                     .for_each(|menu| menu.menu_event(MenuEvent::Deactivate));
+                self.dismiss_buffer();
                 self.run_edit_commands(&[EditCommand::Clear]);
                 self.editor.reset_undo_stack();
                 Ok(EventStatus::Exits(Signal::CtrlC))
             }
-            ReedlineEvent::ClearScreen => {
-                self.menus
-                    .iter_mut()
-                    .for_each(|menu| menu.menu_event(MenuEvent::Deactivate));
-                Ok(EventStatus::Exits(Signal::CtrlL))
-            }
+            ReedlineEvent::ClearScreen => self.clear_terminal(false),
+            ReedlineEvent::ClearScrollback => self.clear_terminal(true),
             ReedlineEvent::Enter => {
                 for menu in self.menus.iter_mut() {
                     if menu.is_active() {
+                        let keep_open = menu.keeps_open(self.completer.as_ref());
                         menu.replace_in_buffer(self.editor.line_buffer());
-                        menu.menu_event(MenuEvent::Deactivate);
+                        if keep_open {
+                            menu.menu_event(MenuEvent::Edit(false));
+                        } else {
+                            menu.menu_event(MenuEvent::Deactivate);
+                        }
+                        self.completions_accepted += 1;
 
                         return Ok(EventStatus::Handled);
                     }
                 }
+                if self.paste_in_progress && self.confirm_multiline_paste {
+                    // Pasted lines wait for an Enter of their own
+                    self.insert_newline();
+                    return Ok(EventStatus::Handled);
+                }
+
+                self.expand_abbreviation();
                 let buffer = self.editor.get_buffer().to_string();
                 if matches!(self.validator.validate(&buffer), ValidationResult::Complete) {
                     self.hide_hints = true;
                     // Additional repaint to show the content without hints etc.
                     self.repaint(prompt)?;
                     self.history.append(self.editor.get_buffer());
+                    self.history_sync_at = Some(Instant::now());
                     self.run_edit_commands(&[EditCommand::Clear]);
                     self.editor.reset_undo_stack();
 
                     Ok(EventStatus::Exits(Signal::Success(buffer)))
                 } else {
-                    #[cfg(windows)]
-                    {
-                        self.run_edit_commands(&[EditCommand::InsertChar('\r')]);
-                    }
-                    self.run_edit_commands(&[EditCommand::InsertChar('\n')]);
+                    self.insert_newline();
 
                     Ok(EventStatus::Handled)
                 }
             }
             ReedlineEvent::Edit(commands) => {
                 self.run_edit_commands(&commands);
+                if !self.menu_refresh_delay.is_zero() && self.active_menu().is_some() {
+                    self.menu_refresh_at = Some(Instant::now());
+                    return Ok(EventStatus::Handled);
+                }
                 if let Some(menu) = self.menus.iter_mut().find(|men| men.is_active()) {
                     if self.quick_completions {
                         menu.update_values(
@@ -727,7 +2069,7 @@ impl Reedline {
                             self.history.as_ref(),
                             self.completer.as_ref(),
                         );
-                        if menu.get_values().len() == 1 {
+                        if menu.total_values() == 1 {
                             return self.handle_editor_event(prompt, ReedlineEvent::Enter);
                         }
                     }
@@ -777,6 +2119,106 @@ impl Reedline {
                 self.enter_history_search();
                 Ok(EventStatus::Handled)
             }
+            ReedlineEvent::HistoryTogglePin => {
+                let selected = self
+                    .active_menu()
+                    .and_then(|menu| menu.selected_value())
+                    .map(|(_, value)| value);
+
+                match selected {
+                    Some(entry) => {
+                        self.toggle_pin(&entry);
+                        // The menu collects its values again to show the new order
+                        if let Some(menu) = self.active_menu() {
+                            menu.menu_event(MenuEvent::Edit(false));
+                        }
+                        Ok(EventStatus::Handled)
+                    }
+                    None => Ok(EventStatus::Inapplicable),
+                }
+            }
+            ReedlineEvent::HistoryDelete => {
+                let selected = self
+                    .active_menu()
+                    .and_then(|menu| menu.selected_value())
+                    .map(|(_, value)| value);
+
+                match selected {
+                    Some(entry) => {
+                        // The entry is deleted once the next key confirms it
+                        self.pending_deletion = Some(entry);
+                        if let Some(menu) = self.active_menu() {
+                            menu.menu_event(MenuEvent::Custom(CONFIRM_DELETION.to_string()));
+                        }
+                        Ok(EventStatus::Handled)
+                    }
+                    None => Ok(EventStatus::Inapplicable),
+                }
+            }
+            ReedlineEvent::HistoryTimeRange => {
+                self.active_menu()
+                    .map_or(Ok(EventStatus::Inapplicable), |menu| {
+                        menu.menu_event(MenuEvent::Custom(NEXT_TIME_RANGE.to_string()));
+                        Ok(EventStatus::Handled)
+                    })
+            }
+            ReedlineEvent::MenuPartialComplete => {
+                for menu in self.menus.iter_mut() {
+                    if menu.is_active() && menu.insert_common_prefix(self.editor.line_buffer()) {
+                        return Ok(EventStatus::Handled);
+                    }
+                }
+                Ok(EventStatus::Inapplicable)
+            }
+            ReedlineEvent::MenuCustom(command) => {
+                self.active_menu()
+                    .map_or(Ok(EventStatus::Inapplicable), |menu| {
+                        menu.menu_event(MenuEvent::Custom(command));
+                        Ok(EventStatus::Handled)
+                    })
+            }
+            ReedlineEvent::SkipAbbreviation => {
+                self.skip_abbreviation = true;
+                Ok(EventStatus::Handled)
+            }
+            ReedlineEvent::NextPlaceholder => {
+                if self.editor.next_placeholder() {
+                    Ok(EventStatus::Handled)
+                } else {
+                    Ok(EventStatus::Inapplicable)
+                }
+            }
+            ReedlineEvent::UniversalArgument => Ok(EventStatus::Inapplicable),
+            // The recording starts and stops when the keys are parsed
+            ReedlineEvent::StartMacro(_) | ReedlineEvent::EndMacro => Ok(EventStatus::Handled),
+            ReedlineEvent::ReplayMacro(register) => self.replay_macro(prompt, register),
+            ReedlineEvent::Write => {
+                let buffer = self.editor.get_buffer().to_string();
+                for hook in self.hooks.iter_mut() {
+                    hook.on_write(&buffer);
+                }
+                Ok(EventStatus::Handled)
+            }
+            ReedlineEvent::SearchReplace => {
+                for menu in self.menus.iter_mut() {
+                    menu.menu_event(MenuEvent::Deactivate);
+                }
+                self.input_mode = InputMode::SearchReplace;
+                self.search_replace = SearchReplace::default();
+                Ok(EventStatus::Handled)
+            }
+            ReedlineEvent::CommentAndAccept(prefix) => {
+                for menu in self.menus.iter_mut() {
+                    menu.menu_event(MenuEvent::Deactivate);
+                }
+                self.editor.line_buffer().comment_lines(&prefix);
+                self.history.append(self.editor.get_buffer());
+                self.history_sync_at = Some(Instant::now());
+                self.run_edit_commands(&[EditCommand::Clear]);
+                self.editor.reset_undo_stack();
+
+                Ok(EventStatus::Handled)
+            }
             ReedlineEvent::Multiple(events) => {
                 let mut latest_signal = EventStatus::Inapplicable;
                 for event in events {
@@ -799,8 +2241,14 @@ impl Reedline {
                 Ok(latest_signal)
             }
             ReedlineEvent::UntilFound(events) => {
-                for event in events {
-                    match self.handle_editor_event(prompt, event)? {
+                let last = events.len().saturating_sub(1);
+                for (index, event) in events.into_iter().enumerate() {
+                    let status = match event {
+                        // A menu without values leaves the key to the events after it
+                        ReedlineEvent::Menu(name) if index < last => self.open_menu(&name, true),
+                        event => self.handle_editor_event(prompt, event)?,
+                    };
+                    match status {
                         EventStatus::Inapplicable => {
                             // Try again with the next event handler
                         }
@@ -809,35 +2257,268 @@ impl Reedline {
                         }
                     }
                 }
-                // Exhausting the event handlers is still considered handled
+                // None of the events applies
                 Ok(EventStatus::Inapplicable)
             }
+            ReedlineEvent::When(conditions, event) => {
+                if self.conditions_hold(&conditions) {
+                    self.handle_editor_event(prompt, *event)
+                } else {
+                    Ok(EventStatus::Inapplicable)
+                }
+            }
             ReedlineEvent::None => Ok(EventStatus::Inapplicable),
         }
     }
 
-    fn active_menu(&mut self) -> Option<&mut Box<dyn Menu>> {
-        self.menus.iter_mut().find(|men| men.is_active())
-    }
-
-    fn previous_history(&mut self) {
-        if self.input_mode != InputMode::HistoryTraversal {
-            self.input_mode = InputMode::HistoryTraversal;
-            self.set_history_navigation_based_on_line_buffer();
+    /// Opens the menu, or inserts its only value with quick completions. The menu is
+    /// inapplicable when a menu is open already, and without values if it `needs_values`
+    fn open_menu(&mut self, name: &str, needs_values: bool) -> EventStatus {
+        // Menus can't be painted without moving the cursor
+        if self.active_menu().is_some() || self.painter.is_dumb() {
+            return EventStatus::Inapplicable;
         }
+        let menu = match self.menus.iter_mut().find(|menu| menu.name() == name) {
+            Some(menu) => menu,
+            None => return EventStatus::Inapplicable,
+        };
 
-        self.history.back();
-        self.update_buffer_from_history();
-    }
+        let updated = self.quick_completions || needs_values;
+        if updated {
+            menu.update_values(
+                self.editor.line_buffer(),
+                self.history.as_ref(),
+                self.completer.as_ref(),
+            );
+            if needs_values && menu.total_values() == 0 && !menu.is_pending() {
+                return EventStatus::Inapplicable;
+            }
+        }
 
-    fn next_history(&mut self) {
-        if self.input_mode != InputMode::HistoryTraversal {
-            self.input_mode = InputMode::HistoryTraversal;
-            self.set_history_navigation_based_on_line_buffer();
+        if self.quick_completions && menu.total_values() == 1 {
+            let keep_open = menu.keeps_open(self.completer.as_ref());
+            menu.replace_in_buffer(self.editor.line_buffer());
+            self.completions_accepted += 1;
+            if !keep_open {
+                return EventStatus::Handled;
+            }
+            // The values for the next part are collected when painted
+            menu.menu_event(MenuEvent::Activate(false));
+        } else {
+            menu.menu_event(MenuEvent::Activate(updated));
         }
+        self.menu_activated = true;
+        EventStatus::Handled
+    }
 
-        self.history.forward();
-        self.update_buffer_from_history();
+    /// Whether all the conditions of a [`ReedlineEvent::When`] hold
+    fn conditions_hold(&self, conditions: &[BindingCondition]) -> bool {
+        conditions
+            .iter()
+            .all(|condition| self.condition_holds(condition))
+    }
+
+    fn condition_holds(&self, condition: &BindingCondition) -> bool {
+        let buffer = self.editor.get_buffer();
+        let offset = self.editor.offset();
+        match condition {
+            BindingCondition::BufferEmpty => buffer.is_empty(),
+            BindingCondition::CursorAtLineStart => buffer[..offset]
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .is_empty(),
+            BindingCondition::CursorAtEnd => offset == buffer.len(),
+            BindingCondition::MenuActive => self.menus.iter().any(|menu| menu.is_active()),
+            BindingCondition::ViNormalMode => matches!(
+                self.edit_mode.edit_mode(),
+                PromptEditMode::Vi(PromptViMode::Normal)
+            ),
+            BindingCondition::ViInsertMode => matches!(
+                self.edit_mode.edit_mode(),
+                PromptEditMode::Vi(PromptViMode::Insert)
+            ),
+            BindingCondition::Not(condition) => !self.condition_holds(condition),
+        }
+    }
+
+    /// The event of a key, from the keys of the open menu or else the edit mode
+    fn parse_key_event(&mut self, event: Event) -> ReedlineEvent {
+        match self.menu_key_event(&event) {
+            Some(menu_event) => menu_event,
+            None => self.edit_mode.parse_event(event),
+        }
+    }
+
+    /// Handles the keys of a macro as if they were typed, until one of them ends the read
+    fn replay_macro(&mut self, prompt: &dyn Prompt, register: char) -> io::Result<EventStatus> {
+        let events = match self.macros.begin_replay(register) {
+            Some(events) => events,
+            None => return Ok(EventStatus::Inapplicable),
+        };
+
+        let mut status = Ok(EventStatus::Handled);
+        for event in events {
+            let reedline_event = self.parse_key_event(event);
+            status = self.handle_event(prompt, reedline_event);
+            if matches!(status, Ok(EventStatus::Exits(_)) | Err(_)) {
+                break;
+            }
+        }
+        self.macros.end_replay();
+
+        match status {
+            Ok(EventStatus::Inapplicable) => Ok(EventStatus::Handled),
+            status => status,
+        }
+    }
+
+    /// The event for a key of a menu, before the edit mode sees it. The keys that move
+    /// through the open menu select its next or previous value, and a trigger key opens
+    /// its menu or selects the next value if the menu is already open
+    fn menu_key_event(&self, event: &Event) -> Option<ReedlineEvent> {
+        // Keys completing a sequence of the edit mode belong to it
+        if self.edit_mode.has_pending_keys() {
+            return None;
+        }
+        let key = key_combination(event)?;
+
+        let navigation = self
+            .menus
+            .iter()
+            .find(|menu| menu.is_active())
+            .and_then(|menu| self.menu_navigation(menu.name()));
+        if let Some(navigation) = navigation {
+            if navigation.next.as_ref() == Some(&key) {
+                return Some(ReedlineEvent::MenuNext);
+            }
+            if navigation.previous.as_ref() == Some(&key) {
+                return Some(ReedlineEvent::MenuPrevious);
+            }
+        }
+
+        let (_, name) = self
+            .menu_triggers
+            .iter()
+            .find(|(trigger, _)| *trigger == key)?;
+        Some(ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::MenuNext,
+            ReedlineEvent::Menu(name.clone()),
+        ]))
+    }
+
+    /// Whether the event is a key that moves through one of the menus. What it does
+    /// depends on the menu that is open when it is handled
+    fn is_menu_navigation_key(&self, event: &Event) -> bool {
+        key_combination(event).is_some_and(|key| {
+            self.menu_navigation.iter().any(|(_, navigation)| {
+                navigation.next.as_ref() == Some(&key) || navigation.previous.as_ref() == Some(&key)
+            })
+        })
+    }
+
+    fn active_menu(&mut self) -> Option<&mut Box<dyn Menu>> {
+        self.menus.iter_mut().find(|men| men.is_active())
+    }
+
+    /// Collects the values of the open menu for the edits that waited for the menu
+    /// refresh delay
+    fn refresh_menu(&mut self) {
+        if self.menu_refresh_at.take().is_none() {
+            return;
+        }
+        if let Some(menu) = self.menus.iter_mut().find(|menu| menu.is_active()) {
+            menu.update_values(
+                self.editor.line_buffer(),
+                self.history.as_ref(),
+                self.completer.as_ref(),
+            );
+            menu.menu_event(MenuEvent::Edit(true));
+        }
+    }
+
+    /// The open menu is closed while the completer works on its values, which are
+    /// no longer needed
+    fn cancel_pending_menu(&mut self) {
+        if self.active_menu().is_some_and(|menu| menu.is_pending()) {
+            self.completer.cancel_completions();
+        }
+    }
+
+    /// Keeps the buffer that is dropped by ctrl-c
+    fn dismiss_buffer(&mut self) {
+        let buffer = self.editor.get_buffer();
+        self.dismissed = (!buffer.is_empty()).then(|| buffer.to_string());
+    }
+
+    /// Pins the history entry or removes its pin
+    fn toggle_pin(&mut self, entry: &str) {
+        if self.history.is_pinned(entry) {
+            self.history.unpin(entry);
+        } else {
+            self.history.pin(entry);
+        }
+        self.history_sync_at = Some(Instant::now());
+    }
+
+    /// Deletes the entry from the history, and from the values of the menu
+    fn delete_history_entry(&mut self, entry: &str) {
+        self.history.delete(entry);
+        self.history_sync_at = Some(Instant::now());
+        if let Some(menu) = self.active_menu() {
+            menu.menu_event(MenuEvent::Edit(false));
+        }
+    }
+
+    /// Saves the history while there is no input. When it fails it is tried again
+    /// after the next pause
+    fn sync_history(&mut self) {
+        match self.history.sync() {
+            Ok(()) => {
+                self.history_sync_at = None;
+                self.history_sync_failures = 0;
+                self.history_sync_error = None;
+            }
+            Err(error) => {
+                self.history_sync_failures = self.history_sync_failures.saturating_add(1);
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    failures = self.history_sync_failures,
+                    "saving the history failed: {}",
+                    error
+                );
+                self.history_sync_at = Some(Instant::now());
+                self.history_sync_error = Some(error);
+            }
+        }
+    }
+
+    /// How long the input has to pause before the history is saved, longer after
+    /// each failed save
+    fn history_sync_wait(&self) -> Duration {
+        let backoff = self.history_sync_failures.min(MAX_HISTORY_SYNC_BACKOFF);
+        self.history_sync_delay * 2u32.pow(backoff)
+    }
+
+    fn previous_history(&mut self) {
+        if self.input_mode != InputMode::HistoryTraversal {
+            self.input_mode = InputMode::HistoryTraversal;
+            self.set_history_navigation_based_on_line_buffer();
+        }
+
+        self.history.back();
+        self.update_buffer_from_history();
+    }
+
+    fn next_history(&mut self) {
+        if self.input_mode != InputMode::HistoryTraversal {
+            self.input_mode = InputMode::HistoryTraversal;
+            self.set_history_navigation_based_on_line_buffer();
+        }
+
+        self.history.forward();
+        self.update_buffer_from_history();
     }
 
     /// Enable the search and navigation through the history from the line buffer prompt
@@ -911,6 +2592,53 @@ impl Reedline {
         }
     }
 
+    /// Dispatches the applicable [`EditCommand`] actions for the search-and-replace:
+    /// typing the pattern and the replacement, then confirming each replacement.
+    fn run_search_replace_commands(&mut self, commands: &[EditCommand]) -> io::Result<()> {
+        for command in commands {
+            if self.input_mode != InputMode::SearchReplace {
+                break;
+            }
+
+            let line_buffer = self.editor.line_buffer();
+            let matches_left = match (self.search_replace.stage(), command) {
+                (Stage::Confirm, EditCommand::InsertChar('y' | ' ')) => {
+                    let matches_left = self.search_replace.replace(line_buffer);
+                    self.editor.remember_undo_state(true);
+                    matches_left
+                }
+                (Stage::Confirm, EditCommand::InsertChar('n')) => {
+                    self.search_replace.skip(line_buffer)
+                }
+                (Stage::Confirm, EditCommand::InsertChar('a' | '!')) => {
+                    self.search_replace.replace_all(line_buffer);
+                    self.editor.remember_undo_state(true);
+                    false
+                }
+                (Stage::Confirm, EditCommand::InsertChar('q')) => false,
+                (Stage::Confirm, _) => {
+                    self.ring_bell()?;
+                    true
+                }
+                (_, EditCommand::InsertChar(c)) => {
+                    self.search_replace.push(*c);
+                    true
+                }
+                (_, EditCommand::Backspace) => {
+                    self.search_replace.pop();
+                    true
+                }
+                _ => true,
+            };
+
+            if !matches_left {
+                self.input_mode = InputMode::Regular;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Set the buffer contents for history traversal/search in the standard prompt
     ///
     /// When using the up/down traversal or fish/zsh style prefix search update the main line buffer accordingly.
@@ -921,15 +2649,18 @@ impl Reedline {
                 if let Some(buffer_to_paint) = self.history.string_at_cursor() {
                     self.editor.set_buffer(buffer_to_paint.clone());
                     self.editor.set_insertion_point(buffer_to_paint.len());
+                    self.recalled = true;
                 } else {
                     // Hack
                     self.editor.set_line_buffer(original);
+                    self.recalled = false;
                 }
             }
             HistoryNavigationQuery::PrefixSearch(prefix) => {
                 if let Some(prefix_result) = self.history.string_at_cursor() {
                     self.editor.set_buffer(prefix_result.clone());
                     self.editor.set_insertion_point(prefix_result.len());
+                    self.recalled = true;
                 } else {
                     self.editor.set_buffer(prefix.clone());
                     self.editor.set_insertion_point(prefix.len());
@@ -954,9 +2685,66 @@ impl Reedline {
         }
 
         // Run the commands over the edit buffer
+        let mut pasted = String::new();
         for command in commands {
+            if self.paste_in_progress {
+                // Pasted characters are inserted at once, with a single undo entry
+                if let EditCommand::InsertChar(c) = command {
+                    pasted.push(*c);
+                    continue;
+                }
+                self.insert_pasted(&mut pasted);
+            }
+
+            if *command == EditCommand::InsertChar(' ') {
+                self.expand_abbreviation();
+            }
             self.editor.run_edit_command(command);
         }
+        self.insert_pasted(&mut pasted);
+    }
+
+    fn insert_newline(&mut self) {
+        #[cfg(windows)]
+        {
+            self.run_edit_commands(&[EditCommand::InsertChar('\r')]);
+        }
+        self.run_edit_commands(&[EditCommand::InsertChar('\n')]);
+    }
+
+    fn insert_pasted(&mut self, pasted: &mut String) {
+        if pasted.is_empty() {
+            return;
+        }
+
+        let start = self.editor.offset();
+        self.editor
+            .run_edit_command(&EditCommand::InsertString(std::mem::take(pasted)));
+        let end = self.editor.offset();
+        // The lines of a paste are inserted one after the other
+        self.pasted = match self.pasted.take() {
+            Some(previous) if previous.end == start => Some(previous.start..end),
+            _ => Some(start..end),
+        };
+    }
+
+    /// Expands the abbreviation before the cursor, unless it was skipped or is
+    /// part of a paste
+    fn expand_abbreviation(&mut self) {
+        if std::mem::take(&mut self.skip_abbreviation) || self.paste_in_progress {
+            return;
+        }
+
+        let line_buffer = self.editor.line_buffer();
+        if let Some((range, expansion)) = self
+            .abbreviations
+            .find(line_buffer.get_buffer(), line_buffer.offset())
+        {
+            let offset = range.start + expansion.len();
+            line_buffer.replace(range, expansion);
+            line_buffer.set_insertion_point(offset);
+            self.editor.remember_undo_state(true);
+        }
     }
 
     fn up_command(&mut self) {
@@ -981,11 +2769,31 @@ impl Reedline {
 
     /// Checks if hints should be displayed and are able to be completed
     fn hints_active(&self) -> bool {
-        !self.hide_hints && self.input_mode == InputMode::Regular
+        !self.hide_hints
+            && !self.screen_reader
+            && self.input_mode == InputMode::Regular
+            && (self.hints_on_paste || !self.paste_in_progress)
+            && self.editor.get_buffer().chars().count() >= self.hint_min_chars
     }
 
     /// Repaint of either the buffer or the parts for reverse history search
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn repaint(&mut self, prompt: &dyn Prompt) -> io::Result<()> {
+        // Another instance read a line since the last repaint, the prompt is painted
+        // again below what it left
+        if let Some((manager, _)) = &self.terminal_manager {
+            let handovers = manager.handovers();
+            if handovers != self.handovers_seen {
+                self.handovers_seen = handovers;
+                self.painter.initialize_prompt_position()?;
+            }
+        }
+
+        self.last_repaint = Instant::now();
+        self.flash_shown = self.bell_flash;
+        self.painter.set_prompt_flash(self.bell_flash);
+        self.bell_flash = false;
+
         // Repainting
         if self.input_mode == InputMode::HistorySearch {
             self.history_search_paint(prompt)
@@ -998,11 +2806,11 @@ impl Reedline {
     ///
     /// Overwrites the prompt indicator and highlights the search string
     /// separately from the result bufer.
-    fn history_search_paint(&mut self, prompt: &dyn Prompt) -> Result<()> {
+    fn history_search_paint(&mut self, prompt: &dyn Prompt) -> io::Result<()> {
         let navigation = self.history.get_navigation();
 
         if let HistoryNavigationQuery::SubstringSearch(substring) = navigation {
-            let status = if !substring.is_empty() && self.history.string_at_cursor().is_none() {
+            let status = if self.history_search_failing() {
                 PromptHistorySearchStatus::Failing
             } else {
                 PromptHistorySearchStatus::Passing
@@ -1014,8 +2822,10 @@ impl Reedline {
 
             // Highlight matches
             let res_string = if self.use_ansi_coloring {
-                let match_highlighter = SimpleMatchHighlighter::new(substring);
-                let styled = match_highlighter.highlight(&res_string);
+                let mut styled = self
+                    .highlighter
+                    .highlight_with_context(&res_string, &self.highlight_context);
+                self.highlight_search_matches(&mut styled, &substring);
                 styled.render_simple()
             } else {
                 res_string
@@ -1037,32 +2847,170 @@ impl Reedline {
         Ok(())
     }
 
+    /// Paints the occurrences of the search term with the search match style
+    fn highlight_search_matches(&self, styled: &mut StyledText, term: &str) {
+        if term.is_empty() {
+            return;
+        }
+
+        let matches: Vec<Span> = styled
+            .raw_string()
+            .match_indices(term)
+            .map(|(start, found)| Span::new(start, start + found.len()))
+            .collect();
+        match self.search_match_style {
+            Some(match_style) => styled.restyle(&matches, |_| match_style),
+            None => styled.restyle(&matches, |style| style.reverse()),
+        }
+    }
+
     /// Triggers a full repaint including the prompt parts
     ///
     /// Includes the highlighting and hinting calls.
-    fn buffer_paint(&mut self, prompt: &dyn Prompt) -> Result<()> {
+    fn buffer_paint(&mut self, prompt: &dyn Prompt) -> io::Result<()> {
+        // Updating the working details of the active menu first, its search term is
+        // highlighted in the buffer
+        for menu in self.menus.iter_mut() {
+            if menu.is_active() {
+                menu.update_working_details(
+                    self.editor.line_buffer(),
+                    self.history.as_ref(),
+                    self.completer.as_ref(),
+                    &self.painter,
+                );
+            }
+        }
+
         let cursor_position_in_buffer = self.editor.offset();
         let buffer_to_paint = self.editor.get_buffer();
 
-        let (before_cursor, after_cursor) = self
-            .highlighter
-            .highlight(buffer_to_paint)
-            .render_around_insertion_point(
-                cursor_position_in_buffer,
+        let mut styled_buffer = {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("highlight").entered();
+            self.highlighter
+                .highlight_with_context(buffer_to_paint, &self.highlight_context)
+        };
+        styled_buffer
+            .underlines
+            .extend(self.validator.diagnostics(buffer_to_paint));
+        if let Some(placeholder) = self.editor.current_placeholder() {
+            styled_buffer.underline(
+                Underline::new(Span::new(placeholder.start, placeholder.end))
+                    .with_style(UnderlineStyle::Dotted),
+            );
+        }
+        if self.visible_whitespace {
+            styled_buffer.highlight_whitespace(cursor_position_in_buffer, Color::Red);
+        }
+        if let Some(pasted) = self.pasted.as_ref().filter(|_| self.highlight_paste) {
+            styled_buffer.restyle(&[Span::new(pasted.start, pasted.end)], |style| {
+                style.reverse()
+            });
+        }
+        if let Some(term) = self
+            .menus
+            .iter()
+            .find(|menu| menu.is_active())
+            .and_then(|menu| menu.search_term())
+        {
+            self.highlight_search_matches(&mut styled_buffer, term);
+        }
+        if self.input_mode == InputMode::SearchReplace {
+            let current = self.search_replace.current_match(buffer_to_paint);
+            let (current, others): (Vec<_>, Vec<_>) = self
+                .search_replace
+                .matches(buffer_to_paint)
+                .into_iter()
+                .map(|range| Span::new(range.start, range.end))
+                .partition(|span| current == Some(span.start..span.end));
+            styled_buffer.restyle(&others, |style| style.reverse());
+            styled_buffer.restyle(&current, |_| {
+                Style::new().fg(Color::Black).on(Color::Yellow)
+            });
+        }
+
+        if let Some(theme) = self.theme.filter(|theme| theme.monochrome) {
+            for (style, _) in styled_buffer.buffer.iter_mut() {
+                *style = theme.buffer_style(*style);
+            }
+            for underline in styled_buffer.underlines.iter_mut() {
+                underline.color = None;
+            }
+        }
+
+        let (styled_buffer, painted_cursor) = if self.rtl_reordering {
+            styled_buffer.visual(cursor_position_in_buffer)
+        } else {
+            (styled_buffer, cursor_position_in_buffer)
+        };
+
+        let (mut before_cursor, mut after_cursor) = styled_buffer
+            .render_around_insertion_point_with_tabs(
+                painted_cursor,
                 prompt.render_prompt_multiline_indicator().borrow(),
                 self.use_ansi_coloring,
+                self.tab_display,
             );
 
-        let hint: String = if self.hints_active() {
-            self.hinter.handle(
+        // The composition sits between the two halves, with the cursor placed in it
+        if let Some(preedit) = self.ime.as_ref().and_then(ImeComposition::preedit) {
+            let (composed, rest) = preedit.text.split_at(preedit.cursor);
+            let paint = |text: &str| {
+                if self.use_ansi_coloring && !text.is_empty() {
+                    Style::new().underline().paint(text).to_string()
+                } else {
+                    text.to_string()
+                }
+            };
+            before_cursor.push_str(&paint(composed));
+            after_cursor.insert_str(0, &paint(rest));
+        }
+
+        let hint: String = if let Some(command_line) = self.edit_mode.command_line() {
+            // The command line of the edit mode is typed below the buffer
+            if self.use_ansi_coloring {
+                format!("\n{}", Style::new().bold().paint(command_line))
+            } else {
+                format!("\n{}", command_line)
+            }
+        } else if self.hints_active() {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("hint").entered();
+            let hint = self.hinter.handle_with_completer(
                 buffer_to_paint,
                 cursor_position_in_buffer,
                 self.history.as_ref(),
+                self.completer.as_ref(),
                 self.use_ansi_coloring,
-            )
+            );
+            match self.theme {
+                Some(theme) if self.use_ansi_coloring && !hint.is_empty() => {
+                    theme.hint.paint(strip_ansi(&hint)).to_string()
+                }
+                _ => hint,
+            }
+        } else if self.input_mode == InputMode::SearchReplace {
+            // The search-and-replace asks below the buffer
+            let status = self.search_replace.status();
+            if self.use_ansi_coloring {
+                format!("\n{}", Style::new().bold().paint(status))
+            } else {
+                format!("\n{}", status)
+            }
         } else {
             String::new()
         };
+        // The status of the host comes first, then the preview of the hooks
+        let status = if self.hide_hints {
+            String::new()
+        } else {
+            self.status
+                .iter()
+                .chain(self.preview.iter())
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
 
         // Needs to add return carriage to newlines because when not in raw mode
         // some OS don't fully return the carriage
@@ -1074,18 +3022,32 @@ impl Reedline {
             &before_cursor,
             &after_cursor,
             &hint,
-        );
+        )
+        .with_status(&status);
 
-        // Updating the working details of the active menu
-        for menu in self.menus.iter_mut() {
-            if menu.is_active() {
-                menu.update_working_details(
-                    self.editor.line_buffer(),
-                    self.history.as_ref(),
-                    self.completer.as_ref(),
-                    &self.painter,
-                );
+        // The hooks announce the selection when it moved
+        let selection = self
+            .active_menu()
+            .and_then(|menu| menu.selected_value())
+            .map(|(_, value)| value);
+        if selection != self.announced_selection {
+            if let Some(value) = &selection {
+                for hook in self.hooks.iter_mut() {
+                    hook.on_menu_selection(value);
+                }
             }
+            self.announced_selection = selection;
+        }
+
+        // Completing found no candidates, once the menu got its values
+        let menu_pending = self.active_menu().is_some_and(|menu| menu.is_pending());
+        if !menu_pending
+            && std::mem::take(&mut self.menu_activated)
+            && self
+                .active_menu()
+                .is_some_and(|menu| menu.total_values() == 0)
+        {
+            self.ring_bell()?;
         }
 
         let menu = self
@@ -1099,8 +3061,1697 @@ impl Reedline {
     }
 }
 
+/// The key of a key press, in the form the bindings use
+fn key_combination(event: &Event) -> Option<KeyCombination> {
+    match event {
+        Event::Key(KeyEvent { code, modifiers }) => Some(KeyCombination {
+            modifier: *modifiers,
+            key_code: *code,
+        }),
+        _ => None,
+    }
+}
+
 #[test]
 fn thread_safe() {
     fn f<S: Send>(_: S) {}
     f(Reedline::create().unwrap());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        default_emacs_keybindings, CompletionHinter, CompletionMenu, DefaultPrompt, HistoryMenu,
+        LineBuffer, MemoryBackend, MenuRow, Vi,
+    };
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::{
+        borrow::Cow,
+        io::Write,
+        sync::{Arc, Mutex},
+        task::Poll,
+    };
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[rstest]
+    #[case(false, 5)]
+    #[case(true, 2)]
+    fn batched_output_skips_unchanged_repaints(
+        #[case] batched_output: bool,
+        #[case] expected_repaints: usize,
+    ) {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Esc),
+            key(KeyCode::Esc),
+            key(KeyCode::Enter),
+        ]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_batched_output(batched_output)
+            .with_backend(Box::new(backend.clone()));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "a"));
+        // Every repaint starts moving to the prompt origin
+        let output = backend.output();
+        assert_eq!(output.matches("\u{1b}[1;1H").count(), expected_repaints);
+        assert_eq!(output.contains("\u{1b}[?25l"), !batched_output);
+    }
+
+    #[rstest]
+    #[case(TitleOnDrop::Keep, "\u{1b}]0;build\u{7}")]
+    #[case(TitleOnDrop::Clear, "\u{1b}]0;build\u{7}\u{1b}]0;\u{7}")]
+    #[case(TitleOnDrop::Restore, "\u{1b}[22;0t\u{1b}]0;build\u{7}\u{1b}[23;0t")]
+    fn title_is_handled_on_drop(#[case] title_on_drop: TitleOnDrop, #[case] expected: &str) {
+        let backend = MemoryBackend::new(80, 24);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_title_on_drop(title_on_drop)
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.set_title("build").unwrap();
+        drop(line_editor);
+
+        assert_eq!(backend.output(), expected);
+    }
+
+    #[rstest]
+    #[case::bound_keys(vec![key(KeyCode::Char('a'))], 0)]
+    #[case::unbound_key(vec![key(KeyCode::F(5))], 1)]
+    #[case::unbound_keys_in_a_batch(vec![key(KeyCode::F(5)); 3], 1)]
+    #[case::pasted_unbound_key(
+        type_text("echo hello world").into_iter().chain([key(KeyCode::F(5))]).collect(),
+        0
+    )]
+    #[case::failing_history_search(
+        vec![
+            Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL)),
+            key(KeyCode::Char('z')),
+            key(KeyCode::Enter),
+        ],
+        1
+    )]
+    #[case::no_completions(vec![key(KeyCode::Tab)], 1)]
+    fn bell_is_rung(#[case] mut events: Vec<Event>, #[case] bells: usize) {
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_menu(Box::new(CompletionMenu::default()))
+            .with_bell(BellStyle::Audible)
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert_eq!(backend.output().matches('\u{7}').count(), bells);
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn history_menu_on_the_alternate_screen(#[case] alternate_screen: bool) {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![key(KeyCode::F(2))]);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::F(2),
+            ReedlineEvent::Menu("history_menu".to_string()),
+        );
+        let menu = HistoryMenu::default().with_alternate_screen(alternate_screen);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_menu(Box::new(menu))
+            .with_backend(Box::new(backend.clone()));
+
+        // The read ends while the menu is open
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+
+        let output = backend.output();
+        let entered = output.find("\u{1b}[?1049h");
+        let left = output.find("\u{1b}[?1049l");
+        assert_eq!(entered.is_some(), alternate_screen);
+        assert_eq!(left.is_some(), alternate_screen);
+        assert!(entered <= left);
+        assert!(output[entered.unwrap_or(0)..left.unwrap_or(output.len())].contains("Page 1"));
+
+        // Closing the menu goes back to the prompt on the main screen
+        backend.push_event(key(KeyCode::Enter));
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+
+        let output = backend.output();
+        let reopened = output.rfind("\u{1b}[?1049h");
+        let closed = output.rfind("\u{1b}[?1049l");
+        let last_repaint = output.rfind("\u{1b}[1;1H");
+        assert!(reopened <= closed && closed < last_repaint);
+        assert!(!output[last_repaint.unwrap()..].contains("Page 1"));
+    }
+
+    #[rstest]
+    #[case(BellStyle::None, false, false)]
+    #[case(BellStyle::Audible, true, false)]
+    #[case(BellStyle::Visual, false, true)]
+    fn bell_styles(#[case] bell: BellStyle, #[case] beeps: bool, #[case] flashes: bool) {
+        let backend =
+            MemoryBackend::new(80, 24).with_events(vec![key(KeyCode::F(5)), key(KeyCode::Enter)]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_bell(bell)
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&DefaultPrompt).unwrap();
+
+        let output = backend.output();
+        assert_eq!(output.contains('\u{7}'), beeps);
+        assert_eq!(output.contains("\u{1b}[7m"), flashes);
+    }
+
+    fn read_with_abbreviations(events: Vec<Event>) -> String {
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_abbreviations(Abbreviations::new().with_abbreviation("gco", "git checkout"))
+            .with_backend(Box::new(backend));
+
+        match line_editor.read_line(&DefaultPrompt).unwrap() {
+            Signal::Success(line) => line,
+            signal => panic!("unexpected signal {:?}", signal),
+        }
+    }
+
+    fn type_text(text: &str) -> Vec<Event> {
+        text.chars().map(|c| key(KeyCode::Char(c))).collect()
+    }
+
+    #[rstest]
+    #[case::on_space("gco main", "git checkout main")]
+    #[case::on_enter("gco", "git checkout")]
+    #[case::not_as_argument("echo gco", "echo gco")]
+    fn abbreviations_are_expanded(#[case] typed: &str, #[case] expected: &str) {
+        let mut events = type_text(typed);
+        events.push(key(KeyCode::Enter));
+
+        assert_eq!(read_with_abbreviations(events), expected);
+    }
+
+    #[test]
+    fn abbreviation_can_be_skipped() {
+        let mut events = type_text("gco");
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Char(' '),
+            KeyModifiers::CONTROL,
+        )));
+        events.push(key(KeyCode::Enter));
+
+        assert_eq!(read_with_abbreviations(events), "gco ");
+    }
+
+    #[test]
+    fn commented_line_is_stored_without_submitting_it() {
+        let mut events = type_text("rm -rf x");
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Char('#'),
+            KeyModifiers::ALT,
+        )));
+        events.extend([key(KeyCode::Up), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create().unwrap().with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "# rm -rf x"));
+    }
+
+    #[rstest]
+    #[case::one_by_one("yn", "mv b.txt a.txt")]
+    #[case::all_at_once("a", "mv b.txt b.txt")]
+    #[case::stopped("q", "mv a.txt a.txt")]
+    fn search_replace_asks_for_each_match(#[case] answers: &str, #[case] expected: &str) {
+        let mut events = type_text("mv a.txt a.txt");
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Char('%'),
+            KeyModifiers::ALT,
+        )));
+        events.extend(type_text("a."));
+        events.push(key(KeyCode::Enter));
+        events.extend(type_text("b."));
+        events.push(key(KeyCode::Enter));
+        events.extend(type_text(answers));
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create().unwrap().with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == expected));
+    }
+
+    #[test]
+    fn pasted_text_is_undone_at_once() {
+        let mut events = type_text("echo hello world");
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Char('z'),
+            KeyModifiers::CONTROL,
+        )));
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create().unwrap().with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line.is_empty()));
+    }
+
+    #[rstest]
+    #[case::kept(PastePolicy::Keep, None)]
+    #[case::stripped(PastePolicy::Strip, Some("echo hi there"))]
+    fn pasted_control_characters(#[case] policy: PastePolicy, #[case] expected: Option<&str>) {
+        let mut events = type_text("echo hi");
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL,
+        )));
+        events.extend(type_text(" there"));
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_paste_policy(policy)
+            .with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        match expected {
+            Some(expected) => assert!(matches!(signal, Signal::Success(line) if line == expected)),
+            None => assert!(matches!(signal, Signal::CtrlC)),
+        }
+    }
+
+    #[test]
+    fn rejected_paste_keeps_the_lines_before_the_control_character() {
+        let mut events = type_text("echo one");
+        events.push(key(KeyCode::Enter));
+        events.extend(type_text("echo"));
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL,
+        )));
+        events.extend(type_text(" two"));
+        events.push(key(KeyCode::Enter));
+        events.extend(type_text("echo three"));
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_paste_policy(PastePolicy::Reject)
+            .with_backend(Box::new(backend.clone()));
+
+        // The first line was submitted before the control character arrived
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+        assert!(matches!(signal, Signal::Success(line) if line == "echo one"));
+
+        // The line with it and the ones after it are dropped
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+        backend.push_event(key(KeyCode::Enter));
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+        assert!(matches!(signal, Signal::Success(line) if line.is_empty()));
+    }
+
+    #[test]
+    fn multiline_paste_waits_for_enter() {
+        let mut events = type_text("echo one");
+        events.push(key(KeyCode::Enter));
+        events.extend(type_text("echo two"));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_confirm_multiline_paste(true)
+            .with_backend(Box::new(backend.clone()));
+
+        // Nothing is submitted until the events run out
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+        backend.push_event(key(KeyCode::Enter));
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "echo one\necho two"));
+    }
+
+    #[test]
+    fn pasted_text_is_highlighted_until_the_next_key() {
+        let backend = MemoryBackend::new(80, 24).with_events(type_text("echo hello world"));
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_highlight_paste(true)
+            .with_backend(Box::new(backend.clone()));
+
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+        let pasted = backend.output();
+        assert!(pasted.contains("\u{1b}[7;37mecho hello world"));
+
+        backend.push_event(key(KeyCode::Left));
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+        let moved = &backend.output()[pasted.len()..];
+        assert!(moved.contains("echo hello world"));
+        assert!(!moved.contains("\u{1b}[7"));
+    }
+
+    // Records the lines it is asked to hint
+    #[derive(Clone, Default)]
+    struct RecordingHinter(Arc<Mutex<Vec<String>>>);
+
+    impl Hinter for RecordingHinter {
+        fn handle(&mut self, line: &str, _pos: usize, _: &dyn History, _: bool) -> String {
+            self.0.lock().unwrap().push(line.to_string());
+            String::new()
+        }
+
+        fn complete_hint(&self) -> String {
+            String::new()
+        }
+
+        fn next_hint_token(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[rstest]
+    #[case(0, vec!["", "abc"])]
+    #[case(3, vec!["abc"])]
+    #[case(4, vec![])]
+    fn hints_wait_for_the_minimum_characters(
+        #[case] min_chars: usize,
+        #[case] expected: Vec<&str>,
+    ) {
+        let mut events = type_text("abc");
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let hinter = RecordingHinter::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_hinter(Box::new(hinter.clone()))
+            .with_hint_min_chars(min_chars)
+            .with_backend(Box::new(backend));
+
+        line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert_eq!(*hinter.0.lock().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(false, vec!["echo hello world", "echo hello world!"])]
+    #[case(true, vec!["echo hello world", "echo hello world", "echo hello world!"])]
+    fn hints_are_hidden_while_pasting(#[case] hints_on_paste: bool, #[case] expected: Vec<&str>) {
+        let backend = MemoryBackend::new(80, 24).with_events(type_text("echo hello world"));
+        let hinter = RecordingHinter::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_hinter(Box::new(hinter.clone()))
+            .with_hint_min_chars(1)
+            .with_hints_on_paste(hints_on_paste)
+            .with_backend(Box::new(backend.clone()));
+
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+        assert_eq!(hinter.0.lock().unwrap().len(), usize::from(hints_on_paste));
+
+        // The next read and the typed key show the hints again
+        backend.push_event(key(KeyCode::Char('!')));
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+        assert_eq!(*hinter.0.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn completion_hint_comes_from_the_engine_completer() {
+        let mut events = type_text("he");
+        events.extend([key(KeyCode::Right), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_completer(Box::new(DefaultCompleter::new(vec!["hello".into()])))
+            .with_hinter(Box::new(CompletionHinter::default()))
+            .with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "hello"));
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingHook(Arc<Mutex<Vec<String>>>);
+
+    impl Hook for RecordingHook {
+        fn before_read(&mut self) {
+            self.0.lock().unwrap().push("read".to_string());
+        }
+
+        fn after_submit(&mut self, line: &str, _elapsed: Duration) {
+            self.0.lock().unwrap().push(format!("submitted {}", line));
+        }
+
+        fn on_menu_selection(&mut self, value: &str) {
+            self.0.lock().unwrap().push(format!("selected {}", value));
+        }
+
+        fn on_write(&mut self, line: &str) {
+            self.0.lock().unwrap().push(format!("wrote {}", line));
+        }
+    }
+
+    #[test]
+    fn hooks_are_called_around_reads() {
+        let mut events = type_text("ls");
+        events.push(key(KeyCode::Enter));
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL,
+        )));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let hook = RecordingHook::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_hook(Box::new(hook.clone()))
+            .with_backend(Box::new(backend));
+
+        line_editor.read_line(&DefaultPrompt).unwrap();
+        line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert_eq!(
+            *hook.0.lock().unwrap(),
+            vec!["read", "submitted ls", "read"]
+        );
+    }
+
+    #[test]
+    fn vi_command_line_edits_and_writes_the_buffer() {
+        let mut events = type_text("foo foo");
+        events.push(key(KeyCode::Esc));
+        events.extend(type_text(":%s/foo/bar/g"));
+        events.push(key(KeyCode::Enter));
+        events.extend(type_text(":w"));
+        events.extend([key(KeyCode::Enter), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let hook = RecordingHook::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Vi::default()))
+            .with_hook(Box::new(hook.clone()))
+            .with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "bar bar"));
+        assert_eq!(
+            *hook.0.lock().unwrap(),
+            vec!["read", "wrote bar bar", "submitted bar bar"]
+        );
+    }
+
+    #[test]
+    fn emacs_macro_is_recorded_and_replayed() {
+        let ctrl = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+        let mut events = vec![ctrl('x'), key(KeyCode::Char('('))];
+        events.extend(type_text("ab"));
+        events.extend([ctrl('x'), key(KeyCode::Char(')'))]);
+        events.extend([ctrl('u'), key(KeyCode::Char('2'))]);
+        events.extend([ctrl('x'), key(KeyCode::Char('e')), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create().unwrap().with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "ababab"));
+    }
+
+    #[test]
+    fn quoted_insert_types_control_characters() {
+        let mut events = vec![
+            Event::Key(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL)),
+            key(KeyCode::Esc),
+        ];
+        events.extend(type_text("c"));
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create().unwrap().with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "\x1bc"));
+    }
+
+    #[test]
+    fn binding_conditions_choose_the_event() {
+        let mut events = vec![key(KeyCode::Tab)];
+        events.extend(type_text("a"));
+        events.extend([key(KeyCode::Tab), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::When(
+                    vec![
+                        BindingCondition::CursorAtLineStart,
+                        BindingCondition::Not(Box::new(BindingCondition::MenuActive)),
+                    ],
+                    Box::new(ReedlineEvent::Edit(vec![EditCommand::InsertString(
+                        "  ".to_string(),
+                    )])),
+                ),
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('!')]),
+            ]),
+        );
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "  a!"));
+    }
+
+    #[test]
+    fn menu_selection_is_announced() {
+        let mut events = type_text("he");
+        events.extend([key(KeyCode::Tab), key(KeyCode::Tab), key(KeyCode::Tab)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::Menu("completion_menu".to_string()),
+                ReedlineEvent::MenuNext,
+            ]),
+        );
+        let hook = RecordingHook::default();
+        let completer = DefaultCompleter::new(vec!["hello".into(), "help".into()]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_completer(Box::new(completer))
+            .with_menu(Box::new(CompletionMenu::default()))
+            .with_hook(Box::new(hook.clone()))
+            .with_backend(Box::new(backend));
+
+        // The read ends while the menu is open
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert_eq!(
+            *hook.0.lock().unwrap(),
+            vec!["read", "selected hello", "selected help", "selected hello"]
+        );
+    }
+
+    #[rstest]
+    #[case::menu_with_values("he", "hello")]
+    #[case::menu_without_values("xy", "xy\t")]
+    fn until_found_falls_back_from_a_menu_without_values(
+        #[case] typed: &str,
+        #[case] expected: &str,
+    ) {
+        let mut events = type_text(typed);
+        events.extend([key(KeyCode::Tab), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::Menu("completion_menu".to_string()),
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('\t')]),
+            ]),
+        );
+        let completer = DefaultCompleter::new(vec!["hello".into()]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_completer(Box::new(completer))
+            .with_quick_completions(true)
+            .with_menu(Box::new(CompletionMenu::default()))
+            .with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == expected));
+    }
+
+    #[rstest]
+    #[case::one_line("ls", 1)]
+    #[case::wrapped("echo hello", 2)]
+    fn layout_of_the_last_prompt_is_kept(#[case] typed: &str, #[case] lines: u16) {
+        let mut events = type_text(typed);
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(10, 24).with_events(events);
+        let mut line_editor = Reedline::create().unwrap().with_backend(Box::new(backend));
+        assert_eq!(line_editor.last_prompt_layout(), None);
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert_eq!(
+            line_editor.last_prompt_layout(),
+            Some(PromptLayout {
+                start_row: 0,
+                lines,
+                cursor: (0, lines),
+            })
+        );
+    }
+
+    #[test]
+    fn metadata_of_the_accepted_line_is_kept() {
+        let mut events = type_text("hel");
+        events.extend([key(KeyCode::Tab), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let completer = DefaultCompleter::new(vec!["hello".into()]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_completer(Box::new(completer))
+            .with_quick_completions(true)
+            .with_menu_trigger(Box::new(CompletionMenu::default()), "tab".parse().unwrap())
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+        let metadata = line_editor.last_line_metadata().unwrap();
+        assert!(!metadata.from_history);
+        assert_eq!(metadata.completions_accepted, 1);
+
+        backend.push_event(key(KeyCode::Up));
+        backend.push_event(key(KeyCode::Enter));
+        line_editor.read_line(&FixedPrompt).unwrap();
+        let metadata = line_editor.last_line_metadata().unwrap();
+        assert!(metadata.from_history);
+        assert_eq!(metadata.completions_accepted, 0);
+
+        backend.push_event(key(KeyCode::Char('c')));
+        backend.push_event(Event::Key(KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL,
+        )));
+        line_editor.read_line(&FixedPrompt).unwrap();
+        assert_eq!(line_editor.last_line_metadata(), None);
+    }
+
+    #[test]
+    fn buffer_dropped_by_ctrl_c_is_kept() {
+        let ctrl_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        let mut events = type_text("git push");
+        events.push(ctrl_c);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+
+        assert!(matches!(
+            line_editor.read_line(&FixedPrompt).unwrap(),
+            Signal::CtrlC
+        ));
+        assert_eq!(line_editor.dismissed_buffer(), Some("git push"));
+
+        backend.push_event(ctrl_c);
+        line_editor.read_line(&FixedPrompt).unwrap();
+        assert_eq!(line_editor.dismissed_buffer(), None);
+    }
+
+    #[test]
+    fn named_histories_keep_their_lines_apart() {
+        let backend = MemoryBackend::new(80, 24);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_named_history("python", Box::new(FileBackedHistory::default()))
+            .with_backend(Box::new(backend.clone()));
+        let read = |line_editor: &mut Reedline, mut events: Vec<Event>| {
+            events.push(key(KeyCode::Enter));
+            for event in events {
+                backend.push_event(event);
+            }
+            match line_editor.read_line(&FixedPrompt).unwrap() {
+                Signal::Success(line) => line,
+                _ => panic!("the line wasn't accepted"),
+            }
+        };
+
+        read(&mut line_editor, type_text("ls"));
+        line_editor.switch_history("python").unwrap();
+        read(&mut line_editor, type_text("1 + 1"));
+        line_editor.switch_history("default").unwrap();
+
+        let recalled = read(&mut line_editor, vec![key(KeyCode::Up)]);
+        assert_eq!(recalled, "ls");
+        line_editor.switch_history("python").unwrap();
+        let recalled = read(&mut line_editor, vec![key(KeyCode::Up)]);
+        assert_eq!(recalled, "1 + 1");
+
+        assert!(matches!(
+            line_editor.switch_history("sql"),
+            Err(ReedlineError::Config(_))
+        ));
+        assert_eq!(line_editor.history_name(), "python");
+    }
+
+    #[rstest]
+    #[case(Duration::ZERO, vec!["ls"])]
+    #[case(Duration::from_secs(60), vec![])]
+    fn history_is_saved_while_waiting_for_input(
+        #[case] delay: Duration,
+        #[case] expected: Vec<&str>,
+    ) {
+        let tmp = tempfile::tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        let history = FileBackedHistory::with_file(10, histfile.clone()).unwrap();
+        let backend = MemoryBackend::new(80, 24);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_history(Box::new(history))
+            .unwrap()
+            .with_history_sync_delay(delay)
+            .with_backend(Box::new(backend.clone()));
+
+        let mut events = type_text("ls");
+        events.push(key(KeyCode::Enter));
+        for event in events {
+            backend.push_event(event);
+        }
+        assert!(matches!(
+            line_editor.read_line(&FixedPrompt),
+            Ok(Signal::Success(_))
+        ));
+        // The next read only waits, until the backend runs out of polls
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        let saved = FileBackedHistory::with_file(10, histfile).unwrap();
+        assert_eq!(saved.iter_chronologic().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn failing_history_save_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("gone");
+        std::fs::create_dir(&dir).unwrap();
+        let history = FileBackedHistory::with_file(10, dir.join(".history")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut events = type_text("ls");
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_history(Box::new(history))
+            .unwrap()
+            .with_history_sync_delay(Duration::ZERO)
+            .with_backend(Box::new(backend));
+
+        assert!(line_editor.read_line(&FixedPrompt).is_ok());
+        assert!(line_editor.history_sync_error().is_none());
+        // The next read only waits, until the backend runs out of polls
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert!(matches!(
+            line_editor.history_sync_error(),
+            Some(ReedlineError::History(_))
+        ));
+    }
+
+    #[rstest]
+    #[case('y', vec!["ls"])]
+    #[case('n', vec!["ls", "pwd"])]
+    fn history_entry_is_deleted_once_confirmed(#[case] answer: char, #[case] expected: Vec<&str>) {
+        let tmp = tempfile::tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        let history = FileBackedHistory::with_file(10, histfile.clone()).unwrap();
+        let backend = MemoryBackend::new(80, 24);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::F(2),
+            ReedlineEvent::Menu("history_menu".to_string()),
+        );
+        keybindings.add_binding(
+            KeyModifiers::ALT,
+            KeyCode::Char('x'),
+            ReedlineEvent::HistoryDelete,
+        );
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_history(Box::new(history))
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_menu(Box::new(HistoryMenu::default()))
+            .with_backend(Box::new(backend.clone()));
+        for line in ["ls", "pwd"] {
+            let mut events = type_text(line);
+            events.push(key(KeyCode::Enter));
+            for event in events {
+                backend.push_event(event);
+            }
+            line_editor.read_line(&FixedPrompt).unwrap();
+        }
+
+        // The newest entry is selected when the menu opens
+        let delete = [
+            key(KeyCode::F(2)),
+            Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT)),
+        ];
+        for event in delete {
+            backend.push_event(event);
+        }
+        // The read ends while the question is asked
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+        assert!(backend
+            .output()
+            .contains("Delete \"pwd\" from the history? (y/n)"));
+
+        for event in delete.iter().copied().chain([key(KeyCode::Char(answer))]) {
+            backend.push_event(event);
+        }
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+        drop(line_editor);
+
+        let saved = FileBackedHistory::with_file(10, histfile).unwrap();
+        assert_eq!(saved.iter_chronologic().collect::<Vec<_>>(), expected);
+    }
+
+    #[rstest]
+    #[case::history_search(vec![Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL))])]
+    #[case::menu_filter(vec![key(KeyCode::F(2))])]
+    fn search_term_is_highlighted_in_the_buffer(#[case] search: Vec<Event>) {
+        let backend = MemoryBackend::new(80, 24);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::F(2),
+            ReedlineEvent::Menu("history_menu".to_string()),
+        );
+        let match_style = Color::Black.on(Color::Yellow);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_menu(Box::new(HistoryMenu::default()))
+            .with_search_match_style(match_style)
+            .with_backend(Box::new(backend.clone()));
+        let mut events = type_text("echo hi");
+        events.push(key(KeyCode::Enter));
+        for event in events {
+            backend.push_event(event);
+        }
+        line_editor.read_line(&FixedPrompt).unwrap();
+        assert!(!backend
+            .output()
+            .contains(&match_style.paint("hi").to_string()));
+
+        for event in search.into_iter().chain(type_text("hi")) {
+            backend.push_event(event);
+        }
+        // The read ends while the search is going on
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert!(backend
+            .output()
+            .contains(&match_style.paint("hi").to_string()));
+    }
+
+    // Answers after being polled a number of times, recording a cancellation
+    #[derive(Clone, Default)]
+    struct SlowCompleter {
+        // Polls left before the completions are ready, and whether they were cancelled
+        state: Arc<Mutex<(usize, bool)>>,
+    }
+
+    impl Completer for SlowCompleter {
+        fn complete(&self, _line: &str, pos: usize) -> Vec<(Span, String)> {
+            vec![(Span::new(0, pos), "hello".to_string())]
+        }
+
+        fn poll_completions(&self, _line: &str, _pos: usize) -> Poll<()> {
+            let mut state = self.state.lock().unwrap();
+            if state.0 == 0 {
+                return Poll::Ready(());
+            }
+            state.0 -= 1;
+            Poll::Pending
+        }
+
+        fn cancel_completions(&self) {
+            self.state.lock().unwrap().1 = true;
+        }
+    }
+
+    #[rstest]
+    #[case::loaded(vec![], false)]
+    #[case::cancelled(vec![key(KeyCode::Esc)], true)]
+    fn slow_completions_are_loaded_or_cancelled(
+        #[case] after_tab: Vec<Event>,
+        #[case] cancelled: bool,
+    ) {
+        let mut events = type_text("he");
+        events.push(key(KeyCode::Tab));
+        events.extend(after_tab);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let completer = SlowCompleter::default();
+        completer.state.lock().unwrap().0 = 3;
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::Menu("completion_menu".to_string()),
+        );
+        let hook = RecordingHook::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_completer(Box::new(completer.clone()))
+            .with_quick_completions(true)
+            .with_menu(Box::new(CompletionMenu::default()))
+            .with_hook(Box::new(hook.clone()))
+            .with_backend(Box::new(backend.clone()));
+
+        // The read ends when the events run out
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert!(backend.output().contains("LOADING COMPLETIONS"));
+        assert_eq!(completer.state.lock().unwrap().1, cancelled);
+        assert_eq!(
+            hook.0.lock().unwrap().last().map(String::as_str),
+            Some(if cancelled { "read" } else { "selected hello" })
+        );
+    }
+
+    #[test]
+    fn completion_hint_waits_for_a_slow_completer() {
+        let backend = MemoryBackend::new(80, 24).with_events(type_text("he"));
+        let completer = SlowCompleter::default();
+        completer.state.lock().unwrap().0 = 3;
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_completer(Box::new(completer.clone()))
+            .with_completion_cache(true)
+            .with_hinter(Box::new(CompletionHinter::default()))
+            .with_ansi_colors(false)
+            .with_backend(Box::new(backend.clone()));
+
+        // The read ends when the events run out
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert_eq!(completer.state.lock().unwrap().0, 0);
+        assert!(backend.output().contains("llo"));
+    }
+
+    // Records the lines it is asked to complete
+    #[derive(Clone, Default)]
+    struct RecordingCompleter(Arc<Mutex<Vec<String>>>);
+
+    impl Completer for RecordingCompleter {
+        fn complete(&self, line: &str, pos: usize) -> Vec<(Span, String)> {
+            self.0.lock().unwrap().push(line.to_string());
+            vec![
+                (Span::new(0, pos), "hello".to_string()),
+                (Span::new(0, pos), "help".to_string()),
+            ]
+        }
+    }
+
+    #[rstest]
+    #[case::every_edit(Duration::ZERO, vec![], true)]
+    #[case::after_the_delay(Duration::from_secs(3600), vec![], false)]
+    #[case::when_the_menu_is_used(Duration::from_secs(3600), vec![key(KeyCode::Down)], true)]
+    fn open_menu_is_refreshed_after_the_delay(
+        #[case] delay: Duration,
+        #[case] after_typing: Vec<Event>,
+        #[case] refreshed: bool,
+    ) {
+        let mut events = type_text("he");
+        events.push(key(KeyCode::Tab));
+        events.extend(type_text("lp"));
+        events.extend(after_typing);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let completer = RecordingCompleter::default();
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::Menu("completion_menu".to_string()),
+        );
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_completer(Box::new(completer.clone()))
+            .with_menu(Box::new(CompletionMenu::default()))
+            .with_menu_refresh_delay(delay)
+            .with_backend(Box::new(backend));
+
+        // The read ends when the events run out
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        let lines = completer.0.lock().unwrap();
+        assert!(lines.iter().any(|line| line == "he"));
+        assert!(!lines.iter().any(|line| line == "hel") || delay.is_zero());
+        assert_eq!(lines.iter().any(|line| line == "help"), refreshed);
+    }
+
+    // Completes the paths of a small tree, the directories end with a slash
+    struct PathCompleter;
+
+    impl Completer for PathCompleter {
+        fn complete(&self, line: &str, pos: usize) -> Vec<(Span, String)> {
+            let start = line[..pos].rfind(' ').map_or(0, |space| space + 1);
+            ["src/", "src/lib.rs", "src/main.rs"]
+                .iter()
+                .filter(|path| path.starts_with(&line[start..pos]))
+                .filter(|path| {
+                    path.trim_end_matches('/').matches('/').count()
+                        == line[start..pos].matches('/').count()
+                })
+                .map(|path| (Span::new(start, pos), path.to_string()))
+                .collect()
+        }
+
+        fn keeps_menu_open(&self, _span: Span, value: &str) -> bool {
+            value.ends_with('/')
+        }
+    }
+
+    #[rstest]
+    #[case::quick_completion(true, 2)]
+    #[case::selected_in_the_menu(false, 3)]
+    fn directories_keep_the_menu_open(#[case] quick_completions: bool, #[case] accepts: usize) {
+        let mut events = type_text("cd s");
+        events.push(key(KeyCode::Tab));
+        // Accepts like Enter, without ending the batch of events before the menu is painted
+        events.extend(std::iter::repeat(key(KeyCode::F(3))).take(accepts));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::Menu("completion_menu".to_string()),
+        );
+        keybindings.add_binding(KeyModifiers::NONE, KeyCode::F(3), ReedlineEvent::Enter);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_completer(Box::new(PathCompleter))
+            .with_quick_completions(quick_completions)
+            .with_menu(Box::new(CompletionMenu::default()))
+            .with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "cd src/lib.rs"));
+    }
+
+    // Reads a line with a popup prompt when `?` is typed
+    struct PopupHook {
+        popup: Reedline,
+        backend: MemoryBackend,
+        answer: Arc<Mutex<Option<(String, bool)>>>,
+    }
+
+    impl Hook for PopupHook {
+        fn on_change(&mut self, line: &str, _pos: usize) -> Option<String> {
+            if line != "?" {
+                return None;
+            }
+            for event in type_text("yes") {
+                self.backend.push_event(event);
+            }
+            self.backend.push_event(key(KeyCode::Enter));
+            if let Ok(Signal::Success(answer)) = self.popup.read_line(&FixedPrompt) {
+                *self.answer.lock().unwrap() = Some((answer, self.backend.is_raw_mode()));
+            }
+
+            // The main prompt goes on
+            self.backend.push_event(key(KeyCode::Backspace));
+            for event in type_text("ok") {
+                self.backend.push_event(event);
+            }
+            self.backend.push_event(key(KeyCode::Enter));
+            None
+        }
+    }
+
+    #[test]
+    fn popup_read_keeps_the_terminal_of_the_main_prompt() {
+        let backend = MemoryBackend::new(80, 24).with_events(type_text("?"));
+        let terminal = TerminalManager::default();
+        let answer = Arc::new(Mutex::new(None));
+        let popup = Reedline::create()
+            .unwrap()
+            .with_terminal_manager(terminal.clone())
+            .with_backend(Box::new(backend.clone()));
+        let hook = PopupHook {
+            popup,
+            backend: backend.clone(),
+            answer: answer.clone(),
+        };
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_terminal_manager(terminal.clone())
+            .with_change_debounce(Duration::ZERO)
+            .with_hook(Box::new(hook))
+            .with_backend(Box::new(backend.clone()));
+
+        let signal = line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "ok"));
+        // The terminal stayed in raw mode for the main prompt after the popup read
+        assert_eq!(*answer.lock().unwrap(), Some(("yes".to_string(), true)));
+        assert!(!backend.is_raw_mode());
+        assert!(!terminal.is_reading());
+    }
+
+    #[test]
+    fn highlighter_gets_the_context_of_the_host() {
+        let mut events = type_text("gti");
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+        line_editor
+            .highlight_context_mut()
+            .commands
+            .insert("git".to_string());
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert!(backend
+            .output()
+            .contains(&Color::Red.paint("gti").to_string()));
+    }
+
+    #[test]
+    fn menu_is_opened_by_its_trigger_key() {
+        let mut events = type_text("he");
+        events.extend([key(KeyCode::Tab), key(KeyCode::Tab)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let hook = RecordingHook::default();
+        let completer = DefaultCompleter::new(vec!["hello".into(), "help".into()]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_completer(Box::new(completer))
+            .with_menu_trigger(Box::new(CompletionMenu::default()), "tab".parse().unwrap())
+            .with_hook(Box::new(hook.clone()))
+            .with_backend(Box::new(backend));
+
+        // The key isn't bound by the edit mode: the first press opens the menu and the
+        // second one selects the next value
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert_eq!(
+            *hook.0.lock().unwrap(),
+            vec!["read", "selected hello", "selected help"]
+        );
+    }
+
+    #[test]
+    fn open_menu_is_moved_through_with_its_own_keys() {
+        let ctrl = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+        let mut events = type_text("he");
+        events.extend([
+            // Without an open menu the keys go to the edit mode
+            ctrl('n'),
+            key(KeyCode::Tab),
+            ctrl('n'),
+            ctrl('n'),
+            ctrl('p'),
+            Event::Key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)),
+        ]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let hook = RecordingHook::default();
+        let completer = DefaultCompleter::new(vec!["hello".into(), "help".into(), "helm".into()]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_completer(Box::new(completer))
+            .with_menu_trigger(Box::new(CompletionMenu::default()), "tab".parse().unwrap())
+            .with_hook(Box::new(hook.clone()))
+            .with_backend(Box::new(backend));
+        line_editor.set_menu_navigation(
+            "completion_menu",
+            MenuNavigation {
+                next: Some("ctrl-n".parse().unwrap()),
+                previous: Some("ctrl-p".parse().unwrap()),
+            },
+        );
+
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert_eq!(
+            *hook.0.lock().unwrap(),
+            vec![
+                "read",
+                "selected hello",
+                "selected helm",
+                "selected help",
+                "selected helm",
+                "selected hello"
+            ]
+        );
+    }
+
+    #[test]
+    fn menus_are_replaced_and_removed_by_name() {
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_menu(Box::new(HistoryMenu::default()))
+            .with_menu_trigger(Box::new(CompletionMenu::default()), "tab".parse().unwrap());
+        assert_eq!(
+            line_editor.menu_names(),
+            vec!["history_menu", "completion_menu"]
+        );
+
+        let replaced = line_editor.replace_menu(Box::new(CompletionMenu::default()));
+        assert!(replaced.is_some());
+        assert_eq!(
+            line_editor.menu_names(),
+            vec!["history_menu", "completion_menu"]
+        );
+        assert_eq!(
+            line_editor.menu_trigger("completion_menu"),
+            Some(&"tab".parse().unwrap())
+        );
+
+        // The key moves to the history menu
+        line_editor.set_menu_trigger("history_menu", Some("tab".parse().unwrap()));
+        assert_eq!(line_editor.menu_trigger("completion_menu"), None);
+
+        assert!(line_editor.remove_menu("history_menu").is_some());
+        assert!(line_editor.remove_menu("history_menu").is_none());
+        assert_eq!(line_editor.menu_names(), vec!["completion_menu"]);
+        assert_eq!(line_editor.menu_trigger("history_menu"), None);
+    }
+
+    // A menu of the host that only records its commands
+    #[derive(Clone, Default)]
+    struct CommandMenu {
+        active: bool,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Menu for CommandMenu {
+        fn name(&self) -> &str {
+            "commands"
+        }
+
+        fn is_active(&self) -> bool {
+            self.active
+        }
+
+        fn menu_event(&mut self, event: MenuEvent) {
+            match event {
+                MenuEvent::Activate(_) => self.active = true,
+                MenuEvent::Deactivate => self.active = false,
+                MenuEvent::Custom(command) => self.commands.lock().unwrap().push(command),
+                _ => {}
+            }
+        }
+
+        fn update_values(
+            &mut self,
+            _line_buffer: &mut LineBuffer,
+            _history: &dyn History,
+            _completer: &dyn Completer,
+        ) {
+        }
+
+        fn update_working_details(
+            &mut self,
+            _line_buffer: &mut LineBuffer,
+            _history: &dyn History,
+            _completer: &dyn Completer,
+            _painter: &Painter,
+        ) {
+        }
+
+        fn replace_in_buffer(&self, _line_buffer: &mut LineBuffer) {}
+
+        fn menu_required_lines(&self, _terminal_columns: u16) -> u16 {
+            1
+        }
+
+        fn menu_rows(&self, _available_lines: u16) -> Vec<MenuRow<'_>> {
+            vec![]
+        }
+
+        fn min_rows(&self) -> u16 {
+            1
+        }
+
+        fn get_values(&self) -> &[(Span, String)] {
+            &[]
+        }
+    }
+
+    #[test]
+    fn custom_commands_reach_the_open_menu() {
+        let alt_p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::ALT));
+        let backend = MemoryBackend::new(80, 24).with_events(vec![
+            // Without an open menu the command isn't handled
+            alt_p,
+            key(KeyCode::F(2)),
+            alt_p,
+        ]);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::ALT,
+            KeyCode::Char('p'),
+            ReedlineEvent::MenuCustom("toggle preview".to_string()),
+        );
+        let menu = CommandMenu::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_menu_trigger(Box::new(menu.clone()), "f2".parse().unwrap())
+            .with_backend(Box::new(backend));
+
+        assert!(line_editor.read_line(&FixedPrompt).is_err());
+
+        assert_eq!(*menu.commands.lock().unwrap(), vec!["toggle preview"]);
+    }
+
+    #[test]
+    fn screen_reader_mode_prints_typed_text_on_its_own() {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Left),
+            key(KeyCode::End),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Enter),
+        ]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_screen_reader_mode(true)
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        // Moving the cursor repaints the line, typing at the end doesn't
+        let output = backend.output();
+        assert_eq!(output.matches("\u{1b}[1;1H").count(), 3);
+        assert!(output.contains("\u{1b}8\u{1b}[37ma\u{1b}[0m\u{1b}7\u{1b}[1;1H"));
+        assert!(output.ends_with("\u{1b}8\u{1b}[37mb\u{1b}[0m\u{1b}7\u{1b}[2;1H"));
+        assert!(!output.contains("\u{1b}[?25l"));
+    }
+
+    #[test]
+    fn monochrome_theme_paints_without_colors() {
+        let mut events = type_text("ls  ");
+        events.extend([key(KeyCode::Home), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_theme(Theme::by_name("monochrome").unwrap())
+            .with_visible_whitespace(true)
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        let output = backend.output();
+        assert!(output.contains("\u{1b}[1m~> \u{1b}[0m"));
+        assert!(!output.contains("\u{1b}[3"));
+        assert!(!output.contains("\u{1b}[4"));
+        // The trailing whitespace is reversed instead of painted red
+        assert!(output.contains("\u{1b}[7m  "));
+    }
+
+    struct Preview;
+
+    impl Hook for Preview {
+        fn on_change(&mut self, line: &str, pos: usize) -> Option<String> {
+            Some(format!("{} characters, cursor at {}", line.len(), pos))
+        }
+    }
+
+    #[test]
+    fn preview_of_the_changed_buffer_is_shown() {
+        let backend = MemoryBackend::new(80, 24).with_events(type_text("ls *.rs"));
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_hook(Box::new(Preview))
+            .with_change_debounce(Duration::ZERO)
+            .with_backend(Box::new(backend.clone()));
+
+        assert!(line_editor.read_line(&DefaultPrompt).is_err());
+
+        assert!(backend.output().contains("7 characters, cursor at 7"));
+    }
+
+    #[test]
+    fn status_is_shown_below_the_buffer_until_submitted() {
+        let mut events = type_text("ls");
+        events.push(key(KeyCode::Enter));
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+        line_editor.set_status(vec!["first".to_string(), "second".to_string()]);
+
+        line_editor.read_line(&DefaultPrompt).unwrap();
+
+        let output = backend.output();
+        assert!(output.contains("ls\u{1b}[0m\u{1b}7\r\nfirst\r\nsecond"));
+        // The submitted buffer is repainted without it
+        assert!(output.rfind("first") < output.rfind("ls"));
+    }
+
+    #[test]
+    fn prompt_stays_at_the_bottom() {
+        let mut events = type_text("ls");
+        events.extend([key(KeyCode::Enter), key(KeyCode::Enter)]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_prompt_at_bottom(true)
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+        write!(backend.clone(), "output\r\n").unwrap();
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        let output = backend.output();
+        // The rows above the last one are cleared on the first repaint
+        assert!(output.starts_with("\u{1b}[?25l\u{1b}[1;1H\u{1b}[J\u{1b}[24;1H"));
+        assert!(output
+            .split("\u{1b}[?25l")
+            .skip(1)
+            .all(|repaint| repaint.contains("\u{1b}[24;1H\u{1b}[38;5;12m~> ")
+                || repaint.contains("\u{1b}[24;1H\u{1b}[J\u{1b}[38;5;12m~> ")));
+        assert!(output.rfind("output") < output.rfind("\u{1b}[24;1H"));
+    }
+
+    #[rstest]
+    #[case::screen(KeyModifiers::CONTROL, KeyCode::Char('l'), false)]
+    #[case::scrollback(KeyModifiers::NONE, KeyCode::F(3), true)]
+    fn clearing_the_screen_keeps_the_buffer(
+        #[case] modifiers: KeyModifiers,
+        #[case] code: KeyCode,
+        #[case] purged: bool,
+    ) {
+        let mut events = type_text("ls -a");
+        events.extend([
+            key(KeyCode::Left),
+            Event::Key(KeyEvent::new(code, modifiers)),
+            key(KeyCode::Char('l')),
+            key(KeyCode::Enter),
+        ]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::F(3),
+            ReedlineEvent::ClearScrollback,
+        );
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_backend(Box::new(backend.clone()));
+
+        let signal = line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "ls -la"));
+        let output = backend.output();
+        let cleared = output.find(&"\n".repeat(48)).unwrap();
+        assert_eq!(output.contains("\u{1b}[3J"), purged);
+        // The prompt is painted again on the first line
+        assert!(output[cleared..].contains("\u{1b}[1;1H\u{1b}[J\u{1b}[38;5;12m~> "));
+    }
+
+    #[test]
+    fn clearing_the_screen_closes_the_menu() {
+        let mut events = type_text("he");
+        events.extend([
+            key(KeyCode::Tab),
+            Event::Key(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL)),
+            key(KeyCode::Enter),
+        ]);
+        let backend = MemoryBackend::new(80, 24).with_events(events);
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::NONE,
+            KeyCode::Tab,
+            ReedlineEvent::Menu("completion_menu".to_string()),
+        );
+        let completer = DefaultCompleter::new(vec!["hello".into(), "help".into()]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_completer(Box::new(completer))
+            .with_menu(Box::new(CompletionMenu::default()))
+            .with_backend(Box::new(backend));
+
+        let signal = line_editor.read_line(&FixedPrompt).unwrap();
+
+        // Enter submits the buffer instead of taking the selected value
+        assert!(matches!(signal, Signal::Success(line) if line == "he"));
+    }
+
+    #[rstest]
+    #[case(false, 2)]
+    #[case(true, 3)]
+    fn repaint_is_requested_from_a_handle(#[case] requested: bool, #[case] repaints: usize) {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![key(KeyCode::Enter)]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+        let handle = line_editor.repaint_handle();
+        if requested {
+            handle.request_repaint();
+        }
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert_eq!(backend.output().matches("\u{1b}[?25l").count(), repaints);
+    }
+
+    #[test]
+    fn external_printer_prints_above_the_prompt() {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![key(KeyCode::Enter)]);
+        let printer = ExternalPrinter::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_external_printer(printer.clone())
+            .with_backend(Box::new(backend.clone()));
+        printer.print("job done");
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        let output = backend.output();
+        let printed = output.find("\u{1b}[1;1H\u{1b}[Jjob done\r\n").unwrap();
+        // The prompt is painted again on the next row
+        let repainted = output
+            .find("\u{1b}[2;1H\u{1b}[J\u{1b}[38;5;12m~> ")
+            .unwrap();
+        assert!(printed < repainted);
+    }
+
+    #[test]
+    fn composition_is_shown_until_it_is_committed() {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![key(KeyCode::Enter)]);
+        let composition = ImeComposition::default();
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_ime_composition(composition.clone())
+            .with_backend(Box::new(backend.clone()));
+        composition.set_preedit("ni", 1);
+
+        let signal = line_editor.read_line(&FixedPrompt).unwrap();
+
+        // The composed text isn't part of the buffer
+        assert!(matches!(signal, Signal::Success(line) if line.is_empty()));
+        // The cursor is saved where the composition puts it
+        assert!(backend
+            .output()
+            .contains("~> \u{1b}[0m\u{1b}[4mn\u{1b}[0m\u{1b}7\u{1b}[4mi\u{1b}[0m"));
+
+        composition.commit("你");
+        backend.push_event(key(KeyCode::Enter));
+        let signal = line_editor.read_line(&FixedPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "你"));
+    }
+
+    #[rstest]
+    #[case::every_change(Duration::ZERO, 5)]
+    #[case::coalesced(Duration::from_secs(3600), 2)]
+    fn repaints_are_throttled(#[case] frame_interval: Duration, #[case] expected_repaints: usize) {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Left),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Enter),
+        ]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_frame_interval(frame_interval)
+            .with_backend(Box::new(backend.clone()));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "ba"));
+        // The first paint and the one of the submitted buffer are not put off
+        let output = backend.output();
+        assert_eq!(output.matches("\u{1b}[1;1H").count(), expected_repaints);
+    }
+
+    struct FixedPrompt;
+
+    impl Prompt for FixedPrompt {
+        fn render_prompt_left(&self) -> Cow<'_, str> {
+            "~".into()
+        }
+
+        fn render_prompt_right(&self) -> Cow<'_, str> {
+            "".into()
+        }
+
+        fn render_prompt_indicator(&self, _edit_mode: PromptEditMode) -> Cow<'_, str> {
+            "> ".into()
+        }
+
+        fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
+            "::: ".into()
+        }
+
+        fn render_prompt_history_search_indicator(
+            &self,
+            _history_search: PromptHistorySearch,
+        ) -> Cow<'_, str> {
+            "? ".into()
+        }
+    }
+
+    fn painted_output(backend: MemoryBackend) -> String {
+        let mut events = type_text("echo hello world");
+        events.extend([
+            key(KeyCode::Home),
+            key(KeyCode::Delete),
+            key(KeyCode::Enter),
+        ]);
+        let backend = backend.with_events(events);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&FixedPrompt).unwrap();
+
+        backend.output()
+    }
+
+    #[test]
+    fn output_is_the_same_on_every_run() {
+        let output = painted_output(MemoryBackend::new(80, 24));
+
+        assert_eq!(painted_output(MemoryBackend::new(80, 24)), output);
+        assert_eq!(
+            output,
+            "\u{1b}[?25l\u{1b}[1;1H\u{1b}[J\u{1b}[38;5;12m~> \u{1b}[0m\u{1b}7\u{1b}[37m\u{1b}[0m\u{1b}8\u{1b}[?25h\
+             \u{1b}[?25l\u{1b}[1;1H\u{1b}[J\u{1b}[38;5;12m~> \u{1b}[0m\u{1b}7\u{1b}[37mcho hello world\u{1b}[0m\u{1b}8\u{1b}[?25h\
+             \u{1b}[2;1H"
+        );
+    }
+}
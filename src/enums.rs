@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use strum_macros::EnumIter;
 
 /// Valid ways how `Reedline::read_line()` can return
@@ -11,9 +12,25 @@ pub enum Signal {
     /// Abort with `Ctrl+D` signalling `EOF` or abort of a whole interactive session
     CtrlD, // End terminal session
     /// Signal to clear the current screen. Buffer content remains untouched.
+    #[deprecated(
+        note = "the screen is cleared while reading with `ReedlineEvent::ClearScreen`, so `read_line` no longer returns it"
+    )]
     CtrlL, // FormFeed/Clear current screen
 }
 
+/// How the line of the last [`Signal::Success`] came to be, for hosts that collect
+/// telemetry or rank their history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineMetadata {
+    /// Time since the prompt was first painted
+    pub editing_time: Duration,
+    /// The line was recalled from the history, by moving through it or searching it.
+    /// It may have been edited afterwards
+    pub from_history: bool,
+    /// Values taken from a menu or completed from a hint
+    pub completions_accepted: usize,
+}
+
 /// Editing actions which can be mapped to key bindings.
 ///
 /// Executed by `Reedline::run_edit_commands()`
@@ -31,6 +48,15 @@ pub enum EditCommand {
     /// Move to the end of the current line
     MoveToLineEnd,
 
+    /// Move to the line and column given, counted from zero. Positions past the end
+    /// of the buffer or of the line stay at the end
+    MoveToPosition {
+        /// The line of the buffer
+        line: usize,
+        /// The grapheme in the line
+        column: usize,
+    },
+
     /// Move one character to the left
     MoveLeft,
 
@@ -49,6 +75,10 @@ pub enum EditCommand {
     /// Insert a string at the current insertion point
     InsertString(String),
 
+    /// Insert a snippet with placeholders like `git checkout ${1:branch}` and move to
+    /// the first placeholder. [`ReedlineEvent::NextPlaceholder`] moves to the next one
+    InsertSnippet(String),
+
     /// Backspace delete from the current insertion point
     Backspace,
 
@@ -103,12 +133,63 @@ pub enum EditCommand {
     /// Capitalize the current character
     CapitalizeChar,
 
+    /// Capitalize the current word: upper case its first character and lower case the rest
+    CapitalizeWord,
+
+    /// Switch the case of the current character and move right (vi `~`)
+    SwitchcaseChar,
+
+    /// Swap the current line with the line above
+    SwapLineUp,
+
+    /// Swap the current line with the line below
+    SwapLineDown,
+
+    /// Duplicate the current line below it
+    DuplicateLine,
+
+    /// Add the amount to the number at or after the insertion point in the current line
+    IncrementNumber(i64),
+
+    /// Comment out the current line with the given prefix, like `# `, or uncomment it
+    ToggleComment(String),
+
+    /// Upper case the current line
+    UppercaseLine,
+
+    /// Lower case the current line
+    LowercaseLine,
+
     /// Swap the current word with the word to the right
     SwapWords,
 
     /// Swap the current grapheme/character with the one to the right
     SwapGraphemes,
 
+    /// Overwrite the character under the cursor, or insert at the end of the line,
+    /// and move right (vi replace mode). The characters typed in a row are undone together
+    ReplaceChar(char),
+
+    /// Move left, giving back the character overwritten there by the last
+    /// [`EditCommand::ReplaceChar`] (backspace in the vi replace mode)
+    ReplaceBackspace,
+
+    /// Replace the given number of characters from the cursor with the character and
+    /// move onto the last of them (vi `r`). Nothing changes when the line is shorter
+    ReplaceChars(usize, char),
+
+    /// Replace the matches of the pattern, which is matched literally (vi `:s`)
+    Substitute {
+        /// The text that is replaced
+        pattern: String,
+        /// The text it is replaced with
+        replacement: String,
+        /// Every line of the buffer instead of the current line (vi `:%s`)
+        all_lines: bool,
+        /// Every match of a line instead of the first one (vi `g` flag)
+        every_match: bool,
+    },
+
     /// Undo the previous edit command
     Undo,
 
@@ -150,6 +231,7 @@ impl EditCommand {
             | EditCommand::MoveToEnd
             | EditCommand::MoveToLineStart
             | EditCommand::MoveToLineEnd
+            | EditCommand::MoveToPosition { .. }
             | EditCommand::MoveLeft
             | EditCommand::MoveRight
             | EditCommand::MoveWordLeft
@@ -162,10 +244,14 @@ impl EditCommand {
             // Coalesceable insert
             EditCommand::InsertChar(_) => UndoBehavior::Coalesce,
 
+            // Typing in the replace mode
+            EditCommand::ReplaceChar(_) | EditCommand::ReplaceBackspace => UndoBehavior::Group,
+
             // Full edits
             EditCommand::Backspace
             | EditCommand::Delete
             | EditCommand::InsertString(_)
+            | EditCommand::InsertSnippet(_)
             | EditCommand::BackspaceWord
             | EditCommand::DeleteWord
             | EditCommand::Clear
@@ -182,6 +268,17 @@ impl EditCommand {
             | EditCommand::UppercaseWord
             | EditCommand::LowercaseWord
             | EditCommand::CapitalizeChar
+            | EditCommand::CapitalizeWord
+            | EditCommand::SwitchcaseChar
+            | EditCommand::ReplaceChars(..)
+            | EditCommand::Substitute { .. }
+            | EditCommand::SwapLineUp
+            | EditCommand::SwapLineDown
+            | EditCommand::DuplicateLine
+            | EditCommand::ToggleComment(_)
+            | EditCommand::IncrementNumber(_)
+            | EditCommand::UppercaseLine
+            | EditCommand::LowercaseLine
             | EditCommand::SwapWords
             | EditCommand::SwapGraphemes
             | EditCommand::CutRightUntil(_)
@@ -206,18 +303,66 @@ pub enum UndoBehavior {
     ///
     /// e.g. insertion of characters by typing
     Coalesce,
+    /// The operation continues the group of operations before it, which is undone as
+    /// a whole. The group ends with any other operation or with [`ReedlineEvent::Esc`]
+    ///
+    /// e.g. the characters typed in the vi replace mode
+    Group,
+}
+
+/// What happens to the terminal title set with `Reedline::set_title()` when the
+/// engine is dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TitleOnDrop {
+    /// The last title set stays
+    #[default]
+    Keep,
+    /// The title is cleared
+    Clear,
+    /// The title from before the first `Reedline::set_title()` is restored, on
+    /// terminals that keep a stack of titles
+    Restore,
+}
+
+/// How the line editor rings the bell: when completing finds no candidates, when
+/// the history search fails or when a key without binding is pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BellStyle {
+    /// The bell is silent
+    #[default]
+    None,
+    /// The terminal beeps
+    Audible,
+    /// The prompt flashes in reverse video
+    Visual,
+}
+
+/// Where the status lines set with `Reedline::set_status()` go while a menu is shown.
+/// Without a menu they are below the buffer and its hint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusPosition {
+    /// Between the buffer and the menu
+    AboveMenu,
+    /// Below the menu
+    #[default]
+    BelowMenu,
 }
 
 /// Reedline supported actions.
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, EnumIter)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default, EnumIter)]
 pub enum ReedlineEvent {
     /// No op event
+    #[default]
     None,
 
-    /// Complete history hint (default in full)
+    /// Accept the current hint in full
+    ///
+    /// Only applies when the cursor is at the end of the buffer
     HistoryHintComplete,
 
-    /// Complete a single token/word of the history hint
+    /// Accept only the next token/word of the current hint, leaving the rest as a hint
+    ///
+    /// Only applies when the cursor is at the end of the buffer
     HistoryHintWordComplete,
 
     /// Action event
@@ -241,9 +386,14 @@ pub enum ReedlineEvent {
     /// Bubble up [`Signal::CtrlC`]
     CtrlC,
 
-    /// Clears the screen and sets prompt to first line
+    /// Clears the screen and paints the prompt on the first line, keeping the buffer
+    /// and the cursor position
     ClearScreen,
 
+    /// Clears the screen like [`ReedlineEvent::ClearScreen`] and also the scrollback
+    /// of the terminal, where it is supported
+    ClearScrollback,
+
     /// Handle enter event
     Enter,
 
@@ -283,13 +433,115 @@ pub enum ReedlineEvent {
     /// Search the history for a string
     SearchHistory,
 
+    /// Pins the selected history entry or removes its pin. Pinned entries are shown
+    /// first in the history menu
+    HistoryTogglePin,
+
+    /// Narrows the history menu to the entries added today, then to the ones added this
+    /// week and back to all of them
+    HistoryTimeRange,
+
+    /// Deletes the selected entry of the history menu from the history. The deletion is
+    /// confirmed by typing `y`, any other key keeps the entry
+    HistoryDelete,
+
+    /// Leaves the next abbreviation as it was typed instead of expanding it
+    SkipAbbreviation,
+
+    /// Moves to the next placeholder of the snippet inserted with
+    /// [`EditCommand::InsertSnippet`]
+    NextPlaceholder,
+
+    /// Searches for a pattern in the buffer and replaces the matches one by one or all
+    /// at once, asking first for the pattern and the replacement
+    SearchReplace,
+
+    /// Starts a numeric argument of four for the next command, or multiplies the one
+    /// being typed by four (emacs `ctrl-u`). The edit mode applies it to the command
+    UniversalArgument,
+
+    /// Starts recording the keys in the register, to replay them as a keyboard macro
+    /// (vi `q{register}`, emacs `ctrl-x (`)
+    StartMacro(char),
+
+    /// Stops recording the keyboard macro (vi `q`, emacs `ctrl-x )`)
+    EndMacro,
+
+    /// Replays the keys recorded in the register, with `@` for the last macro
+    /// recorded or replayed (vi `@{register}`, emacs `ctrl-x e`)
+    ReplayMacro(char),
+
+    /// Hands the buffer to [`Hook::on_write`] and leaves it as it is (vi `:w`)
+    ///
+    /// [`Hook::on_write`]: crate::Hook::on_write
+    Write,
+
+    /// Comments out the lines of the buffer with the given prefix and stores them in
+    /// the history without submitting them, like `alt-#` in bash
+    CommentAndAccept(String),
+
     /// In vi mode multiple reedline events can be chained while parsing the
     /// command or movement characters
     Multiple(Vec<ReedlineEvent>),
 
-    /// Test
+    /// Handles the first of the events that applies, like "complete the hint, else open
+    /// the completion menu, else insert a tab":
+    ///
+    /// - the menu events apply when a menu is open, and [`ReedlineEvent::Menu`] when none
+    ///   is. Before the last event it also needs the menu to have values
+    /// - the hint completions apply with a hint and the cursor at the end of the buffer
+    /// - [`ReedlineEvent::When`] applies when its conditions hold and its event applies,
+    ///   and [`ReedlineEvent::Multiple`] when one of its events applies
+    /// - [`ReedlineEvent::None`] never applies and the edits always do
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use crossterm::event::{KeyCode, KeyModifiers};
+    /// use reedline::{default_emacs_keybindings, EditCommand, ReedlineEvent};
+    ///
+    /// let mut keybindings = default_emacs_keybindings();
+    /// keybindings.add_binding(
+    ///     KeyModifiers::NONE,
+    ///     KeyCode::Tab,
+    ///     ReedlineEvent::UntilFound(vec![
+    ///         ReedlineEvent::HistoryHintComplete,
+    ///         ReedlineEvent::MenuNext,
+    ///         ReedlineEvent::Menu("completion_menu".to_string()),
+    ///         ReedlineEvent::Edit(vec![EditCommand::InsertChar('\t')]),
+    ///     ]),
+    /// );
+    /// ```
     UntilFound(Vec<ReedlineEvent>),
 
+    /// The event, only when all the conditions hold and otherwise inapplicable, so an
+    /// [`ReedlineEvent::UntilFound`] goes on with its next event
+    ///
+    /// ## Example
+    ///
+    /// Tab indents at the start of a line and opens the completion menu elsewhere:
+    ///
+    /// ```rust
+    /// use crossterm::event::{KeyCode, KeyModifiers};
+    /// use reedline::{default_emacs_keybindings, BindingCondition, EditCommand, ReedlineEvent};
+    ///
+    /// let mut keybindings = default_emacs_keybindings();
+    /// keybindings.add_binding(
+    ///     KeyModifiers::NONE,
+    ///     KeyCode::Tab,
+    ///     ReedlineEvent::UntilFound(vec![
+    ///         ReedlineEvent::When(
+    ///             vec![BindingCondition::CursorAtLineStart],
+    ///             Box::new(ReedlineEvent::Edit(vec![EditCommand::InsertString(
+    ///                 "    ".to_string(),
+    ///             )])),
+    ///         ),
+    ///         ReedlineEvent::Menu("completion_menu".to_string()),
+    ///     ]),
+    /// );
+    /// ```
+    When(Vec<BindingCondition>, Box<ReedlineEvent>),
+
     /// Trigger a menu event. It activates a menu with the event name
     Menu(String),
 
@@ -316,8 +568,45 @@ pub enum ReedlineEvent {
 
     /// Move to the previous history page
     MenuPagePrevious,
+
+    /// Move to the first element in the menu
+    MenuFirst,
+
+    /// Move to the last element in the menu
+    MenuLast,
+
+    /// Inserts the longest prefix shared by the values of the open menu, see
+    /// [`Menu::insert_common_prefix`]. Inapplicable if it adds nothing
+    ///
+    /// [`Menu::insert_common_prefix`]: crate::Menu::insert_common_prefix
+    MenuPartialComplete,
+
+    /// Sends a command of the host to the open menu, as [`MenuEvent::Custom`]
+    ///
+    /// [`MenuEvent::Custom`]: crate::MenuEvent::Custom
+    MenuCustom(String),
 }
 
+/// A condition on the state of the line editor for [`ReedlineEvent::When`]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub enum BindingCondition {
+    /// The buffer is empty
+    BufferEmpty,
+    /// Only whitespace is before the cursor on its line
+    CursorAtLineStart,
+    /// The cursor is at the end of the buffer
+    CursorAtEnd,
+    /// A menu is open
+    MenuActive,
+    /// The vi edit mode is in normal mode
+    ViNormalMode,
+    /// The vi edit mode is in insert mode
+    ViInsertMode,
+    /// The condition doesn't hold
+    Not(Box<BindingCondition>),
+}
+
+#[derive(Debug)]
 pub(crate) enum EventStatus {
     Handled,
     Inapplicable,
@@ -0,0 +1,63 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Prints lines from other threads while [`Reedline::read_line()`](crate::Reedline::read_line)
+/// is waiting for input
+///
+/// The lines are printed above the prompt, which is painted again below them, so
+/// they don't mix with the buffer being edited. Clones print through the same editor.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{ExternalPrinter, Reedline};
+///
+/// let printer = ExternalPrinter::default();
+/// let line_editor = Reedline::create()?.with_external_printer(printer.clone());
+///
+/// std::thread::spawn(move || printer.print("build finished"));
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExternalPrinter {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl ExternalPrinter {
+    /// Queues the line to be printed the next time the editor checks for input
+    pub fn print(&self, line: impl Into<String>) {
+        self.queue().push_back(line.into());
+    }
+
+    /// Takes the lines waiting to be printed
+    pub(crate) fn take(&self) -> Vec<String> {
+        self.queue().drain(..).collect()
+    }
+
+    fn queue(&self) -> MutexGuard<'_, VecDeque<String>> {
+        // A thread that panicked while queueing can't leave the queue broken
+        self.lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn clones_share_the_lines() {
+        let printer = ExternalPrinter::default();
+        let clone = printer.clone();
+
+        clone.print("first");
+        printer.print(String::from("second"));
+
+        assert_eq!(printer.take(), vec!["first", "second"]);
+        assert!(clone.take().is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
+
+/// What the host knows about the line being highlighted, like the commands that exist
+///
+/// Set with [`Reedline::set_highlight_context`](crate::Reedline::set_highlight_context)
+/// and passed to [`Highlighter::highlight_with_context`](crate::Highlighter::highlight_with_context)
+/// on every repaint, so a highlighter can show unknown commands in red without a global
+/// table of its own. Anything else the host wants to pass goes in [`HighlightContext::data`].
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{HighlightContext, Reedline};
+///
+/// let mut context = HighlightContext::default();
+/// context.commands.insert("git".to_string());
+/// context.aliases.insert("g".to_string(), "git".to_string());
+/// context.cwd = std::env::current_dir().ok();
+///
+/// assert!(context.is_command("g"));
+/// assert!(!context.is_command("gti"));
+///
+/// let mut line_editor = Reedline::create()?;
+/// line_editor.set_highlight_context(context);
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HighlightContext {
+    /// Names of the commands that can be run
+    pub commands: HashSet<String>,
+    /// Aliases with the command they stand for
+    pub aliases: HashMap<String, String>,
+    /// Directory the commands run in
+    pub cwd: Option<PathBuf>,
+    data: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl HighlightContext {
+    /// Whether the name is a command or an alias
+    pub fn is_command(&self, name: &str) -> bool {
+        self.commands.contains(name) || self.aliases.contains_key(name)
+    }
+
+    /// Whether the host passed no commands or aliases, so every name may be a command
+    pub fn knows_no_commands(&self) -> bool {
+        self.commands.is_empty() && self.aliases.is_empty()
+    }
+
+    /// Builder that attaches data of the host for its own highlighter
+    pub fn with_data<T: Any + Send + Sync>(mut self, data: T) -> Self {
+        self.data = Some(Arc::new(data));
+        self
+    }
+
+    /// The data attached with [`HighlightContext::with_data`], if it has the type `T`
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref()?.downcast_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn data_is_found_by_its_type() {
+        let context = HighlightContext::default().with_data(vec!["main", "dev"]);
+
+        assert_eq!(context.data::<Vec<&str>>(), Some(&vec!["main", "dev"]));
+        assert_eq!(context.data::<String>(), None);
+        assert_eq!(HighlightContext::default().data::<String>(), None);
+    }
+}
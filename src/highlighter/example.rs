@@ -1,4 +1,4 @@
-use crate::highlighter::Highlighter;
+use crate::highlighter::{HighlightContext, Highlighter};
 use crate::StyledText;
 use nu_ansi_term::{Color, Style};
 
@@ -56,6 +56,33 @@ impl Highlighter for ExampleHighlighter {
 
         styled_text
     }
+
+    /// With commands in the context the first word is shown as a known or an unknown
+    /// command, the rest of the line in the neutral color
+    fn highlight_with_context(&self, line: &str, context: &HighlightContext) -> StyledText {
+        if context.knows_no_commands() {
+            return self.highlight(line);
+        }
+
+        let start = line.len() - line.trim_start().len();
+        let end = line[start..]
+            .find(char::is_whitespace)
+            .map_or(line.len(), |len| start + len);
+        let command_color = if context.is_command(&line[start..end]) {
+            self.match_color
+        } else {
+            self.notmatch_color
+        };
+
+        let mut styled_text = StyledText::new();
+        styled_text.push((
+            Style::new().fg(self.neutral_color),
+            line[..start].to_string(),
+        ));
+        styled_text.push((Style::new().fg(command_color), line[start..end].to_string()));
+        styled_text.push((Style::new().fg(self.neutral_color), line[end..].to_string()));
+        styled_text
+    }
 }
 impl ExampleHighlighter {
     /// Construct the default highlighter with a given set of extern commands/keywords to detect and highlight
@@ -85,3 +112,29 @@ impl Default for ExampleHighlighter {
         ExampleHighlighter::new(vec![])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("git status", Color::Green)]
+    #[case("  g push", Color::Green)]
+    #[case("gti status", Color::Red)]
+    fn commands_of_the_context_are_highlighted(#[case] line: &str, #[case] color: Color) {
+        let mut context = HighlightContext::default();
+        context.commands.insert("git".to_string());
+        context.aliases.insert("g".to_string(), "git".to_string());
+
+        let styled = ExampleHighlighter::default().highlight_with_context(line, &context);
+
+        let command = line.split_whitespace().next().unwrap();
+        assert_eq!(
+            styled.buffer[1],
+            (Style::new().fg(color), command.to_string())
+        );
+        assert_eq!(styled.raw_string(), line);
+    }
+}
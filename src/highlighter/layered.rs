@@ -0,0 +1,234 @@
+use crate::highlighter::{HighlightContext, Highlighter};
+use crate::{Span, StyledText};
+use nu_ansi_term::Style;
+
+/// Paints highlighters on top of each other, like brackets colored by depth over the
+/// colors of a syntax highlighter
+///
+/// The base highlighter styles the whole line. Each layer highlights the same line
+/// and is merged into the parts it gives a style other than the default one, as set by
+/// its [`LayerMerge`]. Text a layer leaves in the default style keeps the style from
+/// below. Layers with a higher priority are painted over the ones with a lower one,
+/// and layers with the same priority in the order they were added. The underlines of
+/// all the highlighters are kept, the ones of the top layers win.
+///
+/// ## Example
+///
+/// ```rust
+/// use nu_ansi_term::Color;
+/// use reedline::{
+///     ExampleHighlighter, LayerMerge, LayeredHighlighter, RainbowBrackets, Reedline,
+///     SimpleMatchHighlighter,
+/// };
+///
+/// let highlighter = LayeredHighlighter::new(Box::new(ExampleHighlighter::default()))
+///     .with_layer(Box::new(RainbowBrackets::default()))
+///     .with_layer_at(
+///         Box::new(SimpleMatchHighlighter::new("TODO".into()).with_match_style(Color::Black.on(Color::Yellow))),
+///         10,
+///         LayerMerge::Combine,
+///     );
+/// let line_editor = Reedline::create()?.with_highlighter(Box::new(highlighter));
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+pub struct LayeredHighlighter {
+    base: Box<dyn Highlighter>,
+    // Sorted by priority, the top layer last
+    layers: Vec<Layer>,
+}
+
+/// How the style a layer gives a part of the line is merged with the style from below
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayerMerge {
+    /// The style of the layer replaces the one from below
+    #[default]
+    Replace,
+    /// The colors and the attributes the layer sets are added to the style from
+    /// below, like the background of a search match over the colors of the syntax
+    Combine,
+}
+
+struct Layer {
+    highlighter: Box<dyn Highlighter>,
+    priority: i32,
+    merge: LayerMerge,
+}
+
+impl LayeredHighlighter {
+    /// Construct the stack on top of the highlighter that styles the whole line
+    pub fn new(base: Box<dyn Highlighter>) -> Self {
+        Self {
+            base,
+            layers: vec![],
+        }
+    }
+
+    /// A builder that adds a layer with the priority 0, replacing the styles below
+    pub fn with_layer(self, layer: Box<dyn Highlighter>) -> Self {
+        self.with_layer_at(layer, 0, LayerMerge::Replace)
+    }
+
+    /// A builder that adds a layer with the given priority and way to merge its styles
+    pub fn with_layer_at(
+        mut self,
+        highlighter: Box<dyn Highlighter>,
+        priority: i32,
+        merge: LayerMerge,
+    ) -> Self {
+        let index = self
+            .layers
+            .iter()
+            .position(|layer| layer.priority > priority)
+            .unwrap_or(self.layers.len());
+        self.layers.insert(
+            index,
+            Layer {
+                highlighter,
+                priority,
+                merge,
+            },
+        );
+        self
+    }
+
+    fn paint_over(styled_text: &mut StyledText, layer: StyledText, merge: LayerMerge) {
+        let mut start = 0;
+        for (style, text) in layer.buffer {
+            let end = start + text.len();
+            if style != Style::default() {
+                styled_text.restyle(&[Span::new(start, end)], |below| match merge {
+                    LayerMerge::Replace => style,
+                    LayerMerge::Combine => combine(below, style),
+                });
+            }
+            start = end;
+        }
+        styled_text.underlines.extend(layer.underlines);
+    }
+}
+
+/// The style below with the colors and the attributes set by the style on top
+fn combine(below: Style, top: Style) -> Style {
+    Style {
+        foreground: top.foreground.or(below.foreground),
+        background: top.background.or(below.background),
+        is_bold: below.is_bold || top.is_bold,
+        is_dimmed: below.is_dimmed || top.is_dimmed,
+        is_italic: below.is_italic || top.is_italic,
+        is_underline: below.is_underline || top.is_underline,
+        is_blink: below.is_blink || top.is_blink,
+        is_reverse: below.is_reverse || top.is_reverse,
+        is_hidden: below.is_hidden || top.is_hidden,
+        is_strikethrough: below.is_strikethrough || top.is_strikethrough,
+    }
+}
+
+impl Highlighter for LayeredHighlighter {
+    fn highlight(&self, line: &str) -> StyledText {
+        let mut styled_text = self.base.highlight(line);
+        for layer in &self.layers {
+            Self::paint_over(
+                &mut styled_text,
+                layer.highlighter.highlight(line),
+                layer.merge,
+            );
+        }
+        styled_text
+    }
+
+    fn highlight_with_context(&self, line: &str, context: &HighlightContext) -> StyledText {
+        let mut styled_text = self.base.highlight_with_context(line, context);
+        for layer in &self.layers {
+            let highlighted = layer.highlighter.highlight_with_context(line, context);
+            Self::paint_over(&mut styled_text, highlighted, layer.merge);
+        }
+        styled_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RainbowBrackets, SimpleMatchHighlighter};
+    use nu_ansi_term::Color;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn layers_paint_over_the_styled_parts_only() {
+        let base = SimpleMatchHighlighter::new("echo".to_string())
+            .with_neutral_style(Color::White.normal());
+        let highlighter = LayeredHighlighter::new(Box::new(base))
+            .with_layer(Box::new(RainbowBrackets::default()));
+
+        let styled = highlighter.highlight("echo (a)");
+
+        let white = Color::White.normal();
+        let bracket = RainbowBrackets::default().style_at_depth(0);
+        assert_eq!(
+            styled.buffer,
+            vec![
+                (Color::Green.normal(), "echo".to_string()),
+                (white, " ".to_string()),
+                (bracket, "(".to_string()),
+                (white, "a".to_string()),
+                (bracket, ")".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn layers_are_merged_by_priority() {
+        let search = SimpleMatchHighlighter::new("b".to_string());
+        let highlighter = LayeredHighlighter::new(Box::new(SimpleMatchHighlighter::default()))
+            .with_layer_at(
+                Box::new(search.with_match_style(Style::new().on(Color::Yellow))),
+                10,
+                LayerMerge::Combine,
+            )
+            .with_layer(Box::new(RainbowBrackets::default()));
+
+        let styled = highlighter.highlight("(b)");
+
+        let bracket = RainbowBrackets::default().style_at_depth(0);
+        assert_eq!(
+            styled.buffer,
+            vec![
+                (bracket, "(".to_string()),
+                (Style::new().on(Color::Yellow), "b".to_string()),
+                (bracket, ")".to_string()),
+            ]
+        );
+
+        let highlighter = LayeredHighlighter::new(Box::new(RainbowBrackets::default()))
+            .with_layer_at(
+                Box::new(
+                    SimpleMatchHighlighter::new("(".to_string())
+                        .with_match_style(Color::Black.on(Color::Yellow)),
+                ),
+                1,
+                LayerMerge::Combine,
+            )
+            .with_layer_at(
+                Box::new(SimpleMatchHighlighter::new("(".to_string())),
+                -1,
+                LayerMerge::Replace,
+            );
+        let styled = highlighter.highlight("()");
+
+        assert_eq!(styled.buffer[0].0, Color::Black.on(Color::Yellow));
+    }
+
+    #[test]
+    fn combined_layers_keep_the_colors_below() {
+        let below = Color::Green.bold();
+
+        assert_eq!(
+            combine(below, Style::new().on(Color::Yellow)),
+            Color::Green.bold().on(Color::Yellow)
+        );
+        assert_eq!(
+            combine(below, Color::Red.underline()),
+            Color::Red.bold().underline()
+        );
+    }
+}
@@ -1,13 +1,28 @@
+mod context;
 mod example;
+mod layered;
+mod rainbow;
 mod simple_match;
+mod syntax;
 
 use crate::styled_text::StyledText;
 
+pub use context::HighlightContext;
 pub use example::ExampleHighlighter;
+pub use layered::{LayerMerge, LayeredHighlighter};
+pub use rainbow::RainbowBrackets;
 pub use simple_match::SimpleMatchHighlighter;
+pub use syntax::{SyntaxHighlighter, SyntaxStyles};
 /// The syntax highlighting trait. Implementers of this trait will take in the current string and then
 /// return a `StyledText` object, which represents the contents of the original line as styled strings
 pub trait Highlighter: Send {
     /// The action that will handle the current buffer as a line and return the corresponding `StyledText` for the buffer
     fn highlight(&self, line: &str) -> StyledText;
+
+    /// Highlights the line with what the host knows about it, like the commands that
+    /// exist. This is what the engine calls, highlighters that don't use the context
+    /// only implement [`Highlighter::highlight`]
+    fn highlight_with_context(&self, line: &str, _context: &HighlightContext) -> StyledText {
+        self.highlight(line)
+    }
 }
@@ -0,0 +1,163 @@
+use crate::highlighter::Highlighter;
+use crate::StyledText;
+use nu_ansi_term::{Color, Style};
+
+/// Colors the brackets by how deep they are nested, so the pairs are easy to tell apart
+///
+/// Each pair of `()`, `[]` and `{}` gets the color of its depth, the colors start over
+/// after the last one. A closing bracket without its opening one gets the error style.
+/// Brackets in quoted strings are left alone, and so is the rest of the line: it is
+/// meant as a layer of a [`LayeredHighlighter`](crate::LayeredHighlighter) on top of the
+/// highlighter of the host.
+pub struct RainbowBrackets {
+    colors: Vec<Color>,
+    error_style: Style,
+}
+
+impl Default for RainbowBrackets {
+    fn default() -> Self {
+        Self {
+            colors: vec![Color::Yellow, Color::Purple, Color::Cyan],
+            error_style: Color::Red.bold().underline(),
+        }
+    }
+}
+
+impl RainbowBrackets {
+    /// A builder that sets the colors of the depths, the first one for the outermost
+    /// brackets. Without colors every bracket keeps the style from below
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// A builder that sets the style of the closing brackets without an opening one
+    pub fn with_error_style(mut self, error_style: Style) -> Self {
+        self.error_style = error_style;
+        self
+    }
+
+    /// The style of the brackets at the given depth, 0 being the outermost
+    pub fn style_at_depth(&self, depth: usize) -> Style {
+        match self.colors.len() {
+            0 => Style::default(),
+            len => Style::new().fg(self.colors[depth % len]),
+        }
+    }
+}
+
+impl Highlighter for RainbowBrackets {
+    fn highlight(&self, line: &str) -> StyledText {
+        let mut styled_text = StyledText::new();
+        let mut open = Vec::new();
+        let mut quote = None;
+        let mut escaped = false;
+        let mut plain_start = 0;
+
+        for (index, c) in line.char_indices() {
+            let style = match (quote, c) {
+                _ if escaped => {
+                    escaped = false;
+                    continue;
+                }
+                (_, '\\') => {
+                    escaped = true;
+                    continue;
+                }
+                (Some(q), _) => {
+                    if c == q {
+                        quote = None;
+                    }
+                    continue;
+                }
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    continue;
+                }
+                (None, '(' | '[' | '{') => {
+                    open.push(c);
+                    self.style_at_depth(open.len() - 1)
+                }
+                (None, ')' | ']' | '}') => {
+                    let opening = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    if open.last() == Some(&opening) {
+                        open.pop();
+                        self.style_at_depth(open.len())
+                    } else {
+                        self.error_style
+                    }
+                }
+                _ => continue,
+            };
+
+            if plain_start < index {
+                styled_text.push((Style::default(), line[plain_start..index].to_string()));
+            }
+            styled_text.push((style, c.to_string()));
+            plain_start = index + c.len_utf8();
+        }
+        if plain_start < line.len() {
+            styled_text.push((Style::default(), line[plain_start..].to_string()));
+        }
+
+        styled_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    /// The brackets of the line with their depth, or `None` for an error
+    fn depths(line: &str) -> Vec<(String, Option<usize>)> {
+        let rainbow = RainbowBrackets::default().with_colors(vec![
+            Color::Fixed(0),
+            Color::Fixed(1),
+            Color::Fixed(2),
+            Color::Fixed(3),
+        ]);
+        rainbow
+            .highlight(line)
+            .buffer
+            .into_iter()
+            .filter(|(style, _)| *style != Style::default())
+            .map(|(style, text)| {
+                let depth = match style.foreground {
+                    Some(Color::Fixed(depth)) if !style.is_underline => Some(depth as usize),
+                    _ => None,
+                };
+                (text, depth)
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case("f(a[1], {b})", vec![("(", Some(0)), ("[", Some(1)), ("]", Some(1)), ("{", Some(1)), ("}", Some(1)), (")", Some(0))])]
+    #[case("echo \"(\" ')' (x)", vec![("(", Some(0)), (")", Some(0))])]
+    #[case("a) (b]", vec![(")", None), ("(", Some(0)), ("]", None)])]
+    #[case("\\( (", vec![("(", Some(0))])]
+    fn brackets_get_the_color_of_their_depth(
+        #[case] line: &str,
+        #[case] expected: Vec<(&str, Option<usize>)>,
+    ) {
+        let expected: Vec<(String, Option<usize>)> = expected
+            .into_iter()
+            .map(|(text, depth)| (text.to_string(), depth))
+            .collect();
+        assert_eq!(depths(line), expected);
+    }
+
+    #[test]
+    fn colors_start_over_after_the_last_one() {
+        let rainbow = RainbowBrackets::default().with_colors(vec![Color::Red, Color::Blue]);
+
+        assert_eq!(rainbow.style_at_depth(2), Style::new().fg(Color::Red));
+        assert_eq!(rainbow.highlight("((()))").raw_string(), "((()))");
+    }
+}
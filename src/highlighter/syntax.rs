@@ -0,0 +1,208 @@
+use crate::highlighter::Highlighter;
+use crate::StyledText;
+use nu_ansi_term::{Color, Style};
+
+/// Styles of the categories of text a [`SyntaxHighlighter`] tells apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyntaxStyles {
+    /// Style of the text in no other category
+    pub neutral: Style,
+    /// Style of the words in the keyword list
+    pub keyword: Style,
+    /// Style of the quoted strings, quotes included
+    pub string: Style,
+    /// Style of the numeric literals
+    pub number: Style,
+}
+
+impl Default for SyntaxStyles {
+    fn default() -> Self {
+        Self {
+            neutral: Style::new(),
+            keyword: Color::Purple.bold(),
+            string: Color::Green.normal(),
+            number: Color::Cyan.normal(),
+        }
+    }
+}
+
+/// A highlighter for simple languages: keywords, quoted strings and numbers, each
+/// with its own style
+///
+/// Words are runs of letters, digits, `_`, `-` and `.`. A word is a keyword when it is
+/// in the keyword list, and a number when it is an integer, a decimal or a hexadecimal
+/// literal, with an optional sign and `_` between the digits. A string runs from a
+/// quote to the next one of the same kind, a backslash escapes the character after it,
+/// and a string that isn't closed goes to the end of the line.
+///
+/// ## Example
+///
+/// ```rust
+/// use nu_ansi_term::Color;
+/// use reedline::{Reedline, SyntaxHighlighter, SyntaxStyles};
+///
+/// let highlighter = SyntaxHighlighter::new(vec!["let".into(), "if".into(), "else".into()])
+///     .with_styles(SyntaxStyles {
+///         keyword: Color::Yellow.bold(),
+///         ..SyntaxStyles::default()
+///     });
+/// let line_editor = Reedline::create()?.with_highlighter(Box::new(highlighter));
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+pub struct SyntaxHighlighter {
+    keywords: Vec<String>,
+    quotes: Vec<char>,
+    styles: SyntaxStyles,
+}
+
+impl Default for SyntaxHighlighter {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl SyntaxHighlighter {
+    /// Construct a highlighter for the given keywords, with double and single quotes
+    /// around strings
+    pub fn new(keywords: Vec<String>) -> Self {
+        Self {
+            keywords,
+            quotes: vec!['"', '\''],
+            styles: SyntaxStyles::default(),
+        }
+    }
+
+    /// A builder that replaces the keywords
+    pub fn with_keywords(mut self, keywords: Vec<String>) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// A builder that sets the characters that start and end a string
+    pub fn with_quotes(mut self, quotes: Vec<char>) -> Self {
+        self.quotes = quotes;
+        self
+    }
+
+    /// A builder that sets the styles of the categories
+    pub fn with_styles(mut self, styles: SyntaxStyles) -> Self {
+        self.styles = styles;
+        self
+    }
+
+    fn word_style(&self, word: &str) -> Style {
+        if self.keywords.iter().any(|keyword| keyword == word) {
+            self.styles.keyword
+        } else if is_number(word) {
+            self.styles.number
+        } else {
+            self.styles.neutral
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+fn is_number(word: &str) -> bool {
+    let digits = word.trim_start_matches(['-', '+']);
+    if digits.len() + 1 < word.len() || !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let digits = digits.replace('_', "");
+    match digits.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => {
+            digits.chars().all(|c| c.is_ascii_digit() || c == '.') && digits.parse::<f64>().is_ok()
+        }
+    }
+}
+
+impl Highlighter for SyntaxHighlighter {
+    fn highlight(&self, line: &str) -> StyledText {
+        let mut styled_text = StyledText::new();
+        let mut push = |style: Style, text: &str| match styled_text.buffer.last_mut() {
+            Some((last_style, last_text)) if *last_style == style => last_text.push_str(text),
+            _ => styled_text.push((style, text.to_string())),
+        };
+
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if self.quotes.contains(&c) {
+                let mut end = line.len();
+                while let Some((index, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c {
+                        end = index + next.len_utf8();
+                        break;
+                    }
+                }
+                push(self.styles.string, &line[start..end]);
+            } else if is_word_char(c) {
+                let mut end = start + c.len_utf8();
+                while let Some(&(index, next)) = chars.peek() {
+                    if !is_word_char(next) {
+                        break;
+                    }
+                    end = index + next.len_utf8();
+                    chars.next();
+                }
+                let word = &line[start..end];
+                push(self.word_style(word), word);
+            } else {
+                push(self.styles.neutral, &line[start..start + c.len_utf8()]);
+            }
+        }
+
+        styled_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn highlighter() -> SyntaxHighlighter {
+        SyntaxHighlighter::new(vec!["let".into(), "if".into()])
+    }
+
+    #[rstest]
+    #[case("let x = 1", vec![("let", "keyword"), (" x = ", "neutral"), ("1", "number")])]
+    #[case("if-else", vec![("if-else", "neutral")])]
+    #[case("echo \"a \\\" b\" 'c'", vec![("echo ", "neutral"), ("\"a \\\" b\"", "string"), (" ", "neutral"), ("'c'", "string")])]
+    #[case("say \"open", vec![("say ", "neutral"), ("\"open", "string")])]
+    #[case("-1.5 0xff 1_000", vec![("-1.5", "number"), (" ", "neutral"), ("0xff", "number"), (" ", "neutral"), ("1_000", "number")])]
+    #[case("v1.2.3 x2 --1", vec![("v1.2.3 x2 --1", "neutral")])]
+    fn categories_get_their_styles(#[case] line: &str, #[case] expected: Vec<(&str, &str)>) {
+        let styles = SyntaxStyles::default();
+        let style_of = |category: &str| match category {
+            "keyword" => styles.keyword,
+            "string" => styles.string,
+            "number" => styles.number,
+            _ => styles.neutral,
+        };
+
+        let styled = highlighter().highlight(line);
+
+        let expected: Vec<(Style, String)> = expected
+            .into_iter()
+            .map(|(text, category)| (style_of(category), text.to_string()))
+            .collect();
+        assert_eq!(styled.buffer, expected);
+    }
+
+    #[test]
+    fn quotes_can_be_configured() {
+        let styled = highlighter().with_quotes(vec!['`']).highlight("`a 'b` c");
+
+        assert_eq!(
+            styled.buffer[0],
+            (SyntaxStyles::default().string, "`a 'b`".to_string())
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use {
+    super::Hinter,
+    crate::{Completer, History},
+    nu_ansi_term::Style,
+};
+
+/// A hinter that queries several hinters in priority order and shows the first hint found
+///
+/// Every hinter in the chain keeps its own style. A style given when adding the hinter
+/// to the chain takes precedence over it.
+///
+/// ```
+/// use nu_ansi_term::{Color, Style};
+/// use reedline::{DefaultHinter, HinterChain, Reedline};
+///
+/// let hinter = HinterChain::default()
+///     .with_hinter(Box::new(DefaultHinter::default()))
+///     .with_styled_hinter(
+///         Box::new(DefaultHinter::default().with_min_chars(3)),
+///         Style::new().fg(Color::Cyan),
+///     );
+///
+/// let line_editor = Reedline::create().unwrap().with_hinter(Box::new(hinter));
+/// ```
+#[derive(Default)]
+pub struct HinterChain {
+    hinters: Vec<(Box<dyn Hinter>, Option<Style>)>,
+    active: Option<usize>,
+}
+
+impl HinterChain {
+    /// A builder that appends a hinter to the chain, keeping the hinter's own style
+    pub fn with_hinter(mut self, hinter: Box<dyn Hinter>) -> HinterChain {
+        self.hinters.push((hinter, None));
+        self
+    }
+
+    /// A builder that appends a hinter to the chain, painting its hints with the given style
+    pub fn with_styled_hinter(mut self, hinter: Box<dyn Hinter>, style: Style) -> HinterChain {
+        self.hinters.push((hinter, Some(style)));
+        self
+    }
+
+    /// Asks the hinters in order, with the completer of the engine when there is one
+    fn hint(
+        &mut self,
+        line: &str,
+        pos: usize,
+        history: &dyn History,
+        completer: Option<&dyn Completer>,
+        use_ansi_coloring: bool,
+    ) -> String {
+        self.active = None;
+
+        for (idx, (hinter, style)) in self.hinters.iter_mut().enumerate() {
+            // A hinter painted with the style of the chain leaves its hint unstyled
+            let own_style = use_ansi_coloring && style.is_none();
+            let rendered = match completer {
+                Some(completer) => {
+                    hinter.handle_with_completer(line, pos, history, completer, own_style)
+                }
+                None => hinter.handle(line, pos, history, own_style),
+            };
+            let hint = hinter.complete_hint();
+            if hint.is_empty() {
+                continue;
+            }
+
+            self.active = Some(idx);
+            return match style {
+                Some(style) if use_ansi_coloring => style.paint(hint).to_string(),
+                _ => rendered,
+            };
+        }
+
+        String::new()
+    }
+
+    fn active_hinter(&self) -> Option<&dyn Hinter> {
+        self.active
+            .and_then(|idx| self.hinters.get(idx))
+            .map(|(hinter, _)| hinter.as_ref())
+    }
+}
+
+impl Hinter for HinterChain {
+    fn handle(
+        &mut self,
+        line: &str,
+        pos: usize,
+        history: &dyn History,
+        use_ansi_coloring: bool,
+    ) -> String {
+        self.hint(line, pos, history, None, use_ansi_coloring)
+    }
+
+    fn handle_with_completer(
+        &mut self,
+        line: &str,
+        pos: usize,
+        history: &dyn History,
+        completer: &dyn Completer,
+        use_ansi_coloring: bool,
+    ) -> String {
+        self.hint(line, pos, history, Some(completer), use_ansi_coloring)
+    }
+
+    fn complete_hint(&self) -> String {
+        self.active_hinter()
+            .map_or_else(String::new, |hinter| hinter.complete_hint())
+    }
+
+    fn next_hint_token(&self) -> String {
+        self.active_hinter()
+            .map_or_else(String::new, |hinter| hinter.next_hint_token())
+    }
+
+    /// Only the hinters asked before the one that found the hint count
+    fn is_pending(&self) -> bool {
+        let asked = self.active.map_or(self.hinters.len(), |idx| idx + 1);
+        self.hinters
+            .iter()
+            .take(asked)
+            .any(|(hinter, _)| hinter.is_pending())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FileBackedHistory;
+    use pretty_assertions::assert_eq;
+
+    struct FixedHinter(&'static str, String);
+
+    impl Hinter for FixedHinter {
+        fn handle(&mut self, line: &str, _pos: usize, _: &dyn History, _: bool) -> String {
+            self.1 = self
+                .0
+                .strip_prefix(line)
+                .filter(|_| !line.is_empty())
+                .unwrap_or_default()
+                .to_string();
+            self.1.clone()
+        }
+
+        fn complete_hint(&self) -> String {
+            self.1.clone()
+        }
+
+        fn next_hint_token(&self) -> String {
+            self.1.clone()
+        }
+    }
+
+    fn chain() -> HinterChain {
+        HinterChain::default()
+            .with_hinter(Box::new(FixedHinter("foo bar", String::new())))
+            .with_styled_hinter(
+                Box::new(FixedHinter("fizz", String::new())),
+                Style::new().bold(),
+            )
+    }
+
+    #[test]
+    fn first_hinter_with_a_hint_wins() {
+        let history = FileBackedHistory::default();
+        let mut hinter = chain();
+
+        assert_eq!(hinter.handle("f", 1, &history, false), "oo bar");
+        assert_eq!(hinter.complete_hint(), "oo bar");
+    }
+
+    #[test]
+    fn falls_through_to_next_hinter() {
+        let history = FileBackedHistory::default();
+        let mut hinter = chain();
+
+        assert_eq!(
+            hinter.handle("fi", 2, &history, true),
+            Style::new().bold().paint("zz").to_string()
+        );
+        assert_eq!(hinter.complete_hint(), "zz");
+    }
+
+    #[test]
+    fn no_hint_clears_active() {
+        let history = FileBackedHistory::default();
+        let mut hinter = chain();
+
+        hinter.handle("f", 1, &history, false);
+        assert_eq!(hinter.handle("x", 1, &history, false), "");
+        assert_eq!(hinter.complete_hint(), "");
+        assert_eq!(hinter.next_hint_token(), "");
+    }
+}
@@ -0,0 +1,240 @@
+use {
+    super::{first_hint_token, Hinter},
+    crate::{Completer, History},
+    nu_ansi_term::{Color, Style},
+    std::task::Poll,
+};
+
+/// A hinter that shows the remainder of the top completion as a hint
+///
+/// Useful to suggest commands that are not yet part of the history. The completions
+/// come from the completer of the engine, so the hint matches what the completion
+/// menu offers. They go through the completion cache of the engine when it is enabled,
+/// and a completer that is still working is not waited for: the hint shows up with a
+/// later repaint once [`Completer::poll_completions`] is ready.
+///
+/// ```
+/// use reedline::{CompletionHinter, DefaultCompleter, Reedline};
+///
+/// let completer = DefaultCompleter::new_with_wordlen(vec!["hello".into()], 2);
+///
+/// let line_editor = Reedline::create()
+///     .unwrap()
+///     .with_completer(Box::new(completer))
+///     .with_hinter(Box::new(CompletionHinter::default()));
+/// ```
+pub struct CompletionHinter {
+    style: Style,
+    current_hint: String,
+    min_chars: usize,
+    // The line and position the current hint was found for
+    query: Option<(String, usize)>,
+    pending: bool,
+}
+
+impl Default for CompletionHinter {
+    fn default() -> Self {
+        CompletionHinter {
+            style: Style::new().fg(Color::LightGray),
+            current_hint: String::new(),
+            min_chars: 1,
+            query: None,
+            pending: false,
+        }
+    }
+}
+
+impl CompletionHinter {
+    /// A builder that sets the style applied to the hint as part of the buffer
+    pub fn with_style(mut self, style: Style) -> CompletionHinter {
+        self.style = style;
+        self
+    }
+
+    /// A builder that sets the number of characters that have to be present to enable completion hints
+    pub fn with_min_chars(mut self, min_chars: usize) -> CompletionHinter {
+        self.min_chars = min_chars;
+        self
+    }
+
+    fn top_suggestion(
+        &self,
+        line: &str,
+        pos: usize,
+        completer: &dyn Completer,
+    ) -> Poll<Option<String>> {
+        // A hint is only shown behind the cursor at the end of the line
+        if pos != line.len() || line.chars().count() < self.min_chars {
+            return Poll::Ready(None);
+        }
+        if completer.poll_completions(line, pos).is_pending() {
+            return Poll::Pending;
+        }
+
+        let suggestion = completer
+            .complete(line, pos)
+            .into_iter()
+            .next()
+            .filter(|(span, _)| span.end == pos)
+            .and_then(|(span, value)| {
+                value
+                    .strip_prefix(line.get(span.start..span.end)?)
+                    .filter(|rest| !rest.is_empty())
+                    .map(str::to_string)
+            });
+        Poll::Ready(suggestion)
+    }
+}
+
+impl Hinter for CompletionHinter {
+    /// Without the completer of the engine there is nothing to hint
+    fn handle(
+        &mut self,
+        _line: &str,
+        _pos: usize,
+        _history: &dyn History,
+        _use_ansi_coloring: bool,
+    ) -> String {
+        self.current_hint.clear();
+        self.query = None;
+        self.pending = false;
+        String::new()
+    }
+
+    fn handle_with_completer(
+        &mut self,
+        line: &str,
+        pos: usize,
+        #[allow(unused_variables)] history: &dyn History,
+        completer: &dyn Completer,
+        use_ansi_coloring: bool,
+    ) -> String {
+        // Repaints of the same line show the hint found before without asking again
+        let query = (line.to_string(), pos);
+        if self.pending || self.query.as_ref() != Some(&query) {
+            match self.top_suggestion(line, pos, completer) {
+                Poll::Ready(hint) => {
+                    self.current_hint = hint.unwrap_or_default();
+                    self.query = Some(query);
+                    self.pending = false;
+                }
+                Poll::Pending => {
+                    self.current_hint.clear();
+                    self.query = None;
+                    self.pending = true;
+                }
+            }
+        }
+
+        if use_ansi_coloring && !self.current_hint.is_empty() {
+            self.style.paint(&self.current_hint).to_string()
+        } else {
+            self.current_hint.clone()
+        }
+    }
+
+    fn complete_hint(&self) -> String {
+        self.current_hint.clone()
+    }
+
+    fn next_hint_token(&self) -> String {
+        first_hint_token(&self.current_hint)
+    }
+
+    fn is_pending(&self) -> bool {
+        self.pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DefaultCompleter, FileBackedHistory, Span};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+
+    #[rstest]
+    #[case("he", 2, "llo")]
+    #[case("hello", 5, "")]
+    #[case("xy", 2, "")]
+    #[case("he", 1, "")]
+    #[case("", 0, "")]
+    fn hint_from_top_completion(#[case] line: &str, #[case] pos: usize, #[case] expected: &str) {
+        let completer = DefaultCompleter::new_with_wordlen(vec!["hello".into()], 2);
+        let mut hinter = CompletionHinter::default();
+        let history = FileBackedHistory::default();
+
+        assert_eq!(
+            hinter.handle_with_completer(line, pos, &history, &completer, false),
+            expected
+        );
+        assert_eq!(hinter.complete_hint(), expected);
+    }
+
+    // Counts the completions collected, pending for the given number of polls
+    #[derive(Default)]
+    struct SlowCompleter {
+        polls: Mutex<usize>,
+        calls: AtomicUsize,
+    }
+
+    impl Completer for SlowCompleter {
+        fn complete(&self, _line: &str, pos: usize) -> Vec<(Span, String)> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            vec![(Span::new(0, pos), "hello".to_string())]
+        }
+
+        fn poll_completions(&self, _line: &str, _pos: usize) -> Poll<()> {
+            let mut polls = self.polls.lock().unwrap();
+            if *polls == 0 {
+                return Poll::Ready(());
+            }
+            *polls -= 1;
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn pending_completer_is_not_waited_for() {
+        let completer = SlowCompleter {
+            polls: Mutex::new(1),
+            ..SlowCompleter::default()
+        };
+        let mut hinter = CompletionHinter::default();
+        let history = FileBackedHistory::default();
+
+        assert_eq!(
+            hinter.handle_with_completer("he", 2, &history, &completer, false),
+            ""
+        );
+        assert!(hinter.is_pending());
+        assert_eq!(completer.calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(
+            hinter.handle_with_completer("he", 2, &history, &completer, false),
+            "llo"
+        );
+        assert!(!hinter.is_pending());
+
+        // Repaints of the same line don't ask the completer again
+        hinter.handle_with_completer("he", 2, &history, &completer, false);
+        assert_eq!(completer.calls.load(Ordering::SeqCst), 1);
+        hinter.handle_with_completer("hel", 3, &history, &completer, false);
+        assert_eq!(completer.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn no_hint_without_a_completer() {
+        let completer = DefaultCompleter::new_with_wordlen(vec!["hello".into()], 2);
+        let mut hinter = CompletionHinter::default();
+        let history = FileBackedHistory::default();
+
+        hinter.handle_with_completer("he", 2, &history, &completer, false);
+        assert_eq!(hinter.handle("he", 2, &history, false), "");
+        assert_eq!(hinter.complete_hint(), "");
+    }
+}
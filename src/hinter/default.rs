@@ -0,0 +1,120 @@
+use {
+    super::{first_hint_token, Hinter},
+    crate::History,
+    nu_ansi_term::{Color, Style},
+};
+
+/// A default example hinter that use the completions or the history to show a hint to the user
+pub struct DefaultHinter {
+    style: Style,
+    current_hint: String,
+    min_chars: usize,
+    skip_failed: bool,
+}
+
+impl Hinter for DefaultHinter {
+    fn handle(
+        &mut self,
+        line: &str,
+        #[allow(unused_variables)] pos: usize,
+        history: &dyn History,
+        use_ansi_coloring: bool,
+    ) -> String {
+        if line.chars().count() < self.min_chars {
+            self.current_hint = String::new()
+        } else {
+            self.current_hint = history
+                .iter_navigation()
+                .rev()
+                .find(|entry| {
+                    entry.starts_with(line) && !(self.skip_failed && has_failed(history, entry))
+                })
+                .map_or_else(String::new, |entry| entry[line.len()..].to_string());
+        }
+
+        if use_ansi_coloring && !self.current_hint.is_empty() {
+            self.style.paint(&self.current_hint).to_string()
+        } else {
+            self.current_hint.clone()
+        }
+    }
+
+    fn complete_hint(&self) -> String {
+        self.current_hint.clone()
+    }
+
+    fn next_hint_token(&self) -> String {
+        first_hint_token(&self.current_hint)
+    }
+}
+
+impl Default for DefaultHinter {
+    fn default() -> Self {
+        DefaultHinter {
+            style: Style::new().fg(Color::LightGray),
+            current_hint: String::new(),
+            min_chars: 1,
+            skip_failed: false,
+        }
+    }
+}
+
+impl DefaultHinter {
+    /// A builder that sets the style applied to the hint as part of the buffer
+    pub fn with_style(mut self, style: Style) -> DefaultHinter {
+        self.style = style;
+        self
+    }
+
+    /// A builder that sets the number of characters that have to be present to enable history hints
+    pub fn with_min_chars(mut self, min_chars: usize) -> DefaultHinter {
+        self.min_chars = min_chars;
+        self
+    }
+
+    /// A builder that leaves out of the hints the history entries whose last run exited
+    /// with an error, as recorded with [`History::update_last`]
+    pub fn with_skip_failed(mut self, skip_failed: bool) -> DefaultHinter {
+        self.skip_failed = skip_failed;
+        self
+    }
+}
+
+/// The last run of the entry exited with a non-zero status
+fn has_failed(history: &dyn History, entry: &str) -> bool {
+    history
+        .entry_metadata(entry)
+        .and_then(|metadata| metadata.exit_status)
+        .is_some_and(|status| status != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileBackedHistory, HistoryEntryMetadata};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(false, "est", " test")]
+    #[case(true, "", " build")]
+    fn failed_entries_can_be_skipped(
+        #[case] skip_failed: bool,
+        #[case] expected: &str,
+        #[case] expected_older: &str,
+    ) {
+        let mut history = FileBackedHistory::default();
+        for (entry, exit_status) in [("cargo build", Some(0)), ("cargo test", Some(101))] {
+            history.append(entry);
+            history.update_last(HistoryEntryMetadata {
+                exit_status,
+                duration: None,
+            });
+        }
+        let mut hinter = DefaultHinter::default().with_skip_failed(skip_failed);
+
+        assert_eq!(hinter.handle("cargo t", 7, &history, false), expected);
+        // Older entries that succeeded are still hinted
+        assert_eq!(hinter.handle("cargo", 5, &history, false), expected_older);
+    }
+}
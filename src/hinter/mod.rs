@@ -0,0 +1,66 @@
+mod chain;
+mod completion;
+mod default;
+
+use crate::{Completer, History};
+
+pub use chain::HinterChain;
+pub use completion::CompletionHinter;
+pub use default::DefaultHinter;
+
+/// A trait that's responsible for returning the hint for the current line and position
+/// Hints are often shown in-line as part of the buffer, showing the user text they can accept or ignore
+pub trait Hinter: Send {
+    /// Handle the hinting duty by using the line, position, and current history
+    ///
+    /// Returns the formatted output to show the user
+    fn handle(
+        &mut self,
+        line: &str,
+        pos: usize,
+        history: &dyn History,
+        use_ansi_coloring: bool,
+    ) -> String;
+
+    /// Same as [`Hinter::handle`], also given the completer of the engine for the
+    /// hinters that show its completions
+    fn handle_with_completer(
+        &mut self,
+        line: &str,
+        pos: usize,
+        history: &dyn History,
+        #[allow(unused_variables)] completer: &dyn Completer,
+        use_ansi_coloring: bool,
+    ) -> String {
+        self.handle(line, pos, history, use_ansi_coloring)
+    }
+
+    /// Return the current hint unformatted to perform the completion of the full hint
+    fn complete_hint(&self) -> String;
+
+    /// Return the first semantic token of the hint
+    /// for incremental completion
+    fn next_hint_token(&self) -> String;
+
+    /// The hint waits for a completer that is still working. The engine paints it
+    /// again after a short wait to show the hint once it is found
+    fn is_pending(&self) -> bool {
+        false
+    }
+}
+
+/// Returns the first semantic token of a hint, including the whitespace leading up to it
+pub(crate) fn first_hint_token(hint: &str) -> String {
+    let mut reached_content = false;
+    hint.chars()
+        .take_while(|c| match (c.is_whitespace(), reached_content) {
+            (true, true) => false,
+            (true, false) => true,
+            (false, true) => true,
+            (false, false) => {
+                reached_content = true;
+                true
+            }
+        })
+        .collect()
+}
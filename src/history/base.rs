@@ -1,5 +1,6 @@
-use crate::core_editor::LineBuffer;
-use std::collections::vec_deque::Iter;
+use crate::{core_editor::LineBuffer, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone};
+use std::{collections::vec_deque::Iter, task::Poll};
 
 /// Browsing modes for a [`History`]
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,6 +15,67 @@ pub enum HistoryNavigationQuery {
     // Fuzzy Search
 }
 
+/// Time bounds of a history query. Entries are kept if they were added at or after
+/// `since` and before `until`; a missing bound doesn't limit the query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    /// Start of the range
+    pub since: Option<DateTime<Local>>,
+    /// End of the range, excluded
+    pub until: Option<DateTime<Local>>,
+}
+
+impl TimeRange {
+    /// The entries added since midnight
+    pub fn today() -> Self {
+        Self {
+            since: start_of_day(Local::now().naive_local().date()),
+            until: None,
+        }
+    }
+
+    /// The entries added since the last Monday at midnight
+    pub fn this_week() -> Self {
+        let today = Local::now().naive_local().date();
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+
+        Self {
+            since: start_of_day(monday),
+            until: None,
+        }
+    }
+
+    /// Checks if neither bound is set
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Checks if the time is within the bounds
+    pub fn contains(&self, time: DateTime<Local>) -> bool {
+        self.since.map_or(true, |since| since <= time)
+            && self.until.map_or(true, |until| time < until)
+    }
+}
+
+/// The first moment of the day. Where the daylight saving time starts at midnight,
+/// midnight doesn't exist that day and the day starts when the clocks go forward
+fn start_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    (0..=12)
+        .map(|quarter| midnight + Duration::minutes(15 * quarter))
+        .find_map(|time| Local.from_local_datetime(&time).earliest())
+}
+
+/// What the host learned about an entry when it ran it, recorded with
+/// [`History::update_last`] to rank the history later
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistoryEntryMetadata {
+    /// Exit status of the command
+    pub exit_status: Option<i32>,
+    /// How long the command ran
+    pub duration: Option<std::time::Duration>,
+}
+
 /// Interface of a history datastructure that supports stateful navigation via [`HistoryNavigationQuery`].
 pub trait History: Send {
     /// Append entry to the history, if capacity management is part of the implementation may perform that as well
@@ -22,6 +84,12 @@ pub trait History: Send {
     /// Chronologic interaction over all entries present in the history
     fn iter_chronologic(&self) -> Iter<'_, String>;
 
+    /// Chronologic interaction over the entries browsed with Up/Down and used for hints.
+    /// Histories that can be scoped to the running session only return its entries
+    fn iter_navigation(&self) -> Iter<'_, String> {
+        self.iter_chronologic()
+    }
+
     /// This moves the cursor backwards respecting the navigation query that is set
     /// - Results in a no-op if the cursor is at the initial point
     fn back(&mut self);
@@ -44,6 +112,81 @@ pub trait History: Send {
     /// Query the values in the history entries
     fn query_entries(&self, search: &str) -> Vec<String>;
 
+    /// Query the values in the history entries that were added within the time range,
+    /// each one with the time it was added
+    ///
+    /// Histories that don't record when the entries were added ignore the range and
+    /// return the entries without time
+    fn query_entries_between(
+        &self,
+        search: &str,
+        _range: TimeRange,
+    ) -> Vec<(String, Option<DateTime<Local>>)> {
+        self.query_entries(search)
+            .into_iter()
+            .map(|entry| (entry, None))
+            .collect()
+    }
+
+    /// Non-blocking version of [`History::query_entries`] for histories whose entries
+    /// live in a remote service.
+    ///
+    /// While the results aren't available it returns [`Poll::Pending`] and the query is
+    /// repeated after a short wait, so the implementation should start the request in
+    /// the background the first time and return its results once they arrive.
+    /// The entries come with the time they were added, like in
+    /// [`History::query_entries_between`]. By default the query is answered right away
+    fn poll_query_entries(&self, search: &str) -> Poll<Vec<(String, Option<DateTime<Local>>)>> {
+        Poll::Ready(self.query_entries_between(search, TimeRange::default()))
+    }
+
     /// Max number of values that can be queried from the history
     fn max_values(&self) -> usize;
+
+    /// Marks the entry as pinned. Pinned entries are shown first in the history menu
+    /// and they aren't removed when the history is trimmed to its capacity
+    fn pin(&mut self, _entry: &str) {}
+
+    /// Removes the pinned mark from the entry
+    fn unpin(&mut self, _entry: &str) {}
+
+    /// Checks if the entry is pinned
+    fn is_pinned(&self, _entry: &str) -> bool {
+        false
+    }
+
+    /// The pinned entries, the most recently pinned first
+    fn pinned_entries(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Writes the entries that aren't saved yet to the storage of the history, and
+    /// picks up the entries other sessions saved meanwhile.
+    ///
+    /// The engine calls it while it waits for input, so a session that is killed
+    /// only loses the lines of the last moments. Histories without a storage of
+    /// their own do nothing
+    fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Records the metadata of the last entry appended, once the host ran it. Nothing
+    /// is recorded when the last line appended was empty. Histories that don't keep
+    /// metadata ignore it
+    fn update_last(&mut self, _metadata: HistoryEntryMetadata) {}
+
+    /// The metadata of the latest run of the entry, if it was recorded
+    fn entry_metadata(&self, _entry: &str) -> Option<HistoryEntryMetadata> {
+        None
+    }
+
+    /// Removes every occurrence of the entry from the history and from its storage.
+    /// Histories that can't delete entries ignore it
+    fn delete(&mut self, _entry: &str) {}
+
+    /// When the entry at the index of [`History::iter_chronologic`] was added, if the
+    /// history records it
+    fn time_at(&self, _index: usize) -> Option<DateTime<Local>> {
+        None
+    }
 }
@@ -0,0 +1,109 @@
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use std::fmt::Write;
+
+/// Size in bytes of the keys used to encrypt the history
+pub const HISTORY_KEY_SIZE: usize = 32;
+
+// Size in bytes of the nonce stored before each encrypted entry
+const NONCE_SIZE: usize = 12;
+
+/// Encrypts the history entries with ChaCha20-Poly1305. Every entry uses a random
+/// nonce that is stored with it, hex encoded in a single line
+pub(crate) struct EntryCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl EntryCipher {
+    pub(crate) fn new(key: &[u8; HISTORY_KEY_SIZE]) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+        }
+    }
+
+    pub(crate) fn encrypt(&self, entry: &str) -> String {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, entry.as_bytes())
+            .expect("encrypting an entry in memory can't fail");
+
+        nonce
+            .iter()
+            .chain(ciphertext.iter())
+            .fold(String::new(), |mut line, byte| {
+                let _ = write!(line, "{:02x}", byte);
+                line
+            })
+    }
+
+    /// Decrypts an entry. Returns `None` if it was encrypted with another key or
+    /// the line is damaged
+    pub(crate) fn decrypt(&self, line: &str) -> Option<String> {
+        let bytes = decode_hex(line)?;
+        if bytes.len() < NONCE_SIZE {
+            return None;
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_SIZE);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()?;
+
+        String::from_utf8(plaintext).ok()
+    }
+}
+
+impl std::fmt::Debug for EntryCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The key is never printed
+        f.write_str("EntryCipher")
+    }
+}
+
+fn decode_hex(line: &str) -> Option<Vec<u8>> {
+    if line.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..line.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(line.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn encrypted_entry_can_be_decrypted() {
+        let cipher = EntryCipher::new(&[7; HISTORY_KEY_SIZE]);
+        let line = cipher.encrypt("ssh admin@server\nexit");
+
+        assert!(!line.contains("admin"));
+        assert_eq!(
+            cipher.decrypt(&line),
+            Some("ssh admin@server\nexit".to_string())
+        );
+    }
+
+    #[test]
+    fn same_entry_uses_different_nonces() {
+        let cipher = EntryCipher::new(&[7; HISTORY_KEY_SIZE]);
+        assert_ne!(cipher.encrypt("ls"), cipher.encrypt("ls"));
+    }
+
+    #[test]
+    fn wrong_key_fails_to_decrypt() {
+        let line = EntryCipher::new(&[7; HISTORY_KEY_SIZE]).encrypt("ls");
+        let other = EntryCipher::new(&[8; HISTORY_KEY_SIZE]);
+
+        assert_eq!(other.decrypt(&line), None);
+        assert_eq!(other.decrypt("not hex"), None);
+    }
+}
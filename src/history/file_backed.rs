@@ -1,31 +1,120 @@
-use super::{base::HistoryNavigationQuery, History};
-use crate::core_editor::LineBuffer;
+#[cfg(feature = "history_encryption")]
+use super::encryption::{EntryCipher, HISTORY_KEY_SIZE};
+#[cfg(feature = "history_redaction")]
+use super::Redactor;
+use super::{
+    base::{HistoryEntryMetadata, HistoryNavigationQuery, TimeRange},
+    History,
+};
+use crate::{
+    core_editor::LineBuffer,
+    result::{ReedlineError, Result},
+};
+use chrono::{DateTime, Local, TimeZone, Utc};
 use std::{
-    collections::{vec_deque::Iter, VecDeque},
-    fs::OpenOptions,
-    io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    borrow::Cow,
+    collections::{vec_deque::Iter, HashMap, VecDeque},
+    error::Error,
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
     ops::{Deref, DerefMut},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 /// Default size of the [`FileBackedHistory`] used when calling [`FileBackedHistory::default()`]
 pub const HISTORY_SIZE: usize = 1000;
 pub const NEWLINE_ESCAPE: &str = "<\\n>";
+/// Version of the file format written by [`FileBackedHistory`]
+pub const HISTORY_FORMAT_VERSION: u32 = 3;
+// First line of the history files that have a versioned format
+const FORMAT_HEADER: &str = "#reedline-history-format:";
+// Flag added to the header when the entries are encrypted
+const ENCRYPTED_FLAG: &str = "encrypted";
+// Separates the time an entry was added from its text
+const TIME_SEPARATOR: char = '\t';
+// Separates the exit status and the duration in milliseconds of a run from the time
+const METADATA_SEPARATOR: char = ',';
+
+// An entry with the Unix time it was added and the metadata of its run, if they are known
+type TimedEntry = (String, Option<i64>, Option<HistoryEntryMetadata>);
+
+/// Problems with the format of a history file.
+///
+/// They are returned as [`ReedlineError::HistoryFormat`] when the file is opened or synced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryFormatError {
+    /// The file was written with a newer format than [`HISTORY_FORMAT_VERSION`]
+    UnsupportedVersion(u32),
+    /// The version in the format header can't be read
+    InvalidHeader(String),
+    /// The entries are encrypted and the key is missing or it doesn't match
+    Encrypted,
+}
+
+impl Display for HistoryFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryFormatError::UnsupportedVersion(version) => write!(
+                f,
+                "history file format version {} is newer than the supported version {}",
+                version, HISTORY_FORMAT_VERSION
+            ),
+            HistoryFormatError::InvalidHeader(header) => {
+                write!(f, "invalid history file header: {}", header)
+            }
+            HistoryFormatError::Encrypted => {
+                write!(
+                    f,
+                    "history file is encrypted with a different or missing key"
+                )
+            }
+        }
+    }
+}
+
+impl Error for HistoryFormatError {}
+
+impl From<HistoryFormatError> for io::Error {
+    fn from(error: HistoryFormatError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
 
 /// Stateful history that allows up/down-arrow browsing with an internal cursor.
 ///
 /// Can optionally be associated with a newline separated history file using the [`FileBackedHistory::with_file()`] constructor.
-/// Similar to bash's behavior without HISTTIMEFORMAT.
+/// Similar to bash's behavior with HISTTIMEFORMAT, the time each entry was added is kept.
 /// (See <https://www.gnu.org/software/bash/manual/html_node/Bash-History-Facilities.html>)
-/// If the history is associated to a file all new changes within a given history capacity will be written to disk when History is dropped.
+/// If the history is associated to a file all new changes within a given history capacity will be written to disk when History is dropped,
+/// and by the engine when it waits for input (see [`Reedline::with_history_sync_delay()`](crate::Reedline::with_history_sync_delay)).
+///
+/// With [`FileBackedHistory::with_session_navigation()`] the Up/Down browsing, prefix
+/// searches and hints only use the entries of the running session, while substring
+/// searches and the history menu use the whole history.
 #[derive(Debug)]
 pub struct FileBackedHistory {
     capacity: usize,
     entries: VecDeque<String>,
-    cursor: usize, // If cursor == entries.len() outside history browsing
+    times: VecDeque<Option<i64>>, // When each of the entries was added
+    metadata: VecDeque<Option<HistoryEntryMetadata>>, // What the run of each entry reported
+    last_appended: bool,          // The last line appended is the last entry
+    cursor: usize,                // If cursor == entries.len() outside history browsing
     file: Option<PathBuf>,
     len_on_disk: usize, // Keep track what was previously written to disk
     query: HistoryNavigationQuery,
+    pinned: Vec<String>,
+    pinned_changed: bool,      // The pinned entries have to be written to disk
+    session: VecDeque<String>, // Entries appended by the running session
+    session_navigation: bool,
+    deleted: Vec<String>, // Entries to remove from the file when it is synced
+    // Text of the entries of this history that were redacted in the file, by their
+    // time and redacted text
+    originals: HashMap<(i64, String), String>,
+    #[cfg(feature = "history_redaction")]
+    redactor: Option<Redactor>,
+    #[cfg(feature = "history_encryption")]
+    cipher: Option<EntryCipher>,
 }
 
 impl Default for FileBackedHistory {
@@ -57,13 +146,39 @@ impl History for FileBackedHistory {
             .map_or(true, |previous| previous != entry)
             && !entry.is_empty()
         {
-            if self.entries.len() == self.capacity {
+            // Pinned entries count against the capacity, so a history full of them
+            // drops the oldest one
+            while !self.entries.is_empty() && self.entries.len() >= self.capacity {
                 // History is "full", so we delete the oldest entry first,
-                // before adding a new one.
-                self.entries.pop_front();
-                self.len_on_disk = self.len_on_disk.saturating_sub(1);
+                // before adding a new one. Pinned entries are kept
+                let oldest = self
+                    .entries
+                    .iter()
+                    .position(|entry| !self.pinned.contains(entry))
+                    .unwrap_or(0);
+                self.entries.remove(oldest);
+                self.times.remove(oldest);
+                self.metadata.remove(oldest);
+                if oldest < self.len_on_disk {
+                    self.len_on_disk -= 1;
+                }
             }
             self.entries.push_back(entry.to_string());
+            self.times.push_back(Some(Utc::now().timestamp()));
+            self.metadata.push_back(None);
+        }
+        self.last_appended = !entry.is_empty();
+
+        if self
+            .session
+            .back()
+            .map_or(true, |previous| previous != entry)
+            && !entry.is_empty()
+        {
+            if self.session.len() == self.capacity {
+                self.session.pop_front();
+            }
+            self.session.push_back(entry.to_string());
         }
         self.reset_cursor();
     }
@@ -72,6 +187,14 @@ impl History for FileBackedHistory {
         self.entries.iter()
     }
 
+    fn iter_navigation(&self) -> Iter<'_, String> {
+        if self.session_navigation {
+            self.session.iter()
+        } else {
+            self.entries.iter()
+        }
+    }
+
     fn back(&mut self) {
         match self.query.clone() {
             HistoryNavigationQuery::Normal(_) => {
@@ -91,7 +214,7 @@ impl History for FileBackedHistory {
     fn forward(&mut self) {
         match self.query.clone() {
             HistoryNavigationQuery::Normal(_) => {
-                if self.cursor < self.entries.len() {
+                if self.cursor < self.browsed().len() {
                     self.cursor += 1;
                 }
             }
@@ -105,7 +228,7 @@ impl History for FileBackedHistory {
     }
 
     fn string_at_cursor(&self) -> Option<String> {
-        self.entries.get(self.cursor).cloned()
+        self.browsed().get(self.cursor).cloned()
     }
 
     fn set_navigation(&mut self, navigation: HistoryNavigationQuery) {
@@ -125,9 +248,106 @@ impl History for FileBackedHistory {
             .collect::<Vec<String>>()
     }
 
+    fn query_entries_between(
+        &self,
+        search: &str,
+        range: TimeRange,
+    ) -> Vec<(String, Option<DateTime<Local>>)> {
+        self.entries
+            .iter()
+            .zip(self.times.iter())
+            .rev()
+            .filter(|(entry, _)| entry.contains(search))
+            .map(|(entry, time)| (entry.clone(), time.and_then(local_time)))
+            .filter(|(_, added)| {
+                range.is_unbounded() || added.is_some_and(|added| range.contains(added))
+            })
+            .collect()
+    }
+
     fn max_values(&self) -> usize {
         self.entries.len()
     }
+
+    fn pin(&mut self, entry: &str) {
+        if !self.is_pinned(entry) {
+            self.pinned.push(entry.to_string());
+            self.pinned_changed = true;
+        }
+    }
+
+    fn unpin(&mut self, entry: &str) {
+        if self.is_pinned(entry) {
+            self.pinned.retain(|pinned| pinned != entry);
+            self.pinned_changed = true;
+        }
+    }
+
+    fn is_pinned(&self, entry: &str) -> bool {
+        self.pinned.iter().any(|pinned| pinned == entry)
+    }
+
+    fn pinned_entries(&self) -> Vec<String> {
+        self.pinned.iter().rev().cloned().collect()
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        self.sync_file().map_err(ReedlineError::history)
+    }
+
+    /// The metadata is saved with the entry, so an entry that is already in the file
+    /// doesn't get it
+    fn update_last(&mut self, metadata: HistoryEntryMetadata) {
+        if self.last_appended && self.len_on_disk < self.entries.len() {
+            if let Some(last) = self.metadata.back_mut() {
+                *last = Some(metadata);
+            }
+        }
+    }
+
+    fn entry_metadata(&self, entry: &str) -> Option<HistoryEntryMetadata> {
+        self.entries
+            .iter()
+            .zip(self.metadata.iter())
+            .rev()
+            .find(|(other, _)| *other == entry)
+            .and_then(|(_, metadata)| *metadata)
+    }
+
+    /// The entry is removed from the file when the history is synced next
+    fn delete(&mut self, entry: &str) {
+        let on_disk = self.entries.range(..self.len_on_disk);
+        self.len_on_disk -= on_disk.filter(|other| *other == entry).count();
+
+        let mut index = 0;
+        while index < self.entries.len() {
+            if self.entries[index] == entry {
+                self.entries.remove(index);
+                self.times.remove(index);
+                self.metadata.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+        self.session.retain(|other| other != entry);
+        if self.pinned.iter().any(|pinned| pinned == entry) {
+            self.pinned.retain(|pinned| pinned != entry);
+            self.pinned_changed = true;
+        }
+        self.last_appended = false;
+        if self.file.is_some() {
+            self.deleted.push(entry.to_string());
+        }
+        self.reset_cursor();
+    }
+
+    fn time_at(&self, index: usize) -> Option<DateTime<Local>> {
+        self.times
+            .get(index)
+            .copied()
+            .flatten()
+            .and_then(local_time)
+    }
 }
 
 impl FileBackedHistory {
@@ -143,42 +363,305 @@ impl FileBackedHistory {
         FileBackedHistory {
             capacity,
             entries: VecDeque::with_capacity(capacity),
+            times: VecDeque::with_capacity(capacity),
+            metadata: VecDeque::with_capacity(capacity),
+            last_appended: false,
             cursor: 0,
             file: None,
             len_on_disk: 0,
             query: HistoryNavigationQuery::Normal(LineBuffer::default()),
+            pinned: Vec::new(),
+            pinned_changed: false,
+            session: VecDeque::new(),
+            session_navigation: false,
+            deleted: Vec::new(),
+            originals: HashMap::new(),
+            #[cfg(feature = "history_redaction")]
+            redactor: None,
+            #[cfg(feature = "history_encryption")]
+            cipher: None,
         }
     }
 
     /// Creates a new history with an associated history file.
     ///
-    /// History file format: commands separated by new lines.
+    /// History file format: a header line with the format version followed by the commands
+    /// separated by new lines, each one after the Unix time it was added and a tab. The
+    /// exit status and the duration in milliseconds recorded with
+    /// [`History::update_last`] follow the time, separated by commas. Files
+    /// written in older formats are migrated, without the times of their entries, and files
+    /// in a newer format return a [`HistoryFormatError`].
     /// If file exists file will be read otherwise empty file will be created.
+    /// The pinned entries are kept next to it, in a file with the `.pinned` extension added.
     ///
     ///
     /// **Side effects:** creates all nested directories to the file
     ///
-    pub fn with_file(capacity: usize, file: PathBuf) -> std::io::Result<Self> {
+    pub fn with_file(capacity: usize, file: PathBuf) -> Result<Self> {
         let mut hist = Self::new(capacity);
+        hist.open_file(file).map_err(ReedlineError::history)?;
+        Ok(hist)
+    }
+
+    /// History builder that scrubs secrets from the entries before they are written
+    /// to the file. The entries of the running session keep their original content.
+    ///
+    /// Requires that the feature `history_redaction` is enabled
+    #[cfg(feature = "history_redaction")]
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Creates a new history with an associated history file whose entries are
+    /// encrypted with the given key. The pinned entries are encrypted as well.
+    ///
+    /// Existing plain text files are encrypted when they are opened. Files encrypted
+    /// with another key return [`HistoryFormatError::Encrypted`].
+    ///
+    /// Requires that the feature `history_encryption` is enabled
+    #[cfg(feature = "history_encryption")]
+    pub fn with_encrypted_file(
+        capacity: usize,
+        file: PathBuf,
+        key: &[u8; HISTORY_KEY_SIZE],
+    ) -> Result<Self> {
+        let mut hist = Self::new(capacity);
+        hist.cipher = Some(EntryCipher::new(key));
+        hist.open_file(file).map_err(ReedlineError::history)?;
+        Ok(hist)
+    }
+
+    fn open_file(&mut self, file: PathBuf) -> io::Result<()> {
         if let Some(base_dir) = file.parent() {
             std::fs::create_dir_all(base_dir)?;
         }
-        hist.file = Some(file);
-        hist.sync()?;
-        Ok(hist)
+        self.file = Some(file);
+        self.load_pinned()?;
+        self.sync_file()
+    }
+
+    fn is_encrypted(&self) -> bool {
+        #[cfg(feature = "history_encryption")]
+        return self.cipher.is_some();
+
+        #[cfg(not(feature = "history_encryption"))]
+        false
+    }
+
+    /// First line of the history file
+    fn format_header(&self) -> String {
+        if self.is_encrypted() {
+            format!(
+                "{}{} {}",
+                FORMAT_HEADER, HISTORY_FORMAT_VERSION, ENCRYPTED_FLAG
+            )
+        } else {
+            format!("{}{}", FORMAT_HEADER, HISTORY_FORMAT_VERSION)
+        }
+    }
+
+    /// The entry as it is stored on disk, with its secrets scrubbed
+    fn redacted<'a>(&self, entry: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "history_redaction")]
+        if let Some(redactor) = &self.redactor {
+            return redactor.redact(entry);
+        }
+
+        Cow::Borrowed(entry)
+    }
+
+    /// Encodes the entry as it is written to the pinned file
+    fn disk_entry(&self, entry: &str) -> String {
+        self.encode(&self.redacted(entry))
+    }
+
+    /// Encodes the entry, the time it was added and the metadata of its run as they are
+    /// written to the history file
+    fn disk_line(
+        &self,
+        entry: &str,
+        time: Option<i64>,
+        metadata: Option<HistoryEntryMetadata>,
+    ) -> String {
+        let mut time = time.map(|time| time.to_string()).unwrap_or_default();
+        if let Some(metadata) = metadata {
+            let exit_status = metadata.exit_status.map(|status| status.to_string());
+            let duration = metadata
+                .duration
+                .map(|duration| duration.as_millis().to_string());
+            time = format!(
+                "{}{}{}{}{}",
+                time,
+                METADATA_SEPARATOR,
+                exit_status.unwrap_or_default(),
+                METADATA_SEPARATOR,
+                duration.unwrap_or_default()
+            );
+        }
+        self.encode(&format!(
+            "{}{}{}",
+            time,
+            TIME_SEPARATOR,
+            self.redacted(entry)
+        ))
+    }
+
+    fn encode(&self, entry: &str) -> String {
+        #[cfg(feature = "history_encryption")]
+        if let Some(cipher) = &self.cipher {
+            return cipher.encrypt(entry);
+        }
+
+        encode_entry(entry)
+    }
+
+    /// Decodes an entry read from the file
+    fn read_entry(&self, line: &str, encrypted: bool) -> io::Result<String> {
+        if !encrypted {
+            return Ok(decode_entry(line));
+        }
+
+        #[cfg(feature = "history_encryption")]
+        if let Some(cipher) = &self.cipher {
+            return cipher
+                .decrypt(line)
+                .ok_or_else(|| HistoryFormatError::Encrypted.into());
+        }
+
+        Err(HistoryFormatError::Encrypted.into())
+    }
+
+    /// Reads the entries from the lines of a history file, migrating them from older formats.
+    ///
+    /// Files without a format header were written before the format was versioned and
+    /// are read as version 0. The returned flag is set when the file has to be written
+    /// again with the current format or encryption
+    fn read_entries(
+        &self,
+        mut lines: VecDeque<String>,
+    ) -> io::Result<(VecDeque<TimedEntry>, bool)> {
+        let (version, encrypted) = match lines.front() {
+            Some(line) if line.starts_with(FORMAT_HEADER) => {
+                let invalid_header = || HistoryFormatError::InvalidHeader(line.clone());
+                let mut header = line[FORMAT_HEADER.len()..].split_whitespace();
+                let version = header
+                    .next()
+                    .and_then(|version| version.parse::<u32>().ok())
+                    .ok_or_else(invalid_header)?;
+                let encrypted = match header.next() {
+                    None => false,
+                    Some(ENCRYPTED_FLAG) => true,
+                    Some(_) => return Err(invalid_header().into()),
+                };
+                lines.pop_front();
+                (version, encrypted)
+            }
+            _ => (0, false),
+        };
+
+        if version > HISTORY_FORMAT_VERSION {
+            return Err(HistoryFormatError::UnsupportedVersion(version).into());
+        }
+
+        // Version 1 only added the header, so the entries of version 0 are read the same way.
+        // Version 2 added the times of the entries, version 3 the metadata of their runs.
+        // The migrations of future versions go here
+        let entries = lines
+            .iter()
+            .map(|line| {
+                let entry = self.read_entry(line, encrypted)?;
+                if version < 2 {
+                    return Ok((entry, None, None));
+                }
+
+                Ok(match entry.split_once(TIME_SEPARATOR) {
+                    Some((time, text)) => {
+                        let (time, metadata) = read_time(time);
+                        (text.to_string(), time, metadata)
+                    }
+                    None => (entry, None, None),
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        let outdated = version < HISTORY_FORMAT_VERSION || encrypted != self.is_encrypted();
+        Ok((entries, outdated))
+    }
+
+    /// File where the pinned entries are stored
+    fn pinned_file(&self) -> Option<PathBuf> {
+        self.file.as_ref().map(|file| {
+            let mut name = file.clone().into_os_string();
+            name.push(".pinned");
+            PathBuf::from(name)
+        })
+    }
+
+    /// Reads the pinned entries stored next to the history file. Pins that can't be
+    /// read, for example after enabling the encryption, are dropped
+    fn load_pinned(&mut self) -> io::Result<()> {
+        if let Some(fname) = self.pinned_file() {
+            if fname.exists() {
+                let reader = BufReader::new(File::open(fname)?);
+                let lines = reader.lines().collect::<io::Result<Vec<_>>>()?;
+                self.pinned = lines
+                    .iter()
+                    .filter_map(|line| self.read_entry(line, self.is_encrypted()).ok())
+                    .collect();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the pinned entries if they changed since they were read
+    fn save_pinned(&mut self) -> io::Result<()> {
+        if let (Some(fname), true) = (self.pinned_file(), self.pinned_changed) {
+            replace_file(&fname, |writer| {
+                for line in &self.pinned {
+                    writer.write_all(self.disk_entry(line).as_bytes())?;
+                    writer.write_all("\n".as_bytes())?;
+                }
+                Ok(())
+            })?;
+            self.pinned_changed = false;
+        }
+
+        Ok(())
+    }
+
+    /// History builder that limits the Up/Down browsing, the prefix searches and the
+    /// hints to the entries of the running session
+    pub fn with_session_navigation(mut self, session_navigation: bool) -> Self {
+        self.session_navigation = session_navigation;
+        self.reset_cursor();
+        self
+    }
+
+    /// Entries browsed with the current navigation query. Substring searches always
+    /// browse the whole history
+    fn browsed(&self) -> &VecDeque<String> {
+        match self.query {
+            HistoryNavigationQuery::SubstringSearch(_) => &self.entries,
+            _ if self.session_navigation => &self.session,
+            _ => &self.entries,
+        }
     }
 
     fn back_with_criteria(&mut self, criteria: &dyn Fn(&str) -> bool) {
-        if !self.entries.is_empty() {
-            let previous_match = self.entries.get(self.cursor);
-            if let Some((next_cursor, _)) = self
-                .entries
+        let entries = self.browsed();
+        if !entries.is_empty() {
+            let previous_match = entries.get(self.cursor);
+            let next_cursor = entries
                 .iter()
                 .take(self.cursor)
                 .enumerate()
                 .rev()
                 .find(|(_, entry)| criteria(entry) && previous_match != Some(entry))
-            {
+                .map(|(next_cursor, _)| next_cursor);
+
+            if let Some(next_cursor) = next_cursor {
                 // set to entry
                 self.cursor = next_cursor;
             }
@@ -186,91 +669,232 @@ impl FileBackedHistory {
     }
 
     fn forward_with_criteria(&mut self, criteria: &dyn Fn(&str) -> bool) {
-        let previous_match = self.entries.get(self.cursor);
-        if let Some((next_cursor, _)) = self
-            .entries
+        let entries = self.browsed();
+        let previous_match = entries.get(self.cursor);
+        let next_cursor = entries
             .iter()
             .enumerate()
             .skip(self.cursor + 1)
             .find(|(_, entry)| criteria(entry) && previous_match != Some(entry))
-        {
+            .map(|(next_cursor, _)| next_cursor);
+
+        match next_cursor {
             // set to entry
-            self.cursor = next_cursor;
-        } else {
-            self.reset_cursor();
+            Some(next_cursor) => self.cursor = next_cursor,
+            None => self.reset_cursor(),
         }
     }
 
     /// Writes unwritten history contents to disk.
     ///
     /// If file would exceed `capacity` truncates the oldest entries.
-    pub fn sync(&mut self) -> std::io::Result<()> {
+    pub fn sync(&mut self) -> Result<()> {
+        self.sync_file().map_err(ReedlineError::history)
+    }
+
+    fn sync_file(&mut self) -> io::Result<()> {
         if let Some(fname) = &self.file {
             // The unwritten entries
-            let own_entries = self.entries.range(self.len_on_disk..);
-
-            let mut f_lock = fd_lock::RwLock::new(
-                OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .read(true)
-                    .open(fname)?,
-            );
-            let mut writer_guard = f_lock.write()?;
+            let own_entries = self
+                .entries
+                .range(self.len_on_disk..)
+                .zip(self.times.range(self.len_on_disk..))
+                .zip(self.metadata.range(self.len_on_disk..))
+                .map(|((line, time), metadata)| (line, time, metadata));
+
+            let mut f_lock;
+            let mut writer_guard = loop {
+                f_lock = fd_lock::RwLock::new(
+                    OpenOptions::new()
+                        .create(true)
+                        .truncate(false)
+                        .write(true)
+                        .read(true)
+                        .open(fname)?,
+                );
+                let guard = f_lock.write()?;
+                if is_current(guard.deref(), fname)? {
+                    break guard;
+                }
+            };
             let (mut foreign_entries, truncate) = {
                 let reader = BufReader::new(writer_guard.deref());
-                let mut from_file = reader
-                    .lines()
-                    .map(|o| o.map(|i| decode_entry(&i)))
-                    .collect::<Result<VecDeque<_>, _>>()?;
-                if from_file.len() + own_entries.len() > self.capacity {
-                    (
-                        from_file.split_off(from_file.len() - (self.capacity - own_entries.len())),
-                        true,
-                    )
+                let lines = reader.lines().collect::<io::Result<VecDeque<_>>>()?;
+                let (mut from_file, outdated) = self.read_entries(lines)?;
+                let read = from_file.len();
+                // The file has the deleted entries as they were redacted
+                let deleted: Vec<_> = self
+                    .deleted
+                    .iter()
+                    .map(|entry| self.redacted(entry))
+                    .collect();
+                from_file.retain(|(entry, ..)| !deleted.iter().any(|deleted| deleted == entry));
+                // Deleted entries are only removed by writing the file again
+                let outdated = outdated || from_file.len() < read;
+                let mut excess =
+                    (from_file.len() + own_entries.len()).saturating_sub(self.capacity);
+                if excess > 0 {
+                    // The oldest entries are dropped, the pinned ones only when the
+                    // others aren't enough
+                    from_file.retain(|(entry, ..)| {
+                        let drop = excess > 0 && !self.pinned.contains(entry);
+                        excess -= drop as usize;
+                        !drop
+                    });
+                    from_file.drain(..excess.min(from_file.len()));
+                    (from_file, true)
                 } else {
-                    (from_file, false)
+                    // Files in older formats are written again in the current one
+                    (from_file, outdated)
                 }
             };
 
-            {
-                let mut writer = BufWriter::new(writer_guard.deref_mut());
-                if truncate {
-                    writer.seek(SeekFrom::Start(0))?;
-
-                    for line in &foreign_entries {
-                        writer.write_all(encode_entry(line).as_bytes())?;
+            if truncate {
+                replace_file(fname, |writer| {
+                    writeln!(writer, "{}", self.format_header())?;
+                    let entries = foreign_entries
+                        .iter()
+                        .map(|(line, time, metadata)| (line, time, metadata))
+                        .chain(own_entries);
+                    for (line, time, metadata) in entries {
+                        writer.write_all(self.disk_line(line, *time, *metadata).as_bytes())?;
                         writer.write_all("\n".as_bytes())?;
                     }
-                } else {
-                    writer.seek(SeekFrom::End(0))?;
-                }
-                for line in own_entries {
-                    writer.write_all(encode_entry(line).as_bytes())?;
+                    Ok(())
+                })?;
+            } else {
+                // Appending leaves the entries already in the file untouched
+                let mut writer = BufWriter::new(writer_guard.deref_mut());
+                writer.seek(SeekFrom::End(0))?;
+                for (line, time, metadata) in own_entries {
+                    writer.write_all(self.disk_line(line, *time, *metadata).as_bytes())?;
                     writer.write_all("\n".as_bytes())?;
                 }
                 writer.flush()?;
             }
-            if truncate {
-                let file = writer_guard.deref_mut();
-                let file_len = file.stream_position()?;
-                file.set_len(file_len)?;
+
+            // The entries of this history keep their original text in memory
+            let mut originals = HashMap::new();
+            for (line, time, _) in foreign_entries.iter_mut() {
+                let original = time.and_then(|time| self.originals.remove(&(time, line.clone())));
+                if let (Some(time), Some(original)) = (*time, original) {
+                    let redacted = std::mem::replace(line, original.clone());
+                    originals.insert((time, redacted), original);
+                }
+            }
+            let own_entries = self
+                .entries
+                .range(self.len_on_disk..)
+                .zip(self.times.range(self.len_on_disk..));
+            for (line, time) in own_entries {
+                let redacted = self.redacted(line);
+                if let (Some(time), true) = (*time, redacted != line.as_str()) {
+                    originals.insert((time, redacted.into_owned()), line.clone());
+                }
             }
+            self.originals = originals;
 
-            let own_entries = self.entries.drain(self.len_on_disk..);
+            let own_entries = self
+                .entries
+                .drain(self.len_on_disk..)
+                .zip(self.times.drain(self.len_on_disk..))
+                .zip(self.metadata.drain(self.len_on_disk..))
+                .map(|((line, time), metadata)| (line, time, metadata));
             foreign_entries.extend(own_entries);
-            self.entries = foreign_entries;
+            self.entries.clear();
+            self.times.clear();
+            self.metadata.clear();
+            for (line, time, metadata) in foreign_entries {
+                self.entries.push_back(line);
+                self.times.push_back(time);
+                self.metadata.push_back(metadata);
+            }
 
             self.len_on_disk = self.entries.len();
+            self.deleted.clear();
         }
 
-        Ok(())
+        self.save_pinned()
     }
 
     /// Reset the internal browsing cursor
     fn reset_cursor(&mut self) {
-        self.cursor = self.entries.len();
+        self.cursor = self.browsed().len();
+    }
+}
+
+/// The Unix time of the first column of a line, with the metadata that may follow it
+fn read_time(column: &str) -> (Option<i64>, Option<HistoryEntryMetadata>) {
+    let mut parts = column.split(METADATA_SEPARATOR);
+    // Times out of the range of the calendar are dropped
+    let time = parts
+        .next()
+        .and_then(|time| time.parse().ok())
+        .filter(|time| local_time(*time).is_some());
+    let metadata = match (parts.next(), parts.next()) {
+        (Some(exit_status), Some(duration)) => Some(HistoryEntryMetadata {
+            exit_status: exit_status.parse().ok(),
+            duration: duration.parse().ok().map(std::time::Duration::from_millis),
+        }),
+        _ => None,
+    };
+    (time, metadata)
+}
+
+/// The local time of a Unix time, if it is in the range of the calendar
+fn local_time(time: i64) -> Option<DateTime<Local>> {
+    Local.timestamp_opt(time, 0).single()
+}
+
+/// Writes the file anew in a temporary file that replaces it once it is complete, so
+/// a crash or a power loss while writing leaves the previous contents in place
+fn replace_file(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+
+    let written = File::create(&temp).and_then(|file| {
+        // The history may hold secrets, so the copy keeps the permissions
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        std::fs::rename(&temp, path)
+    });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+        return written;
+    }
+
+    // The rename itself is only durable once the directory is written
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
     }
+    Ok(())
+}
+
+/// Whether the locked file is still the one at the path, as another session may have
+/// replaced it while this one waited for the lock
+#[cfg(unix)]
+fn is_current(file: &File, path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let (locked, current) = (file.metadata()?, std::fs::metadata(path)?);
+    Ok((locked.dev(), locked.ino()) == (current.dev(), current.ino()))
+}
+
+#[cfg(not(unix))]
+fn is_current(_file: &File, _path: &Path) -> io::Result<bool> {
+    Ok(true)
 }
 
 impl Drop for FileBackedHistory {
@@ -470,6 +1094,249 @@ mod tests {
         assert_eq!(hist.string_at_cursor(), None);
     }
 
+    #[test]
+    fn pinned_entries_are_kept_when_full() {
+        let mut hist = FileBackedHistory::new(3);
+        hist.append("pinned");
+        hist.append("old");
+        hist.pin("pinned");
+        hist.append("new");
+        hist.append("newest");
+
+        let actual: Vec<_> = hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["pinned", "new", "newest"]);
+        assert_eq!(hist.pinned_entries(), vec!["pinned".to_string()]);
+
+        hist.unpin("pinned");
+        assert!(!hist.is_pinned("pinned"));
+    }
+
+    #[test]
+    fn pinned_entries_count_against_the_capacity() {
+        let mut hist = FileBackedHistory::new(2);
+        ["a", "b"].iter().for_each(|e| {
+            hist.append(e);
+            hist.pin(e);
+        });
+        // Only pinned entries are left to drop for "c", then "c" is dropped for "d"
+        hist.append("c");
+        hist.append("d");
+
+        let actual: Vec<_> = hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["b", "d"]);
+    }
+
+    #[test]
+    fn pinned_entries_are_persisted() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+
+        {
+            let mut hist = FileBackedHistory::with_file(3, histfile.clone()).unwrap();
+            ["keep", "x", "y"].iter().for_each(|e| hist.append(e));
+            hist.pin("keep");
+        }
+
+        {
+            let mut hist = FileBackedHistory::with_file(3, histfile.clone()).unwrap();
+            ["z", "w"].iter().for_each(|e| hist.append(e));
+        }
+
+        let reading_hist = FileBackedHistory::with_file(3, histfile).unwrap();
+
+        let actual: Vec<_> = reading_hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["keep", "z", "w"]);
+        assert!(reading_hist.is_pinned("keep"));
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn session_navigation_browses_session_entries() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+
+        {
+            let mut hist = FileBackedHistory::with_file(5, histfile.clone()).unwrap();
+            hist.append("previous session");
+        }
+
+        let mut hist = FileBackedHistory::with_file(5, histfile)
+            .unwrap()
+            .with_session_navigation(true);
+        hist.append("this session");
+
+        hist.back();
+        hist.back();
+        assert_eq!(hist.string_at_cursor(), Some("this session".to_string()));
+
+        hist.set_navigation(HistoryNavigationQuery::PrefixSearch("previous".to_string()));
+        hist.back();
+        assert_eq!(hist.string_at_cursor(), None);
+
+        hist.set_navigation(HistoryNavigationQuery::SubstringSearch(
+            "previous".to_string(),
+        ));
+        hist.back();
+        assert_eq!(
+            hist.string_at_cursor(),
+            Some("previous session".to_string())
+        );
+
+        let hints: Vec<_> = hist.iter_navigation().collect();
+        assert_eq!(hints, vec!["this session"]);
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn migrates_unversioned_file() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(&histfile, "first<\\n>line\nsecond\n").unwrap();
+
+        {
+            let hist = FileBackedHistory::with_file(5, histfile.clone()).unwrap();
+            let actual: Vec<_> = hist.iter_chronologic().collect();
+            assert_eq!(actual, vec!["first\nline", "second"]);
+        }
+
+        let content = std::fs::read_to_string(&histfile).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "{}{}\n\tfirst<\\n>line\n\tsecond\n",
+                FORMAT_HEADER, HISTORY_FORMAT_VERSION
+            )
+        );
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn migrates_file_without_times() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(&histfile, format!("{}1\nold\n", FORMAT_HEADER)).unwrap();
+
+        {
+            let mut hist = FileBackedHistory::with_file(5, histfile.clone()).unwrap();
+            hist.append("new");
+            hist.times[1] = Some(1_600_000_000);
+        }
+
+        let content = std::fs::read_to_string(&histfile).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "{}{}\n\told\n1600000000\tnew\n",
+                FORMAT_HEADER, HISTORY_FORMAT_VERSION
+            )
+        );
+
+        let hist = FileBackedHistory::with_file(5, histfile).unwrap();
+        assert_eq!(hist.times, vec![None, Some(1_600_000_000)]);
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn times_out_of_range_are_dropped() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(
+            &histfile,
+            format!(
+                "{}{}\n{}\tfar\n1600000000\tnear\n",
+                FORMAT_HEADER,
+                HISTORY_FORMAT_VERSION,
+                i64::MAX
+            ),
+        )
+        .unwrap();
+
+        let hist = FileBackedHistory::with_file(5, histfile).unwrap();
+        assert_eq!(hist.times, vec![None, Some(1_600_000_000)]);
+        assert_eq!(hist.time_at(0), None);
+        assert!(hist.time_at(1).is_some());
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn query_is_limited_to_the_time_range() {
+        let mut hist = FileBackedHistory::default();
+        hist.append("cargo build");
+        hist.append("cargo test");
+        hist.append("ls");
+        hist.append("cargo run");
+        hist.times = vec![None, Some(1_000), Some(2_000), Some(3_000)].into();
+        let query = |search: &str, range: TimeRange| -> Vec<String> {
+            hist.query_entries_between(search, range)
+                .into_iter()
+                .map(|(entry, _)| entry)
+                .collect()
+        };
+
+        let range = TimeRange {
+            since: Local.timestamp_opt(1_000, 0).single(),
+            until: Local.timestamp_opt(3_000, 0).single(),
+        };
+        assert_eq!(query("cargo", range), vec!["cargo test"]);
+
+        let range = TimeRange {
+            since: Local.timestamp_opt(2_000, 0).single(),
+            until: None,
+        };
+        assert_eq!(query("", range), vec!["cargo run", "ls"]);
+
+        assert_eq!(
+            hist.query_entries_between("cargo", TimeRange::default()),
+            vec![
+                (
+                    "cargo run".to_string(),
+                    Local.timestamp_opt(3_000, 0).single()
+                ),
+                (
+                    "cargo test".to_string(),
+                    Local.timestamp_opt(1_000, 0).single()
+                ),
+                ("cargo build".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_format_version_is_an_error() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(&histfile, format!("{}99\nentry\n", FORMAT_HEADER)).unwrap();
+
+        let error = FileBackedHistory::with_file(5, histfile.clone()).unwrap_err();
+        assert!(matches!(
+            error,
+            ReedlineError::HistoryFormat(HistoryFormatError::UnsupportedVersion(99))
+        ));
+
+        // The file is left untouched
+        let content = std::fs::read_to_string(&histfile).unwrap();
+        assert_eq!(content, format!("{}99\nentry\n", FORMAT_HEADER));
+
+        tmp.close().unwrap();
+    }
+
     #[test]
     fn writes_to_new_file() {
         use tempfile::tempdir;
@@ -534,10 +1401,10 @@ mod tests {
         let histfile = tmp.path().join(".history");
 
         let capacity = 5;
-        let initial_entries = vec!["test 1", "test 2"];
-        let appending_entries = vec!["test 3", "test 4"];
+        let initial_entries = ["test 1", "test 2"];
+        let appending_entries = ["test 3", "test 4"];
         let expected_appended_entries = vec!["test 1", "test 2", "test 3", "test 4"];
-        let truncating_entries = vec!["test 5", "test 6", "test 7", "test 8"];
+        let truncating_entries = ["test 5", "test 6", "test 7", "test 8"];
         let expected_truncated_entries = vec!["test 4", "test 5", "test 6", "test 7", "test 8"];
 
         {
@@ -590,7 +1457,7 @@ mod tests {
         let tmp = tempdir().unwrap();
         let histfile = tmp.path().join(".history");
 
-        let overly_large_previous_entries = vec![
+        let overly_large_previous_entries = [
             "test 1", "test 2", "test 3", "test 4", "test 5", "test 6", "test 7", "test 8",
         ];
         let expected_truncated_entries = vec!["test 4", "test 5", "test 6", "test 7", "test 8"];
@@ -621,6 +1488,133 @@ mod tests {
         tmp.close().unwrap();
     }
 
+    #[test]
+    fn metadata_of_the_last_entry_is_saved() {
+        use std::time::Duration;
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        let failed = HistoryEntryMetadata {
+            exit_status: Some(2),
+            duration: Some(Duration::from_millis(350)),
+        };
+
+        {
+            let mut hist = FileBackedHistory::with_file(5, histfile.clone()).unwrap();
+            hist.append("make");
+            hist.update_last(failed);
+            hist.append("ls");
+            hist.append("");
+            // The empty line isn't in the history
+            hist.update_last(HistoryEntryMetadata::default());
+            hist.times = vec![Some(1_600_000_000), Some(1_600_000_001)].into();
+        }
+
+        let content = std::fs::read_to_string(&histfile).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "{}{}\n1600000000,2,350\tmake\n1600000001\tls\n",
+                FORMAT_HEADER, HISTORY_FORMAT_VERSION
+            )
+        );
+
+        let mut hist = FileBackedHistory::with_file(5, histfile).unwrap();
+        assert_eq!(hist.entry_metadata("make"), Some(failed));
+        assert_eq!(hist.entry_metadata("ls"), None);
+
+        // Entries already in the file don't change
+        hist.update_last(HistoryEntryMetadata::default());
+        assert_eq!(hist.entry_metadata("ls"), None);
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn deleted_entries_are_removed_from_the_file() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+
+        {
+            let mut hist = FileBackedHistory::with_file(10, histfile.clone()).unwrap();
+            ["a", "b", "a", "c"].iter().for_each(|e| hist.append(e));
+            hist.pin("a");
+        }
+
+        {
+            let mut hist = FileBackedHistory::with_file(10, histfile.clone()).unwrap();
+            hist.append("a");
+            hist.delete("a");
+            let actual: Vec<_> = hist.iter_chronologic().collect();
+            assert_eq!(actual, vec!["b", "c"]);
+            assert!(!hist.is_pinned("a"));
+        }
+
+        let reading_hist = FileBackedHistory::with_file(10, histfile).unwrap();
+        let actual: Vec<_> = reading_hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["b", "c"]);
+        assert!(reading_hist.pinned_entries().is_empty());
+
+        tmp.close().unwrap();
+    }
+
+    #[cfg(feature = "history_redaction")]
+    #[test]
+    fn deleted_redacted_entries_are_removed_from_the_file() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+
+        {
+            let mut hist = FileBackedHistory::with_file(10, histfile.clone())
+                .unwrap()
+                .with_redactor(Redactor::common());
+            hist.append("ls");
+            hist.append("mysql --password hunter2 db");
+            hist.sync().unwrap();
+            hist.delete("mysql --password hunter2 db");
+            hist.sync().unwrap();
+
+            let actual: Vec<_> = hist.iter_chronologic().collect();
+            assert_eq!(actual, vec!["ls"]);
+        }
+
+        let reading_hist = FileBackedHistory::with_file(10, histfile).unwrap();
+        let actual: Vec<_> = reading_hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["ls"]);
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn interrupted_rewrite_keeps_the_previous_file() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(&histfile, "previous\n").unwrap();
+
+        let result = replace_file(&histfile, |writer| {
+            writer.write_all(b"half of the")?;
+            Err(io::Error::other("power loss"))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&histfile).unwrap(), "previous\n");
+
+        replace_file(&histfile, |writer| writer.write_all(b"next\n")).unwrap();
+        assert_eq!(std::fs::read_to_string(&histfile).unwrap(), "next\n");
+
+        // No temporary file is left behind
+        let files: Vec<_> = std::fs::read_dir(tmp.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+
+        tmp.close().unwrap();
+    }
+
     #[test]
     fn concurrent_histories_dont_erase_eachother() {
         use tempfile::tempdir;
@@ -629,9 +1623,9 @@ mod tests {
         let histfile = tmp.path().join(".history");
 
         let capacity = 7;
-        let initial_entries = vec!["test 1", "test 2", "test 3", "test 4", "test 5"];
-        let entries_a = vec!["A1", "A2", "A3"];
-        let entries_b = vec!["B1", "B2", "B3"];
+        let initial_entries = ["test 1", "test 2", "test 3", "test 4", "test 5"];
+        let entries_a = ["A1", "A2", "A3"];
+        let entries_b = ["B1", "B2", "B3"];
         let expected_entries = vec!["test 5", "B1", "B2", "B3", "A1", "A2", "A3"];
 
         {
@@ -720,4 +1714,98 @@ mod tests {
 
         tmp.close().unwrap();
     }
+
+    #[cfg(feature = "history_redaction")]
+    #[test]
+    fn redacts_entries_written_to_file() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+
+        {
+            let mut hist = FileBackedHistory::with_file(5, histfile.clone())
+                .unwrap()
+                .with_redactor(Redactor::common());
+            hist.append("login --password hunter2");
+
+            let actual: Vec<_> = hist.iter_chronologic().collect();
+            assert_eq!(actual, vec!["login --password hunter2"]);
+        }
+
+        let reading_hist = FileBackedHistory::with_file(5, histfile).unwrap();
+
+        let actual: Vec<_> = reading_hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["login --password [REDACTED]"]);
+
+        tmp.close().unwrap();
+    }
+
+    #[cfg(feature = "history_redaction")]
+    #[test]
+    fn redacted_entries_keep_their_text_in_memory_across_syncs() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+
+        let mut hist = FileBackedHistory::with_file(5, histfile.clone())
+            .unwrap()
+            .with_redactor(Redactor::common());
+        hist.append("mysql --password hunter2 db");
+        hist.sync().unwrap();
+        hist.append("ls");
+        hist.sync().unwrap();
+
+        let actual: Vec<_> = hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["mysql --password hunter2 db", "ls"]);
+
+        let reading_hist = FileBackedHistory::with_file(5, histfile).unwrap();
+        let actual: Vec<_> = reading_hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["mysql --password [REDACTED] db", "ls"]);
+
+        tmp.close().unwrap();
+    }
+
+    #[cfg(feature = "history_encryption")]
+    #[test]
+    fn encrypted_file_needs_the_key() {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(&histfile, "plain text\n").unwrap();
+        let key = [42; HISTORY_KEY_SIZE];
+
+        {
+            let mut hist =
+                FileBackedHistory::with_encrypted_file(5, histfile.clone(), &key).unwrap();
+            hist.append("mysql -p secret");
+        }
+
+        let content = std::fs::read_to_string(&histfile).unwrap();
+        assert!(content.starts_with(&format!(
+            "{}{} encrypted\n",
+            FORMAT_HEADER, HISTORY_FORMAT_VERSION
+        )));
+        assert!(!content.contains("plain text") && !content.contains("secret"));
+
+        let reading_hist =
+            FileBackedHistory::with_encrypted_file(5, histfile.clone(), &key).unwrap();
+        let actual: Vec<_> = reading_hist.iter_chronologic().collect();
+        assert_eq!(actual, vec!["plain text", "mysql -p secret"]);
+        drop(reading_hist);
+
+        for result in [
+            FileBackedHistory::with_file(5, histfile.clone()),
+            FileBackedHistory::with_encrypted_file(5, histfile.clone(), &[0; HISTORY_KEY_SIZE]),
+        ] {
+            assert!(matches!(
+                result.unwrap_err(),
+                ReedlineError::HistoryFormat(HistoryFormatError::Encrypted)
+            ));
+        }
+
+        tmp.close().unwrap();
+    }
 }
@@ -1,5 +1,15 @@
 mod base;
+#[cfg(feature = "history_encryption")]
+mod encryption;
 mod file_backed;
+#[cfg(feature = "history_redaction")]
+mod redaction;
 
-pub use base::{History, HistoryNavigationQuery};
-pub use file_backed::{FileBackedHistory, HISTORY_SIZE};
+pub use base::{History, HistoryEntryMetadata, HistoryNavigationQuery, TimeRange};
+#[cfg(feature = "history_encryption")]
+pub use encryption::HISTORY_KEY_SIZE;
+pub use file_backed::{
+    FileBackedHistory, HistoryFormatError, HISTORY_FORMAT_VERSION, HISTORY_SIZE,
+};
+#[cfg(feature = "history_redaction")]
+pub use redaction::{Redactor, REDACTED};
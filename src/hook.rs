@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+/// Callbacks of the host around each [`Reedline::read_line()`](crate::Reedline::read_line)
+///
+/// The methods do nothing by default, so a hook only implements the ones it needs.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{Hook, Reedline};
+/// use std::time::Duration;
+///
+/// struct Timer;
+///
+/// impl Hook for Timer {
+///     fn after_submit(&mut self, line: &str, elapsed: Duration) {
+///         eprintln!("{} was typed in {:?}", line, elapsed);
+///     }
+/// }
+///
+/// let line_editor = Reedline::create()?.with_hook(Box::new(Timer));
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+pub trait Hook: Send {
+    /// Called when a read starts, before the prompt is first painted
+    fn before_read(&mut self) {}
+
+    /// Called when a line is accepted, with the time since the prompt was first painted
+    ///
+    /// The cursor is already below the buffer, so the hook can print.
+    fn after_submit(&mut self, _line: &str, _elapsed: Duration) {}
+
+    /// Called with the buffer and the cursor position once the buffer stopped changing
+    /// for the time set by [`Reedline::with_change_debounce`](crate::Reedline::with_change_debounce)
+    ///
+    /// The returned text is a preview shown below the buffer, after the lines of
+    /// [`Reedline::set_status`](crate::Reedline::set_status), for example what a glob
+    /// expands to. It stays until the next call and can span several lines.
+    fn on_change(&mut self, _line: &str, _pos: usize) -> Option<String> {
+        None
+    }
+
+    /// Called with the value selected in a menu when the menu opens and whenever the
+    /// selection moves, for example to announce it with a screen reader
+    fn on_menu_selection(&mut self, _value: &str) {}
+
+    /// Called with the buffer on [`ReedlineEvent::Write`](crate::ReedlineEvent::Write),
+    /// the vi `:w` command. The buffer stays in the editor, so the host can keep a
+    /// draft of a long edit
+    fn on_write(&mut self, _line: &str) {}
+}
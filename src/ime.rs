@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Text being composed with an input method, like the syllables of a CJK word
+/// before it is picked
+///
+/// Terminals show the composition themselves, at their cursor, and only send the picked
+/// text. Hosts that get the composition from the input method, like an editor
+/// embedding a terminal widget, pass it on with this handle instead: the composed
+/// text is shown underlined at the cursor without entering the buffer, and the picked
+/// text is inserted as a single edit. Clones update the same editor.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{ImeComposition, Reedline};
+///
+/// let composition = ImeComposition::default();
+/// let line_editor = Reedline::create()?.with_ime_composition(composition.clone());
+///
+/// // Called by the input method integration of the host
+/// composition.set_preedit("ni", 2);
+/// composition.set_preedit("你", "你".len());
+/// composition.commit("你");
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ImeComposition {
+    state: Arc<Mutex<CompositionState>>,
+}
+
+#[derive(Debug, Default)]
+struct CompositionState {
+    preedit: Option<Preedit>,
+    committed: Vec<String>,
+    changed: bool,
+}
+
+/// The text being composed, and the cursor in it as a byte offset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Preedit {
+    pub text: String,
+    pub cursor: usize,
+}
+
+impl ImeComposition {
+    /// Shows `text` as the composition, with the cursor `cursor` bytes into it.
+    /// An empty text ends the composition without inserting anything
+    pub fn set_preedit(&self, text: impl Into<String>, cursor: usize) {
+        let text = text.into();
+        let mut cursor = cursor.min(text.len());
+        while !text.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+
+        let mut state = self.state();
+        state.preedit = (!text.is_empty()).then_some(Preedit { text, cursor });
+        state.changed = true;
+    }
+
+    /// Ends the composition and inserts the picked `text` at the cursor
+    pub fn commit(&self, text: impl Into<String>) {
+        let mut state = self.state();
+        state.preedit = None;
+        state.committed.push(text.into());
+        state.changed = true;
+    }
+
+    /// Ends the composition without inserting anything
+    pub fn cancel(&self) {
+        self.set_preedit("", 0);
+    }
+
+    /// Takes the committed texts, and whether anything changed since the last call
+    pub(crate) fn take_changes(&self) -> (Vec<String>, bool) {
+        let mut state = self.state();
+        let changed = std::mem::take(&mut state.changed);
+        (std::mem::take(&mut state.committed), changed)
+    }
+
+    /// The text being composed
+    pub(crate) fn preedit(&self) -> Option<Preedit> {
+        self.state().preedit.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, CompositionState> {
+        // A thread that panicked while composing can't leave the state broken
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn cursor_is_kept_on_a_char_boundary() {
+        let composition = ImeComposition::default();
+
+        composition.set_preedit("你好", 4);
+        assert_eq!(
+            composition.preedit(),
+            Some(Preedit {
+                text: "你好".into(),
+                cursor: 3
+            })
+        );
+
+        composition.set_preedit("ni", 10);
+        assert_eq!(composition.preedit().map(|preedit| preedit.cursor), Some(2));
+    }
+
+    #[test]
+    fn commit_ends_the_composition() {
+        let composition = ImeComposition::default();
+        let clone = composition.clone();
+
+        clone.set_preedit("ni", 2);
+        assert_eq!(composition.take_changes(), (vec![], true));
+        assert_eq!(composition.take_changes(), (vec![], false));
+
+        clone.commit("你");
+        clone.set_preedit("hao", 3);
+        clone.commit("好");
+        assert_eq!(
+            composition.take_changes(),
+            (vec!["你".to_string(), "好".to_string()], true)
+        );
+        assert_eq!(composition.preedit(), None);
+
+        clone.set_preedit("ma", 2);
+        clone.cancel();
+        assert_eq!(composition.preedit(), None);
+    }
+}
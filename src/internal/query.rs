@@ -12,7 +12,7 @@ use strum::IntoEnumIterator;
 struct KeyCodes;
 impl KeyCodes {
     pub fn iterator() -> std::slice::Iter<'static, KeyCode> {
-        static KEYCODE: [KeyCode; 29] = [
+        static KEYCODE: [KeyCode; 41] = [
             crossterm::event::KeyCode::Backspace,
             crossterm::event::KeyCode::Enter,
             crossterm::event::KeyCode::Left,
@@ -39,6 +39,18 @@ impl KeyCodes {
             crossterm::event::KeyCode::F(10),
             crossterm::event::KeyCode::F(11),
             crossterm::event::KeyCode::F(12),
+            crossterm::event::KeyCode::F(13),
+            crossterm::event::KeyCode::F(14),
+            crossterm::event::KeyCode::F(15),
+            crossterm::event::KeyCode::F(16),
+            crossterm::event::KeyCode::F(17),
+            crossterm::event::KeyCode::F(18),
+            crossterm::event::KeyCode::F(19),
+            crossterm::event::KeyCode::F(20),
+            crossterm::event::KeyCode::F(21),
+            crossterm::event::KeyCode::F(22),
+            crossterm::event::KeyCode::F(23),
+            crossterm::event::KeyCode::F(24),
             crossterm::event::KeyCode::Char('a'),
             crossterm::event::KeyCode::Null,
             crossterm::event::KeyCode::Esc,
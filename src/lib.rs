@@ -27,9 +27,6 @@
 //!              println!("\nAborted!");
 //!              break;
 //!          }
-//!          Ok(Signal::CtrlL) => {
-//!              line_editor.clear_screen();
-//!          }
 //!          x => {
 //!              println!("Event: {:?}", x);
 //!          }
@@ -178,18 +175,65 @@
 mod core_editor;
 pub use core_editor::LineBuffer;
 
+mod result;
+pub use result::{ReedlineError, Result};
+
 mod text_manipulation;
 
+mod bidi;
+
+mod abbreviation;
+pub use abbreviation::Abbreviations;
+
+mod search_replace;
+
+mod macros;
+
+mod paste;
+pub use paste::PastePolicy;
+
+mod hook;
+pub use hook::Hook;
+
+mod external_printer;
+pub use external_printer::ExternalPrinter;
+
+mod repaint_handle;
+pub use repaint_handle::RepaintHandle;
+
+mod ime;
+pub use ime::ImeComposition;
+
 mod enums;
-pub use enums::{EditCommand, ReedlineEvent, Signal, UndoBehavior};
+pub use enums::{
+    BellStyle, BindingCondition, EditCommand, LineMetadata, ReedlineEvent, Signal, StatusPosition,
+    TitleOnDrop, UndoBehavior,
+};
 
 mod painter;
+pub use painter::PromptLayout;
+
+mod terminal_backend;
+pub use terminal_backend::{CrosstermBackend, MemoryBackend, TerminalBackend};
+
+mod terminal_guard;
+pub use terminal_guard::{install_panic_hook, restore_terminal, TerminalGuard};
+
+mod terminal_manager;
+pub use terminal_manager::TerminalManager;
 
 mod engine;
 pub use engine::Reedline;
 
 mod history;
-pub use history::{FileBackedHistory, History, HistoryNavigationQuery, HISTORY_SIZE};
+#[cfg(feature = "history_encryption")]
+pub use history::HISTORY_KEY_SIZE;
+pub use history::{
+    FileBackedHistory, History, HistoryEntryMetadata, HistoryFormatError, HistoryNavigationQuery,
+    TimeRange, HISTORY_FORMAT_VERSION, HISTORY_SIZE,
+};
+#[cfg(feature = "history_redaction")]
+pub use history::{Redactor, REDACTED};
 
 mod prompt;
 pub use prompt::{
@@ -200,26 +244,36 @@ pub use prompt::{
 mod edit_mode;
 pub use edit_mode::{
     default_emacs_keybindings, default_vi_insert_keybindings, default_vi_normal_keybindings,
-    EditMode, Emacs, Keybindings, Vi,
+    EditMode, Emacs, KeyCombination, Keybindings, Vi,
 };
 
 mod highlighter;
-pub use highlighter::{ExampleHighlighter, Highlighter, SimpleMatchHighlighter};
+pub use highlighter::{
+    ExampleHighlighter, HighlightContext, Highlighter, LayerMerge, LayeredHighlighter,
+    RainbowBrackets, SimpleMatchHighlighter, SyntaxHighlighter, SyntaxStyles,
+};
 
 mod styled_text;
-pub use styled_text::StyledText;
+pub use styled_text::{StyledText, TabDisplay, Underline, UnderlineStyle};
+
+mod theme;
+pub use theme::{Theme, THEME_NAMES};
 
 mod completion;
-pub use completion::{Completer, DefaultCompleter, Span};
+pub use completion::{
+    Completer, CompletionQuoting, DefaultCompleter, PosixQuoting, Span, Tokenizer,
+};
 
 mod hinter;
-pub use hinter::{DefaultHinter, Hinter};
+pub use hinter::{CompletionHinter, DefaultHinter, Hinter, HinterChain};
 
 mod validator;
 pub use validator::{DefaultValidator, ValidationResult, Validator};
 
 mod menu;
-pub use menu::{CompletionMenu, HistoryMenu, Menu, MenuEvent};
+pub use menu::{
+    ColumnWidth, CompletionMenu, HistoryMenu, Menu, MenuBorder, MenuEvent, MenuNavigation, MenuRow,
+};
 
 mod internal;
 pub use internal::{
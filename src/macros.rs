@@ -0,0 +1,135 @@
+use crate::ReedlineEvent;
+use crossterm::event::Event;
+use std::collections::HashMap;
+
+/// The register that stands for the last macro recorded or replayed, as in vi `@@`
+pub(crate) const LAST_REGISTER: char = '@';
+
+/// The register of the emacs macro, the unnamed register `"` of vi
+pub(crate) const EMACS_REGISTER: char = '"';
+
+/// Keyboard macros: key events recorded in a register and replayed from it
+///
+/// The engine keeps the macros, so they are shared by the edit modes. The keys of the
+/// command that starts or stops the recording are not part of the macro, and the keys
+/// of a replay are not recorded again.
+#[derive(Default)]
+pub(crate) struct Macros {
+    registers: HashMap<char, Vec<Event>>,
+    recording: Option<(char, Vec<Event>)>,
+    // Keys of a command that isn't complete yet, recorded once it turns out not to
+    // control the recording
+    pending: Vec<Event>,
+    last: Option<char>,
+    replaying: bool,
+}
+
+impl Macros {
+    /// Records a key with the event the edit mode parsed from it. Keys of an
+    /// incomplete command wait for the rest of it.
+    ///
+    /// The recording starts and stops here, when the key is parsed, as the keys that
+    /// follow may be parsed before the event is handled
+    pub fn record(&mut self, event: Event, parsed: &ReedlineEvent, pending_keys: bool) {
+        if !pending_keys {
+            match macro_control(parsed) {
+                Some(ReedlineEvent::StartMacro(register)) => {
+                    self.recording = Some((*register, Vec::new()));
+                    self.pending.clear();
+                    return;
+                }
+                Some(_) => {
+                    self.pending.clear();
+                    if let Some((register, events)) = self.recording.take() {
+                        self.registers.insert(register, events);
+                        self.last = Some(register);
+                    }
+                    return;
+                }
+                None => {}
+            }
+        }
+
+        if let Some((_, recorded)) = &mut self.recording {
+            self.pending.push(event);
+            if !pending_keys {
+                recorded.append(&mut self.pending);
+            }
+        }
+    }
+
+    /// The keys of the register, to be replayed until [`Macros::end_replay`]. A macro
+    /// can't replay another one
+    pub fn begin_replay(&mut self, register: char) -> Option<Vec<Event>> {
+        if self.replaying {
+            return None;
+        }
+
+        let register = match register {
+            LAST_REGISTER => self.last?,
+            register => register,
+        };
+        let events = self.registers.get(&register)?.clone();
+        self.last = Some(register);
+        self.replaying = true;
+        Some(events)
+    }
+
+    pub fn end_replay(&mut self) {
+        self.replaying = false;
+    }
+}
+
+/// The event that starts or stops the recording
+fn macro_control(event: &ReedlineEvent) -> Option<&ReedlineEvent> {
+    match event {
+        ReedlineEvent::StartMacro(_) | ReedlineEvent::EndMacro => Some(event),
+        ReedlineEvent::Multiple(events) => events.iter().find_map(macro_control),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EditCommand;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use pretty_assertions::assert_eq;
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    fn insert(c: char) -> ReedlineEvent {
+        ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)])
+    }
+
+    #[test]
+    fn keys_are_recorded_between_start_and_end() {
+        let mut macros = Macros::default();
+        macros.record(key('a'), &insert('a'), false);
+        macros.record(key('q'), &ReedlineEvent::StartMacro('x'), false);
+        macros.record(key('b'), &insert('b'), false);
+        // A command of two keys
+        macros.record(key('d'), &ReedlineEvent::None, true);
+        macros.record(key('w'), &ReedlineEvent::Multiple(vec![]), false);
+        // The keys that stop the recording
+        macros.record(key('z'), &ReedlineEvent::None, true);
+        macros.record(key('q'), &ReedlineEvent::EndMacro, false);
+
+        assert_eq!(
+            macros.begin_replay('x'),
+            Some(vec![key('b'), key('d'), key('w')])
+        );
+        // No replay in a replay
+        assert_eq!(macros.begin_replay('x'), None);
+        macros.end_replay();
+
+        assert_eq!(
+            macros.begin_replay(LAST_REGISTER).map(|keys| keys.len()),
+            Some(3)
+        );
+        macros.end_replay();
+        assert_eq!(macros.begin_replay('y'), None);
+    }
+}
@@ -8,9 +8,10 @@ use {
         default_emacs_keybindings, default_vi_insert_keybindings, default_vi_normal_keybindings,
         get_reedline_default_keybindings, get_reedline_edit_commands,
         get_reedline_keybinding_modifiers, get_reedline_keycodes, get_reedline_prompt_edit_modes,
-        get_reedline_reedline_events, CompletionMenu, DefaultCompleter, DefaultHinter,
-        DefaultPrompt, EditMode, Emacs, ExampleHighlighter, FileBackedHistory, HistoryMenu,
-        Keybindings, Reedline, ReedlineEvent, Signal, Vi,
+        get_reedline_reedline_events, install_panic_hook, CompletionHinter, CompletionMenu,
+        DefaultCompleter, DefaultHinter, DefaultPrompt, EditCommand, EditMode, Emacs,
+        ExampleHighlighter, FileBackedHistory, HinterChain, HistoryMenu, Keybindings, Reedline,
+        ReedlineEvent, Signal, Vi,
     },
     std::{
         io::{stdout, Write},
@@ -36,6 +37,8 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
+    install_panic_hook();
+
     let history = Box::new(FileBackedHistory::with_file(50, "history.txt".into())?);
     let commands = vec![
         "test".into(),
@@ -60,17 +63,25 @@ fn main() -> Result<()> {
         "this is the reedline crate".into(),
     ];
 
-    let completer = Box::new(DefaultCompleter::new_with_wordlen(commands.clone(), 2));
+    let completer = DefaultCompleter::new_with_wordlen(commands.clone(), 2);
+
+    // History hints take precedence, commands never run before are suggested by the completer
+    let hinter = HinterChain::default()
+        .with_hinter(Box::new(
+            DefaultHinter::default().with_style(Style::new().fg(Color::DarkGray)),
+        ))
+        .with_hinter(Box::new(
+            CompletionHinter::default().with_style(Style::new().fg(Color::DarkGray).italic()),
+        ));
 
     let mut line_editor = Reedline::create()?
         .with_history(history)?
-        .with_completer(completer)
+        .with_completer(Box::new(completer))
         .with_quick_completions(false)
         .with_highlighter(Box::new(ExampleHighlighter::new(commands)))
-        .with_hinter(Box::new(
-            DefaultHinter::default().with_style(Style::new().fg(Color::DarkGray)),
-        ))
-        .with_ansi_colors(true);
+        .with_hinter(Box::new(hinter))
+        .with_ansi_colors(true)
+        .with_visible_whitespace(true);
 
     // Adding default menus for the compiled reedline
     let completion_menu = Box::new(CompletionMenu::default());
@@ -86,7 +97,10 @@ fn main() -> Result<()> {
         add_menu_keybindings(&mut normal_keybindings);
         add_menu_keybindings(&mut insert_keybindings);
 
-        Box::new(Vi::new(insert_keybindings, normal_keybindings))
+        Box::new(
+            Vi::new(insert_keybindings, normal_keybindings)
+                .with_esc_timeout(Duration::from_millis(20)),
+        )
     } else {
         let mut keybindings = default_emacs_keybindings();
         add_menu_keybindings(&mut keybindings);
@@ -126,9 +140,9 @@ fn main() -> Result<()> {
             Ok(Signal::CtrlC) => {
                 // Prompt has been cleared and should start on the next line
             }
-            Ok(Signal::CtrlL) => {
-                line_editor.clear_screen()?;
-            }
+            // The screen is cleared while reading, it doesn't end the read anymore
+            #[allow(deprecated)]
+            Ok(Signal::CtrlL) => {}
             Err(err) => {
                 println!("Error: {:?}", err);
             }
@@ -212,19 +226,59 @@ fn add_menu_keybindings(keybindings: &mut Keybindings) {
         ReedlineEvent::MenuPagePrevious,
     );
 
+    keybindings.add_binding(
+        KeyModifiers::ALT,
+        KeyCode::Char('t'),
+        ReedlineEvent::HistoryTimeRange,
+    );
+
+    keybindings.add_binding(
+        KeyModifiers::ALT,
+        KeyCode::Char('x'),
+        ReedlineEvent::HistoryDelete,
+    );
+
     keybindings.add_binding(
         KeyModifiers::NONE,
         KeyCode::Tab,
         ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::NextPlaceholder,
             ReedlineEvent::Menu("completion_menu".to_string()),
+            ReedlineEvent::MenuPartialComplete,
             ReedlineEvent::MenuNext,
         ]),
     );
 
     keybindings.add_binding(
-        KeyModifiers::SHIFT,
-        KeyCode::BackTab,
-        ReedlineEvent::MenuPrevious,
+        KeyModifiers::ALT,
+        KeyCode::Char('g'),
+        ReedlineEvent::Edit(vec![EditCommand::InsertSnippet(
+            "git checkout -b ${1:branch} ${2:origin/main}".to_string(),
+        )]),
+    );
+
+    keybindings.add_binding(
+        KeyModifiers::ALT,
+        KeyCode::Up,
+        ReedlineEvent::Edit(vec![EditCommand::SwapLineUp]),
+    );
+
+    keybindings.add_binding(
+        KeyModifiers::ALT,
+        KeyCode::Down,
+        ReedlineEvent::Edit(vec![EditCommand::SwapLineDown]),
+    );
+
+    keybindings.add_binding(
+        KeyModifiers::ALT | KeyModifiers::SHIFT,
+        KeyCode::Down,
+        ReedlineEvent::Edit(vec![EditCommand::DuplicateLine]),
+    );
+
+    keybindings.add_binding(
+        KeyModifiers::ALT,
+        KeyCode::Char('/'),
+        ReedlineEvent::Edit(vec![EditCommand::ToggleComment("# ".to_string())]),
     );
 }
 
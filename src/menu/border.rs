@@ -0,0 +1,127 @@
+use nu_ansi_term::Style;
+
+/// Box drawn around a menu to separate it from the buffer
+///
+/// ```
+/// use nu_ansi_term::{Color, Style};
+/// use reedline::{CompletionMenu, MenuBorder};
+///
+/// let menu = CompletionMenu::default().with_border(
+///     MenuBorder::default()
+///         .with_title("Completions")
+///         .with_style(Style::new().fg(Color::DarkGray)),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuBorder {
+    /// Top left corner character
+    pub top_left: char,
+    /// Top right corner character
+    pub top_right: char,
+    /// Bottom left corner character
+    pub bottom_left: char,
+    /// Bottom right corner character
+    pub bottom_right: char,
+    /// Character used for the top and bottom edges
+    pub horizontal: char,
+    /// Character used for the left and right edges
+    pub vertical: char,
+    /// Spaces between the vertical edges and the menu content
+    pub padding: u16,
+    /// Title shown in the top edge
+    pub title: Option<String>,
+    /// Style applied to the edges and the title
+    pub style: Style,
+}
+
+impl Default for MenuBorder {
+    fn default() -> Self {
+        Self {
+            top_left: '┌',
+            top_right: '┐',
+            bottom_left: '└',
+            bottom_right: '┘',
+            horizontal: '─',
+            vertical: '│',
+            padding: 1,
+            title: None,
+            style: Style::default(),
+        }
+    }
+}
+
+impl MenuBorder {
+    /// A border with rounded corners
+    pub fn rounded() -> Self {
+        Self {
+            top_left: '╭',
+            top_right: '╮',
+            bottom_left: '╰',
+            bottom_right: '╯',
+            ..Self::default()
+        }
+    }
+
+    /// A border drawn only with ASCII characters, for terminals without box drawing glyphs
+    pub fn ascii() -> Self {
+        Self {
+            top_left: '+',
+            top_right: '+',
+            bottom_left: '+',
+            bottom_right: '+',
+            horizontal: '-',
+            vertical: '|',
+            ..Self::default()
+        }
+    }
+
+    /// Border builder with the corner characters, clockwise from the top left corner
+    pub fn with_corners(
+        mut self,
+        top_left: char,
+        top_right: char,
+        bottom_right: char,
+        bottom_left: char,
+    ) -> Self {
+        self.top_left = top_left;
+        self.top_right = top_right;
+        self.bottom_right = bottom_right;
+        self.bottom_left = bottom_left;
+        self
+    }
+
+    /// Border builder with the edge characters
+    pub fn with_edges(mut self, horizontal: char, vertical: char) -> Self {
+        self.horizontal = horizontal;
+        self.vertical = vertical;
+        self
+    }
+
+    /// Border builder with the padding between the edges and the content
+    pub fn with_padding(mut self, padding: u16) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Border builder with a title shown in the top edge
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Border builder with the style of the edges and title
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Lines taken by the top and bottom edges
+    pub(crate) fn vertical_space(&self) -> u16 {
+        2
+    }
+
+    /// Columns taken by the vertical edges and the padding
+    pub(crate) fn horizontal_space(&self) -> u16 {
+        2 + 2 * self.padding
+    }
+}
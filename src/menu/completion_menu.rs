@@ -1,6 +1,24 @@
-use super::{Menu, MenuEvent, MenuTextStyle};
-use crate::{painter::Painter, Completer, History, LineBuffer, Span};
-use nu_ansi_term::{ansi::RESET, Style};
+use super::{Menu, MenuBorder, MenuEvent, MenuRow, MenuTextStyle};
+use crate::{
+    completion::{partial_values, quoted_value},
+    painter::{truncate_with_ellipsis, Painter},
+    Completer, CompletionQuoting, History, LineBuffer, Span,
+};
+use nu_ansi_term::Style;
+use unicode_width::UnicodeWidthStr;
+
+/// Frames of the spinner shown while the completer is working
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Width of a single column of the completion menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnWidth {
+    /// A fixed number of characters
+    Fixed(usize),
+    /// A share of the screen width left by the fixed columns, relative
+    /// to the weights of the other columns
+    Weight(u16),
+}
 
 /// Default values used as reference for the menu. These values are set during
 /// the initial declaration of the menu and are always kept as reference for the
@@ -10,8 +28,15 @@ struct DefaultColumnDetails {
     pub columns: u16,
     /// Column width
     pub col_width: Option<usize>,
+    /// Width of every column. When not empty it overrides the number of
+    /// columns and the column width
+    pub col_widths: Vec<ColumnWidth>,
+    /// Column width after which values are truncated
+    pub max_col_width: Option<usize>,
     /// Column padding
     pub col_padding: usize,
+    /// The number of columns and their widths are fitted to the values
+    pub auto_columns: bool,
 }
 
 impl Default for DefaultColumnDetails {
@@ -19,7 +44,10 @@ impl Default for DefaultColumnDetails {
         Self {
             columns: 4,
             col_width: None,
+            col_widths: Vec::new(),
+            max_col_width: None,
             col_padding: 2,
+            auto_columns: false,
         }
     }
 }
@@ -32,10 +60,22 @@ struct ColumnDetails {
     pub columns: u16,
     /// Column width
     pub col_width: usize,
+    /// Width of every column when they are specified one by one
+    pub col_widths: Vec<usize>,
     /// Column padding
     pub col_padding: usize,
 }
 
+/// Cell of the menu grid used when the completions are shown in groups
+enum GridCell {
+    /// Index of the value among the collected values
+    Value(usize),
+    /// Group label shown at the start of its own row
+    Header(String),
+    /// Space left after a header or at the end of a group
+    Empty,
+}
+
 /// Completion menu definition
 pub struct CompletionMenu {
     active: bool,
@@ -49,8 +89,17 @@ pub struct CompletionMenu {
     min_rows: u16,
     /// Working column details keep changing based on the collected values
     working_details: ColumnDetails,
-    /// Menu cached values
+    /// Menu cached values. Only a window of the completions around the
+    /// visible rows is collected from the completer
     values: Vec<(Span, String)>,
+    /// Index of the first cached value among all the completions
+    values_start: usize,
+    /// Total number of completions available from the completer
+    total_values: usize,
+    /// All the completions, when the completer doesn't collect them a window at a time
+    completions: Option<Vec<(Span, String)>>,
+    /// Number of rows kept in the cache before and after the selected row
+    window_rows: u16,
     /// column position of the cursor. Starts from 0
     col_pos: u16,
     /// row position in the menu. Starts from 0
@@ -59,6 +108,24 @@ pub struct CompletionMenu {
     marker: String,
     /// Event sent to the menu
     event: Option<MenuEvent>,
+    /// Border drawn around the menu
+    border: Option<MenuBorder>,
+    /// Completions are collected in groups and shown under a header
+    grouped: bool,
+    /// Label and number of values of each group
+    groups: Vec<(String, usize)>,
+    /// Grid with the headers and values when the completions are grouped
+    cells: Vec<GridCell>,
+    /// Style used for the group headers
+    header_style: Style,
+    /// Navigation continues from the other end of the menu after the edges
+    wrap_around: bool,
+    /// The completer is still working on the completions
+    pending: bool,
+    /// Frame of the spinner shown while the completions are pending
+    spinner_frame: usize,
+    /// How the values are quoted and escaped when inserted
+    quoting: Option<Box<dyn CompletionQuoting>>,
 }
 
 impl Default for CompletionMenu {
@@ -70,10 +137,23 @@ impl Default for CompletionMenu {
             min_rows: 3,
             working_details: ColumnDetails::default(),
             values: Vec::new(),
+            values_start: 0,
+            total_values: 0,
+            completions: None,
+            window_rows: 50,
             col_pos: 0,
             row_pos: 0,
             marker: "| ".to_string(),
             event: None,
+            border: None,
+            grouped: false,
+            groups: Vec::new(),
+            cells: Vec::new(),
+            header_style: Style::new().bold().underline(),
+            wrap_around: true,
+            pending: false,
+            spinner_frame: 0,
+            quoting: None,
         }
     }
 }
@@ -103,6 +183,40 @@ impl CompletionMenu {
         self
     }
 
+    /// Menu builder with a width for every column. The number of columns is
+    /// the number of widths given and values that don't fit in their column
+    /// are truncated
+    ///
+    /// ```
+    /// use reedline::{ColumnWidth, CompletionMenu};
+    ///
+    /// let menu = CompletionMenu::default().with_column_widths(vec![
+    ///     ColumnWidth::Weight(2),
+    ///     ColumnWidth::Weight(1),
+    ///     ColumnWidth::Fixed(10),
+    /// ]);
+    /// ```
+    pub fn with_column_widths(mut self, col_widths: Vec<ColumnWidth>) -> Self {
+        self.default_details.col_widths = col_widths;
+        self
+    }
+
+    /// Menu builder with a maximum column width. Longer values are truncated
+    /// with `…` and the selected value is shown in full below the menu
+    pub fn with_max_column_width(mut self, max_col_width: Option<usize>) -> Self {
+        self.default_details.max_col_width = max_col_width;
+        self
+    }
+
+    /// Menu builder that fits the columns to the values instead of using the
+    /// number of columns, like `ls -C`. The menu has as many columns as fit in
+    /// the screen, each one as wide as its longest value. The widths given with
+    /// [`CompletionMenu::with_column_widths`] take precedence
+    pub fn with_auto_columns(mut self, auto_columns: bool) -> Self {
+        self.default_details.auto_columns = auto_columns;
+        self
+    }
+
     /// Menu builder with new column width value
     pub fn with_column_padding(mut self, col_padding: usize) -> Self {
         self.default_details.col_padding = col_padding;
@@ -115,6 +229,118 @@ impl CompletionMenu {
         self
     }
 
+    /// Menu builder with a border drawn around the menu
+    pub fn with_border(mut self, border: MenuBorder) -> Self {
+        self.border = Some(border);
+        self
+    }
+
+    /// Menu builder that shows the completions in groups, each one under a header
+    /// with its label. Navigation skips the headers.
+    ///
+    /// The groups come from [`Completer::complete_grouped`], which collects all the
+    /// completions at once instead of only the visible window
+    pub fn with_groups(mut self, grouped: bool) -> Self {
+        self.grouped = grouped;
+        self
+    }
+
+    /// Menu builder with new value for the group header style
+    pub fn with_header_style(mut self, header_style: Style) -> Self {
+        self.header_style = header_style;
+        self
+    }
+
+    /// Menu builder with new value for the wrap around. When disabled, the
+    /// navigation stops at the edges of the menu
+    pub fn with_wrap_around(mut self, wrap_around: bool) -> Self {
+        self.wrap_around = wrap_around;
+        self
+    }
+
+    /// Menu builder that quotes and escapes the values for the syntax of the shell when
+    /// they are inserted, like the values with spaces or inside quotes. Without it the
+    /// values are inserted as they are
+    pub fn with_quoting(mut self, quoting: Box<dyn CompletionQuoting>) -> Self {
+        self.quoting = Some(quoting);
+        self
+    }
+
+    /// Builds the grid of the grouped completions. Every group starts in a new row
+    /// with its header, unless the group doesn't have a label
+    fn update_cells(&mut self) {
+        let cols = self.get_cols() as usize;
+        let mut index = 0;
+
+        self.cells.clear();
+        for (label, len) in &self.groups {
+            if !label.is_empty() {
+                self.cells.push(GridCell::Header(label.clone()));
+                self.cells
+                    .extend(std::iter::repeat_with(|| GridCell::Empty).take(cols - 1));
+            }
+
+            self.cells.extend((index..index + len).map(GridCell::Value));
+            index += len;
+
+            let remainder = len % cols;
+            if remainder > 0 {
+                self.cells
+                    .extend(std::iter::repeat_with(|| GridCell::Empty).take(cols - remainder));
+            }
+        }
+    }
+
+    /// Number of positions in the menu grid
+    fn grid_len(&self) -> usize {
+        if self.grouped {
+            self.cells.len()
+        } else {
+            self.total_values()
+        }
+    }
+
+    /// Headers and empty cells of grouped menus can't be selected
+    fn is_selectable(&self, index: usize) -> bool {
+        !self.grouped || matches!(self.cells.get(index), Some(GridCell::Value(_)))
+    }
+
+    /// Applies the movement and repeats it until a value is selected. If no
+    /// value is found the selection doesn't change
+    fn move_and_skip(&mut self, movement: fn(&mut Self)) {
+        let position = (self.row_pos, self.col_pos);
+        movement(self);
+        self.skip_unselectable(movement);
+
+        if !self.is_selectable(self.index()) {
+            (self.row_pos, self.col_pos) = position;
+        }
+    }
+
+    /// Repeats the movement while the selected position isn't a value
+    fn skip_unselectable(&mut self, movement: fn(&mut Self)) {
+        for _ in 0..self.grid_len() {
+            if self.is_selectable(self.index()) {
+                break;
+            }
+            movement(self);
+        }
+    }
+
+    /// Move menu cursor to the first element
+    fn move_to_start(&mut self) {
+        self.reset_position();
+        self.skip_unselectable(Self::move_next);
+    }
+
+    /// Move menu cursor to the last element
+    fn move_to_end(&mut self) {
+        let last = self.grid_len().saturating_sub(1);
+        self.row_pos = (last / self.get_cols() as usize) as u16;
+        self.col_pos = (last % self.get_cols() as usize) as u16;
+        self.skip_unselectable(Self::move_previous);
+    }
+
     /// Move menu cursor to the next element
     fn move_next(&mut self) {
         let mut new_col = self.col_pos + 1;
@@ -125,14 +351,11 @@ impl CompletionMenu {
             new_col = 0;
         }
 
-        if new_row >= self.get_rows() {
-            new_row = 0;
-            new_col = 0;
-        }
-
-        let position = new_row * self.get_cols() + new_col;
-        if position >= self.get_values().len() as u16 {
-            self.reset_position();
+        let position = new_row as usize * self.get_cols() as usize + new_col as usize;
+        if new_row >= self.get_rows() || position >= self.grid_len() {
+            if self.wrap_around {
+                self.reset_position();
+            }
         } else {
             self.col_pos = new_col;
             self.row_pos = new_row;
@@ -147,6 +370,7 @@ impl CompletionMenu {
             Some(col) => (col, self.row_pos),
             None => match self.row_pos.checked_sub(1) {
                 Some(row) => (self.get_cols().saturating_sub(1), row),
+                None if !self.wrap_around => return,
                 None => (
                     self.get_cols().saturating_sub(1),
                     self.get_rows().saturating_sub(1),
@@ -154,9 +378,9 @@ impl CompletionMenu {
             },
         };
 
-        let position = new_row * self.get_cols() + new_col;
-        if position >= self.get_values().len() as u16 {
-            self.col_pos = (self.get_values().len() as u16 % self.get_cols()).saturating_sub(1);
+        let position = new_row as usize * self.get_cols() as usize + new_col as usize;
+        if position >= self.grid_len() {
+            self.col_pos = ((self.grid_len() % self.get_cols() as usize) as u16).saturating_sub(1);
             self.row_pos = self.get_rows().saturating_sub(1);
         } else {
             self.col_pos = new_col;
@@ -168,10 +392,12 @@ impl CompletionMenu {
     fn move_up(&mut self) {
         self.row_pos = if let Some(new_row) = self.row_pos.checked_sub(1) {
             new_row
+        } else if !self.wrap_around {
+            self.row_pos
         } else {
             let new_row = self.get_rows().saturating_sub(1);
-            let index = new_row * self.get_cols() + self.col_pos;
-            if index >= self.values.len() as u16 {
+            let index = new_row as usize * self.get_cols() as usize + self.col_pos as usize;
+            if index >= self.grid_len() {
                 new_row.saturating_sub(1)
             } else {
                 new_row
@@ -182,15 +408,13 @@ impl CompletionMenu {
     /// Move menu cursor left
     fn move_down(&mut self) {
         let new_row = self.row_pos + 1;
-        self.row_pos = if new_row >= self.get_rows() {
+        let index = new_row as usize * self.get_cols() as usize + self.col_pos as usize;
+        self.row_pos = if new_row < self.get_rows() && index < self.grid_len() {
+            new_row
+        } else if self.wrap_around {
             0
         } else {
-            let index = new_row * self.get_cols() + self.col_pos;
-            if index >= self.values.len() as u16 {
-                0
-            } else {
-                new_row
-            }
+            self.row_pos
         }
     }
 
@@ -198,7 +422,7 @@ impl CompletionMenu {
     fn move_left(&mut self) {
         self.col_pos = if let Some(row) = self.col_pos.checked_sub(1) {
             row
-        } else if self.index() == self.values.len() - 1 {
+        } else if self.index() == self.grid_len().saturating_sub(1) || !self.wrap_around {
             0
         } else {
             self.get_cols().saturating_sub(1)
@@ -208,38 +432,187 @@ impl CompletionMenu {
     /// Move menu cursor element
     fn move_right(&mut self) {
         let new_col = self.col_pos + 1;
-        self.col_pos = if new_col >= self.get_cols() || self.index() + 1 > self.values.len() - 1 {
+        self.col_pos = if new_col < self.get_cols() && self.index() + 1 < self.grid_len() {
+            new_col
+        } else if self.wrap_around {
             0
         } else {
-            new_col
+            self.col_pos
         }
     }
 
     /// Menu index based on column and row position
     fn index(&self) -> usize {
-        let index = self.row_pos * self.get_cols() + self.col_pos;
-        index as usize
+        self.row_pos as usize * self.get_cols() as usize + self.col_pos as usize
+    }
+
+    /// Get a value from the cached window by its index among all the completions
+    fn value_at(&self, index: usize) -> Option<&(Span, String)> {
+        if self.grouped {
+            return match self.cells.get(index) {
+                Some(GridCell::Value(index)) => self.values.get(*index),
+                _ => None,
+            };
+        }
+
+        index
+            .checked_sub(self.values_start)
+            .and_then(|index| self.values.get(index))
     }
 
     /// Get selected value from the menu
     fn get_value(&self) -> Option<(Span, String)> {
-        self.get_values().get(self.index()).cloned()
+        self.value_at(self.index()).cloned()
     }
 
     /// Calculates how many rows the Menu will use
     fn get_rows(&self) -> u16 {
-        let rows = self.get_values().len() as u16 / self.get_cols();
+        self.grid_len().div_ceil(self.get_cols() as usize) as u16
+    }
+
+    /// Collects from the completer the window of values around the selected row.
+    /// The window is only collected again when the rows that could be displayed
+    /// are not cached
+    fn update_window(&mut self, line_buffer: &LineBuffer, completer: &dyn Completer, force: bool) {
+        // Grouped completions are collected all at once
+        if self.grouped {
+            return;
+        }
+
+        let cols = self.get_cols().max(self.max_columns()) as usize;
+        let first_row = self.row_pos.saturating_sub(self.window_rows) as usize;
+        let last_row = self.row_pos as usize + self.window_rows as usize;
+
+        let needed_start = first_row * self.get_cols() as usize;
+        let needed_end = ((last_row + 1) * self.get_cols() as usize).min(self.total_values());
+        let cached = self.values_start <= needed_start
+            && needed_end <= self.values_start + self.values.len();
+        if cached && !force {
+            return;
+        }
+
+        // If there is a new line character in the line buffer, the completer
+        // doesn't calculate the suggested values correctly. This happens when
+        // editing a multiline buffer.
+        // Also, by replacing the new line character with a space, the insert
+        // position is maintain in the line buffer.
+        let trimmed_buffer = line_buffer.get_buffer().replace("\n", " ");
+        let window_len = (last_row - first_row + 1) * cols;
+        self.values_start = needed_start;
+        self.values = match &self.completions {
+            Some(completions) => partial_values(completions, needed_start, window_len),
+            None => completer.partial_complete(
+                trimmed_buffer.as_str(),
+                line_buffer.offset(),
+                needed_start,
+                window_len,
+            ),
+        };
+    }
 
-        if self.get_values().len() as u16 % self.get_cols() != 0 {
-            rows + 1
+    /// Returns working details col width for the column
+    fn get_width(&self, column: usize) -> usize {
+        self.working_details
+            .col_widths
+            .get(column)
+            .copied()
+            .unwrap_or(self.working_details.col_width)
+    }
+
+    /// Width available for a value in the column when values are truncated
+    fn truncation_width(&self, column: usize) -> Option<usize> {
+        if self.working_details.col_widths.is_empty()
+            && self.default_details.max_col_width.is_none()
+        {
+            None
         } else {
-            rows
+            let width = self.get_width(column);
+            Some(
+                width
+                    .saturating_sub(self.working_details.col_padding)
+                    .max(1),
+            )
         }
     }
 
-    /// Returns working details col width
-    fn get_width(&self) -> usize {
-        self.working_details.col_width
+    /// The selected value when it doesn't fit in its column
+    fn truncated_selection(&self) -> Option<&str> {
+        let column = self.col_pos as usize;
+        let (_, value) = self.value_at(self.index())?;
+        let available = self.truncation_width(column)?;
+
+        (value.width() > available).then_some(value.as_str())
+    }
+
+    /// Maximum number of columns the menu can have
+    fn max_columns(&self) -> u16 {
+        if self.default_details.col_widths.is_empty() {
+            self.default_details.columns
+        } else {
+            self.default_details.col_widths.len() as u16
+        }
+    }
+
+    /// Splits the screen width between the columns given one by one
+    fn specified_widths(&self, screen_width: usize) -> Vec<usize> {
+        let widths = &self.default_details.col_widths;
+        let fixed: usize = widths
+            .iter()
+            .map(|width| match width {
+                ColumnWidth::Fixed(width) => *width,
+                ColumnWidth::Weight(_) => 0,
+            })
+            .sum();
+        let weights: usize = widths
+            .iter()
+            .map(|width| match width {
+                ColumnWidth::Fixed(_) => 0,
+                ColumnWidth::Weight(weight) => *weight as usize,
+            })
+            .sum();
+        let remaining = screen_width.saturating_sub(fixed);
+
+        widths
+            .iter()
+            .map(|width| match width {
+                ColumnWidth::Fixed(width) => *width,
+                ColumnWidth::Weight(weight) => remaining * *weight as usize / weights.max(1),
+            })
+            .collect()
+    }
+
+    /// Widths of the most columns the collected values fit in, each one as wide
+    /// as its longest value. A single column takes the screen width when not
+    /// even it fits
+    fn fitted_widths(&self, screen_width: usize) -> Vec<usize> {
+        let max_col_width = self.default_details.max_col_width.unwrap_or(usize::MAX);
+        let widths: Vec<usize> = self
+            .values
+            .iter()
+            .map(|(_, value)| (value.width() + self.working_details.col_padding).min(max_col_width))
+            .collect();
+
+        // Position of every value in its run of rows. Each group starts a new row
+        let positions: Vec<usize> = if self.grouped {
+            self.groups.iter().flat_map(|(_, len)| 0..*len).collect()
+        } else {
+            (self.values_start..self.values_start + self.values.len()).collect()
+        };
+
+        let narrowest = widths.iter().copied().min().unwrap_or(1).max(1);
+        let most = (screen_width / narrowest).clamp(1, widths.len().max(1));
+        (1..=most)
+            .rev()
+            .map(|cols| {
+                let mut col_widths = vec![0; cols];
+                for (position, width) in positions.iter().zip(&widths) {
+                    let column = position % cols;
+                    col_widths[column] = col_widths[column].max(*width);
+                }
+                col_widths
+            })
+            .find(|col_widths| col_widths.iter().sum::<usize>() <= screen_width)
+            .unwrap_or_else(|| vec![screen_width])
     }
 
     /// Reset menu position
@@ -248,18 +621,20 @@ impl CompletionMenu {
         self.row_pos = 0;
     }
 
-    fn no_records_msg(&self, use_ansi_coloring: bool) -> String {
-        let msg = "NO RECORDS FOUND";
-        if use_ansi_coloring {
-            format!(
-                "{}{}{}",
-                self.color.selected_text_style.prefix(),
-                msg,
-                RESET
-            )
-        } else {
-            msg.to_string()
-        }
+    fn pending_msg(&self) -> MenuRow<'_> {
+        let mut row = MenuRow::default();
+        let frame = SPINNER[self.spinner_frame % SPINNER.len()];
+        row.push(
+            self.color.selected_text_style,
+            format!("{} LOADING COMPLETIONS", frame),
+        );
+        row
+    }
+
+    fn no_records_msg(&self) -> MenuRow<'_> {
+        let mut row = MenuRow::default();
+        row.push(self.color.selected_text_style, "NO RECORDS FOUND");
+        row
     }
 
     /// Returns working details columns
@@ -267,59 +642,12 @@ impl CompletionMenu {
         self.working_details.columns.max(1)
     }
 
-    /// End of line for menu
-    fn end_of_line(&self, column: u16) -> &str {
-        if column == self.get_cols().saturating_sub(1) {
-            "\r\n"
-        } else {
-            ""
-        }
-    }
-
     /// Text style for menu
-    fn text_style(&self, index: usize) -> String {
+    fn text_style(&self, index: usize) -> Style {
         if index == self.index() {
-            self.color.selected_text_style.prefix().to_string()
-        } else {
-            self.color.text_style.prefix().to_string()
-        }
-    }
-
-    /// Creates default string that represents one line from a menu
-    fn create_string(
-        &self,
-        line: &str,
-        index: usize,
-        column: u16,
-        empty_space: usize,
-        use_ansi_coloring: bool,
-    ) -> String {
-        if use_ansi_coloring {
-            format!(
-                "{}{}{}{:empty$}{}",
-                self.text_style(index),
-                &line,
-                RESET,
-                "",
-                self.end_of_line(column),
-                empty = empty_space
-            )
+            self.color.selected_text_style
         } else {
-            // If no ansi coloring is found, then the selection word is
-            // the line in uppercase
-            let line_str = if index == self.index() {
-                format!(">{}", line.to_uppercase())
-            } else {
-                line.to_string()
-            };
-
-            // Final string with formatting
-            format!(
-                "{:width$}{}",
-                line_str,
-                self.end_of_line(column),
-                width = self.get_width()
-            )
+            self.color.text_style
         }
     }
 }
@@ -342,32 +670,77 @@ impl Menu for CompletionMenu {
 
     /// Selects what type of event happened with the menu
     fn menu_event(&mut self, event: MenuEvent) {
-        if let MenuEvent::Activate(_) = event {
-            self.active = true;
+        match event {
+            MenuEvent::Activate(_) => self.active = true,
+            // Results that arrive after the menu is closed are dropped
+            MenuEvent::Deactivate => self.pending = false,
+            _ => {}
         }
 
         self.event = Some(event)
     }
 
     /// Updates menu values
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn update_values(
         &mut self,
         line_buffer: &mut LineBuffer,
         _history: &dyn History,
         completer: &dyn Completer,
     ) {
-        // If there is a new line character in the line buffer, the completer
-        // doesn't calculate the suggested values correctly. This happens when
-        // editing a multiline buffer.
-        // Also, by replacing the new line character with a space, the insert
-        // position is maintain in the line buffer.
         let trimmed_buffer = line_buffer.get_buffer().replace("\n", " ");
-        self.values = completer.complete(trimmed_buffer.as_str(), line_buffer.offset());
-        self.reset_position();
+        self.pending = completer
+            .poll_completions(&trimmed_buffer, line_buffer.offset())
+            .is_pending();
+        if self.pending {
+            self.spinner_frame += 1;
+            self.values.clear();
+            self.values_start = 0;
+            self.total_values = 0;
+            self.completions = None;
+            self.groups.clear();
+            self.cells.clear();
+            self.move_to_start();
+            return;
+        }
+
+        if self.grouped {
+            let groups = completer.complete_grouped(&trimmed_buffer, line_buffer.offset());
+            self.groups = groups
+                .iter()
+                .filter(|(_, values)| !values.is_empty())
+                .map(|(label, values)| (label.clone(), values.len()))
+                .collect();
+            self.values = groups.into_iter().flat_map(|(_, values)| values).collect();
+            self.values_start = 0;
+            self.total_values = self.values.len();
+            self.completions = None;
+            self.update_cells();
+        } else {
+            // Completers that don't collect the completions a window at a time are only
+            // asked once, the windows are taken from their completions
+            match completer.total_completions(&trimmed_buffer, line_buffer.offset()) {
+                Some(total) => {
+                    self.total_values = total;
+                    self.completions = None;
+                }
+                None => {
+                    let completions = completer.complete(&trimmed_buffer, line_buffer.offset());
+                    self.total_values = completions.len();
+                    self.completions = Some(completions);
+                }
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(values = self.total_values, "completed");
+
+        self.move_to_start();
+        self.update_window(line_buffer, completer, true);
     }
 
     /// The working details for the menu changes based on the size of the lines
     /// collected from the completer
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn update_working_details(
         &mut self,
         line_buffer: &mut LineBuffer,
@@ -376,6 +749,10 @@ impl Menu for CompletionMenu {
         painter: &Painter,
     ) {
         if let Some(event) = self.event.take() {
+            // The window of values around the selected row covers at least the full screen
+            self.window_rows = self.window_rows.max(painter.screen_height());
+            let values_changed = matches!(event, MenuEvent::Activate(_) | MenuEvent::Edit(_));
+
             match event {
                 MenuEvent::Activate(updated) => {
                     self.active = true;
@@ -393,19 +770,31 @@ impl Menu for CompletionMenu {
                         self.update_values(line_buffer, history, completer);
                     }
                 }
-                MenuEvent::NextElement => self.move_next(),
-                MenuEvent::PreviousElement => self.move_previous(),
-                MenuEvent::MoveUp => self.move_up(),
-                MenuEvent::MoveDown => self.move_down(),
-                MenuEvent::MoveLeft => self.move_left(),
-                MenuEvent::MoveRight => self.move_right(),
+                MenuEvent::NextElement => self.move_and_skip(Self::move_next),
+                MenuEvent::PreviousElement => self.move_and_skip(Self::move_previous),
+                MenuEvent::MoveUp => self.move_and_skip(Self::move_up),
+                MenuEvent::MoveDown => self.move_and_skip(Self::move_down),
+                MenuEvent::MoveLeft => self.move_and_skip(Self::move_left),
+                MenuEvent::MoveRight => self.move_and_skip(Self::move_right),
+                MenuEvent::MoveToStart => self.move_to_start(),
+                MenuEvent::MoveToEnd => self.move_to_end(),
                 MenuEvent::PreviousPage | MenuEvent::NextPage => {
                     // The completion menu doest have the concept of pages, yet
                 }
+                MenuEvent::Custom(_) => {}
             }
 
+            self.update_window(line_buffer, completer, false);
+
+            // The layout is only calculated when new values are collected so it
+            // doesn't change while moving through the cached windows
+            if !values_changed {
+                return;
+            }
+
+            self.working_details.col_padding = self.default_details.col_padding;
             let max_width = self.get_values().iter().fold(0, |acc, (_, string)| {
-                let str_len = string.len() + self.working_details.col_padding;
+                let str_len = string.width() + self.working_details.col_padding;
                 if str_len > acc {
                     str_len
                 } else {
@@ -413,12 +802,19 @@ impl Menu for CompletionMenu {
                 }
             });
 
+            // The border takes part of the screen width
+            let screen_width = painter.screen_width().saturating_sub(
+                self.border
+                    .as_ref()
+                    .map_or(0, |border| border.horizontal_space()),
+            );
+
             // If no default width is found, then the total screen width is used to estimate
             // the column width based on the default number of columns
             let default_width = match self.default_details.col_width {
                 Some(col_width) => col_width,
                 None => {
-                    let col_width = painter.screen_width() / self.default_details.columns;
+                    let col_width = screen_width / self.default_details.columns;
                     col_width as usize
                 }
             };
@@ -431,73 +827,570 @@ impl Menu for CompletionMenu {
                 self.working_details.col_width = default_width;
             };
 
+            // Long values are truncated instead of widening every column
+            if let Some(max_col_width) = self.default_details.max_col_width {
+                self.working_details.col_width = self.working_details.col_width.min(max_col_width);
+            }
+
             // The working columns is adjusted based on possible number of columns
             // that could be fitted in the screen with the calculated column width
-            let possible_cols = painter.screen_width() / self.working_details.col_width as u16;
+            let possible_cols = screen_width / self.working_details.col_width.max(1) as u16;
             if possible_cols > self.default_details.columns {
                 self.working_details.columns = self.default_details.columns.max(1);
             } else {
                 self.working_details.columns = possible_cols;
             }
+
+            // Columns specified one by one keep their widths regardless of the values
+            self.working_details.col_widths = self.specified_widths(screen_width as usize);
+            if !self.working_details.col_widths.is_empty() {
+                self.working_details.columns = self.working_details.col_widths.len() as u16;
+            } else if self.default_details.auto_columns && !self.values.is_empty() {
+                self.working_details.col_widths = self.fitted_widths(screen_width as usize);
+                self.working_details.columns = self.working_details.col_widths.len() as u16;
+                // More columns may need more values than the ones collected
+                self.update_window(line_buffer, completer, false);
+            }
+
+            // The groups are placed again in the grid with the new number of columns
+            if self.grouped {
+                self.update_cells();
+                self.move_to_start();
+            }
         }
     }
 
     /// The buffer gets replaced in the Span location
     fn replace_in_buffer(&self, line_buffer: &mut LineBuffer) {
         if let Some((span, value)) = self.get_value() {
-            let mut offset = line_buffer.offset();
-            offset += value.len() - (span.end - span.start);
+            let value = match &self.quoting {
+                Some(quoting) => {
+                    quoted_value(quoting.as_ref(), line_buffer.get_buffer(), span, &value)
+                }
+                None => value,
+            };
+            let offset = (line_buffer.offset() + value.len()).saturating_sub(span.end - span.start);
 
             line_buffer.replace(span.start..span.end, &value);
             line_buffer.set_insertion_point(offset);
         }
     }
 
+    fn border(&self) -> Option<&MenuBorder> {
+        self.border.as_ref()
+    }
+
+    fn selected_value(&self) -> Option<(Span, String)> {
+        self.get_value()
+    }
+
+    fn keeps_open(&self, completer: &dyn Completer) -> bool {
+        self.get_value()
+            .is_some_and(|(span, value)| completer.keeps_menu_open(span, &value))
+    }
+
     /// Minimum rows that should be displayed by the menu
     fn min_rows(&self) -> u16 {
         self.get_rows().min(self.min_rows)
     }
 
-    /// Gets values from filler that will be displayed in the menu
+    /// Gets the window of values collected from the completer
     fn get_values(&self) -> &[(Span, String)] {
         &self.values
     }
 
+    fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Total number of completions, including the ones that are not cached
+    fn total_values(&self) -> usize {
+        self.total_values
+    }
+
     fn menu_required_lines(&self, _terminal_columns: u16) -> u16 {
-        self.get_rows()
+        // The extra line shows the full selected value when it is truncated
+        self.get_rows() + self.truncated_selection().map_or(0, |_| 1)
     }
 
-    fn menu_string(&self, available_lines: u16, use_ansi_coloring: bool) -> String {
-        if self.get_values().is_empty() {
-            self.no_records_msg(use_ansi_coloring)
+    fn menu_rows(&self, available_lines: u16) -> Vec<MenuRow<'_>> {
+        if self.pending {
+            vec![self.pending_msg()]
+        } else if self.total_values() == 0 {
+            vec![self.no_records_msg()]
         } else {
+            let detail = self.truncated_selection();
+            let available_lines = if detail.is_some() {
+                available_lines.saturating_sub(1).max(1)
+            } else {
+                available_lines
+            };
+
             // The skip values represent the number of lines that should be skipped
             // while printing the menu
             let skip_values = if self.row_pos >= available_lines {
                 let skip_lines = self.row_pos.saturating_sub(available_lines) + 1;
-                (skip_lines * self.get_cols()) as usize
+                skip_lines as usize * self.get_cols() as usize
             } else {
                 0
             };
 
-            // It seems that crossterm prefers to have a complete string ready to be printed
-            // rather than looping through the values and printing multiple things
-            // This reduces the flickering when printing the menu
-            let available_values = (available_lines * self.get_cols()) as usize;
-            self.get_values()
+            let available_values = available_lines as usize * self.get_cols() as usize;
+            let mut rows: Vec<MenuRow> = Vec::with_capacity(available_lines as usize);
+            let end = (skip_values + available_values).min(self.grid_len());
+            for index in skip_values..end {
+                if index % self.get_cols() as usize == 0 {
+                    rows.push(MenuRow::default());
+                }
+
+                if let Some(GridCell::Header(label)) = self.cells.get(index) {
+                    if let Some(row) = rows.last_mut() {
+                        row.push(self.header_style, label.as_str());
+                    }
+                    continue;
+                }
+
+                let line = match self.value_at(index) {
+                    Some((_, line)) => line,
+                    None => continue,
+                };
+
+                if let Some(row) = rows.last_mut() {
+                    let column = index % self.get_cols() as usize;
+                    let width = self.get_width(column);
+
+                    let line = match self.truncation_width(column) {
+                        Some(available) => truncate_with_ellipsis(line, available),
+                        None => line.as_str().into(),
+                    };
+                    let empty_space = width.saturating_sub(line.width());
+
+                    if index == self.index() {
+                        row.push_selected(self.text_style(index), line);
+                    } else {
+                        row.push(self.text_style(index), line);
+                    }
+                    row.push_space(empty_space);
+                }
+            }
+
+            if let Some(value) = detail {
+                let mut row = MenuRow::default();
+                row.push(self.color.text_style, value);
+                rows.push(row);
+            }
+
+            rows
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileBackedHistory;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    /// Completes the numbers from 0 to the given total
+    struct NumberCompleter(usize);
+
+    impl Completer for NumberCompleter {
+        fn complete(&self, line: &str, pos: usize) -> Vec<(Span, String)> {
+            self.partial_complete(line, pos, 0, self.0)
+        }
+
+        fn partial_complete(
+            &self,
+            _line: &str,
+            pos: usize,
+            start: usize,
+            len: usize,
+        ) -> Vec<(Span, String)> {
+            (start..(start + len).min(self.0))
+                .map(|number| (Span::new(0, pos), number.to_string()))
+                .collect()
+        }
+
+        fn total_completions(&self, _line: &str, _pos: usize) -> Option<usize> {
+            Some(self.0)
+        }
+    }
+
+    fn painter() -> Painter {
+        let mut painter = Painter::new(Box::new(crate::MemoryBackend::new(0, 0)));
+        painter.handle_resize(80, 24);
+        painter
+    }
+
+    #[rstest]
+    #[case("he", &["hello", "help", "helper"], Some("hel"))]
+    #[case("hé", &["héllo", "hélp"], Some("hél"))]
+    #[case("hel", &["hello", "help"], None)]
+    #[case("x", &[], None)]
+    fn insert_common_prefix_of_the_values(
+        #[case] typed: &str,
+        #[case] values: &[&str],
+        #[case] expected: Option<&str>,
+    ) {
+        let mut menu = CompletionMenu::default();
+        let completer =
+            crate::DefaultCompleter::new(values.iter().map(|value| value.to_string()).collect());
+        let history = FileBackedHistory::default();
+        let mut line_buffer = LineBuffer::new();
+        line_buffer.insert_str(typed);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+
+        assert_eq!(
+            menu.insert_common_prefix(&mut line_buffer),
+            expected.is_some()
+        );
+        assert_eq!(line_buffer.get_buffer(), expected.unwrap_or(typed));
+        assert_eq!(line_buffer.offset(), line_buffer.get_buffer().len());
+
+        // The values are collected again for the longer text, which has no longer prefix
+        menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        assert_eq!(menu.total_values(), values.len());
+        assert!(!menu.insert_common_prefix(&mut line_buffer));
+    }
+
+    #[test]
+    fn only_a_window_of_values_is_collected() {
+        let mut menu = CompletionMenu::default();
+        let completer = NumberCompleter(100_000);
+        let history = FileBackedHistory::default();
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut LineBuffer::new(), &history, &completer, &painter());
+
+        assert_eq!(menu.total_values(), 100_000);
+        assert!(menu.get_values().len() < 1_000);
+        assert_eq!(menu.get_value().map(|(_, value)| value), Some("0".into()));
+    }
+
+    #[test]
+    fn window_follows_the_selection() {
+        let mut menu = CompletionMenu::default();
+        let completer = NumberCompleter(100_000);
+        let history = FileBackedHistory::default();
+        let mut line_buffer = LineBuffer::new();
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        for _ in 0..500 {
+            menu.menu_event(MenuEvent::MoveDown);
+            menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        }
+
+        let expected = (500 * menu.get_cols() as usize).to_string();
+        assert_eq!(menu.get_value().map(|(_, value)| value), Some(expected));
+        assert!(menu.values_start > 0);
+
+        let rows = menu.menu_rows(5);
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().all(|row| !row.content.is_empty()));
+    }
+
+    /// Counts the times it is asked for the completions
+    #[derive(Default)]
+    struct CountingCompleter(std::sync::atomic::AtomicUsize);
+
+    impl Completer for CountingCompleter {
+        fn complete(&self, _line: &str, pos: usize) -> Vec<(Span, String)> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            (0..1000)
+                .map(|number| (Span::new(0, pos), number.to_string()))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn completer_without_windows_is_asked_once() {
+        let mut menu = CompletionMenu::default();
+        let completer = CountingCompleter::default();
+        let history = FileBackedHistory::default();
+        let mut line_buffer = LineBuffer::new();
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        // The selection leaves the first window
+        for _ in 0..100 {
+            menu.menu_event(MenuEvent::MoveDown);
+            menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        }
+
+        assert_eq!(completer.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(menu.total_values(), 1000);
+        let expected = (100 * menu.get_cols() as usize).to_string();
+        assert_eq!(menu.get_value().map(|(_, value)| value), Some(expected));
+        assert!(menu.values_start > 0);
+    }
+
+    #[test]
+    fn weighted_columns_share_remaining_width() {
+        let menu = CompletionMenu::default().with_column_widths(vec![
+            ColumnWidth::Weight(2),
+            ColumnWidth::Fixed(10),
+            ColumnWidth::Weight(1),
+        ]);
+
+        assert_eq!(menu.specified_widths(100), vec![60, 10, 30]);
+    }
+
+    #[test]
+    fn specified_columns_truncate_values() {
+        let mut menu = CompletionMenu::default()
+            .with_column_widths(vec![ColumnWidth::Fixed(6), ColumnWidth::Fixed(4)]);
+        let completer = NumberCompleter(100_000);
+        let history = FileBackedHistory::default();
+        let mut line_buffer = LineBuffer::new();
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        for _ in 0..6000 {
+            menu.menu_event(MenuEvent::MoveDown);
+            menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        }
+
+        assert_eq!(menu.get_cols(), 2);
+        let rows = menu.menu_rows(1);
+        // The padding of 2 leaves 4 and 2 characters for "12000" and "12001"
+        assert_eq!(rows[0].raw_string(), "120…  1…  ");
+    }
+
+    struct ListCompleter(Vec<&'static str>);
+
+    impl Completer for ListCompleter {
+        fn complete(&self, _line: &str, pos: usize) -> Vec<(Span, String)> {
+            self.0
                 .iter()
-                .skip(skip_values)
-                .take(available_values)
-                .enumerate()
-                .map(|(index, (_, line))| {
-                    // Correcting the enumerate index based on the number of skipped values
-                    let index = index + skip_values;
-                    let column = index as u16 % self.get_cols();
-                    let empty_space = self.get_width().saturating_sub(line.len());
-
-                    self.create_string(line, index, column, empty_space, use_ansi_coloring)
-                })
+                .map(|value| (Span::new(0, pos), value.to_string()))
                 .collect()
         }
     }
+
+    #[test]
+    fn long_values_are_truncated_with_detail_line() {
+        let mut menu = CompletionMenu::default()
+            .with_columns(2)
+            .with_max_column_width(Some(8));
+        let completer = ListCompleter(vec!["short", "a long value", "other", "tiny"]);
+        let history = FileBackedHistory::default();
+        let mut line_buffer = LineBuffer::new();
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        assert_eq!(menu.get_width(0), 8);
+        assert_eq!(menu.menu_required_lines(80), 2);
+
+        menu.menu_event(MenuEvent::NextElement);
+        menu.update_working_details(&mut line_buffer, &history, &completer, &painter());
+        assert_eq!(menu.menu_required_lines(80), 3);
+
+        let rows = menu.menu_rows(5);
+        let rows: Vec<String> = rows.iter().map(|row| row.raw_string()).collect();
+        assert_eq!(
+            rows,
+            vec!["short   a lon…  ", "other   tiny    ", "a long value"]
+        );
+    }
+
+    #[rstest]
+    #[case(30, vec!["aaaaaaaaaa  b  c  d  e  f  "])]
+    #[case(20, vec!["aaaaaaaaaa  b  c  ", "d           e  f  "])]
+    #[case(8, vec!["aaaaa…  ", "b       ", "c       ", "d       ", "e       ", "f       "])]
+    fn auto_columns_fit_the_values(#[case] screen_width: u16, #[case] expected: Vec<&str>) {
+        let mut menu = CompletionMenu::default().with_auto_columns(true);
+        let completer = ListCompleter(vec!["aaaaaaaaaa", "b", "c", "d", "e", "f"]);
+        let history = FileBackedHistory::default();
+        let mut painter = painter();
+        painter.handle_resize(screen_width, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut LineBuffer::new(), &history, &completer, &painter);
+
+        let rows = menu.menu_rows(10);
+        let rows: Vec<String> = rows.iter().map(|row| row.raw_string()).collect();
+        // Only the selected value is shown in full below the menu
+        let rows = &rows[..expected.len()];
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn empty_completions_show_message() {
+        let mut menu = CompletionMenu::default();
+        let completer = NumberCompleter(0);
+        let history = FileBackedHistory::default();
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut LineBuffer::new(), &history, &completer, &painter());
+
+        let rows = menu.menu_rows(5);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].raw_string(), "NO RECORDS FOUND");
+    }
+
+    /// Completes flags and files in two groups
+    struct GroupedCompleter;
+
+    impl Completer for GroupedCompleter {
+        fn complete(&self, line: &str, pos: usize) -> Vec<(Span, String)> {
+            self.complete_grouped(line, pos)
+                .into_iter()
+                .flat_map(|(_, values)| values)
+                .collect()
+        }
+
+        fn complete_grouped(&self, _line: &str, pos: usize) -> Vec<(String, Vec<(Span, String)>)> {
+            let group = |values: &[&str]| {
+                values
+                    .iter()
+                    .map(|value| (Span::new(0, pos), value.to_string()))
+                    .collect()
+            };
+
+            vec![
+                ("Flags".into(), group(&["--all", "--long", "--size"])),
+                ("History".into(), vec![]),
+                ("Files".into(), group(&["a.txt", "b.txt"])),
+            ]
+        }
+    }
+
+    fn grouped_menu() -> CompletionMenu {
+        let mut menu = CompletionMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(8))
+            .with_groups(true);
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(
+            &mut LineBuffer::new(),
+            &FileBackedHistory::default(),
+            &GroupedCompleter,
+            &painter(),
+        );
+        menu
+    }
+
+    #[test]
+    fn groups_are_shown_under_headers() {
+        let menu = grouped_menu();
+
+        let rows = menu.menu_rows(10);
+        let rows: Vec<String> = rows.iter().map(|row| row.raw_string()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                "Flags",
+                "--all   --long  ",
+                "--size  ",
+                "Files",
+                "a.txt   b.txt   "
+            ]
+        );
+        assert_eq!(menu.total_values(), 5);
+        assert_eq!(
+            menu.get_value().map(|(_, value)| value),
+            Some("--all".into())
+        );
+    }
+
+    #[rstest]
+    #[case(vec![MenuEvent::NextElement], "--long")]
+    #[case(vec![MenuEvent::NextElement; 3], "a.txt")]
+    #[case(vec![MenuEvent::PreviousElement], "b.txt")]
+    #[case(vec![MenuEvent::MoveDown], "--size")]
+    #[case(vec![MenuEvent::MoveDown; 2], "a.txt")]
+    #[case(vec![MenuEvent::MoveDown; 3], "--all")]
+    #[case(vec![MenuEvent::MoveUp], "a.txt")]
+    #[case(vec![MenuEvent::MoveRight, MenuEvent::MoveDown], "b.txt")]
+    fn navigation_skips_headers(#[case] events: Vec<MenuEvent>, #[case] expected: &str) {
+        let mut menu = grouped_menu();
+        for event in events {
+            menu.menu_event(event);
+            menu.update_working_details(
+                &mut LineBuffer::new(),
+                &FileBackedHistory::default(),
+                &GroupedCompleter,
+                &painter(),
+            );
+        }
+
+        assert_eq!(
+            menu.get_value().map(|(_, value)| value),
+            Some(expected.into())
+        );
+    }
+
+    #[rstest]
+    #[case(false, vec![MenuEvent::MoveToEnd], "b.txt")]
+    #[case(false, vec![MenuEvent::MoveToEnd, MenuEvent::MoveToStart], "--all")]
+    #[case(true, vec![MenuEvent::MoveToEnd], "99")]
+    #[case(true, vec![MenuEvent::MoveToEnd, MenuEvent::MoveToStart], "0")]
+    fn move_to_start_and_end(
+        #[case] numbers: bool,
+        #[case] events: Vec<MenuEvent>,
+        #[case] expected: &str,
+    ) {
+        let completer: Box<dyn Completer> = if numbers {
+            Box::new(NumberCompleter(100))
+        } else {
+            Box::new(GroupedCompleter)
+        };
+        let mut menu = grouped_menu().with_groups(!numbers);
+        for event in std::iter::once(MenuEvent::Activate(false)).chain(events) {
+            menu.menu_event(event);
+            menu.update_working_details(
+                &mut LineBuffer::new(),
+                &FileBackedHistory::default(),
+                completer.as_ref(),
+                &painter(),
+            );
+        }
+
+        assert_eq!(
+            menu.get_value().map(|(_, value)| value),
+            Some(expected.into())
+        );
+    }
+
+    #[rstest]
+    #[case(MenuEvent::PreviousElement, "--all", "b.txt")]
+    #[case(MenuEvent::MoveUp, "--all", "a.txt")]
+    #[case(MenuEvent::MoveLeft, "--all", "--long")]
+    #[case(MenuEvent::NextElement, "b.txt", "--all")]
+    #[case(MenuEvent::MoveDown, "b.txt", "--long")]
+    #[case(MenuEvent::MoveRight, "b.txt", "a.txt")]
+    fn wrap_around_can_be_disabled(
+        #[case] event: MenuEvent,
+        #[case] start: &str,
+        #[case] wrapped: &str,
+    ) {
+        for wrap_around in [true, false] {
+            let mut menu = grouped_menu().with_wrap_around(wrap_around);
+            let events = if start == "--all" {
+                vec![event.clone()]
+            } else {
+                vec![MenuEvent::MoveToEnd, event.clone()]
+            };
+
+            for event in events {
+                menu.menu_event(event);
+                menu.update_working_details(
+                    &mut LineBuffer::new(),
+                    &FileBackedHistory::default(),
+                    &GroupedCompleter,
+                    &painter(),
+                );
+            }
+
+            let expected = if wrap_around { wrapped } else { start };
+            assert_eq!(
+                menu.get_value().map(|(_, value)| value),
+                Some(expected.into())
+            );
+        }
+    }
 }
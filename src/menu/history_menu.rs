@@ -1,10 +1,17 @@
-use super::{Menu, MenuEvent, MenuTextStyle};
-use crate::{
-    painter::{estimate_single_line_wraps, Painter},
-    Completer, History, LineBuffer, Span,
-};
-use nu_ansi_term::{ansi::RESET, Style};
-use std::iter::Sum;
+use super::{Menu, MenuBorder, MenuEvent, MenuRow, MenuTextStyle};
+use crate::{painter::Painter, Completer, History, LineBuffer, Span, TimeRange};
+use chrono::{DateTime, Local, NaiveDate};
+use nu_ansi_term::{Color, Style};
+use std::{iter::Sum, ops::Range, task::Poll};
+
+/// The [`MenuEvent::Custom`] command that cycles the entries shown by the history menu
+/// between all of them, the ones added today and the ones added this week
+pub(crate) const NEXT_TIME_RANGE: &str = "next time range";
+
+/// The [`MenuEvent::Custom`] commands that make the history menu ask to confirm the
+/// deletion of the selected value, and stop asking
+pub(crate) const CONFIRM_DELETION: &str = "confirm deletion";
+pub(crate) const DELETION_ANSWERED: &str = "deletion answered";
 
 struct Page {
     size: usize,
@@ -29,12 +36,49 @@ impl<'a> Sum<&'a Page> for Page {
     }
 }
 
-/// Struct to store the menu style
+/// Entries shown by the menu, by the time they were added
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    All,
+    Today,
+    ThisWeek,
+}
+
+impl Period {
+    fn next(self) -> Self {
+        match self {
+            Period::All => Period::Today,
+            Period::Today => Period::ThisWeek,
+            Period::ThisWeek => Period::All,
+        }
+    }
+
+    fn range(self) -> TimeRange {
+        match self {
+            Period::All => TimeRange::default(),
+            Period::Today => TimeRange::today(),
+            Period::ThisWeek => TimeRange::this_week(),
+        }
+    }
+
+    /// Label shown in the banner of the menu
+    fn label(self) -> &'static str {
+        match self {
+            Period::All => "",
+            Period::Today => "[TODAY]",
+            Period::ThisWeek => "[THIS WEEK]",
+        }
+    }
+}
 
 /// Context menu definition
 pub struct HistoryMenu {
     /// Menu coloring
     color: MenuTextStyle,
+    /// Style of the text that matches the query in the entries
+    match_text_style: Style,
+    /// Text the entries were filtered by, empty without a query
+    query: String,
     /// Number of history records pulled until page is full
     page_size: usize,
     /// Menu marker displayed when the menu is active
@@ -64,14 +108,38 @@ pub struct HistoryMenu {
     page: usize,
     /// Event sent to the menu
     event: Option<MenuEvent>,
+    /// Border drawn around the menu
+    border: Option<MenuBorder>,
     /// Menu in edit mode
     in_edit: bool,
+    /// Navigation continues from the other end of the menu after the edges
+    wrap_around: bool,
+    /// Max number of lines of the preview with the full selected entry
+    preview_lines: Option<u16>,
+    /// Pinned entries collected from the history
+    pinned: Vec<String>,
+    /// Marker shown before the pinned entries
+    pinned_marker: String,
+    /// The query to the history hasn't returned its results yet
+    pending: bool,
+    /// Only the entries added in this period are shown
+    period: Period,
+    /// The menu is painted on the alternate screen
+    alternate_screen: bool,
+    /// Rows with the day are shown between the entries added on different days
+    day_separators: bool,
+    /// When the values were last added, for the day separators. Pinned entries have none
+    times: Vec<Option<DateTime<Local>>>,
+    /// The banner asks to confirm the deletion of the selected entry
+    confirming_deletion: bool,
 }
 
 impl Default for HistoryMenu {
     fn default() -> Self {
         Self {
             color: MenuTextStyle::default(),
+            match_text_style: Color::Yellow.normal(),
+            query: String::new(),
             page_size: 10,
             row_char: ':',
             active: false,
@@ -84,7 +152,18 @@ impl Default for HistoryMenu {
             multiline_marker: ":::".to_string(),
             pages: Vec::new(),
             event: None,
+            border: None,
             in_edit: false,
+            wrap_around: true,
+            preview_lines: None,
+            pinned: Vec::new(),
+            pinned_marker: "* ".to_string(),
+            pending: false,
+            period: Period::All,
+            alternate_screen: false,
+            day_separators: false,
+            times: Vec::new(),
+            confirming_deletion: false,
         }
     }
 }
@@ -102,6 +181,13 @@ impl HistoryMenu {
         self
     }
 
+    /// Menu builder with new value for the style of the text that matches the
+    /// query. In the selected entry the matched text is underlined instead
+    pub fn with_match_text_style(mut self, match_text_style: Style) -> Self {
+        self.match_text_style = match_text_style;
+        self
+    }
+
     /// Menu builder with page size
     pub fn with_page_size(mut self, page_size: usize) -> Self {
         self.page_size = page_size;
@@ -120,12 +206,55 @@ impl HistoryMenu {
         self
     }
 
+    /// Menu builder with a border drawn around the menu
+    pub fn with_border(mut self, border: MenuBorder) -> Self {
+        self.border = Some(border);
+        self
+    }
+
+    /// Menu builder with new value for the wrap around. When disabled, the
+    /// navigation stops at the first and last entries
+    pub fn with_wrap_around(mut self, wrap_around: bool) -> Self {
+        self.wrap_around = wrap_around;
+        self
+    }
+
+    /// Menu builder with a preview of the selected entry. Multiline entries are
+    /// shown below the menu with up to the given number of lines, keeping their
+    /// newlines and indentation
+    pub fn with_preview_lines(mut self, preview_lines: Option<u16>) -> Self {
+        self.preview_lines = preview_lines;
+        self
+    }
+
+    /// Menu builder with new value for the marker shown before pinned entries
+    pub fn with_pinned_marker(mut self, pinned_marker: String) -> Self {
+        self.pinned_marker = pinned_marker;
+        self
+    }
+
+    /// Menu builder that paints the menu on the alternate screen, using the full height
+    /// of the terminal to show the entries. The prompt view is restored when the menu
+    /// is closed
+    pub fn with_alternate_screen(mut self, alternate_screen: bool) -> Self {
+        self.alternate_screen = alternate_screen;
+        self
+    }
+
     /// Menu builder with max entry lines
     pub fn with_max_entry_lines(mut self, max_lines: u16) -> Self {
         self.max_lines = max_lines;
         self
     }
 
+    /// Menu builder that shows a row with the day, like `— Yesterday —`, above the
+    /// entries of each day. It needs a history that records when the entries were
+    /// added, see [`History::time_at`]
+    pub fn with_day_separators(mut self, day_separators: bool) -> Self {
+        self.day_separators = day_separators;
+        self
+    }
+
     fn update_row_pos(&mut self, new_pos: Option<usize>) {
         if let (Some(row), Some(page)) = (new_pos, self.pages.get(self.page)) {
             let values_before_page = self.pages.iter().take(self.page).sum::<Page>().size;
@@ -136,7 +265,10 @@ impl HistoryMenu {
         }
     }
 
-    fn create_values_no_query(&mut self, history: &dyn History) -> Vec<String> {
+    fn create_values_no_query(
+        &mut self,
+        history: &dyn History,
+    ) -> Vec<(String, Option<DateTime<Local>>)> {
         // When there is no line buffer it is better to get a partial list of all
         // the values that can be queried from the history. There is no point to
         // replicate the whole entries list in the history menu
@@ -147,18 +279,34 @@ impl HistoryMenu {
             .map(|page| page.size)
             .unwrap_or(self.page_size);
 
-        history
+        // The pinned entries are always shown first
+        let pinned = &self.pinned;
+        let unpinned = history
             .iter_chronologic()
+            .enumerate()
             .rev()
+            .filter(|(_, entry)| !pinned.contains(entry))
+            .map(|(index, entry)| (entry, Some(index)));
+
+        // Only the times of the entries shown are looked up
+        pinned
+            .iter()
+            .map(|entry| (entry, None))
+            .chain(unpinned)
             .skip(skip)
             .take(take)
-            .cloned()
-            .collect::<Vec<String>>()
+            .map(|(entry, index)| {
+                let time = index
+                    .filter(|_| self.day_separators)
+                    .and_then(|index| history.time_at(index));
+                (entry.clone(), time)
+            })
+            .collect()
     }
 
-    /// The number of rows an entry from the menu can take considering wrapping
-    fn number_of_lines(&self, entry: &str, terminal_columns: u16) -> u16 {
-        number_of_lines(entry, self.max_lines as usize, terminal_columns)
+    /// The number of rows an entry from the menu can take
+    fn number_of_lines(&self, entry: &str) -> u16 {
+        number_of_lines(entry, self.max_lines as usize)
     }
 
     fn total_values(&self) -> usize {
@@ -197,45 +345,111 @@ impl HistoryMenu {
     fn printable_entries(&self, painter: &Painter) -> usize {
         // The number 2 comes from the prompt line and the banner printed at the bottom
         // of the history menu
-        let available_lines = painter.screen_height().saturating_sub(2);
-        let (printable_entries, _) =
-            self.get_values()
-                .iter()
-                .fold(
-                    (0, Some(0)),
-                    |(lines, total_lines), (_, entry)| match total_lines {
-                        None => (lines, None),
-                        Some(total_lines) => {
-                            let new_total_lines =
-                                total_lines + self.number_of_lines(entry, painter.screen_width());
-
-                            if new_total_lines < available_lines {
-                                (lines + 1, Some(new_total_lines))
-                            } else {
-                                (lines, None)
-                            }
-                        }
-                    },
-                );
+        let border_lines = self
+            .border
+            .as_ref()
+            .map_or(0, |border| border.vertical_space());
+        let available_lines = painter
+            .screen_height()
+            .saturating_sub(2)
+            .saturating_sub(border_lines)
+            .saturating_sub(self.preview_space());
+        let (printable_entries, _) = self.get_values().iter().zip(self.day_separators()).fold(
+            (0, Some(0)),
+            |(lines, total_lines), ((_, entry), separator)| match total_lines {
+                None => (lines, None),
+                Some(total_lines) => {
+                    let new_total_lines =
+                        total_lines + self.number_of_lines(entry) + separator.is_some() as u16;
+
+                    if new_total_lines < available_lines {
+                        (lines + 1, Some(new_total_lines))
+                    } else {
+                        (lines, None)
+                    }
+                }
+            },
+        );
 
         printable_entries
     }
 
-    fn no_page_msg(&self, use_ansi_coloring: bool) -> String {
-        let msg = "PAGE NOT FOUND";
-        if use_ansi_coloring {
-            format!(
-                "{}{}{}",
-                self.color.selected_text_style.prefix(),
-                msg,
-                RESET
-            )
+    /// Range of the values shown in the current page
+    fn page_range(&self) -> Range<usize> {
+        if self.history_size.is_some() {
+            // When there is a history size value it means that only a chunk of the
+            // chronological data from the database was collected
+            return 0..self.values.len();
+        }
+
+        // If no history record then it means that the values hold the result
+        // from the query to the database. This range can be used to get the
+        // data that will be shown in the menu
+        if self.values.is_empty() {
+            return 0..0;
+        }
+
+        let start = self.pages.iter().take(self.page).sum::<Page>().size;
+
+        let end: usize = if self.page >= self.pages.len() {
+            self.page_size + start
         } else {
-            msg.to_string()
+            self.pages.iter().take(self.page + 1).sum::<Page>().size
+        };
+
+        let end = end.min(self.total_values());
+        start..end
+    }
+
+    /// The separator row with the day shown above each value of the page, if the value
+    /// was added on an earlier day than the values above it. The first value of the
+    /// page always shows its day
+    fn day_separators(&self) -> Vec<Option<String>> {
+        let range = self.page_range();
+        if !self.day_separators {
+            return vec![None; range.len()];
         }
+
+        let today = Local::now().naive_local().date();
+        let mut current: Option<NaiveDate> = None;
+        self.times[range]
+            .iter()
+            .map(|time| {
+                let day = time.as_ref()?.naive_local().date();
+                if current.is_some_and(|current| day >= current) {
+                    return None;
+                }
+                current = Some(day);
+                Some(format!("— {} —", day_label(day, today)))
+            })
+            .collect()
+    }
+
+    fn pending_msg(&self) -> MenuRow<'_> {
+        let mut row = MenuRow::default();
+        row.push(self.color.selected_text_style, "SEARCHING...");
+        row
+    }
+
+    fn no_page_msg(&self) -> MenuRow<'_> {
+        let mut row = MenuRow::default();
+        row.push(self.color.selected_text_style, "PAGE NOT FOUND");
+        row
     }
 
-    fn banner_message(&self, page: &Page, use_ansi_coloring: bool) -> String {
+    fn banner_message(&self, page: &Page) -> MenuRow<'_> {
+        if let (true, Some((_, entry))) = (self.confirming_deletion, self.get_value()) {
+            let mut row = MenuRow::default();
+            row.push(
+                self.color.selected_text_style,
+                format!(
+                    "Delete \"{}\" from the history? (y/n)",
+                    entry.lines().next().unwrap_or_default()
+                ),
+            );
+            return row;
+        }
+
         let values_until = self.values_until_current_page().saturating_sub(1);
         let value_before = if self.values.is_empty() || self.page == 0 {
             0
@@ -245,7 +459,7 @@ impl HistoryMenu {
         };
 
         let full_page = if page.full { "[FULL]" } else { "" };
-        let status_bar = format!(
+        let mut status_bar = format!(
             "Page {}: records {} - {}  total: {}  {}",
             self.page + 1,
             value_before,
@@ -253,62 +467,130 @@ impl HistoryMenu {
             self.total_values(),
             full_page,
         );
-
-        if use_ansi_coloring {
-            format!(
-                "{}{}{}",
-                self.color.selected_text_style.prefix(),
-                status_bar,
-                RESET,
-            )
-        } else {
-            status_bar
+        if self.period != Period::All {
+            status_bar.push_str("  ");
+            status_bar.push_str(self.period.label());
         }
-    }
 
-    /// End of line for menu
-    fn end_of_line(&self) -> &str {
-        "\r\n"
+        let mut row = MenuRow::default();
+        row.push(self.color.selected_text_style, status_bar);
+        row
     }
 
     /// Text style for menu
-    fn text_style(&self, index: usize) -> String {
+    fn text_style(&self, index: usize) -> Style {
         if index == self.index() {
-            self.color.selected_text_style.prefix().to_string()
+            self.color.selected_text_style
         } else {
-            self.color.text_style.prefix().to_string()
+            self.color.text_style
+        }
+    }
+
+    /// Lines reserved for the preview. Besides the entry lines, the preview has
+    /// a title and a line to indicate that the entry is longer
+    fn preview_space(&self) -> u16 {
+        self.preview_lines.map_or(0, |lines| lines + 2)
+    }
+
+    /// Creates the rows with the full selected entry when it has several lines
+    fn preview_rows(&self) -> Vec<MenuRow<'_>> {
+        let max_lines = match self.preview_lines {
+            Some(max_lines) => max_lines as usize,
+            None => return Vec::new(),
+        };
+
+        let entry = match self.get_value() {
+            Some((_, entry)) if entry.lines().count() > 1 => entry,
+            _ => return Vec::new(),
+        };
+
+        let mut title = MenuRow::default();
+        title.push(self.color.selected_text_style, "Preview:");
+
+        let lines = entry.lines().take(max_lines).map(|line| {
+            let mut row = MenuRow::default();
+            row.push(self.color.text_style, line.to_string());
+            row
+        });
+
+        let mut rows: Vec<MenuRow> = std::iter::once(title).chain(lines).collect();
+        if entry.lines().count() > max_lines {
+            let mut row = MenuRow::default();
+            row.push(self.color.text_style, "...");
+            rows.push(row);
         }
+
+        rows
     }
 
-    /// Creates default string that represents one line from a menu
-    fn create_string(
-        &self,
-        line: &str,
+    /// Creates the rows that represent one entry from the menu
+    fn create_rows<'a>(
+        &'a self,
+        entry: &'a str,
         index: usize,
-        row_number: &str,
-        use_ansi_coloring: bool,
-    ) -> String {
-        if use_ansi_coloring {
-            format!(
-                "{}{}{}{}{}{}",
-                row_number,
-                self.text_style(index),
-                &line,
-                RESET,
-                "",
-                self.end_of_line(),
-            )
-        } else {
-            // If no ansi coloring is found, then the selection word is
-            // the line in uppercase
-            let line_str = if index == self.index() {
-                format!("{}>{}", row_number, line.to_uppercase())
+        row_number: String,
+    ) -> Vec<MenuRow<'a>> {
+        let style = self.text_style(index);
+        let mut rows = Vec::new();
+
+        // An empty entry still takes a row with its number
+        let lines = entry.lines().chain(entry.is_empty().then_some(""));
+        for (line_number, line) in lines.take(self.max_lines as usize).enumerate() {
+            let mut row = MenuRow::default();
+            if line_number == 0 {
+                row.push(Style::default(), row_number.clone());
+                if self.pinned.iter().any(|pinned| pinned == entry) {
+                    row.push(Style::default(), self.pinned_marker.as_str());
+                }
+                self.push_line(&mut row, line, index);
             } else {
-                format!("{}{}", row_number, line)
-            };
+                row.push(style, self.multiline_marker.as_str());
+                self.push_line(&mut row, line, index);
+            }
+            rows.push(row);
+        }
+
+        if entry.lines().count() > self.max_lines as usize {
+            let mut row = MenuRow::default();
+            row.push(style, format!("{}...", self.multiline_marker));
+            rows.push(row);
+        }
+
+        rows
+    }
 
-            // Final string with formatting
-            format!("{}{}", line_str, self.end_of_line())
+    /// Adds a line of an entry to the row, with the text matching the query in its
+    /// own style
+    fn push_line<'a>(&self, row: &mut MenuRow<'a>, line: &'a str, index: usize) {
+        let selected = index == self.index();
+        let style = self.text_style(index);
+        let match_style = if selected {
+            style.underline()
+        } else {
+            self.match_text_style
+        };
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        if !self.query.is_empty() {
+            for (match_start, matched) in line.match_indices(&self.query) {
+                if start < match_start {
+                    parts.push((style, &line[start..match_start]));
+                }
+                parts.push((match_style, matched));
+                start = match_start + matched.len();
+            }
+        }
+        if start < line.len() || parts.is_empty() {
+            parts.push((style, &line[start..]));
+        }
+
+        for (part_index, (style, text)) in parts.into_iter().enumerate() {
+            if selected && part_index == 0 {
+                row.push_selected(style, text);
+            } else {
+                row.push(style, text);
+            }
         }
     }
 }
@@ -331,7 +613,19 @@ impl Menu for HistoryMenu {
     /// Selects what type of event happened with the menu
     fn menu_event(&mut self, event: MenuEvent) {
         match &event {
+            MenuEvent::Custom(command)
+                if command == CONFIRM_DELETION || command == DELETION_ANSWERED =>
+            {
+                // The banner changes without moving through the menu
+                self.confirming_deletion = command == CONFIRM_DELETION;
+                return;
+            }
             MenuEvent::Activate(_) => self.active = true,
+            // The menu shows all the entries again when it's opened next
+            MenuEvent::Deactivate => {
+                self.period = Period::All;
+                self.confirming_deletion = false;
+            }
             MenuEvent::Edit(_) => self.in_edit = true,
             _ => {}
         }
@@ -340,6 +634,7 @@ impl Menu for HistoryMenu {
     }
 
     /// Collecting the value from the history to be shown in the menu
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn update_values(
         &mut self,
         line_buffer: &mut LineBuffer,
@@ -356,17 +651,43 @@ impl Menu for HistoryMenu {
             self.in_edit = false;
         }
 
-        let values = if query.is_empty() {
+        self.pinned = history.pinned_entries();
+        self.query = query.to_string();
+        let values = if self.period != Period::All {
+            // Entries are only filtered by time when they are queried
+            self.history_size = None;
+            self.pending = false;
+            let mut values = history.query_entries_between(query, self.period.range());
+            values.sort_by_key(|(entry, _)| !self.pinned.contains(entry));
+            values
+        } else if query.is_empty() {
             self.history_size = Some(history.max_values());
             self.create_values_no_query(history)
         } else {
             self.history_size = None;
-            history.query_entries(query)
+            match history.poll_query_entries(query) {
+                Poll::Ready(mut values) => {
+                    self.pending = false;
+                    values.sort_by_key(|(entry, _)| !self.pinned.contains(entry));
+                    values
+                }
+                Poll::Pending => {
+                    self.pending = true;
+                    Vec::new()
+                }
+            }
         };
 
+        // The pinned entries are shown first, out of the order of the days
+        self.times = values
+            .iter()
+            .map(|(entry, time)| {
+                time.filter(|_| self.day_separators && !self.pinned.contains(entry))
+            })
+            .collect();
         self.values = values
             .into_iter()
-            .map(|s| {
+            .map(|(s, _)| {
                 (
                     Span {
                         start: 0,
@@ -378,31 +699,14 @@ impl Menu for HistoryMenu {
             .collect();
     }
 
+    /// The query typed in the buffer, without the row selector
+    fn search_term(&self) -> Option<&str> {
+        Some(self.query.as_str()).filter(|query| !query.is_empty())
+    }
+
     /// Gets values from cached values that will be displayed in the menu
     fn get_values(&self) -> &[(Span, String)] {
-        if self.history_size.is_some() {
-            // When there is a history size value it means that only a chunk of the
-            // chronological data from the database was collected
-            &self.values
-        } else {
-            // If no history record then it means that the values hold the result
-            // from the query to the database. This slice can be used to get the
-            // data that will be shown in the menu
-            if self.values.is_empty() {
-                return &self.values;
-            }
-
-            let start = self.pages.iter().take(self.page).sum::<Page>().size;
-
-            let end: usize = if self.page >= self.pages.len() {
-                self.page_size + start
-            } else {
-                self.pages.iter().take(self.page + 1).sum::<Page>().size
-            };
-
-            let end = end.min(self.total_values());
-            &self.values[start..end]
-        }
+        &self.values[self.page_range()]
     }
 
     /// The buffer gets cleared with the actual value
@@ -412,6 +716,7 @@ impl Menu for HistoryMenu {
         }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn update_working_details(
         &mut self,
         line_buffer: &mut LineBuffer,
@@ -435,6 +740,17 @@ impl Menu for HistoryMenu {
                     });
                 }
                 MenuEvent::Deactivate => self.active = false,
+                MenuEvent::Custom(command) if command == NEXT_TIME_RANGE => {
+                    self.period = self.period.next();
+                    self.reset_position();
+                    self.update_values(line_buffer, history, completer);
+
+                    self.pages.push(Page {
+                        size: self.printable_entries(painter),
+                        full: false,
+                    });
+                }
+                MenuEvent::Custom(_) => {}
                 MenuEvent::Edit(updated) => {
                     if !updated {
                         self.update_values(line_buffer, history, completer);
@@ -460,6 +776,7 @@ impl Menu for HistoryMenu {
                 MenuEvent::PreviousElement | MenuEvent::MoveUp | MenuEvent::MoveLeft => {
                     match self.row_position.checked_sub(1) {
                         Some(new_pos) => self.row_position = new_pos,
+                        None if self.page == 0 && !self.wrap_around => {}
                         None => {
                             let page = match self.page.checked_sub(1) {
                                 Some(page) => self.pages.get(page),
@@ -494,7 +811,7 @@ impl Menu for HistoryMenu {
 
                         self.update_values(line_buffer, history, completer);
                         self.set_actual_page_size(self.printable_entries(painter));
-                    } else {
+                    } else if self.wrap_around {
                         self.row_position = 0;
                         self.page = 0;
                         self.update_values(line_buffer, history, completer);
@@ -503,62 +820,105 @@ impl Menu for HistoryMenu {
                 MenuEvent::PreviousPage => {
                     match self.page.checked_sub(1) {
                         Some(page_num) => self.page = page_num,
+                        None if !self.wrap_around => {}
                         None => self.page = self.pages.len().saturating_sub(1),
                     }
                     self.update_values(line_buffer, history, completer);
                 }
+                MenuEvent::MoveToStart => {
+                    self.row_position = 0;
+                    self.page = 0;
+                    self.update_values(line_buffer, history, completer);
+                }
+                MenuEvent::MoveToEnd => {
+                    // The size of the pages is only known once they are printed, so the
+                    // pages are registered one by one until the last value is reached
+                    while self.values_until_current_page() < self.total_values() {
+                        let progress = (self.page, self.values_until_current_page());
+                        self.event = Some(MenuEvent::NextPage);
+                        self.update_working_details(line_buffer, history, completer, painter);
+
+                        let empty_page = self
+                            .pages
+                            .get(self.page)
+                            .map_or(true, |page| page.size == 0);
+                        if empty_page || progress == (self.page, self.values_until_current_page()) {
+                            break;
+                        }
+                    }
+
+                    let page_size = self.pages.get(self.page).map_or(0, |page| page.size);
+                    self.row_position =
+                        page_size.min(self.get_values().len()).saturating_sub(1) as u16;
+                }
             }
         }
     }
 
     /// Calculates the real required lines for the menu considering how many lines
     /// wrap the terminal and if an entry is larger than the remaining lines
-    fn menu_required_lines(&self, terminal_columns: u16) -> u16 {
-        self.get_values().iter().fold(0, |acc, (_, entry)| {
-            acc + self.number_of_lines(entry, terminal_columns)
-        }) + 1
+    fn menu_required_lines(&self, _terminal_columns: u16) -> u16 {
+        let separators = self.day_separators().iter().flatten().count() as u16;
+        self.get_values()
+            .iter()
+            .fold(0, |acc, (_, entry)| acc + self.number_of_lines(entry))
+            + separators
+            + 1
+            + self.preview_rows().len() as u16
     }
 
-    /// Creates the menu representation as a string which will be painted by the painter
-    fn menu_string(&self, _available_lines: u16, use_ansi_coloring: bool) -> String {
+    /// Creates the rows of the menu that will be painted by the painter
+    fn menu_rows(&self, _available_lines: u16) -> Vec<MenuRow<'_>> {
+        if self.pending {
+            return vec![self.pending_msg()];
+        }
+
         let values_before_page = self.pages.iter().take(self.page).sum::<Page>().size;
         match self.pages.get(self.page) {
             Some(page) => {
-                let lines_string = self
+                let mut rows: Vec<MenuRow> = self
                     .get_values()
                     .iter()
+                    .zip(self.day_separators())
                     .take(page.size)
                     .enumerate()
-                    .map(|(index, (_, line))| {
-                        // Final string with colors
-                        let line = if line.lines().count() > self.max_lines as usize {
-                            let lines = line
-                                .lines()
-                                .take(self.max_lines as usize)
-                                .map(|string| format!("{}\r\n{}", string, self.multiline_marker))
-                                .collect::<String>();
-
-                            lines + "..."
-                        } else {
-                            line.replace("\n", &format!("\r\n{}", self.multiline_marker))
-                        };
-
+                    .flat_map(|(index, ((_, entry), separator))| {
+                        let separator = separator.map(|day| {
+                            let mut row = MenuRow::default();
+                            row.push(self.color.text_style.dimmed(), day);
+                            row
+                        });
                         let row_number = format!("{}: ", index + values_before_page);
-
-                        self.create_string(&line, index, &row_number, use_ansi_coloring)
+                        separator
+                            .into_iter()
+                            .chain(self.create_rows(entry, index, row_number))
                     })
-                    .collect::<String>();
+                    .collect();
 
-                format!(
-                    "{}{}",
-                    lines_string,
-                    self.banner_message(page, use_ansi_coloring)
-                )
+                rows.push(self.banner_message(page));
+                rows.extend(self.preview_rows());
+                rows
             }
-            None => self.no_page_msg(use_ansi_coloring),
+            None => vec![self.no_page_msg()],
         }
     }
 
+    fn alternate_screen(&self) -> bool {
+        self.alternate_screen
+    }
+
+    fn border(&self) -> Option<&MenuBorder> {
+        self.border.as_ref()
+    }
+
+    fn selected_value(&self) -> Option<(Span, String)> {
+        self.get_value()
+    }
+
+    fn is_pending(&self) -> bool {
+        self.pending
+    }
+
     /// Minimum rows that should be displayed by the menu
     fn min_rows(&self) -> u16 {
         self.max_lines + 1
@@ -608,32 +968,35 @@ fn parse_row_selector<'buffer>(
     (buffer, None)
 }
 
-fn number_of_lines(entry: &str, max_lines: usize, terminal_columns: u16) -> u16 {
-    let lines = if entry.contains('\n') {
-        let total_lines = entry.lines().count();
-        let printable_lines = if total_lines > max_lines {
-            // The extra one is there because when printing a large entry and extra line
-            // is added with ...
-            max_lines + 1
-        } else {
-            total_lines
-        };
-
-        let wrap_lines = entry.lines().take(max_lines).fold(0, |acc, line| {
-            acc + estimate_single_line_wraps(line, terminal_columns)
-        });
+/// Name of the day shown in the separator rows
+fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    if day == today {
+        "Today".to_string()
+    } else if Some(day) == today.pred_opt() {
+        "Yesterday".to_string()
+    } else {
+        day.format("%A, %Y-%m-%d").to_string()
+    }
+}
 
-        (printable_lines + wrap_lines) as u16
+fn number_of_lines(entry: &str, max_lines: usize) -> u16 {
+    let total_lines = entry.lines().count().max(1);
+    let printable_lines = if total_lines > max_lines {
+        // The extra one is there because when printing a large entry and extra line
+        // is added with ...
+        max_lines + 1
     } else {
-        1 + estimate_single_line_wraps(entry, terminal_columns) as u16
+        total_lines
     };
 
-    lines
+    printable_lines as u16
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{DefaultCompleter, FileBackedHistory, HistoryNavigationQuery};
+    use std::{cell::Cell, collections::vec_deque::Iter};
 
     #[test]
     fn parse_row_test() {
@@ -701,7 +1064,7 @@ mod tests {
     #[test]
     fn number_of_lines_test() {
         let input = "let a: another:\nsomething\nanother";
-        let res = number_of_lines(input, 5, 30);
+        let res = number_of_lines(input, 5);
 
         // There is an extra line showing ...
         assert_eq!(res, 3);
@@ -710,25 +1073,344 @@ mod tests {
     #[test]
     fn number_one_line_test() {
         let input = "let a: another";
-        let res = number_of_lines(input, 5, 30);
+        let res = number_of_lines(input, 5);
 
         assert_eq!(res, 1);
     }
 
     #[test]
-    fn lines_with_wrap_test() {
+    fn long_line_takes_one_row_test() {
+        // Rows are cut at the screen width by the painter instead of wrapping
         let input = "let a= an1other ver2y large l3ine what 4should wr5ap";
-        let res = number_of_lines(input, 5, 10);
+        let res = number_of_lines(input, 5);
+
+        assert_eq!(res, 1);
+    }
 
-        assert_eq!(res, 6);
+    #[test]
+    fn empty_entry_test() {
+        assert_eq!(number_of_lines("", 5), 1);
     }
 
     #[test]
     fn number_of_max_lines_test() {
         let input = "let a\n: ano\nther:\nsomething\nanother\nmore\nanother\nasdf\nasdfa\n3123";
-        let res = number_of_lines(input, 3, 30);
+        let res = number_of_lines(input, 3);
 
         // There is an extra line showing ...
         assert_eq!(res, 4);
     }
+
+    /// A history with the entries, the oldest first
+    fn history(entries: impl IntoIterator<Item = impl AsRef<str>>) -> FileBackedHistory {
+        let mut history = FileBackedHistory::default();
+        for entry in entries {
+            history.append(entry.as_ref());
+        }
+        history
+    }
+
+    /// Sends the event to the menu and lets it update, as the engine does, with the
+    /// text typed in the buffer
+    fn send(menu: &mut HistoryMenu, history: &dyn History, typed: &str, event: MenuEvent) {
+        let mut line_buffer = LineBuffer::new();
+        line_buffer.insert_str(typed);
+        let mut painter = Painter::new(Box::new(crate::MemoryBackend::new(0, 0)));
+        painter.handle_resize(80, 24);
+
+        menu.menu_event(event);
+        menu.update_working_details(
+            &mut line_buffer,
+            history,
+            &DefaultCompleter::default(),
+            &painter,
+        );
+    }
+
+    /// The text of the rows shown by the menu
+    fn rows(menu: &HistoryMenu) -> Vec<String> {
+        menu.menu_rows(20)
+            .iter()
+            .map(|row| row.raw_string())
+            .collect()
+    }
+
+    fn selected(menu: &HistoryMenu) -> Option<String> {
+        menu.get_value().map(|(_, value)| value)
+    }
+
+    #[test]
+    fn move_to_end_and_start_test() {
+        let history = history((0..50).map(|entry| entry.to_string()));
+        let mut menu = HistoryMenu::default();
+
+        send(&mut menu, &history, "", MenuEvent::Activate(false));
+        assert_eq!(selected(&menu), Some("49".into()));
+
+        send(&mut menu, &history, "", MenuEvent::MoveToEnd);
+        assert_eq!(selected(&menu), Some("0".into()));
+        assert!(menu.page > 0);
+
+        send(&mut menu, &history, "", MenuEvent::MoveToStart);
+        assert_eq!(selected(&menu), Some("49".into()));
+        assert_eq!(menu.page, 0);
+    }
+
+    #[test]
+    fn navigation_stops_at_edges_without_wrap_around_test() {
+        let history = history((0..50).map(|entry| entry.to_string()));
+        let mut menu = HistoryMenu::default().with_wrap_around(false);
+
+        send(&mut menu, &history, "", MenuEvent::Activate(false));
+        send(&mut menu, &history, "", MenuEvent::PreviousElement);
+        assert_eq!(selected(&menu), Some("49".into()));
+
+        send(&mut menu, &history, "", MenuEvent::MoveToEnd);
+        send(&mut menu, &history, "", MenuEvent::NextElement);
+        assert_eq!(selected(&menu), Some("0".into()));
+    }
+
+    #[test]
+    fn entries_are_grouped_by_day_test() {
+        let history = history(["a", "b", "c", "d"]);
+        let mut menu = HistoryMenu::default().with_day_separators(true);
+
+        send(&mut menu, &history, "", MenuEvent::Activate(false));
+        assert_eq!(rows(&menu)[..2], ["— Today —", "0: d"]);
+
+        let now = Local::now();
+        let last_week = now - chrono::Duration::days(8);
+        menu.times = vec![
+            Some(now),
+            Some(now),
+            Some(now - chrono::Duration::days(1)),
+            Some(last_week),
+        ];
+        let last_week = format!("— {} —", last_week.format("%A, %Y-%m-%d"));
+        assert_eq!(
+            rows(&menu)[..7],
+            [
+                "— Today —",
+                "0: d",
+                "1: c",
+                "— Yesterday —",
+                "2: b",
+                last_week.as_str(),
+                "3: a"
+            ]
+        );
+        assert_eq!(menu.menu_required_lines(80), 8);
+    }
+
+    #[test]
+    fn repeated_entries_keep_their_own_day_test() {
+        let tmp = tempfile::tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        let now = Local::now();
+        let last_week = now - chrono::Duration::days(8);
+        std::fs::write(
+            &histfile,
+            format!(
+                "#reedline-history-format:{}\n{}\tls\n{}\tcd\n{}\tls\n",
+                crate::HISTORY_FORMAT_VERSION,
+                last_week.timestamp(),
+                last_week.timestamp(),
+                now.timestamp()
+            ),
+        )
+        .unwrap();
+        let history = FileBackedHistory::with_file(10, histfile).unwrap();
+        let mut menu = HistoryMenu::default().with_day_separators(true);
+
+        send(&mut menu, &history, "ls", MenuEvent::Activate(false));
+
+        let last_week = format!("— {} —", last_week.format("%A, %Y-%m-%d"));
+        assert_eq!(
+            rows(&menu)[..4],
+            ["— Today —", "0: ls", last_week.as_str(), "1: ls"]
+        );
+
+        tmp.close().unwrap();
+    }
+
+    #[test]
+    fn preview_shows_full_multiline_entry_test() {
+        let history = history([
+            "for x in 1..3 {\n    if x > 1 {\n        print x\n    }\n}",
+            "ls",
+        ]);
+        let mut menu = HistoryMenu::default()
+            .with_max_entry_lines(1)
+            .with_preview_lines(Some(4));
+
+        send(&mut menu, &history, "", MenuEvent::Activate(false));
+        assert_eq!(rows(&menu).len(), 4);
+
+        send(&mut menu, &history, "", MenuEvent::NextElement);
+        assert_eq!(
+            rows(&menu)[4..],
+            [
+                "Preview:",
+                "for x in 1..3 {",
+                "    if x > 1 {",
+                "        print x",
+                "    }",
+                "..."
+            ]
+        );
+        assert_eq!(menu.menu_required_lines(80), 10);
+    }
+
+    #[test]
+    fn pinned_entries_are_shown_first_test() {
+        let mut history = history(["ls", "cd", "pwd"]);
+        history.pin("ls");
+        let mut menu = HistoryMenu::default();
+
+        send(&mut menu, &history, "", MenuEvent::Activate(false));
+
+        assert_eq!(rows(&menu)[..3], ["0: * ls", "1: pwd", "2: cd"]);
+    }
+
+    #[test]
+    fn matched_text_is_highlighted_test() {
+        let history = history(["cat log", "ls", "git log --oneline"]);
+        let text_style = Color::White.normal();
+        let selected_style = Color::Green.normal();
+        let match_style = Color::Red.normal();
+        let mut menu = HistoryMenu::default()
+            .with_text_style(text_style)
+            .with_selected_text_style(selected_style)
+            .with_match_text_style(match_style);
+
+        send(&mut menu, &history, "log", MenuEvent::Activate(false));
+
+        let rows = menu.menu_rows(20);
+        assert_eq!(
+            rows[0].content,
+            vec![
+                (Style::default(), "0: ".into()),
+                (selected_style, "git ".into()),
+                (selected_style.underline(), "log".into()),
+                (selected_style, " --oneline".into()),
+            ]
+        );
+        assert_eq!(rows[0].selected, Some(1));
+        assert_eq!(
+            rows[1].content,
+            vec![
+                (Style::default(), "1: ".into()),
+                (text_style, "cat ".into()),
+                (match_style, "log".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn time_range_is_cycled_test() {
+        let history = history(["ls", "cd"]);
+        let mut menu = HistoryMenu::default();
+
+        send(&mut menu, &history, "", MenuEvent::Activate(false));
+        assert_eq!(rows(&menu)[2], "Page 1: records 0 - 1  total: 2  ");
+
+        // The entries were added just now
+        send(
+            &mut menu,
+            &history,
+            "",
+            MenuEvent::Custom(NEXT_TIME_RANGE.to_string()),
+        );
+        assert_eq!(rows(&menu)[..2], ["0: cd", "1: ls"]);
+        assert_eq!(rows(&menu)[2], "Page 1: records 0 - 1  total: 2    [TODAY]");
+
+        send(
+            &mut menu,
+            &history,
+            "",
+            MenuEvent::Custom(NEXT_TIME_RANGE.to_string()),
+        );
+        assert_eq!(
+            rows(&menu)[2],
+            "Page 1: records 0 - 1  total: 2    [THIS WEEK]"
+        );
+
+        send(&mut menu, &history, "", MenuEvent::Deactivate);
+        send(&mut menu, &history, "", MenuEvent::Activate(false));
+        assert_eq!(rows(&menu)[2], "Page 1: records 0 - 1  total: 2  ");
+    }
+
+    /// History that answers the queries after being polled a number of times
+    struct DelayedHistory {
+        history: FileBackedHistory,
+        polls: Cell<usize>,
+    }
+
+    impl History for DelayedHistory {
+        fn append(&mut self, entry: &str) {
+            self.history.append(entry)
+        }
+
+        fn iter_chronologic(&self) -> Iter<'_, String> {
+            self.history.iter_chronologic()
+        }
+
+        fn back(&mut self) {
+            self.history.back()
+        }
+
+        fn forward(&mut self) {
+            self.history.forward()
+        }
+
+        fn string_at_cursor(&self) -> Option<String> {
+            self.history.string_at_cursor()
+        }
+
+        fn set_navigation(&mut self, navigation: HistoryNavigationQuery) {
+            self.history.set_navigation(navigation)
+        }
+
+        fn get_navigation(&self) -> HistoryNavigationQuery {
+            self.history.get_navigation()
+        }
+
+        fn query_entries(&self, search: &str) -> Vec<String> {
+            self.history.query_entries(search)
+        }
+
+        fn poll_query_entries(&self, search: &str) -> Poll<Vec<(String, Option<DateTime<Local>>)>> {
+            match self.polls.get() {
+                0 => Poll::Ready(self.query_entries_between(search, TimeRange::default())),
+                polls => {
+                    self.polls.set(polls - 1);
+                    Poll::Pending
+                }
+            }
+        }
+
+        fn max_values(&self) -> usize {
+            self.history.max_values()
+        }
+    }
+
+    #[test]
+    fn delayed_query_results_test() {
+        let history = DelayedHistory {
+            history: history(["git status", "ls"]),
+            polls: Cell::new(2),
+        };
+        let mut menu = HistoryMenu::default();
+
+        send(&mut menu, &history, "git", MenuEvent::Activate(false));
+        assert!(menu.is_pending());
+        assert_eq!(rows(&menu)[0], "SEARCHING...");
+
+        send(&mut menu, &history, "git", MenuEvent::Edit(false));
+        assert!(menu.is_pending());
+
+        send(&mut menu, &history, "git", MenuEvent::Edit(false));
+        assert!(!menu.is_pending());
+        assert_eq!(selected(&menu), Some("git status".into()));
+    }
 }
@@ -1,10 +1,14 @@
+mod border;
 mod completion_menu;
 mod history_menu;
 
-use crate::{painter::Painter, Completer, History, LineBuffer, Span};
-pub use completion_menu::CompletionMenu;
+use crate::{painter::Painter, Completer, History, KeyCombination, LineBuffer, Span};
+pub use border::MenuBorder;
+pub use completion_menu::{ColumnWidth, CompletionMenu};
 pub use history_menu::HistoryMenu;
+pub(crate) use history_menu::{CONFIRM_DELETION, DELETION_ANSWERED, NEXT_TIME_RANGE};
 use nu_ansi_term::{Color, Style};
+use std::borrow::Cow;
 
 /// Struct to store the menu style
 struct MenuTextStyle {
@@ -21,7 +25,61 @@ impl Default for MenuTextStyle {
     }
 }
 
+/// A single line of a menu. The painter takes care of coloring the row and
+/// fitting it to the screen width
+///
+/// The parts can borrow the text of the menu, so painting a menu doesn't copy its values
+#[derive(Default)]
+pub struct MenuRow<'a> {
+    /// Styled parts that form the row
+    pub content: Vec<(Style, Cow<'a, str>)>,
+    /// Index of the part in `content` that holds the selected value, if the row has it
+    pub selected: Option<usize>,
+}
+
+impl<'a> MenuRow<'a> {
+    /// Add a styled part to the row
+    pub fn push(&mut self, style: Style, text: impl Into<Cow<'a, str>>) {
+        self.content.push((style, text.into()));
+    }
+
+    /// Add the styled part holding the selected value to the row
+    pub fn push_selected(&mut self, style: Style, text: impl Into<Cow<'a, str>>) {
+        self.selected = Some(self.content.len());
+        self.content.push((style, text.into()));
+    }
+
+    /// Add blank space of the given width to the row, unstyled
+    pub fn push_space(&mut self, width: usize) {
+        // Most gaps between the columns fit in the spaces kept here
+        const SPACES: &str = "                                                                ";
+        let space = match SPACES.get(..width) {
+            Some(space) => Cow::Borrowed(space),
+            None => Cow::Owned(" ".repeat(width)),
+        };
+        self.content.push((Style::default(), space));
+    }
+
+    /// The text of the row without its styles
+    pub fn raw_string(&self) -> String {
+        self.content.iter().map(|(_, text)| text.as_ref()).collect()
+    }
+}
+
+/// Keys of a menu that select its next and previous value while it is open, set with
+/// [`Reedline::set_menu_navigation`]
+///
+/// [`Reedline::set_menu_navigation`]: crate::Reedline::set_menu_navigation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MenuNavigation {
+    /// Selects the next value
+    pub next: Option<KeyCombination>,
+    /// Selects the previous value
+    pub previous: Option<KeyCombination>,
+}
+
 /// Defines all possible events that could happen with a menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MenuEvent {
     /// Activation event for the menu. When the bool is true it means that the values
     /// have already being updated. This is true when the option `quick_completions` is true
@@ -47,6 +105,14 @@ pub enum MenuEvent {
     NextPage,
     /// Move to previous page
     PreviousPage,
+    /// Selecting the first element in the menu
+    MoveToStart,
+    /// Selecting the last element in the menu
+    MoveToEnd,
+    /// A command for a menu of its own, like showing a preview of the selected value.
+    /// The history menu also gets the time range and deletion commands of reedline
+    /// this way, other commands are ignored by the menus of reedline
+    Custom(String),
 }
 
 /// Trait that defines how a menu will be printed by the painter
@@ -97,12 +163,96 @@ pub trait Menu: Send {
     /// wrap the terminal or if entries have multiple lines
     fn menu_required_lines(&self, terminal_columns: u16) -> u16;
 
-    /// Creates the menu representation as a string which will be painted by the painter
-    fn menu_string(&self, available_lines: u16, use_ansi_coloring: bool) -> String;
+    /// Creates the rows of the menu that will be painted by the painter
+    fn menu_rows(&self, available_lines: u16) -> Vec<MenuRow<'_>>;
 
     /// Minimum rows that should be displayed by the menu
     fn min_rows(&self) -> u16;
 
+    /// The menu is painted on the alternate screen, where it can take the full height
+    /// of the terminal. The screen with the prompt is restored when the menu closes
+    fn alternate_screen(&self) -> bool {
+        false
+    }
+
+    /// Border drawn around the menu. The painter adds the lines taken by the
+    /// border to the required and minimum lines of the menu
+    fn border(&self) -> Option<&MenuBorder> {
+        None
+    }
+
     /// Gets cached values from menu that will be displayed
     fn get_values(&self) -> &[(Span, String)];
+
+    /// The value selected in the menu
+    fn selected_value(&self) -> Option<(Span, String)> {
+        None
+    }
+
+    /// Whether the menu stays open after the selected value is inserted, to complete
+    /// what comes after it, as the completer tells with [`Completer::keeps_menu_open`]
+    fn keeps_open(&self, _completer: &dyn Completer) -> bool {
+        false
+    }
+
+    /// The text the values of the menu are filtered by. Its occurrences in the buffer
+    /// are highlighted while the menu is open
+    fn search_term(&self) -> Option<&str> {
+        None
+    }
+
+    /// The menu is still waiting for its values. The engine sends an
+    /// [`MenuEvent::Edit`] event after a short wait to collect them again
+    fn is_pending(&self) -> bool {
+        false
+    }
+
+    /// The total number of values in the menu. Menus that only cache the values
+    /// they can show report here how many values exist in total
+    fn total_values(&self) -> usize {
+        self.get_values().len()
+    }
+
+    /// Replaces the text completed by the values of the menu with the longest prefix
+    /// they share, and collects the values again for the longer text, like the menu
+    /// completion of zsh. Returns false if the prefix adds nothing to the text, so the
+    /// next value can be selected instead
+    fn insert_common_prefix(&mut self, line_buffer: &mut LineBuffer) -> bool {
+        let values = self.get_values();
+        let (span, first) = match values.first() {
+            Some(value) => value,
+            None => return false,
+        };
+        // Values that complete different parts of the buffer have nothing in common
+        if values.iter().any(|(other, _)| other != span) {
+            return false;
+        }
+
+        let prefix = values.iter().fold(first.as_str(), |prefix, (_, value)| {
+            common_prefix(prefix, value)
+        });
+        let typed = line_buffer.get_buffer().get(span.start..span.end);
+        if typed.map_or(true, |typed| {
+            prefix.chars().count() <= typed.chars().count()
+        }) {
+            return false;
+        }
+
+        let (span, prefix) = (*span, prefix.to_string());
+        line_buffer.replace(span.start..span.end, &prefix);
+        line_buffer.set_insertion_point(span.start + prefix.len());
+        self.menu_event(MenuEvent::Edit(false));
+        true
+    }
+}
+
+/// The longest prefix of `first` that `second` starts with
+fn common_prefix<'a>(first: &'a str, second: &str) -> &'a str {
+    let len = first
+        .chars()
+        .zip(second.chars())
+        .take_while(|(first, second)| first == second)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    &first[..len]
 }
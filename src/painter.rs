@@ -1,15 +1,25 @@
 use {
     crate::{
-        menu::Menu, prompt::PromptEditMode, styled_text::strip_ansi, Prompt, PromptHistorySearch,
+        menu::{Menu, MenuBorder, MenuRow},
+        prompt::PromptEditMode,
+        styled_text::{strip_ansi, styled_tail},
+        terminal_backend::TerminalBackend,
+        theme::Theme,
+        Prompt, PromptHistorySearch, StatusPosition,
     },
     crossterm::{
         cursor::{self, MoveTo, RestorePosition, SavePosition},
-        style::{Print, ResetColor, SetForegroundColor},
-        terminal::{self, Clear, ClearType, ScrollUp},
+        style::{Attribute, Print, ResetColor, SetAttribute, SetForegroundColor},
+        terminal::{
+            Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, ScrollUp, SetTitle,
+        },
         QueueableCommand, Result,
     },
+    nu_ansi_term::Style,
     std::borrow::Cow,
+    std::fmt::Write as _,
     std::io::Write,
+    unicode_segmentation::UnicodeSegmentation,
     unicode_width::UnicodeWidthStr,
 };
 
@@ -20,6 +30,7 @@ pub struct PromptLines<'prompt> {
     before_cursor: Cow<'prompt, str>,
     after_cursor: Cow<'prompt, str>,
     hint: Cow<'prompt, str>,
+    status: Cow<'prompt, str>,
 }
 
 impl<'prompt> PromptLines<'prompt> {
@@ -53,6 +64,26 @@ impl<'prompt> PromptLines<'prompt> {
             before_cursor,
             after_cursor,
             hint,
+            status: Cow::Borrowed(""),
+        }
+    }
+
+    /// Adds the status lines shown below the buffer
+    pub fn with_status(mut self, status: &'prompt str) -> Self {
+        self.status = coerce_crlf(status);
+        self
+    }
+
+    /// The same lines without styles, for terminals that don't support ANSI escape sequences
+    fn without_ansi(self) -> Self {
+        Self {
+            prompt_str_left: strip_ansi(&self.prompt_str_left).into(),
+            prompt_str_right: strip_ansi(&self.prompt_str_right).into(),
+            prompt_indicator: strip_ansi(&self.prompt_indicator).into(),
+            before_cursor: strip_ansi(&self.before_cursor).into(),
+            after_cursor: strip_ansi(&self.after_cursor).into(),
+            hint: strip_ansi(&self.hint).into(),
+            status: strip_ansi(&self.status).into(),
         }
     }
 
@@ -61,11 +92,15 @@ impl<'prompt> PromptLines<'prompt> {
     /// The plus 1 is to indicate that there should be at least one line.
     fn required_lines(&self, terminal_columns: u16, menu: Option<&dyn Menu>) -> u16 {
         let input = if menu.is_none() {
-            self.prompt_str_left.to_string()
+            let mut input = self.prompt_str_left.to_string()
                 + &self.prompt_indicator
                 + &self.before_cursor
                 + &self.after_cursor
-                + &self.hint
+                + &self.hint;
+            if !self.status.is_empty() {
+                input = input + "\n" + &self.status;
+            }
+            input
         } else {
             self.prompt_str_left.to_string()
                 + &self.prompt_indicator
@@ -76,12 +111,24 @@ impl<'prompt> PromptLines<'prompt> {
         let lines = estimate_required_lines(&input, terminal_columns);
 
         if let Some(menu) = menu {
-            lines as u16 + menu.menu_required_lines(terminal_columns)
+            lines as u16
+                + menu.menu_required_lines(terminal_columns)
+                + menu_frame_lines(menu)
+                + self.status_lines(terminal_columns)
         } else {
             lines as u16
         }
     }
 
+    /// Lines taken by the status, considering that it may wrap the screen
+    fn status_lines(&self, terminal_columns: u16) -> u16 {
+        if self.status.is_empty() {
+            0
+        } else {
+            estimate_required_lines(&self.status, terminal_columns) as u16
+        }
+    }
+
     /// Estimated distance of the cursor to the prompt.
     /// This considers line wrapping
     fn distance_from_prompt(&self, terminal_columns: u16) -> u16 {
@@ -125,6 +172,13 @@ impl<'prompt> PromptLines<'prompt> {
 }
 
 pub(crate) fn estimate_required_lines(input: &str, screen_width: u16) -> usize {
+    // Stripping the styles once instead of for every line of a large buffer
+    let input = if input.contains('\x1b') {
+        Cow::Owned(strip_ansi(input))
+    } else {
+        Cow::Borrowed(input)
+    };
+
     input.lines().fold(0, |acc, line| {
         let wrap = estimate_single_line_wraps(line, screen_width);
 
@@ -142,7 +196,7 @@ pub(crate) fn estimate_single_line_wraps(line: &str, terminal_columns: u16) -> u
     let terminal_columns: usize = terminal_columns.into();
 
     // integer ceiling rounding division for positive divisors
-    let estimated_line_count = (estimated_width + terminal_columns - 1) / terminal_columns;
+    let estimated_line_count = estimated_width.div_ceil(terminal_columns);
 
     // Any wrapping will add to our overall line count
     estimated_line_count.saturating_sub(1)
@@ -150,7 +204,194 @@ pub(crate) fn estimate_single_line_wraps(line: &str, terminal_columns: u16) -> u
 
 /// Compute the line width for ANSI escaped text
 fn line_width(line: &str) -> usize {
-    strip_ansi(line).width()
+    // Most lines of a large buffer have no styles, they don't need a stripped copy
+    if line.contains('\x1b') {
+        strip_ansi(line).width()
+    } else {
+        line.width()
+    }
+}
+
+/// Plain text of a prompt or buffer part for terminals without ANSI support.
+/// Line breaks are shown as `^J` as the line can't be left
+fn dumb_text(text: &str) -> String {
+    strip_ansi(text).replace("\r\n", "^J").replace('\n', "^J")
+}
+
+/// Renders the rows of a menu, one per line, cut at the screen width
+///
+/// The rows are written into `buffer`, which is cleared first, so the same allocation
+/// can be reused on every repaint. When the menu has a border the rows are framed
+/// by it and padded to the width of the widest row.
+fn render_menu_rows(
+    buffer: &mut String,
+    rows: &[MenuRow<'_>],
+    border: Option<&MenuBorder>,
+    screen_width: u16,
+    use_ansi_coloring: bool,
+) {
+    buffer.clear();
+
+    let border = match border {
+        Some(border) => border,
+        None => {
+            for (row_number, row) in rows.iter().enumerate() {
+                if row_number != 0 {
+                    buffer.push_str("\r\n");
+                }
+                render_menu_row(buffer, row, screen_width as usize, use_ansi_coloring);
+            }
+            return;
+        }
+    };
+
+    let padding = border.padding as usize;
+    let title_width = border.title.as_ref().map_or(0, |title| title.width() + 3);
+    let content_width = rows
+        .iter()
+        .map(|row| menu_row_width(row, use_ansi_coloring))
+        .max()
+        .unwrap_or(0)
+        .max(title_width.saturating_sub(2 * padding));
+    let inner_width =
+        content_width.min((screen_width.saturating_sub(border.horizontal_space())) as usize);
+    let edge_width = inner_width + 2 * padding;
+
+    // Top edge with the title
+    let mut top = String::new();
+    top.push(border.top_left);
+    if let Some(title) = &border.title {
+        let title = truncate_to_width(title, edge_width.saturating_sub(3));
+        if !title.is_empty() {
+            top.push(border.horizontal);
+            top.push(' ');
+            top.push_str(title);
+            top.push(' ');
+        }
+    }
+    let top_width = top.width().saturating_sub(1);
+    top.extend(std::iter::repeat(border.horizontal).take(edge_width.saturating_sub(top_width)));
+    top.push(border.top_right);
+    push_border_part(buffer, border, &top, use_ansi_coloring);
+
+    for row in rows {
+        buffer.push_str("\r\n");
+        push_border_part(
+            buffer,
+            border,
+            border.vertical.encode_utf8(&mut [0; 4]),
+            use_ansi_coloring,
+        );
+        buffer.extend(std::iter::repeat(' ').take(padding));
+        let used_width = render_menu_row(buffer, row, inner_width, use_ansi_coloring);
+        buffer
+            .extend(std::iter::repeat(' ').take(inner_width.saturating_sub(used_width) + padding));
+        push_border_part(
+            buffer,
+            border,
+            border.vertical.encode_utf8(&mut [0; 4]),
+            use_ansi_coloring,
+        );
+    }
+
+    // Bottom edge
+    buffer.push_str("\r\n");
+    let mut bottom = String::new();
+    bottom.push(border.bottom_left);
+    bottom.extend(std::iter::repeat(border.horizontal).take(edge_width));
+    bottom.push(border.bottom_right);
+    push_border_part(buffer, border, &bottom, use_ansi_coloring);
+}
+
+fn push_border_part(buffer: &mut String, border: &MenuBorder, part: &str, use_ansi_coloring: bool) {
+    if use_ansi_coloring {
+        // Writing to a String can't fail
+        let _ = write!(buffer, "{}", border.style.paint(part));
+    } else {
+        buffer.push_str(part);
+    }
+}
+
+/// Width the row would take on the screen without being cut
+fn menu_row_width(row: &MenuRow<'_>, use_ansi_coloring: bool) -> usize {
+    let marker = usize::from(!use_ansi_coloring && row.selected.is_some());
+    row.content
+        .iter()
+        .map(|(_, text)| text.width())
+        .sum::<usize>()
+        + marker
+}
+
+/// Renders a single row of a menu cut at the given width and returns the width it takes
+///
+/// Escape codes are only written when the style changes between parts.
+/// Without ansi coloring the selected value is marked with `>`
+fn render_menu_row(
+    buffer: &mut String,
+    row: &MenuRow<'_>,
+    width: usize,
+    use_ansi_coloring: bool,
+) -> usize {
+    let mut remaining_width = width;
+    let mut current_style = Style::default();
+    for (index, (style, text)) in row.content.iter().enumerate() {
+        if remaining_width == 0 {
+            break;
+        }
+
+        if use_ansi_coloring {
+            // Writing to a String can't fail
+            let _ = write!(buffer, "{}", current_style.infix(*style));
+            current_style = *style;
+        } else if row.selected == Some(index) {
+            buffer.push('>');
+            remaining_width -= 1;
+        }
+
+        let text = truncate_to_width(text, remaining_width);
+        remaining_width -= text.width();
+        buffer.push_str(text);
+    }
+
+    if use_ansi_coloring {
+        let _ = write!(buffer, "{}", current_style.suffix());
+    }
+
+    width - remaining_width
+}
+
+/// Returns `text` if it fits in `width` columns, otherwise the longest prefix
+/// that fits together with a trailing `…`
+pub(crate) fn truncate_with_ellipsis(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        Cow::Borrowed(text)
+    } else {
+        let prefix = truncate_to_width(text, width.saturating_sub(1));
+        Cow::Owned(format!("{}…", prefix))
+    }
+}
+
+/// Lines taken by the border of the menu, if it has one
+fn menu_frame_lines(menu: &dyn Menu) -> u16 {
+    menu.border().map_or(0, |border| border.vertical_space())
+}
+
+/// Returns the longest prefix of `text` that fits in `width` columns
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> &str {
+    // The display width of a string is never larger than its length in bytes
+    if text.len() <= width {
+        return text;
+    }
+
+    let mut used_width = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used_width += grapheme.width();
+        if used_width > width {
+            return &text[..index];
+        }
+    }
+
+    text
 }
 
 // Returns a string that skips N number of lines with the next offset of lines
@@ -181,67 +422,227 @@ fn skip_buffer_lines(string: &str, skip: usize, offset: Option<usize>) -> &str {
     string[index..limit].trim_end_matches('\n')
 }
 
-fn coerce_crlf(input: &str) -> Cow<str> {
-    let mut result = Cow::Borrowed(input);
+fn coerce_crlf(input: &str) -> Cow<'_, str> {
+    // Only allocates if a newline needs the carriage return
+    let mut result: Option<String> = None;
     let mut cursor: usize = 0;
     for (idx, _) in input.match_indices('\n') {
         if !(idx > 0 && input.as_bytes()[idx - 1] == b'\r') {
-            if let Cow::Borrowed(_) = result {
-                // Best case 1 allocation, worst case 2 allocations
-                let mut owned = String::with_capacity(input.len() + 1);
-                // Optimization to avoid the `AddAssign for Cow<str>`
-                // optimization for `Cow<str>.is_empty` that would replace the
-                // preallocation
-                owned.push_str(&input[cursor..idx]);
-                result = Cow::Owned(owned);
-            } else {
-                result += &input[cursor..idx];
-            }
-            result += "\r\n";
+            let owned = result.get_or_insert_with(|| String::with_capacity(input.len() + 1));
+            owned.push_str(&input[cursor..idx]);
+            owned.push_str("\r\n");
             // Advance beyond the matched LF char (single byte)
             cursor = idx + 1;
         }
     }
-    if let Cow::Owned(_) = result {
-        result += &input[cursor..input.len()]
+
+    match result {
+        Some(mut owned) => {
+            owned.push_str(&input[cursor..]);
+            Cow::Owned(owned)
+        }
+        None => Cow::Borrowed(input),
     }
-    result
 }
 
-/// the type used by crossterm operations
-pub type W = std::io::BufWriter<std::io::Stderr>;
+/// Where the prompt of the last read was painted, for hosts that keep drawing aligned
+/// with it. Rows and columns start at 0
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use reedline::{DefaultPrompt, Reedline};
+///
+/// let mut line_editor = Reedline::create()?;
+/// line_editor.read_line(&DefaultPrompt::default())?;
+/// if let Some(layout) = line_editor.last_prompt_layout() {
+///     // The output of the command starts on the row below the prompt
+///     assert_eq!(layout.cursor.1, layout.start_row + layout.lines);
+/// }
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptLayout {
+    /// Row of the first line of the prompt
+    pub start_row: u16,
+    /// Number of rows taken by the prompt and the buffer
+    pub lines: u16,
+    /// Column and row where the cursor was left when the read ended
+    pub cursor: (u16, u16),
+}
 
 pub struct Painter {
-    // Stdout
-    stdout: W,
+    // Where the events are read from and the output is painted
+    backend: Box<dyn TerminalBackend>,
     prompt_start_row: u16,
     terminal_size: (u16, u16),
     last_required_lines: u16,
     large_buffer: bool,
     debug_mode: bool,
+    // The terminal can't move the cursor, only the current line is repainted
+    dumb: bool,
+    // The terminal doesn't understand escape sequences in the text
+    ansi: bool,
+    dumb_line_width: usize,
+    // The prompt is painted in reverse video for the visual bell
+    prompt_flash: bool,
+    // Collects a repaint to write it at once, skipping it if it didn't change
+    batched_output: bool,
+    frame: Vec<u8>,
+    last_frame: Vec<u8>,
+    // Reused between repaints to avoid allocating the menu on every key press
+    menu_buffer: String,
+    status_position: StatusPosition,
+    // The row of the prompt on the main screen while a menu is painted on the alternate screen
+    main_screen_row: Option<u16>,
+    // The last line of the buffer stays on the last row of the screen
+    prompt_at_bottom: bool,
+    // Typing at the end of the buffer only prints the typed text, for screen readers
+    screen_reader: bool,
+    // The prompt and the text of the buffer last painted without anything after the cursor
+    last_painted: Option<(String, String)>,
+    // Styles of the prompt and the menus in place of their own
+    theme: Option<Theme>,
+    // Where the prompt was when the last read ended
+    last_layout: Option<PromptLayout>,
 }
 
 impl Painter {
-    pub fn new(stdout: W) -> Self {
+    pub fn new(backend: Box<dyn TerminalBackend>) -> Self {
         Painter {
-            stdout,
+            backend,
             prompt_start_row: 0,
             terminal_size: (0, 0),
             last_required_lines: 0,
             large_buffer: false,
             debug_mode: false,
+            dumb: false,
+            ansi: true,
+            dumb_line_width: 0,
+            prompt_flash: false,
+            batched_output: false,
+            frame: Vec::new(),
+            last_frame: Vec::new(),
+            menu_buffer: String::new(),
+            status_position: StatusPosition::default(),
+            main_screen_row: None,
+            prompt_at_bottom: false,
+            screen_reader: false,
+            last_painted: None,
+            theme: None,
+            last_layout: None,
         }
     }
 
-    pub fn new_with_debug(stdout: W) -> Self {
-        Painter {
-            stdout,
-            prompt_start_row: 0,
-            terminal_size: (0, 0),
-            last_required_lines: 0,
-            large_buffer: false,
-            debug_mode: true,
+    pub(crate) fn set_debug_mode(&mut self, debug_mode: bool) {
+        self.debug_mode = debug_mode;
+    }
+
+    pub(crate) fn set_prompt_flash(&mut self, prompt_flash: bool) {
+        self.prompt_flash = prompt_flash;
+    }
+
+    pub(crate) fn set_batched_output(&mut self, batched_output: bool) {
+        self.batched_output = batched_output;
+        self.invalidate_frame();
+    }
+
+    pub(crate) fn set_status_position(&mut self, status_position: StatusPosition) {
+        self.status_position = status_position;
+    }
+
+    pub(crate) fn set_prompt_at_bottom(&mut self, prompt_at_bottom: bool) {
+        self.prompt_at_bottom = prompt_at_bottom;
+    }
+
+    pub(crate) fn set_screen_reader(&mut self, screen_reader: bool) {
+        self.screen_reader = screen_reader;
+        self.invalidate_frame();
+    }
+
+    pub(crate) fn set_theme(&mut self, theme: Option<Theme>) {
+        self.theme = theme;
+    }
+
+    pub(crate) fn set_backend(&mut self, backend: Box<dyn TerminalBackend>) {
+        self.backend = backend;
+    }
+
+    pub(crate) fn backend(&mut self) -> &mut dyn TerminalBackend {
+        self.backend.as_mut()
+    }
+
+    /// Where a repaint is written, the frame when batching the output
+    fn writer(&mut self) -> &mut dyn Write {
+        // The console API of legacy Windows consoles is called right away by crossterm,
+        // so it can't be collected
+        if self.batched_output && self.ansi {
+            &mut self.frame
+        } else {
+            &mut self.backend
+        }
+    }
+
+    /// Ends a repaint. A batched frame is written with a single write, unless it is
+    /// the same as the last one, that is still on the screen
+    fn flush_frame(&mut self) -> Result<()> {
+        if !self.frame.is_empty() {
+            if self.frame != self.last_frame {
+                self.backend.write_all(&self.frame)?;
+            } else {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(bytes = self.frame.len(), "skipped unchanged frame");
+            }
+            std::mem::swap(&mut self.frame, &mut self.last_frame);
+            self.frame.clear();
+        }
+
+        self.backend.flush()
+    }
+
+    /// Forgets the last frame after writing something else, so the next repaint is
+    /// written even if it is the same
+    fn invalidate_frame(&mut self) {
+        self.last_frame.clear();
+        self.last_painted = None;
+    }
+
+    /// Prints only the text typed at the end of the buffer since the last repaint, if
+    /// nothing else changed. Screen readers then read the typed text instead of the
+    /// whole line again. Returns false if a full repaint is needed
+    fn paint_appended(&mut self, painted: &(String, String), lines: &PromptLines) -> Result<bool> {
+        let painted_len = match &self.last_painted {
+            Some((prompt, before_cursor))
+                if *prompt == painted.0 && painted.1.starts_with(before_cursor.as_str()) =>
+            {
+                before_cursor.len()
+            }
+            _ => return Ok(false),
+        };
+        if painted.1[painted_len..].contains('\n') {
+            return Ok(false);
+        }
+        // A wrapping line needs the scrolling of the full repaint
+        if lines.required_lines(self.screen_width(), None) != self.last_required_lines {
+            return Ok(false);
+        }
+
+        let appended = if self.ansi {
+            styled_tail(&lines.before_cursor, painted_len)
+        } else {
+            painted.1[painted_len..].to_string()
+        };
+        if !appended.is_empty() {
+            self.writer().queue(Print(appended))?.queue(SavePosition)?;
         }
+        self.last_painted = Some(painted.clone());
+        self.flush_frame()?;
+        Ok(true)
+    }
+
+    /// Whether the terminal lacks ANSI support, so only the current line can be painted
+    pub(crate) fn is_dumb(&self) -> bool {
+        self.dumb
     }
 
     pub(crate) fn screen_height(&self) -> u16 {
@@ -270,9 +671,21 @@ impl Painter {
     /// [`Painter::handle_resize()`] instead
     pub(crate) fn initialize_prompt_position(&mut self) -> Result<()> {
         // Update the terminal size
-        self.terminal_size = terminal::size()?;
+        self.terminal_size = self.backend.size()?;
+        self.invalidate_frame();
+
+        // The position can't be asked to a dumb terminal, the prompt starts on
+        // the current line
+        self.dumb = !self.backend.supports_cursor_movement();
+        self.ansi = self.backend.supports_ansi();
+        if self.dumb {
+            self.prompt_start_row = 0;
+            self.dumb_line_width = 0;
+            return Ok(());
+        }
+
         // Cursor positions are 0 based here.
-        let (column, row) = cursor::position()?;
+        let (column, row) = self.backend.cursor_position()?;
         // Assumption: if the cursor is not on the zeroth column,
         // there is content we want to leave intact, thus advance to the next row
         let new_row = if column > 0 { row + 1 } else { row };
@@ -299,6 +712,7 @@ impl Painter {
     ///
     /// Note. The ScrollUp operation in crossterm deletes lines from the top of
     /// the screen.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn repaint_buffer(
         &mut self,
         prompt: &dyn Prompt,
@@ -306,7 +720,61 @@ impl Painter {
         menu: Option<&dyn Menu>,
         use_ansi_coloring: bool,
     ) -> Result<()> {
-        self.stdout.queue(cursor::Hide)?;
+        if self.dumb {
+            return self.repaint_dumb(&lines);
+        }
+
+        // Only a buffer with nothing painted after the cursor can be appended to
+        let painted = (self.screen_reader
+            && menu.is_none()
+            && !self.prompt_flash
+            && strip_ansi(&lines.after_cursor).is_empty()
+            && strip_ansi(&lines.hint).is_empty()
+            && lines.status.is_empty())
+        .then(|| {
+            let prompt = format!(
+                "{}{}{}",
+                lines.prompt_str_left, lines.prompt_indicator, lines.prompt_str_right
+            );
+            (prompt, strip_ansi(&lines.before_cursor))
+        });
+        if let Some(painted) = &painted {
+            if self.paint_appended(painted, &lines)? {
+                return Ok(());
+            }
+        }
+
+        // Without ANSI support styles would be printed as garbage
+        let use_ansi_coloring = use_ansi_coloring && self.ansi;
+        let lines = if self.ansi {
+            lines
+        } else {
+            lines.without_ansi()
+        };
+
+        // Menus on the alternate screen have the full height of the terminal, with the
+        // prompt on the first line
+        let alternate_screen = self.ansi && menu.is_some_and(|menu| menu.alternate_screen());
+        match (alternate_screen, self.main_screen_row) {
+            (true, None) => {
+                self.main_screen_row = Some(self.prompt_start_row);
+                self.prompt_start_row = 0;
+                self.writer().queue(EnterAlternateScreen)?;
+            }
+            (false, Some(row)) => {
+                self.main_screen_row = None;
+                self.prompt_start_row = row;
+                self.writer().queue(LeaveAlternateScreen)?;
+            }
+            _ => {}
+        }
+
+        // A batched frame arrives at once, without the flickering that hiding the
+        // cursor avoids. Screen readers follow the cursor, it stays visible for them
+        let hide_cursor = !self.batched_output && !self.screen_reader;
+        if hide_cursor {
+            self.writer().queue(cursor::Hide)?;
+        }
 
         let screen_width = self.screen_width();
         let screen_height = self.screen_height();
@@ -317,21 +785,42 @@ impl Painter {
 
         // Marking the painter state as larger buffer to avoid animations
         self.large_buffer = required_lines >= screen_height;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(required_lines, large_buffer = self.large_buffer, "painting");
+
+        // Rows painted before above the new start of the prompt are cleared as well
+        let mut clear_from = self.prompt_start_row;
 
         // Moving the start position of the cursor based on the size of the required lines
         if self.large_buffer {
             self.prompt_start_row = 0;
+        } else if self.prompt_at_bottom {
+            // A growing buffer scrolls the output above it up, a shrinking one
+            // leaves empty rows above the prompt
+            let bottom_row = screen_height - required_lines;
+            if bottom_row < self.prompt_start_row {
+                let scroll = self.prompt_start_row - bottom_row;
+                self.writer().queue(ScrollUp(scroll))?;
+            } else {
+                clear_from = clear_from.min(bottom_row);
+            }
+            self.prompt_start_row = bottom_row;
         } else if required_lines >= remaining_lines {
             let extra = required_lines.saturating_sub(remaining_lines);
-            self.stdout.queue(ScrollUp(extra))?;
+            self.writer().queue(ScrollUp(extra))?;
             self.prompt_start_row = self.prompt_start_row.saturating_sub(extra);
         }
+        let clear_from = clear_from.min(self.prompt_start_row);
 
         // Moving the cursor to the start of the prompt
         // from this position everything will be printed
-        self.stdout
-            .queue(cursor::MoveTo(0, self.prompt_start_row))?
+        let prompt_start_row = self.prompt_start_row;
+        self.writer()
+            .queue(cursor::MoveTo(0, clear_from))?
             .queue(Clear(ClearType::FromCursorDown))?;
+        if clear_from != prompt_start_row {
+            self.writer().queue(cursor::MoveTo(0, prompt_start_row))?;
+        }
 
         if self.large_buffer {
             self.print_large_buffer(prompt, &lines, menu, use_ansi_coloring)?
@@ -349,26 +838,70 @@ impl Painter {
             let prompt_lines = lines.prompt_lines_with_wrap(screen_width);
             let prompt_length = lines.prompt_str_left.len() + lines.prompt_indicator.len();
             let estimated_prompt = estimate_single_line_wraps(&lines.prompt_str_left, screen_width);
+            let prompt_start_row = self.prompt_start_row;
 
-            self.stdout
+            self.writer()
                 .queue(Print(format!(" [h{}:", screen_height)))?
                 .queue(Print(format!("w{}] ", screen_width)))?
-                .queue(Print(format!("y:{} ", self.prompt_start_row)))?
+                .queue(Print(format!("y:{} ", prompt_start_row)))?
                 .queue(Print(format!("rm:{} ", remaining_lines)))?
                 .queue(Print(format!("re:{} ", required_lines)))?
                 .queue(Print(format!("di:{} ", cursor_distance)))?
                 .queue(Print(format!("pl:{} ", prompt_lines)))?
                 .queue(Print(format!("pr:{} ", prompt_length)))?
                 .queue(Print(format!("wr:{} ", estimated_prompt)))?
-                .queue(Print(format!("ls:{} ", self.last_required_lines)))?;
+                .queue(Print(format!("ls:{} ", required_lines)))?;
         }
 
-        self.stdout.queue(RestorePosition)?.queue(cursor::Show)?;
+        self.writer().queue(RestorePosition)?;
+        if hide_cursor {
+            self.writer().queue(cursor::Show)?;
+        }
 
-        self.stdout.flush()
+        self.flush_frame()?;
+        self.last_painted = painted;
+        Ok(())
+    }
+
+    /// Repaints the line for terminals without ANSI support, that only understand
+    /// carriage return and backspace. Menus aren't shown and the hint is plain text
+    /// after the buffer
+    fn repaint_dumb(&mut self, lines: &PromptLines) -> Result<()> {
+        let prompt_left = dumb_text(&lines.prompt_str_left);
+        // Only the last line of the prompt is repainted
+        let prompt_left = prompt_left.rsplit("^J").next().unwrap_or_default();
+        let before_cursor = format!(
+            "{}{}{}",
+            prompt_left,
+            dumb_text(&lines.prompt_indicator),
+            dumb_text(&lines.before_cursor)
+        );
+        let after_cursor = format!(
+            "{}{}",
+            dumb_text(&lines.after_cursor),
+            dumb_text(&lines.hint)
+        );
+
+        let width = before_cursor.width() + after_cursor.width();
+        // Overwrite what is left of a longer previous line
+        let padding = self.dumb_line_width.saturating_sub(width);
+        self.dumb_line_width = width;
+
+        self.writer()
+            .queue(Print("\r"))?
+            .queue(Print(before_cursor))?
+            .queue(Print(after_cursor.as_str()))?
+            .queue(Print(" ".repeat(padding)))?
+            .queue(Print("\u{8}".repeat(after_cursor.width() + padding)))?;
+
+        self.flush_frame()
     }
 
     fn print_right_prompt(&mut self, lines: &PromptLines) -> Result<()> {
+        if lines.prompt_str_right.is_empty() {
+            return Ok(());
+        }
+
         let prompt_length_right = line_width(&lines.prompt_str_right);
         let start_position = self
             .screen_width()
@@ -376,9 +909,10 @@ impl Painter {
         let input_width = lines.estimate_first_input_line_width();
 
         if input_width <= start_position {
-            self.stdout
+            let prompt_start_row = self.prompt_start_row;
+            self.writer()
                 .queue(SavePosition)?
-                .queue(cursor::MoveTo(start_position, self.prompt_start_row))?
+                .queue(cursor::MoveTo(start_position, prompt_start_row))?
                 .queue(Print(&coerce_crlf(&lines.prompt_str_right)))?
                 .queue(RestorePosition)?;
         }
@@ -386,6 +920,25 @@ impl Painter {
         Ok(())
     }
 
+    /// Starts the style the prompt is printed with
+    fn queue_prompt_style(&mut self, prompt: &dyn Prompt) -> Result<()> {
+        match self.theme {
+            Some(theme) => {
+                let prefix = theme.prompt.prefix().to_string();
+                self.writer().queue(Print(prefix))?;
+            }
+            None => {
+                self.writer()
+                    .queue(SetForegroundColor(prompt.get_prompt_color()))?;
+            }
+        }
+        if self.prompt_flash {
+            self.writer().queue(SetAttribute(Attribute::Reverse))?;
+        }
+
+        Ok(())
+    }
+
     fn print_menu(
         &mut self,
         menu: &dyn Menu,
@@ -395,21 +948,50 @@ impl Painter {
         let screen_width = self.screen_width();
         let screen_height = self.screen_height();
         let cursor_distance = lines.distance_from_prompt(screen_width);
+        let status_lines = lines.status_lines(screen_width);
 
         // If there is not enough space to print the menu, then the starting
         // drawing point for the menu will overwrite the last rows in the buffer
         let starting_row = if cursor_distance >= screen_height.saturating_sub(1) {
-            screen_height.saturating_sub(menu.min_rows())
+            screen_height.saturating_sub(menu.min_rows() + menu_frame_lines(menu) + status_lines)
         } else {
             self.prompt_start_row + cursor_distance + 1
         };
 
-        let remaining_lines = screen_height.saturating_sub(starting_row);
-        let menu_string = menu.menu_string(remaining_lines, use_ansi_coloring);
-        self.stdout
+        let remaining_lines = screen_height
+            .saturating_sub(starting_row)
+            .saturating_sub(menu_frame_lines(menu))
+            .saturating_sub(status_lines);
+        // It seems that crossterm prefers to have a complete string ready to be printed
+        // rather than looping through the values and printing multiple things
+        // This reduces the flickering when printing the menu
+        let mut rows = menu.menu_rows(remaining_lines);
+        if let Some(theme) = &self.theme {
+            rows.iter_mut().for_each(|row| theme.restyle_menu_row(row));
+        }
+        render_menu_rows(
+            &mut self.menu_buffer,
+            &rows,
+            menu.border(),
+            screen_width,
+            use_ansi_coloring,
+        );
+        let menu_buffer = std::mem::take(&mut self.menu_buffer);
+        self.writer()
             .queue(cursor::MoveTo(0, starting_row))?
-            .queue(Clear(ClearType::FromCursorDown))?
-            .queue(Print(menu_string.trim_end_matches('\n')))?;
+            .queue(Clear(ClearType::FromCursorDown))?;
+        if status_lines > 0 && self.status_position == StatusPosition::AboveMenu {
+            self.writer()
+                .queue(Print(&lines.status))?
+                .queue(Print("\r\n"))?;
+        }
+        self.writer().queue(Print(&menu_buffer))?;
+        if status_lines > 0 && self.status_position == StatusPosition::BelowMenu {
+            self.writer()
+                .queue(Print("\r\n"))?
+                .queue(Print(&lines.status))?;
+        }
+        self.menu_buffer = menu_buffer;
 
         Ok(())
     }
@@ -423,26 +1005,25 @@ impl Painter {
     ) -> Result<()> {
         // print our prompt with color
         if use_ansi_coloring {
-            self.stdout
-                .queue(SetForegroundColor(prompt.get_prompt_color()))?;
+            self.queue_prompt_style(prompt)?;
         }
 
-        self.stdout
+        self.writer()
             .queue(Print(&coerce_crlf(&lines.prompt_str_left)))?;
 
         let prompt_indicator = match menu {
             Some(menu) => menu.indicator(),
             None => &lines.prompt_indicator,
         };
-        self.stdout.queue(Print(&coerce_crlf(prompt_indicator)))?;
+        self.writer().queue(Print(&coerce_crlf(prompt_indicator)))?;
 
         self.print_right_prompt(lines)?;
 
         if use_ansi_coloring {
-            self.stdout.queue(ResetColor)?;
+            self.writer().queue(ResetColor)?;
         }
 
-        self.stdout
+        self.writer()
             .queue(Print(&lines.before_cursor))?
             .queue(SavePosition)?
             .queue(Print(&lines.after_cursor))?;
@@ -450,7 +1031,12 @@ impl Painter {
         if let Some(menu) = menu {
             self.print_menu(menu, lines, use_ansi_coloring)?;
         } else {
-            self.stdout.queue(Print(&lines.hint))?;
+            self.writer().queue(Print(&lines.hint))?;
+            if !lines.status.is_empty() {
+                self.writer()
+                    .queue(Print("\r\n"))?
+                    .queue(Print(&lines.status))?;
+            }
         }
 
         Ok(())
@@ -479,7 +1065,8 @@ impl Painter {
         };
 
         let prompt_indicator_lines = prompt_indicator.lines().count();
-        let before_cursor_lines = lines.before_cursor.lines().count();
+        // Counting the empty line of a cursor at the start of a line, as it is printed too
+        let before_cursor_lines = lines.before_cursor.split('\n').count();
         let total_lines_before = prompt_lines + prompt_indicator_lines + before_cursor_lines - 1;
 
         // Extra rows represent how many rows are "above" the visible area in the terminal
@@ -487,14 +1074,13 @@ impl Painter {
 
         // print our prompt with color
         if use_ansi_coloring {
-            self.stdout
-                .queue(SetForegroundColor(prompt.get_prompt_color()))?;
+            self.queue_prompt_style(prompt)?;
         }
 
         // In case the prompt is made out of multiple lines, the prompt is split by
         // lines and only the required ones are printed
         let prompt_skipped = skip_buffer_lines(&lines.prompt_str_left, extra_rows, None);
-        self.stdout.queue(Print(&coerce_crlf(prompt_skipped)))?;
+        self.writer().queue(Print(&coerce_crlf(prompt_skipped)))?;
 
         if extra_rows == 0 {
             self.print_right_prompt(lines)?;
@@ -504,10 +1090,11 @@ impl Painter {
         let extra_rows = extra_rows.saturating_sub(prompt_lines);
 
         let indicator_skipped = skip_buffer_lines(prompt_indicator, extra_rows, None);
-        self.stdout.queue(Print(&coerce_crlf(indicator_skipped)))?;
+        self.writer()
+            .queue(Print(&coerce_crlf(indicator_skipped)))?;
 
         if use_ansi_coloring {
-            self.stdout.queue(ResetColor)?;
+            self.writer().queue(ResetColor)?;
         }
 
         // The minimum number of lines from the menu are removed from the buffer if there is no more
@@ -520,7 +1107,7 @@ impl Painter {
                     .lines()
                     .count()
                     .saturating_sub(extra_rows)
-                    .saturating_sub(menu.min_rows() as usize);
+                    .saturating_sub((menu.min_rows() + menu_frame_lines(menu)) as usize);
                 Some(rows)
             } else {
                 None
@@ -529,8 +1116,8 @@ impl Painter {
 
         // Selecting the lines before the cursor that will be printed
         let before_cursor_skipped = skip_buffer_lines(&lines.before_cursor, extra_rows, offset);
-        self.stdout.queue(Print(before_cursor_skipped))?;
-        self.stdout.queue(SavePosition)?;
+        self.writer().queue(Print(before_cursor_skipped))?;
+        self.writer().queue(SavePosition)?;
 
         if let Some(menu) = menu {
             // TODO: Also solve the difficult problem of displaying (parts of)
@@ -544,10 +1131,19 @@ impl Painter {
             let offset = remaining_lines.saturating_sub(1) as usize;
             // Selecting lines after the cursor
             let after_cursor_skipped = skip_buffer_lines(&lines.after_cursor, 0, Some(offset));
-            self.stdout.queue(Print(after_cursor_skipped))?;
+            self.writer().queue(Print(after_cursor_skipped))?;
             // Hint lines
             let hint_skipped = skip_buffer_lines(&lines.hint, 0, Some(offset));
-            self.stdout.queue(Print(hint_skipped))?;
+            self.writer().queue(Print(hint_skipped))?;
+            // Status lines that still fit on the screen
+            let hint_lines = hint_skipped.split('\n').count();
+            if !lines.status.is_empty() && hint_lines <= offset {
+                let status_offset = offset - hint_lines;
+                let status_skipped = skip_buffer_lines(&lines.status, 0, Some(status_offset));
+                self.writer()
+                    .queue(Print("\r\n"))?
+                    .queue(Print(status_skipped))?;
+            }
         }
 
         Ok(())
@@ -556,6 +1152,7 @@ impl Painter {
     /// Updates prompt origin and offset to handle a screen resize event
     pub(crate) fn handle_resize(&mut self, width: u16, height: u16) {
         let prev_terminal_size = self.terminal_size;
+        self.invalidate_frame();
         let prev_prompt_row = self.prompt_start_row;
 
         self.terminal_size = (width, height);
@@ -579,47 +1176,175 @@ impl Painter {
 
     /// Writes `line` to the terminal with a following carriage return and newline
     pub(crate) fn paint_line(&mut self, line: &str) -> Result<()> {
-        self.stdout.queue(Print(line))?.queue(Print("\r\n"))?;
+        self.invalidate_frame();
+        self.backend.queue(Print(line))?.queue(Print("\r\n"))?;
+
+        self.backend.flush()
+    }
+
+    /// Prints the lines where the prompt starts, so the prompt is painted again below
+    /// them by the next repaint
+    pub(crate) fn print_above(&mut self, lines: &[String]) -> Result<()> {
+        // The lines stay on the main screen when a menu is painted on the alternate one
+        self.leave_alternate_screen()?;
+        self.invalidate_frame();
+        if self.dumb {
+            // The prompt line is overwritten, there is no way to clear it
+            let padding = self.dumb_line_width;
+            self.dumb_line_width = 0;
+            for line in lines {
+                let width = dumb_text(line).width();
+                self.backend
+                    .queue(Print("\r"))?
+                    .queue(Print(dumb_text(line)))?
+                    .queue(Print(" ".repeat(padding.saturating_sub(width))))?
+                    .queue(Print("\r\n"))?;
+            }
+            return self.backend.flush();
+        }
 
-        self.stdout.flush()
+        self.backend
+            .queue(MoveTo(0, self.prompt_start_row))?
+            .queue(Clear(ClearType::FromCursorDown))?;
+        let mut rows = 0;
+        for line in lines {
+            self.backend
+                .queue(Print(coerce_crlf(line)))?
+                .queue(Print("\r\n"))?;
+            rows += estimate_required_lines(line, self.screen_width()).max(1) as u16;
+        }
+        // The screen scrolls once the lines reach the last row
+        self.prompt_start_row = (self.prompt_start_row + rows).min(self.screen_height() - 1);
+
+        self.backend.flush()
     }
 
     /// Goes to the beginning of the next line
     ///
     /// Also works in raw mode
     pub(crate) fn print_crlf(&mut self) -> Result<()> {
-        self.stdout.queue(Print("\r\n"))?;
+        self.invalidate_frame();
+        self.backend.queue(Print("\r\n"))?;
+
+        self.backend.flush()
+    }
 
-        self.stdout.flush()
+    /// Rings the bell of the terminal
+    pub(crate) fn ring_bell(&mut self) -> Result<()> {
+        self.backend.queue(Print("\u{7}"))?;
+        self.backend.flush()
+    }
+
+    /// Sets the title of the terminal window. Dumb terminals don't have one
+    pub(crate) fn set_title(&mut self, title: &str) -> Result<()> {
+        if !self.backend.supports_cursor_movement() {
+            return Ok(());
+        }
+
+        self.backend.queue(SetTitle(title))?;
+        self.backend.flush()
+    }
+
+    /// Saves the title on the title stack of the terminal, for [`Painter::pop_title()`]
+    pub(crate) fn push_title(&mut self) -> Result<()> {
+        if !self.backend.supports_ansi() {
+            return Ok(());
+        }
+
+        self.backend.queue(Print("\x1b[22;0t"))?;
+        self.backend.flush()
+    }
+
+    /// Restores the title saved with [`Painter::push_title()`]
+    pub(crate) fn pop_title(&mut self) -> Result<()> {
+        if !self.backend.supports_ansi() {
+            return Ok(());
+        }
+
+        self.backend.queue(Print("\x1b[23;0t"))?;
+        self.backend.flush()
+    }
+
+    /// Goes back to the main screen if a menu was painted on the alternate screen.
+    /// The main screen shows the prompt as it was before the menu was opened
+    pub(crate) fn leave_alternate_screen(&mut self) -> Result<()> {
+        if let Some(row) = self.main_screen_row.take() {
+            self.invalidate_frame();
+            self.prompt_start_row = row;
+            self.backend.queue(LeaveAlternateScreen)?;
+            self.backend.flush()?;
+        }
+
+        Ok(())
     }
 
     /// Clear the screen by printing enough whitespace to start the prompt or
     /// other output back at the first line of the terminal.
     pub fn clear_screen(&mut self) -> Result<()> {
-        self.stdout.queue(cursor::Hide)?;
-        let (_, num_lines) = terminal::size()?;
+        self.clear(false)
+    }
+
+    /// Clears the screen like [`Painter::clear_screen()`] and also the scrollback of
+    /// the terminal, if it supports it
+    pub(crate) fn clear_scrollback(&mut self) -> Result<()> {
+        self.clear(true)
+    }
+
+    fn clear(&mut self, scrollback: bool) -> Result<()> {
+        self.leave_alternate_screen()?;
+        self.invalidate_frame();
+        self.prompt_start_row = 0;
+        if self.dumb {
+            self.dumb_line_width = 0;
+            return self.print_crlf();
+        }
+
+        self.backend.queue(cursor::Hide)?;
+        let (_, num_lines) = self.backend.size()?;
         for _ in 0..2 * num_lines {
-            self.stdout.queue(Print("\n"))?;
+            self.backend.queue(Print("\n"))?;
+        }
+        if scrollback {
+            self.backend.queue(Clear(ClearType::Purge))?;
         }
-        self.stdout.queue(MoveTo(0, 0))?;
-        self.stdout.queue(cursor::Show)?;
+        self.backend.queue(MoveTo(0, 0))?;
+        self.backend.queue(cursor::Show)?;
 
-        self.stdout.flush()
+        self.backend.flush()
     }
 
     // The prompt is moved to the end of the buffer after the event was handled
     // If the prompt is in the middle of a multiline buffer, then the output to stdout
     // could overwrite the buffer writing
     pub fn move_cursor_to_end(&mut self) -> Result<()> {
+        self.leave_alternate_screen()?;
+        self.invalidate_frame();
+        if self.dumb {
+            // Without moving the cursor its row is unknown
+            self.last_layout = None;
+            self.dumb_line_width = 0;
+            return self.print_crlf();
+        }
+
         let final_row = self.prompt_start_row + self.last_required_lines;
         let scroll = final_row.saturating_sub(self.screen_height() - 1);
         if scroll != 0 {
-            self.stdout.queue(ScrollUp(scroll))?;
+            self.backend.queue(ScrollUp(scroll))?;
         }
-        self.stdout
-            .queue(MoveTo(0, final_row.min(self.screen_height() - 1)))?;
+        let cursor_row = final_row.min(self.screen_height() - 1);
+        self.backend.queue(MoveTo(0, cursor_row))?;
+        self.last_layout = Some(PromptLayout {
+            start_row: self.prompt_start_row.saturating_sub(scroll),
+            lines: self.last_required_lines,
+            cursor: (0, cursor_row),
+        });
+
+        self.backend.flush()
+    }
 
-        self.stdout.flush()
+    /// Where the prompt was when the last read ended
+    pub(crate) fn last_layout(&self) -> Option<PromptLayout> {
+        self.last_layout
     }
 }
 
@@ -680,6 +1405,111 @@ mod tests {
         assert_eq!(skip_buffer_lines(string, 1, Some(0)), "sentence2",);
     }
 
+    fn menu_rows() -> Vec<MenuRow<'static>> {
+        let mut first = MenuRow::default();
+        first.push_selected(Style::new().bold(), "one".to_string());
+        first.push(Style::default(), "  ".to_string());
+        first.push(Style::new().italic(), "two".to_string());
+
+        let mut second = MenuRow::default();
+        second.push(Style::new().italic(), "three".to_string());
+
+        vec![first, second]
+    }
+
+    #[rstest]
+    #[case(80, "one  two\nthree")]
+    #[case(6, "one  t\nthree")]
+    #[case(3, "one\nthr")]
+    fn test_render_menu_rows(#[case] width: u16, #[case] expected: &str) {
+        let mut rendered = String::new();
+        render_menu_rows(&mut rendered, &menu_rows(), None, width, true);
+
+        // Stripping the ansi codes also removes the carriage return
+        assert_eq!(strip_ansi(&rendered), expected);
+    }
+
+    #[rstest]
+    #[case(80, ">one  two\r\nthree")]
+    #[case(3, ">on\r\nthr")]
+    fn test_render_menu_rows_without_ansi(#[case] width: u16, #[case] expected: &str) {
+        let mut rendered = String::new();
+        render_menu_rows(&mut rendered, &menu_rows(), None, width, false);
+
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_render_menu_rows_styles_parts() {
+        let mut rendered = String::new();
+        render_menu_rows(&mut rendered, &menu_rows()[1..], None, 80, true);
+
+        assert_eq!(rendered, Style::new().italic().paint("three").to_string());
+    }
+
+    #[test]
+    fn test_render_menu_rows_skips_repeated_styles() {
+        let style = Style::new().bold();
+        let mut row = MenuRow::default();
+        row.push(style, "one".to_string());
+        row.push(style, "two".to_string());
+
+        let mut rendered = String::from("previous content");
+        render_menu_rows(&mut rendered, &[row], None, 80, true);
+
+        assert_eq!(rendered, style.paint("onetwo").to_string());
+    }
+
+    #[rstest]
+    #[case(
+        80,
+        None,
+        "+-----------+\r\n| >one  two |\r\n| three     |\r\n+-----------+"
+    )]
+    #[case(
+        80,
+        Some("Menu"),
+        "+- Menu ----+\r\n| >one  two |\r\n| three     |\r\n+-----------+"
+    )]
+    #[case(8, Some("Completions"), "+- Com +\r\n| >one |\r\n| thre |\r\n+------+")]
+    fn test_render_menu_rows_with_border(
+        #[case] width: u16,
+        #[case] title: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        let mut border = MenuBorder::ascii().with_padding(1);
+        if let Some(title) = title {
+            border = border.with_title(title);
+        }
+
+        let mut rendered = String::new();
+        render_menu_rows(&mut rendered, &menu_rows(), Some(&border), width, false);
+
+        assert_eq!(rendered, expected);
+    }
+
+    #[rstest]
+    #[case("sentence", 4, "sen…")]
+    #[case("sentence", 8, "sentence")]
+    #[case("😇😇", 3, "😇…")]
+    #[case("sentence", 1, "…")]
+    fn test_truncate_with_ellipsis(
+        #[case] input: &str,
+        #[case] width: usize,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(truncate_with_ellipsis(input, width), expected);
+    }
+
+    #[rstest]
+    #[case("sentence", 4, "sent")]
+    #[case("sentence", 20, "sentence")]
+    #[case("😇😇", 3, "😇")]
+    #[case("", 2, "")]
+    fn test_truncate_to_width(#[case] input: &str, #[case] width: usize, #[case] expected: &str) {
+        assert_eq!(truncate_to_width(input, width), expected);
+    }
+
     #[rstest]
     #[case("sentence\nsentence", "sentence\r\nsentence")]
     #[case("sentence\r\nsentence", "sentence\r\nsentence")]
@@ -687,6 +1517,7 @@ mod tests {
     #[case("😇\nsentence", "😇\r\nsentence")]
     #[case("sentence\n😇", "sentence\r\n😇")]
     #[case("\n", "\r\n")]
+    #[case("\nsentence", "\r\nsentence")]
     #[case("", "")]
     fn test_coerce_crlf(#[case] input: &str, #[case] expected: &str) {
         let result = coerce_crlf(input);
@@ -698,4 +1529,36 @@ mod tests {
             "Unnecessary allocation"
         )
     }
+
+    #[rstest]
+    #[case("ascii", 5)]
+    #[case("日本語", 6)]
+    #[case("😇 ok", 5)]
+    #[case("e\u{301}te\u{301}", 3)]
+    #[case("\x1b[1;32m日本\x1b[0m", 4)]
+    fn test_line_width(#[case] input: &str, #[case] expected: usize) {
+        assert_eq!(line_width(input), expected);
+    }
+
+    #[rstest]
+    #[case("abcd", 4, 0)]
+    #[case("abcde", 4, 1)]
+    #[case("日本", 4, 0)]
+    #[case("日本語", 4, 1)]
+    #[case("日本語日本語", 4, 2)]
+    fn test_estimate_single_line_wraps(
+        #[case] input: &str,
+        #[case] columns: u16,
+        #[case] expected: usize,
+    ) {
+        assert_eq!(estimate_single_line_wraps(input, columns), expected);
+    }
+
+    #[rstest]
+    #[case("plain", "plain")]
+    #[case("\x1b[1;32mgreen\x1b[0m", "green")]
+    #[case("first\r\nsecond\nthird", "first^Jsecond^Jthird")]
+    fn test_dumb_text(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(dumb_text(input), expected);
+    }
 }
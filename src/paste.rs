@@ -0,0 +1,137 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+/// What to do with the control characters of pasted text
+///
+/// Without bracketed paste the terminal sends pasted text as key presses, so a control
+/// character or an escape sequence in it arrives as a key like `Ctrl+C` or an arrow and
+/// would run its binding. Pasted tabs and newlines are kept as text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PastePolicy {
+    /// Pasted control characters run their keybindings, like typed ones
+    #[default]
+    Keep,
+    /// Pasted control characters are dropped and the rest of the text is inserted
+    Strip,
+    /// Pasted text with control characters is dropped.
+    ///
+    /// The text is checked as it arrives, a line at a time, because a pasted newline
+    /// may submit the line before the rest of the paste is read. The lines before the
+    /// one with the control character are kept, inserted or submitted already, and the
+    /// paste is dropped from that line to its end
+    Reject,
+}
+
+/// Applies the paste `policy` to the events read while pasting, and drops the
+/// newlines at the end of the paste if `trim_newlines` is set
+///
+/// `paste_ends` tells whether these are the last events of the paste. Returns false if
+/// the pasted text was rejected.
+pub(crate) fn sanitize_paste(
+    events: &mut Vec<Event>,
+    policy: PastePolicy,
+    trim_newlines: bool,
+    paste_ends: bool,
+) -> bool {
+    match policy {
+        PastePolicy::Keep => {}
+        PastePolicy::Strip => events.retain(|event| !is_control(event)),
+        PastePolicy::Reject => {
+            if events.iter().any(is_control) {
+                events.clear();
+                return false;
+            }
+        }
+    }
+
+    if trim_newlines && paste_ends {
+        while events.last().is_some_and(is_newline) {
+            events.pop();
+        }
+    }
+
+    true
+}
+
+fn is_newline(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::NONE
+        })
+    )
+}
+
+/// Key presses that don't come from the printable text, tabs or newlines of a paste
+fn is_control(event: &Event) -> bool {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(_),
+            modifiers,
+        }) => *modifiers != KeyModifiers::NONE && *modifiers != KeyModifiers::SHIFT,
+        Event::Key(KeyEvent {
+            code: KeyCode::Tab | KeyCode::Enter,
+            modifiers: KeyModifiers::NONE,
+        }) => false,
+        Event::Key(_) => true,
+        Event::Mouse(_) | Event::Resize(..) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn key(modifiers: KeyModifiers, code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    fn pasted() -> Vec<Event> {
+        vec![
+            key(KeyModifiers::NONE, KeyCode::Char('l')),
+            key(KeyModifiers::SHIFT, KeyCode::Char('S')),
+            key(KeyModifiers::NONE, KeyCode::Tab),
+            key(KeyModifiers::CONTROL, KeyCode::Char('c')),
+            key(KeyModifiers::NONE, KeyCode::Up),
+            key(KeyModifiers::NONE, KeyCode::Enter),
+            key(KeyModifiers::NONE, KeyCode::Enter),
+        ]
+    }
+
+    #[rstest]
+    #[case(PastePolicy::Keep, false, true, pasted())]
+    #[case(PastePolicy::Strip, false, true, vec![
+        pasted()[0],
+        pasted()[1],
+        pasted()[2],
+        pasted()[5],
+        pasted()[6],
+    ])]
+    #[case(PastePolicy::Reject, false, false, vec![])]
+    #[case(PastePolicy::Keep, true, true, pasted()[..5].to_vec())]
+    fn paste_policies(
+        #[case] policy: PastePolicy,
+        #[case] trim_newlines: bool,
+        #[case] accepted: bool,
+        #[case] expected: Vec<Event>,
+    ) {
+        let mut events = pasted();
+
+        assert_eq!(
+            sanitize_paste(&mut events, policy, trim_newlines, true),
+            accepted
+        );
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn newlines_are_trimmed_only_at_the_end_of_the_paste() {
+        let mut events = pasted();
+
+        sanitize_paste(&mut events, PastePolicy::Keep, true, false);
+
+        assert_eq!(events, pasted());
+    }
+}
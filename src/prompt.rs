@@ -64,19 +64,17 @@ pub enum PromptEditMode {
 }
 
 /// The vi-specific modes that the prompt can be in
-#[derive(Serialize, Deserialize, Clone, Debug, EnumIter)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, EnumIter)]
 pub enum PromptViMode {
     /// The default mode
+    #[default]
     Normal,
 
     /// Insertion mode
     Insert,
-}
 
-impl Default for PromptViMode {
-    fn default() -> Self {
-        PromptViMode::Normal
-    }
+    /// Replace mode, where typing overwrites the text
+    Replace,
 }
 
 impl Display for PromptEditMode {
@@ -95,18 +93,18 @@ impl Display for PromptEditMode {
 /// displayed before the `LineBuffer` is drawn.
 pub trait Prompt: Send {
     /// Provide content off the right full prompt
-    fn render_prompt_left(&self) -> Cow<str>;
+    fn render_prompt_left(&self) -> Cow<'_, str>;
     /// Provide content off the left full prompt
-    fn render_prompt_right(&self) -> Cow<str>;
+    fn render_prompt_right(&self) -> Cow<'_, str>;
     /// Render the default prompt indicator
-    fn render_prompt_indicator(&self, prompt_mode: PromptEditMode) -> Cow<str>;
+    fn render_prompt_indicator(&self, prompt_mode: PromptEditMode) -> Cow<'_, str>;
     /// Render the default prompt indicator
-    fn render_prompt_multiline_indicator(&self) -> Cow<str>;
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str>;
     /// Render the default prompt indicator
     fn render_prompt_history_search_indicator(
         &self,
         history_search: PromptHistorySearch,
-    ) -> Cow<str>;
+    ) -> Cow<'_, str>;
     /// Render the vi insert mode prompt indicator
     /// Get back the prompt color
     fn get_prompt_color(&self) -> Color {
@@ -115,20 +113,22 @@ pub trait Prompt: Send {
 }
 
 impl Prompt for DefaultPrompt {
-    fn render_prompt_left(&self) -> Cow<str> {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
         DefaultPrompt::render_prompt_left(self)
     }
 
-    fn render_prompt_right(&self) -> Cow<str> {
+    fn render_prompt_right(&self) -> Cow<'_, str> {
         DefaultPrompt::render_prompt_right(self)
     }
 
-    fn render_prompt_indicator(&self, edit_mode: PromptEditMode) -> Cow<str> {
+    fn render_prompt_indicator(&self, edit_mode: PromptEditMode) -> Cow<'_, str> {
         match edit_mode {
             PromptEditMode::Default | PromptEditMode::Emacs => DEFAULT_PROMPT_INDICATOR.into(),
             PromptEditMode::Vi(vi_mode) => match vi_mode {
                 PromptViMode::Normal => DEFAULT_VI_NORMAL_PROMPT_INDICATOR.into(),
-                PromptViMode::Insert => DEFAULT_VI_INSERT_PROMPT_INDICATOR.into(),
+                PromptViMode::Insert | PromptViMode::Replace => {
+                    DEFAULT_VI_INSERT_PROMPT_INDICATOR.into()
+                }
             },
             PromptEditMode::Custom(str) => {
                 DefaultPrompt::default_wrapped_custom_string(&str).into()
@@ -136,14 +136,14 @@ impl Prompt for DefaultPrompt {
         }
     }
 
-    fn render_prompt_multiline_indicator(&self) -> Cow<str> {
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
         Cow::Borrowed(DEFAULT_MULTILINE_INDICATOR)
     }
 
     fn render_prompt_history_search_indicator(
         &self,
         history_search: PromptHistorySearch,
-    ) -> Cow<str> {
+    ) -> Cow<'_, str> {
         let prefix = match history_search.status {
             PromptHistorySearchStatus::Passing => "",
             PromptHistorySearchStatus::Failing => "failing ",
@@ -173,13 +173,13 @@ impl DefaultPrompt {
         DefaultPrompt {}
     }
 
-    fn render_prompt_left(&self) -> Cow<str> {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
         let left_prompt = get_working_dir().unwrap_or_else(|_| String::from("no path"));
 
         Cow::Owned(left_prompt)
     }
 
-    fn render_prompt_right(&self) -> Cow<str> {
+    fn render_prompt_right(&self) -> Cow<'_, str> {
         Cow::Owned(get_now())
     }
 
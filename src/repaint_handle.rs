@@ -0,0 +1,69 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Asks the editor to paint the prompt again from other threads, without waiting
+/// for a key press
+///
+/// Useful when the prompt shows state that changes on its own, like a clock or the
+/// git branch switched by another process. Created with
+/// [`Reedline::repaint_handle()`](crate::Reedline::repaint_handle), clones ask the same editor.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::Reedline;
+/// use std::{thread, time::Duration};
+///
+/// let line_editor = Reedline::create()?;
+/// let handle = line_editor.repaint_handle();
+///
+/// // Keeps a clock in the prompt up to date
+/// thread::spawn(move || loop {
+///     thread::sleep(Duration::from_secs(1));
+///     handle.request_repaint();
+/// });
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RepaintHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl RepaintHandle {
+    /// Repaints the prompt the next time the editor checks for input. Requests made
+    /// before the editor gets to them are painted once
+    pub fn request_repaint(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Takes the pending request
+    pub(crate) fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+
+    /// Whether a handle was given out, so requests can arrive
+    pub(crate) fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.requested) > 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_taken_once() {
+        let handle = RepaintHandle::default();
+        assert!(!handle.is_shared());
+
+        let clone = handle.clone();
+        assert!(handle.is_shared());
+        clone.request_repaint();
+        clone.request_repaint();
+
+        assert!(handle.take_request());
+        assert!(!handle.take_request());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::HistoryFormatError;
+use std::{error::Error, fmt::Display, io};
+
+/// Errors returned by the line editor and the history
+#[derive(Debug)]
+pub enum ReedlineError {
+    /// Reading events from the terminal or painting on it failed
+    Terminal(io::Error),
+    /// Reading or writing the history file failed
+    History(io::Error),
+    /// The history file has a format that can't be read
+    HistoryFormat(HistoryFormatError),
+    /// A configuration value is not valid
+    Config(String),
+    /// Reading the line was interrupted by a signal before it was completed
+    Interrupted,
+}
+
+/// Result type of the line editor and the history
+pub type Result<T> = std::result::Result<T, ReedlineError>;
+
+impl ReedlineError {
+    /// Error from the terminal, separating the interrupted reads
+    pub(crate) fn terminal(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::Interrupted {
+            ReedlineError::Interrupted
+        } else {
+            ReedlineError::Terminal(error)
+        }
+    }
+
+    /// Error from the history file, separating the format problems
+    pub(crate) fn history(error: io::Error) -> Self {
+        let format_error = error
+            .get_ref()
+            .and_then(|error| error.downcast_ref::<HistoryFormatError>());
+
+        match format_error {
+            Some(format_error) => ReedlineError::HistoryFormat(format_error.clone()),
+            None => ReedlineError::History(error),
+        }
+    }
+}
+
+impl Display for ReedlineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReedlineError::Terminal(error) => write!(f, "terminal error: {}", error),
+            ReedlineError::History(error) => write!(f, "history error: {}", error),
+            ReedlineError::HistoryFormat(error) => error.fmt(f),
+            ReedlineError::Config(message) => write!(f, "invalid configuration: {}", message),
+            ReedlineError::Interrupted => write!(f, "reading the line was interrupted"),
+        }
+    }
+}
+
+impl Error for ReedlineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReedlineError::Terminal(error) | ReedlineError::History(error) => Some(error),
+            ReedlineError::HistoryFormat(error) => Some(error),
+            ReedlineError::Config(_) | ReedlineError::Interrupted => None,
+        }
+    }
+}
+
+/// Allows using `?` with the reedline errors in functions that return [`io::Result`]
+impl From<ReedlineError> for io::Error {
+    fn from(error: ReedlineError) -> Self {
+        match error {
+            ReedlineError::Terminal(error) | ReedlineError::History(error) => error,
+            ReedlineError::HistoryFormat(error) => error.into(),
+            ReedlineError::Config(message) => io::Error::new(io::ErrorKind::InvalidInput, message),
+            ReedlineError::Interrupted => io::Error::from(io::ErrorKind::Interrupted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupted_terminal_reads_are_separated() {
+        let error = ReedlineError::terminal(io::Error::from(io::ErrorKind::Interrupted));
+        assert!(matches!(error, ReedlineError::Interrupted));
+
+        let error = ReedlineError::terminal(io::Error::from(io::ErrorKind::BrokenPipe));
+        assert!(matches!(error, ReedlineError::Terminal(_)));
+    }
+
+    #[test]
+    fn history_format_errors_are_separated() {
+        let error = ReedlineError::history(HistoryFormatError::Encrypted.into());
+        assert!(matches!(
+            error,
+            ReedlineError::HistoryFormat(HistoryFormatError::Encrypted)
+        ));
+
+        let error = ReedlineError::history(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(error, ReedlineError::History(_)));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{text_manipulation, LineBuffer};
+use std::ops::Range;
+
+/// What the search-and-replace mini-mode is waiting for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stage {
+    /// The pattern is being typed
+    Pattern,
+    /// The replacement is being typed
+    Replacement,
+    /// Each match is replaced or skipped, starting from the beginning of the buffer
+    Confirm,
+}
+
+/// State of the search-and-replace mini-mode
+///
+/// The pattern is matched literally. While confirming, `y` or space replaces the
+/// current match, `n` skips it, `a` or `!` replaces it and all the following ones
+/// and `q` stops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SearchReplace {
+    stage: Stage,
+    pattern: String,
+    replacement: String,
+    // Where the search for the current match starts while confirming
+    next: usize,
+}
+
+impl Default for SearchReplace {
+    fn default() -> Self {
+        Self {
+            stage: Stage::Pattern,
+            pattern: String::new(),
+            replacement: String::new(),
+            next: 0,
+        }
+    }
+}
+
+impl SearchReplace {
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Adds a character to the pattern or the replacement being typed
+    pub fn push(&mut self, c: char) {
+        match self.stage {
+            Stage::Pattern => self.pattern.push(c),
+            Stage::Replacement => self.replacement.push(c),
+            Stage::Confirm => {}
+        }
+    }
+
+    /// Removes the last grapheme of the pattern or the replacement being typed
+    pub fn pop(&mut self) {
+        let text = match self.stage {
+            Stage::Pattern => &mut self.pattern,
+            Stage::Replacement => &mut self.replacement,
+            Stage::Confirm => return,
+        };
+        let len = text_manipulation::remove_last_grapheme(text).len();
+        text.truncate(len);
+    }
+
+    /// Moves on from typing the pattern or the replacement
+    ///
+    /// Returns false if there is nothing to replace in the buffer
+    pub fn accept(&mut self, buffer: &str) -> bool {
+        match self.stage {
+            Stage::Pattern => {
+                self.stage = Stage::Replacement;
+                !self.pattern.is_empty()
+            }
+            Stage::Replacement => {
+                self.stage = Stage::Confirm;
+                self.next = 0;
+                self.current_match(buffer).is_some()
+            }
+            Stage::Confirm => false,
+        }
+    }
+
+    /// The matches of the pattern in the buffer
+    pub fn matches(&self, buffer: &str) -> Vec<Range<usize>> {
+        if self.pattern.is_empty() {
+            return Vec::new();
+        }
+
+        buffer
+            .match_indices(&self.pattern)
+            .map(|(start, text)| start..start + text.len())
+            .collect()
+    }
+
+    /// The match that is replaced or skipped next
+    pub fn current_match(&self, buffer: &str) -> Option<Range<usize>> {
+        if self.stage != Stage::Confirm || self.pattern.is_empty() {
+            return None;
+        }
+
+        buffer[self.next..]
+            .find(&self.pattern)
+            .map(|start| self.next + start..self.next + start + self.pattern.len())
+    }
+
+    /// Replaces the current match and moves to the next one
+    ///
+    /// Returns false if there are no matches left
+    pub fn replace(&mut self, line_buffer: &mut LineBuffer) -> bool {
+        if let Some(current) = self.current_match(line_buffer.get_buffer()) {
+            line_buffer.replace(current.clone(), &self.replacement);
+            self.next = current.start + self.replacement.len();
+        }
+
+        self.move_to_current(line_buffer)
+    }
+
+    /// Skips the current match and moves to the next one
+    ///
+    /// Returns false if there are no matches left
+    pub fn skip(&mut self, line_buffer: &mut LineBuffer) -> bool {
+        if let Some(current) = self.current_match(line_buffer.get_buffer()) {
+            self.next = current.end;
+        }
+
+        self.move_to_current(line_buffer)
+    }
+
+    /// Replaces the current match and all the following ones
+    pub fn replace_all(&mut self, line_buffer: &mut LineBuffer) {
+        while self.current_match(line_buffer.get_buffer()).is_some() {
+            self.replace(line_buffer);
+        }
+    }
+
+    /// Puts the insertion point on the current match, or after the last replacement
+    /// if there is none
+    pub fn move_to_current(&self, line_buffer: &mut LineBuffer) -> bool {
+        match self.current_match(line_buffer.get_buffer()) {
+            Some(current) => {
+                line_buffer.set_insertion_point(current.start);
+                true
+            }
+            None => {
+                line_buffer.set_insertion_point(self.next.min(line_buffer.get_buffer().len()));
+                false
+            }
+        }
+    }
+
+    /// The line that shows what the mini-mode is waiting for
+    pub fn status(&self) -> String {
+        match self.stage {
+            Stage::Pattern => format!("replace: {}", self.pattern),
+            Stage::Replacement => format!("replace {} with: {}", self.pattern, self.replacement),
+            Stage::Confirm => format!(
+                "replace {} with {}? (y/n/a/q)",
+                self.pattern, self.replacement
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn search_replace(pattern: &str, replacement: &str, buffer: &str) -> SearchReplace {
+        let mut search_replace = SearchReplace::default();
+        pattern.chars().for_each(|c| search_replace.push(c));
+        search_replace.accept(buffer);
+        replacement.chars().for_each(|c| search_replace.push(c));
+        search_replace.accept(buffer);
+
+        search_replace
+    }
+
+    fn buffer_with(content: &str) -> LineBuffer {
+        let mut line_buffer = LineBuffer::new();
+        line_buffer.insert_str(content);
+
+        line_buffer
+    }
+
+    #[test]
+    fn typing_edits_the_pattern_then_the_replacement() {
+        let mut search_replace = SearchReplace::default();
+        "fop".chars().for_each(|c| search_replace.push(c));
+        search_replace.pop();
+        search_replace.push('o');
+        assert_eq!(search_replace.status(), "replace: foo");
+
+        assert!(search_replace.accept("foo"));
+        search_replace.push('x');
+        assert_eq!(search_replace.status(), "replace foo with: x");
+
+        assert!(search_replace.accept("foo"));
+        assert_eq!(search_replace.stage(), Stage::Confirm);
+        assert_eq!(search_replace.status(), "replace foo with x? (y/n/a/q)");
+    }
+
+    #[test]
+    fn nothing_to_replace_is_not_accepted() {
+        let mut search_replace = SearchReplace::default();
+        assert!(!search_replace.accept("foo"));
+        assert!(!search_replace.accept("foo"));
+
+        let mut search_replace = SearchReplace::default();
+        search_replace.push('x');
+        assert!(search_replace.accept("foo"));
+        assert!(!search_replace.accept("foo"));
+    }
+
+    #[test]
+    fn matches_are_replaced_one_by_one() {
+        let mut line_buffer = buffer_with("cp a.txt a.txt.bak a.txt");
+        let mut search_replace = search_replace("a.txt", "b.md", line_buffer.get_buffer());
+        assert_eq!(
+            search_replace.matches(line_buffer.get_buffer()),
+            vec![3..8, 9..14, 19..24]
+        );
+
+        assert!(search_replace.move_to_current(&mut line_buffer));
+        assert_eq!(line_buffer.offset(), 3);
+
+        assert!(search_replace.replace(&mut line_buffer));
+        assert_eq!(line_buffer.offset(), 8);
+        assert!(search_replace.skip(&mut line_buffer));
+        assert_eq!(line_buffer.offset(), 18);
+        assert!(!search_replace.replace(&mut line_buffer));
+
+        assert_eq!(line_buffer.get_buffer(), "cp b.md a.txt.bak b.md");
+        assert_eq!(line_buffer.offset(), 22);
+    }
+
+    #[test]
+    fn replacement_containing_the_pattern_is_not_replaced_again() {
+        let mut line_buffer = buffer_with("a a");
+        let mut search_replace = search_replace("a", "aa", line_buffer.get_buffer());
+
+        search_replace.replace_all(&mut line_buffer);
+
+        assert_eq!(line_buffer.get_buffer(), "aa aa");
+        assert_eq!(search_replace.current_match(line_buffer.get_buffer()), None);
+    }
+}
@@ -1,9 +1,139 @@
 use nu_ansi_term::{Color, Style};
+use std::{iter::Peekable, str::Chars};
+
+use crate::{bidi, Span};
+
+/// The shape of the line drawn by an [`Underline`]
+///
+/// Everything but `Straight` relies on the extended underline escape codes
+/// (`CSI 4:x m`). Terminals that don't understand them fall back to a straight underline.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    /// A single straight line
+    #[default]
+    Straight,
+    /// Two straight lines
+    Double,
+    /// A wavy line, commonly used to mark errors
+    Curly,
+    /// A dotted line
+    Dotted,
+    /// A dashed line
+    Dashed,
+}
+
+/// An underline drawn below a range of the buffer, e.g. to mark a syntax error in place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Underline {
+    /// The range of the buffer to underline, in bytes
+    pub span: Span,
+    /// The shape of the underline
+    pub style: UnderlineStyle,
+    /// The color of the underline. When `None` the color of the text is used
+    pub color: Option<Color>,
+}
+
+impl Underline {
+    /// Construct a new straight `Underline` over the given span
+    pub fn new(span: Span) -> Self {
+        Self {
+            span,
+            style: UnderlineStyle::default(),
+            color: None,
+        }
+    }
+
+    /// A builder that sets the shape of the underline
+    pub fn with_style(mut self, style: UnderlineStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// A builder that sets the color of the underline
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    fn prefix(&self) -> String {
+        // The plain underline goes first so terminals without support for the
+        // extended variants still show something
+        let mut prefix = String::from("\x1b[4m");
+        let variant = match self.style {
+            UnderlineStyle::Straight => None,
+            UnderlineStyle::Double => Some(2),
+            UnderlineStyle::Curly => Some(3),
+            UnderlineStyle::Dotted => Some(4),
+            UnderlineStyle::Dashed => Some(5),
+        };
+        if let Some(variant) = variant {
+            prefix.push_str(&format!("\x1b[4:{}m", variant));
+        }
+        if let Some(color) = self.color {
+            prefix.push_str(&format!("\x1b[58;{}m", underline_color_code(color)));
+        }
+        prefix
+    }
+}
+
+/// Maps a color onto the parameters of the `CSI 58 m` underline color sequence
+fn underline_color_code(color: Color) -> String {
+    let index = match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Purple | Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::White => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightPurple | Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::LightGray => 15,
+        Color::Fixed(index) => index,
+        Color::Rgb(r, g, b) => return format!("2;{};{};{}", r, g, b),
+    };
+    format!("5;{}", index)
+}
+
+/// How the tab characters of the buffer are painted
+///
+/// A tab is painted with a fixed width, so the cursor is placed where the terminal
+/// shows it no matter the tab stops of the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabDisplay {
+    /// As the given number of spaces
+    Spaces(usize),
+    /// As a `→` followed by spaces up to the given width
+    Arrow(usize),
+}
+
+impl Default for TabDisplay {
+    fn default() -> Self {
+        TabDisplay::Spaces(4)
+    }
+}
+
+impl TabDisplay {
+    fn render(&self) -> String {
+        match *self {
+            TabDisplay::Spaces(width) => " ".repeat(width),
+            TabDisplay::Arrow(width) => format!("→{}", " ".repeat(width.saturating_sub(1))),
+        }
+    }
+}
 
 /// A representation of a buffer with styling, used for doing syntax highlighting
 pub struct StyledText {
     /// The component, styled parts of the text
     pub buffer: Vec<(Style, String)>,
+    /// Underlines drawn on top of the styled parts, e.g. to mark diagnostics
+    pub underlines: Vec<Underline>,
 }
 
 impl Default for StyledText {
@@ -15,7 +145,10 @@ impl Default for StyledText {
 impl StyledText {
     /// Construct a new `StyledText`
     pub fn new() -> Self {
-        Self { buffer: vec![] }
+        Self {
+            buffer: vec![],
+            underlines: vec![],
+        }
     }
 
     /// Add a new styled string to the buffer
@@ -23,46 +156,160 @@ impl StyledText {
         self.buffer.push(styled_string);
     }
 
+    /// Add an underline over a range of the buffer
+    ///
+    /// Where underlines overlap the one added last wins
+    pub fn underline(&mut self, underline: Underline) {
+        self.underlines.push(underline);
+    }
+
+    /// Paints the trailing whitespace of the lines and the spaces that look like a
+    /// normal one, like the non-breaking space, on the `background` color
+    ///
+    /// Trailing whitespace that ends at the insertion point is being typed and is
+    /// left alone
+    pub(crate) fn highlight_whitespace(&mut self, insertion_point: usize, background: Color) {
+        let raw = self.raw_string();
+        let mut spans = Vec::new();
+        let mut line_start = 0;
+        for line in raw.split_inclusive('\n') {
+            let content = line.trim_end_matches('\n').trim_end_matches('\r');
+            let trimmed = content.trim_end_matches(|c| c == ' ' || c == '\t' || is_odd_space(c));
+            let line_end = line_start + content.len();
+            if trimmed.len() < content.len() && insertion_point != line_end {
+                spans.push(Span::new(line_start + trimmed.len(), line_end));
+            }
+            spans.extend(
+                trimmed
+                    .char_indices()
+                    .filter(|(_, c)| is_odd_space(*c))
+                    .map(|(index, c)| {
+                        Span::new(line_start + index, line_start + index + c.len_utf8())
+                    }),
+            );
+            line_start += line.len();
+        }
+
+        self.restyle(&spans, |style| style.on(background));
+    }
+
+    /// Changes the style of the parts of the buffer covered by `spans`
+    pub(crate) fn restyle(&mut self, spans: &[Span], restyle: impl Fn(Style) -> Style) {
+        if spans.is_empty() {
+            return;
+        }
+
+        let mut current_idx = 0;
+        let mut buffer = Vec::with_capacity(self.buffer.len());
+        for (style, text) in self.buffer.drain(..) {
+            let end = current_idx + text.len();
+            let mut cuts: Vec<usize> = spans
+                .iter()
+                .flat_map(|span| [span.start, span.end])
+                .filter(|&cut| current_idx < cut && cut < end)
+                .chain(std::iter::once(end))
+                .collect();
+            cuts.sort_unstable();
+            cuts.dedup();
+
+            let mut start = current_idx;
+            for cut in cuts {
+                let highlighted = spans
+                    .iter()
+                    .any(|span| span.start <= start && start < span.end);
+                let part_style = if highlighted { restyle(style) } else { style };
+                buffer.push((
+                    part_style,
+                    text[start - current_idx..cut - current_idx].to_string(),
+                ));
+                start = cut;
+            }
+            current_idx = end;
+        }
+        self.buffer = buffer;
+    }
+
+    fn underline_at(&self, position: usize) -> Option<&Underline> {
+        self.underlines
+            .iter()
+            .rev()
+            .find(|underline| underline.span.start <= position && position < underline.span.end)
+    }
+
     /// Render the styled string. We use the insertion point to render around so that
     /// we can properly write out the styled string to the screen and find the correct
     /// place to put the cursor. This assumes a logic that prints the first part of the
     /// string, saves the cursor position, prints the second half, and then restores
     /// the cursor position
     ///
-    /// Also inserts the multiline continuation prompt
+    /// Also inserts the multiline continuation prompt and paints the tabs as the
+    /// default [`TabDisplay`]
     pub fn render_around_insertion_point(
         &self,
         insertion_point: usize,
         multiline_prompt: &str,
         use_ansi_coloring: bool,
     ) -> (String, String) {
+        self.render_around_insertion_point_with_tabs(
+            insertion_point,
+            multiline_prompt,
+            use_ansi_coloring,
+            TabDisplay::default(),
+        )
+    }
+
+    /// Same as [`StyledText::render_around_insertion_point`], painting the tabs as
+    /// set by `tab_display`
+    pub fn render_around_insertion_point_with_tabs(
+        &self,
+        insertion_point: usize,
+        multiline_prompt: &str,
+        use_ansi_coloring: bool,
+        tab_display: TabDisplay,
+    ) -> (String, String) {
+        let tab = tab_display.render();
         let mut current_idx = 0;
         let mut left_string = String::new();
         let mut right_string = String::new();
         let prompt_style = Style::new().fg(Color::LightBlue);
-        for pair in &self.buffer {
-            if current_idx >= insertion_point {
-                right_string.push_str(&render_as_string(pair, &prompt_style, multiline_prompt));
-            } else if pair.1.len() + current_idx <= insertion_point {
-                left_string.push_str(&render_as_string(pair, &prompt_style, multiline_prompt));
-            } else if pair.1.len() + current_idx > insertion_point {
-                let offset = insertion_point - current_idx;
-
-                let left_side = pair.1[..offset].to_string();
-                let right_side = pair.1[offset..].to_string();
-
-                left_string.push_str(&render_as_string(
-                    &(pair.0, left_side),
-                    &prompt_style,
-                    multiline_prompt,
-                ));
-                right_string.push_str(&render_as_string(
-                    &(pair.0, right_side),
+
+        let mut boundaries: Vec<usize> = self
+            .underlines
+            .iter()
+            .flat_map(|underline| [underline.span.start, underline.span.end])
+            .chain(std::iter::once(insertion_point))
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        for (style, text) in &self.buffer {
+            let end = current_idx + text.len();
+            let cuts = boundaries
+                .iter()
+                .copied()
+                .filter(|&cut| {
+                    current_idx < cut && cut < end && text.is_char_boundary(cut - current_idx)
+                })
+                .chain(std::iter::once(end));
+
+            let mut start = current_idx;
+            for cut in cuts {
+                let rendered = render_as_string(
+                    style,
+                    self.underline_at(start),
+                    &text[start - current_idx..cut - current_idx],
                     &prompt_style,
                     multiline_prompt,
-                ));
+                    &tab,
+                );
+                if start >= insertion_point {
+                    right_string.push_str(&rendered);
+                } else {
+                    left_string.push_str(&rendered);
+                }
+                start = cut;
             }
-            current_idx += pair.1.len();
+            current_idx = end;
         }
 
         if use_ansi_coloring {
@@ -72,6 +319,89 @@ impl StyledText {
         }
     }
 
+    /// The text in the order it is shown on a terminal that lays out every line from
+    /// left to right, with the right-to-left runs reversed. Returns the position of
+    /// the insertion point in the reordered text
+    ///
+    /// The insertion point stays on the grapheme it is in front of, so the cursor
+    /// covers the same character it would without the reordering.
+    pub(crate) fn visual(&self, insertion_point: usize) -> (StyledText, usize) {
+        let raw = self.raw_string();
+        let mut visual = StyledText::new();
+        if !bidi::has_rtl(&raw) {
+            visual.buffer = self.buffer.clone();
+            visual.underlines = self.underlines.clone();
+            return (visual, insertion_point);
+        }
+
+        let mut visual_insertion_point = raw.len();
+        let mut line_start = 0;
+        for line in raw.split_inclusive('\n') {
+            let content_len = line.trim_end_matches('\n').trim_end_matches('\r').len();
+            let ranges = bidi::visual_order(&line[..content_len])
+                .into_iter()
+                .map(|range| line_start + range.start..line_start + range.end)
+                .chain(std::iter::once(
+                    line_start + content_len..line_start + line.len(),
+                ));
+            for range in ranges {
+                if range.start == insertion_point {
+                    visual_insertion_point = visual.len();
+                }
+                self.push_visual(&mut visual, &raw, range);
+            }
+            line_start += line.len();
+        }
+
+        (visual, visual_insertion_point)
+    }
+
+    /// Appends the text of `range` to `visual` with its style and underline
+    fn push_visual(&self, visual: &mut StyledText, raw: &str, range: std::ops::Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let start = visual.len();
+        let end = start + range.len();
+        if let Some(underline) = self.underline_at(range.start) {
+            match visual.underlines.last_mut() {
+                Some(last)
+                    if last.span.end == start
+                        && last.style == underline.style
+                        && last.color == underline.color =>
+                {
+                    last.span.end = end;
+                }
+                _ => visual.underline(Underline {
+                    span: Span::new(start, end),
+                    ..*underline
+                }),
+            }
+        }
+
+        let style = self.style_at(range.start);
+        match visual.buffer.last_mut() {
+            Some((last_style, text)) if *last_style == style => text.push_str(&raw[range]),
+            _ => visual.push((style, raw[range].to_string())),
+        }
+    }
+
+    fn style_at(&self, position: usize) -> Style {
+        let mut end = 0;
+        for (style, text) in &self.buffer {
+            end += text.len();
+            if position < end {
+                return *style;
+            }
+        }
+        Style::default()
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.iter().map(|(_, text)| text.len()).sum()
+    }
+
     /// Apply the ANSI style formatting to the full string.
     pub fn render_simple(&self) -> String {
         self.buffer
@@ -90,24 +420,380 @@ impl StyledText {
 ///
 /// If parsing fails silently returns the input string
 pub(crate) fn strip_ansi(string: &str) -> String {
-    strip_ansi_escapes::strip(string)
-        .map_err(|_| ())
-        .and_then(|x| String::from_utf8(x).map_err(|_| ()))
-        .unwrap_or_else(|_| string.to_owned())
+    let mut stripped = String::with_capacity(string.len());
+    let mut chars = string.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => skip_escape_sequence(&mut chars),
+            // Like a terminal, only the newlines of the control characters are kept
+            '\n' => stripped.push(c),
+            c if c.is_ascii_control() && c != '\x7f' => {}
+            c => stripped.push(c),
+        }
+    }
+
+    stripped
+}
+
+/// The part of the styled `string` after the first `skip` bytes of its text, as
+/// [`strip_ansi`] counts them, preceded by the control sequences before it so it is
+/// painted with the same style
+pub(crate) fn styled_tail(string: &str, skip: usize) -> String {
+    let mut tail = String::new();
+    let mut text_len = 0;
+    let mut chars = string.char_indices().peekable();
+    while let Some(&(index, c)) = chars.peek() {
+        if c == '\x1b' {
+            chars.next();
+            let mut end = string.len();
+            if chars.next_if(|&(_, c)| c == '[').is_some() {
+                if let Some((last, c)) = chars.find(|&(_, c)| ('@'..='~').contains(&c)) {
+                    end = last + c.len_utf8();
+                }
+            } else if let Some((last, c)) = chars.next() {
+                end = last + c.len_utf8();
+            }
+            tail.push_str(&string[index..end]);
+            continue;
+        }
+
+        if text_len >= skip {
+            tail.push_str(&string[index..]);
+            return tail;
+        }
+        if c == '\n' || !c.is_ascii_control() || c == '\x7f' {
+            text_len += c.len_utf8();
+        }
+        chars.next();
+    }
+
+    String::new()
+}
+
+/// Skips the rest of an escape sequence after its ESC
+fn skip_escape_sequence(chars: &mut Peekable<Chars>) {
+    match chars.next() {
+        // Control sequence, its parameters end with a character from `@` to `~`
+        Some('[') => {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+        // Strings like the hyperlinks of OSC 8, ended by BEL or ESC `\`
+        Some(']' | 'P' | 'X' | '^' | '_') => {
+            while let Some(c) = chars.next() {
+                if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                    break;
+                }
+            }
+        }
+        // Intermediate characters, like the `(` of a character set, and a final one
+        Some(' '..='/') => {
+            while chars.next_if(|c| (' '..='/').contains(c)).is_some() {}
+            chars.next();
+        }
+        _ => {}
+    }
 }
 
 fn render_as_string(
-    renderable: &(Style, String),
+    style: &Style,
+    underline: Option<&Underline>,
+    text: &str,
     prompt_style: &Style,
     multiline_prompt: &str,
+    tab: &str,
 ) -> String {
     let mut rendered = String::new();
     let formatted_multiline_prompt = format!("\n{}", multiline_prompt);
-    for (line_number, line) in renderable.1.split('\n').enumerate() {
+    let mut lines = text.split('\n').peekable();
+    let mut line_number = 0;
+    while let Some(line) = lines.next() {
         if line_number != 0 {
             rendered.push_str(&prompt_style.paint(&formatted_multiline_prompt).to_string());
         }
-        rendered.push_str(&renderable.0.paint(line).to_string());
+        line_number += 1;
+
+        // The carriage return of a CRLF line ending is not shown
+        let (line, line_ending) = match line.strip_suffix('\r') {
+            Some(line) if lines.peek().is_some() => (line, "\r"),
+            _ => (line, ""),
+        };
+
+        let mut start = 0;
+        for (index, c) in line.char_indices() {
+            if c == '\t' {
+                render_part(&mut rendered, style, underline, &line[start..index]);
+                render_part(&mut rendered, style, underline, tab);
+                start = index + 1;
+            } else if let Some(caret) = caret_notation(c) {
+                render_part(&mut rendered, style, underline, &line[start..index]);
+                render_part(&mut rendered, &style.reverse(), underline, &caret);
+                start = index + c.len_utf8();
+            }
+        }
+        render_part(&mut rendered, style, underline, &line[start..]);
+        rendered.push_str(line_ending);
     }
     rendered
 }
+
+fn render_part(rendered: &mut String, style: &Style, underline: Option<&Underline>, text: &str) {
+    match underline {
+        Some(underline) if !text.is_empty() => {
+            rendered.push_str(&format!(
+                "{}{}{}\x1b[0m",
+                style.prefix(),
+                underline.prefix(),
+                text
+            ));
+        }
+        _ => rendered.push_str(&style.paint(text).to_string()),
+    }
+}
+
+/// Spaces that can't be told apart from a normal space on the screen
+fn is_odd_space(c: char) -> bool {
+    matches!(
+        c,
+        '\u{a0}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}'
+    )
+}
+
+/// Shows a control character like `cat -v` does, e.g. ESC as `^[` and CSI as `M-^[`,
+/// so it is painted instead of being interpreted by the terminal.
+///
+/// Returns `None` for the characters that are painted as they are
+fn caret_notation(c: char) -> Option<String> {
+    match c as u32 {
+        code @ 0x00..=0x1f => Some(format!("^{}", char::from(code as u8 + 0x40))),
+        0x7f => Some("^?".to_string()),
+        code @ 0x80..=0x9f => Some(format!("M-^{}", char::from(code as u8 - 0x40))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn styled(text: &str) -> StyledText {
+        let mut styled_text = StyledText::new();
+        styled_text.push((Style::new(), text.to_string()));
+        styled_text
+    }
+
+    #[rstest]
+    #[case(UnderlineStyle::Straight, None, "\x1b[4m")]
+    #[case(UnderlineStyle::Curly, None, "\x1b[4m\x1b[4:3m")]
+    #[case(
+        UnderlineStyle::Dotted,
+        Some(Color::Red),
+        "\x1b[4m\x1b[4:4m\x1b[58;5;1m"
+    )]
+    #[case(
+        UnderlineStyle::Curly,
+        Some(Color::Rgb(1, 2, 3)),
+        "\x1b[4m\x1b[4:3m\x1b[58;2;1;2;3m"
+    )]
+    #[case(
+        UnderlineStyle::Double,
+        Some(Color::Fixed(42)),
+        "\x1b[4m\x1b[4:2m\x1b[58;5;42m"
+    )]
+    fn underline_prefix(
+        #[case] style: UnderlineStyle,
+        #[case] color: Option<Color>,
+        #[case] expected: &str,
+    ) {
+        let underline = Underline {
+            span: Span::new(0, 1),
+            style,
+            color,
+        };
+
+        assert_eq!(underline.prefix(), expected);
+    }
+
+    #[test]
+    fn underline_splits_segments() {
+        let mut styled_text = styled("let x = ;");
+        styled_text.underline(Underline::new(Span::new(8, 9)).with_style(UnderlineStyle::Curly));
+
+        let (left, right) = styled_text.render_around_insertion_point(9, "", true);
+
+        assert_eq!(left, "let x = \x1b[4m\x1b[4:3m;\x1b[0m");
+        assert_eq!(right, "");
+    }
+
+    #[test]
+    fn underline_around_insertion_point() {
+        let mut styled_text = styled("abcd");
+        styled_text.underline(Underline::new(Span::new(1, 3)));
+
+        let (left, right) = styled_text.render_around_insertion_point(2, "", true);
+
+        assert_eq!(left, "a\x1b[4mb\x1b[0m");
+        assert_eq!(right, "\x1b[4mc\x1b[0md");
+    }
+
+    #[test]
+    fn right_to_left_runs_are_reordered_with_their_styles() {
+        let mut styled_text = StyledText::new();
+        styled_text.push((Style::new().bold(), "echo".to_string()));
+        styled_text.push((Style::new(), " שלום עולם\nאב".to_string()));
+        styled_text.underline(Underline::new(Span::new(14, 22)));
+
+        // In front of the first letter, which is shown last
+        let (visual, insertion_point) = styled_text.visual(5);
+
+        assert_eq!(visual.raw_string(), "echo םלוע םולש\nבא");
+        assert_eq!(insertion_point, 20);
+        assert_eq!(visual.buffer[0], (Style::new().bold(), "echo".to_string()));
+        assert_eq!(visual.buffer.len(), 2);
+        assert_eq!(visual.underlines, vec![Underline::new(Span::new(5, 13))]);
+    }
+
+    #[test]
+    fn insertion_point_at_the_end_stays_at_the_end() {
+        let (visual, insertion_point) = styled("ls שלום").visual(12);
+
+        assert_eq!(visual.raw_string(), "ls םולש");
+        assert_eq!(insertion_point, 11);
+    }
+
+    #[test]
+    fn underline_is_stripped_without_ansi() {
+        let mut styled_text = styled("abcd");
+        styled_text.underline(Underline::new(Span::new(0, 4)).with_style(UnderlineStyle::Curly));
+
+        let (left, right) = styled_text.render_around_insertion_point(2, "", false);
+
+        assert_eq!(left, "ab");
+        assert_eq!(right, "cd");
+    }
+
+    #[rstest]
+    #[case("a\x1bb", "a\x1b[7m^[\x1b[0mb")]
+    #[case("\r", "\x1b[7m^M\x1b[0m")]
+    #[case("\x7f", "\x1b[7m^?\x1b[0m")]
+    #[case("\u{9b}", "\x1b[7mM-^[\x1b[0m")]
+    #[case("a\r\nb", "a\r\x1b[94m\n\x1b[0mb")]
+    fn control_characters_use_caret_notation(#[case] buffer: &str, #[case] expected: &str) {
+        let (left, right) = styled(buffer).render_around_insertion_point(buffer.len(), "", true);
+
+        assert_eq!(left, expected);
+        assert_eq!(right, "");
+    }
+
+    #[test]
+    fn caret_notation_is_kept_without_ansi() {
+        let (left, right) = styled("a\x1b[31mb").render_around_insertion_point(1, "", false);
+
+        assert_eq!(left, "a");
+        assert_eq!(right, "^[[31mb");
+    }
+
+    #[rstest]
+    #[case("\x1b[37mab\x1b[0m", 1, "\x1b[37mb\x1b[0m")]
+    #[case(
+        "\x1b[1mls\x1b[0m \x1b[37m-la\x1b[0m",
+        2,
+        "\x1b[1m\x1b[0m \x1b[37m-la\x1b[0m"
+    )]
+    #[case("plain", 2, "ain")]
+    #[case("\x1b[37mab\x1b[0m", 2, "")]
+    fn styled_tail_keeps_the_style(
+        #[case] input: &str,
+        #[case] skip: usize,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(styled_tail(input, skip), expected);
+    }
+
+    #[rstest]
+    #[case("a\x1b[1;31mb\x1b[0m", "ab")]
+    #[case("a\x1b[4:3mb", "ab")]
+    #[case("a\x1b]8;;http://x\x1b\\link\x1b]8;;\x07z", "alinkz")]
+    #[case("a\x1b(Bb", "ab")]
+    #[case("a\tb\r\nc\x07", "ab\nc")]
+    #[case("é😀\x7f", "é😀\x7f")]
+    fn ansi_is_stripped(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(strip_ansi(input), expected);
+    }
+
+    #[rstest]
+    #[case(TabDisplay::Spaces(4), "a    b")]
+    #[case(TabDisplay::Spaces(0), "ab")]
+    #[case(TabDisplay::Arrow(2), "a→ b")]
+    #[case(TabDisplay::Arrow(0), "a→b")]
+    fn tabs_are_painted_with_a_fixed_width(
+        #[case] tab_display: TabDisplay,
+        #[case] expected: &str,
+    ) {
+        let (left, right) =
+            styled("a\tb").render_around_insertion_point_with_tabs(2, "", false, tab_display);
+
+        assert_eq!(left + &right, expected);
+    }
+
+    #[rstest]
+    #[case("ls  ", 0, vec![(Style::new(), "ls"), (Style::new().on(Color::Red), "  ")])]
+    #[case("ls  ", 4, vec![(Style::new(), "ls  ")])]
+    #[case(
+        "a\u{a0}b",
+        3,
+        vec![
+            (Style::new(), "a"),
+            (Style::new().on(Color::Red), "\u{a0}"),
+            (Style::new(), "b"),
+        ]
+    )]
+    #[case(
+        "a \r\nb",
+        5,
+        vec![
+            (Style::new(), "a"),
+            (Style::new().on(Color::Red), " "),
+            (Style::new(), "\r\nb"),
+        ]
+    )]
+    fn whitespace_is_highlighted(
+        #[case] buffer: &str,
+        #[case] insertion_point: usize,
+        #[case] expected: Vec<(Style, &str)>,
+    ) {
+        let mut styled_text = styled(buffer);
+        styled_text.highlight_whitespace(insertion_point, Color::Red);
+
+        let expected: Vec<(Style, String)> = expected
+            .into_iter()
+            .map(|(style, text)| (style, text.to_string()))
+            .collect();
+        assert_eq!(styled_text.buffer, expected);
+    }
+
+    #[test]
+    fn whitespace_highlight_keeps_the_styles() {
+        let mut styled_text = StyledText::new();
+        styled_text.push((Style::new().fg(Color::Green), "ls ".to_string()));
+        styled_text.push((Style::new().fg(Color::Blue), " ".to_string()));
+        styled_text.highlight_whitespace(0, Color::Red);
+
+        assert_eq!(
+            styled_text.buffer,
+            vec![
+                (Style::new().fg(Color::Green), "ls".to_string()),
+                (
+                    Style::new().fg(Color::Green).on(Color::Red),
+                    " ".to_string()
+                ),
+                (Style::new().fg(Color::Blue).on(Color::Red), " ".to_string()),
+            ]
+        );
+    }
+}
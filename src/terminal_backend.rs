@@ -0,0 +1,484 @@
+use {
+    crate::terminal_guard::TerminalGuard,
+    crossterm::{cursor, event, event::Event, terminal},
+    std::{
+        collections::VecDeque,
+        io::{self, BufWriter, Stderr, Write},
+        sync::{Arc, Mutex, MutexGuard},
+        time::Duration,
+    },
+};
+
+/// The terminal the engine reads events from and paints on.
+///
+/// Events use the types of `crossterm`, which the [`EditMode`](crate::EditMode)s
+/// understand. The painted output, text and ANSI escape sequences, is written to the
+/// backend through its [`Write`] implementation.
+///
+/// [`CrosstermBackend`] is used by default. [`MemoryBackend`] runs the engine without
+/// a terminal, for example in tests.
+pub trait TerminalBackend: Write + Send {
+    /// Waits up to `timeout` for an event, returns whether one can be read
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<bool>;
+
+    /// Reads the next event, blocking until there is one
+    fn read_event(&mut self) -> io::Result<Event>;
+
+    /// Size of the terminal as `(columns, rows)`
+    fn size(&self) -> io::Result<(u16, u16)>;
+
+    /// Position of the cursor as `(column, row)`, starting at 0
+    fn cursor_position(&mut self) -> io::Result<(u16, u16)>;
+
+    /// Switches to raw mode, where the keys are read as events instead of lines
+    fn enable_raw_mode(&mut self) -> io::Result<()>;
+
+    /// Leaves raw mode. Calling it when raw mode isn't enabled is harmless
+    fn disable_raw_mode(&mut self) -> io::Result<()>;
+
+    /// Whether the terminal understands ANSI escape sequences written as text.
+    ///
+    /// Without them the painted text has no colors or other styles
+    fn supports_ansi(&self) -> bool {
+        true
+    }
+
+    /// Whether the cursor can be moved and the screen cleared, with escape sequences
+    /// or in another way, like the console API of older Windows versions.
+    ///
+    /// Without it the engine falls back to painting only the current line, using
+    /// carriage returns and backspaces, and doesn't show menus
+    fn supports_cursor_movement(&self) -> bool {
+        self.supports_ansi()
+    }
+}
+
+/// Backend for the terminal of the process using `crossterm`. Paints on stderr.
+///
+/// Terminals with `$TERM` set to `dumb` are detected as supporting neither ANSI escape
+/// sequences nor cursor movement. On Windows consoles without virtual terminal
+/// processing, `crossterm` moves the cursor with the console API and the text is
+/// painted without styles.
+#[derive(Debug)]
+pub struct CrosstermBackend {
+    stderr: BufWriter<Stderr>,
+    raw_mode: Option<TerminalGuard>,
+    ansi: bool,
+    cursor_movement: bool,
+}
+
+impl Default for CrosstermBackend {
+    fn default() -> Self {
+        let (ansi, cursor_movement) = detect_support();
+
+        Self {
+            stderr: BufWriter::new(io::stderr()),
+            raw_mode: None,
+            ansi,
+            cursor_movement,
+        }
+    }
+}
+
+impl CrosstermBackend {
+    /// Backend builder that overrides the detected ANSI support
+    pub fn with_ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Backend builder that overrides the detected support for cursor movement
+    pub fn with_cursor_movement(mut self, cursor_movement: bool) -> Self {
+        self.cursor_movement = cursor_movement;
+        self
+    }
+}
+
+/// Detects the support for ANSI escape sequences and for cursor movement
+fn detect_support() -> (bool, bool) {
+    if std::env::var("TERM").is_ok_and(|term| term == "dumb") {
+        return (false, false);
+    }
+
+    // Legacy consoles still move the cursor, crossterm falls back to the console API
+    #[cfg(windows)]
+    {
+        (crossterm::ansi_support::supports_ansi(), true)
+    }
+    #[cfg(not(windows))]
+    {
+        (true, true)
+    }
+}
+
+impl Write for CrosstermBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stderr.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stderr.flush()
+    }
+}
+
+impl TerminalBackend for CrosstermBackend {
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read_event(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+
+    fn size(&self) -> io::Result<(u16, u16)> {
+        terminal::size()
+    }
+
+    fn cursor_position(&mut self) -> io::Result<(u16, u16)> {
+        cursor::position()
+    }
+
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        if self.raw_mode.is_none() {
+            self.raw_mode = Some(TerminalGuard::raw_mode()?);
+        }
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        match self.raw_mode.take() {
+            Some(guard) => guard.restore(),
+            // Also leaves a raw mode that was enabled by someone else
+            None => terminal::disable_raw_mode(),
+        }
+    }
+
+    fn supports_ansi(&self) -> bool {
+        self.ansi
+    }
+
+    fn supports_cursor_movement(&self) -> bool {
+        self.cursor_movement
+    }
+}
+
+// Empty polls allowed after the scripted events ran out, before failing instead
+// of leaving a test waiting forever
+const EXHAUSTED_POLLS: usize = 100;
+
+#[derive(Debug, Default)]
+struct MemoryTerminal {
+    events: VecDeque<Event>,
+    output: Vec<u8>,
+    sink: Option<Sink>,
+    raw_mode: bool,
+    empty_polls: usize,
+}
+
+// Where the output goes instead of `MemoryTerminal::output`
+struct Sink(Box<dyn Write + Send>);
+
+impl std::fmt::Debug for Sink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Sink")
+    }
+}
+
+/// Backend that reads scripted events and collects the output in memory.
+///
+/// Clones share the same terminal, so a clone can be kept to inspect the output after
+/// handing the backend to the engine. Once the events ran out, polling returns `false`
+/// and, after a few polls, fails with [`io::ErrorKind::UnexpectedEof`].
+///
+/// Polling doesn't wait, so the output only depends on the events and the prompt, as
+/// long as no [frame interval](crate::Reedline::with_frame_interval) is set. With a
+/// prompt that doesn't show the time, like the [`DefaultPrompt`](crate::DefaultPrompt)
+/// does, it can be compared byte for byte with the output of an earlier run.
+///
+/// ## Example
+///
+/// ```rust
+/// use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+/// use reedline::{DefaultPrompt, MemoryBackend, Reedline, Signal};
+///
+/// let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+/// let backend = MemoryBackend::new(80, 24).with_events(vec![
+///     key(KeyCode::Char('l')),
+///     key(KeyCode::Char('s')),
+///     key(KeyCode::Enter),
+/// ]);
+///
+/// let mut line_editor = Reedline::create()?.with_backend(Box::new(backend.clone()));
+/// let signal = line_editor.read_line(&DefaultPrompt)?;
+///
+/// assert!(matches!(signal, Signal::Success(line) if line == "ls"));
+/// assert!(backend.output().contains("ls"));
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryBackend {
+    size: (u16, u16),
+    ansi: bool,
+    cursor_movement: bool,
+    terminal: Arc<Mutex<MemoryTerminal>>,
+}
+
+impl MemoryBackend {
+    /// Creates a backend for a terminal with the given size and no events
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Self {
+            size: (columns, rows),
+            ansi: true,
+            cursor_movement: true,
+            terminal: Arc::new(Mutex::new(MemoryTerminal::default())),
+        }
+    }
+
+    /// Backend builder that queues the events
+    pub fn with_events(self, events: Vec<Event>) -> Self {
+        self.terminal().events.extend(events);
+        self
+    }
+
+    /// Backend builder that sets whether the terminal supports ANSI escape sequences
+    pub fn with_ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Backend builder that sets whether the cursor can be moved
+    pub fn with_cursor_movement(mut self, cursor_movement: bool) -> Self {
+        self.cursor_movement = cursor_movement;
+        self
+    }
+
+    /// Backend builder that writes the output to `sink` instead of collecting it,
+    /// for example to benchmark the painting without keeping all of its output
+    ///
+    /// ```rust
+    /// use reedline::MemoryBackend;
+    ///
+    /// let backend = MemoryBackend::new(80, 24).with_output_sink(std::io::sink());
+    /// assert_eq!(backend.output(), "");
+    /// ```
+    pub fn with_output_sink(self, sink: impl Write + Send + 'static) -> Self {
+        self.terminal().sink = Some(Sink(Box::new(sink)));
+        self
+    }
+
+    /// Queues an event after the already queued ones
+    pub fn push_event(&self, event: Event) {
+        self.terminal().events.push_back(event);
+    }
+
+    /// Everything written to the terminal so far, including the escape sequences.
+    /// Empty if the output goes to a [sink](MemoryBackend::with_output_sink)
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.terminal().output).into_owned()
+    }
+
+    /// Whether the terminal is in raw mode
+    pub fn is_raw_mode(&self) -> bool {
+        self.terminal().raw_mode
+    }
+
+    fn terminal(&self) -> MutexGuard<'_, MemoryTerminal> {
+        // A panic while holding the lock leaves the terminal usable
+        self.terminal
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Write for MemoryBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut terminal = self.terminal();
+        match &mut terminal.sink {
+            Some(Sink(sink)) => sink.write(buf),
+            None => {
+                terminal.output.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.terminal().sink {
+            Some(Sink(sink)) => sink.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TerminalBackend for MemoryBackend {
+    fn poll_event(&mut self, _timeout: Duration) -> io::Result<bool> {
+        let mut terminal = self.terminal();
+        if !terminal.events.is_empty() {
+            terminal.empty_polls = 0;
+            return Ok(true);
+        }
+
+        terminal.empty_polls += 1;
+        if terminal.empty_polls > EXHAUSTED_POLLS {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "no more events in the memory backend",
+            ))
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn read_event(&mut self) -> io::Result<Event> {
+        self.terminal()
+            .events
+            .pop_front()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+    }
+
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(self.size)
+    }
+
+    fn cursor_position(&mut self) -> io::Result<(u16, u16)> {
+        Ok((0, 0))
+    }
+
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        self.terminal().raw_mode = true;
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        self.terminal().raw_mode = false;
+        Ok(())
+    }
+
+    fn supports_ansi(&self) -> bool {
+        self.ansi
+    }
+
+    fn supports_cursor_movement(&self) -> bool {
+        self.cursor_movement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DefaultPrompt, Reedline, ReedlineError, Signal};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use nu_ansi_term::Color;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn reads_line_from_scripted_events() {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Backspace),
+            key(KeyCode::Char('c')),
+            key(KeyCode::Enter),
+        ]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "ac"));
+        assert!(!backend.is_raw_mode());
+        assert!(backend.output().contains("ac"));
+    }
+
+    #[test]
+    fn running_out_of_events_is_an_error() {
+        let backend = MemoryBackend::new(80, 24).with_events(vec![key(KeyCode::Char('a'))]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+
+        let error = line_editor.read_line(&DefaultPrompt).unwrap_err();
+
+        assert!(
+            matches!(error, ReedlineError::Terminal(error) if error.kind() == io::ErrorKind::UnexpectedEof)
+        );
+        assert!(!backend.is_raw_mode());
+    }
+
+    #[test]
+    fn dumb_terminal_gets_plain_output() {
+        let backend = MemoryBackend::new(80, 24)
+            .with_ansi(false)
+            .with_cursor_movement(false)
+            .with_events(vec![
+                key(KeyCode::Char('a')),
+                key(KeyCode::Char('b')),
+                key(KeyCode::Left),
+                key(KeyCode::Enter),
+            ]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+
+        let signal = line_editor.read_line(&DefaultPrompt).unwrap();
+
+        assert!(matches!(signal, Signal::Success(line) if line == "ab"));
+        let output = backend.output();
+        assert!(!output.contains('\u{1b}'));
+        assert!(output.contains("ab\u{8}"));
+        assert!(output.ends_with("\r\n"));
+    }
+
+    #[rstest]
+    #[case(true)]
+    #[case(false)]
+    fn styles_need_ansi_support(#[case] ansi: bool) {
+        let backend = MemoryBackend::new(80, 24).with_ansi(ansi).with_events(vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Enter),
+        ]);
+        let mut line_editor = Reedline::create()
+            .unwrap()
+            .with_backend(Box::new(backend.clone()));
+
+        line_editor.read_line(&DefaultPrompt).unwrap();
+
+        // The cursor is still moved, only the highlighting is left out
+        let output = backend.output();
+        assert_eq!(output.contains(&Color::White.paint("ab").to_string()), ansi);
+        assert!(output.contains("ab"));
+        assert!(output.contains("\u{1b}[1;1H"));
+    }
+
+    #[test]
+    fn clones_share_the_terminal() {
+        let backend = MemoryBackend::new(80, 24);
+        let mut clone = backend.clone();
+
+        clone.push_event(key(KeyCode::Enter));
+        write!(clone, "hello").unwrap();
+
+        assert_eq!(backend.output(), "hello");
+        assert_eq!(clone.read_event().unwrap(), key(KeyCode::Enter));
+        assert!(!clone.poll_event(Duration::ZERO).unwrap());
+    }
+
+    #[test]
+    fn output_goes_to_the_sink() {
+        let sink = MemoryBackend::new(80, 24);
+        let mut backend = MemoryBackend::new(80, 24).with_output_sink(sink.clone());
+
+        write!(backend, "hello").unwrap();
+        backend.flush().unwrap();
+
+        assert_eq!(backend.output(), "");
+        assert_eq!(sink.output(), "hello");
+    }
+}
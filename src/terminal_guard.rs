@@ -0,0 +1,96 @@
+use {
+    crossterm::{cursor, terminal, ExecutableCommand},
+    std::{
+        io, panic,
+        sync::atomic::{AtomicBool, Ordering},
+    },
+};
+
+// Set while a guard keeps the terminal in the alternate screen, so only the screen
+// that was entered is left when restoring
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// Keeps the terminal in raw mode while it is alive.
+///
+/// When the guard is dropped, also when unwinding from a panic, the raw mode is
+/// disabled, the cursor is shown and the alternate screen is left if the guard entered it.
+/// [`Reedline::read_line()`](crate::Reedline::read_line) holds one while reading.
+///
+/// ## Example
+///
+/// ```no_run
+/// use reedline::TerminalGuard;
+///
+/// let guard = TerminalGuard::raw_mode()?.with_alternate_screen()?;
+/// // Draw a full screen interface
+/// guard.restore()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TerminalGuard {
+    restored: bool,
+}
+
+impl TerminalGuard {
+    /// Enables the raw mode of the terminal until the guard is dropped
+    pub fn raw_mode() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(Self { restored: false })
+    }
+
+    /// Guard builder that also switches to the alternate screen
+    pub fn with_alternate_screen(self) -> io::Result<Self> {
+        io::stderr().execute(terminal::EnterAlternateScreen)?;
+        ALTERNATE_SCREEN.store(true, Ordering::SeqCst);
+        Ok(self)
+    }
+
+    /// Restores the terminal returning the errors that dropping the guard would ignore
+    pub fn restore(mut self) -> io::Result<()> {
+        self.restored = true;
+        restore_terminal()
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if !self.restored {
+            let _ = restore_terminal();
+        }
+    }
+}
+
+/// Brings the terminal back to its normal state: disables the raw mode, shows the
+/// cursor and leaves the alternate screen if a [`TerminalGuard`] entered it.
+///
+/// Calling it more than once is harmless.
+pub fn restore_terminal() -> io::Result<()> {
+    let mut stderr = io::stderr();
+    if ALTERNATE_SCREEN.swap(false, Ordering::SeqCst) {
+        stderr.execute(terminal::LeaveAlternateScreen)?;
+    }
+    stderr.execute(cursor::Show)?;
+    terminal::disable_raw_mode()
+}
+
+/// Installs a panic hook that restores the terminal before the panic message is printed.
+///
+/// Without it the message of a panic while reading a line is printed in raw mode.
+/// The previous hook is still called after restoring the terminal.
+///
+/// ## Example
+///
+/// ```no_run
+/// use reedline::{install_panic_hook, Reedline};
+///
+/// install_panic_hook();
+/// let mut line_editor = Reedline::create()?;
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+pub fn install_panic_hook() {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        previous_hook(info);
+    }));
+}
@@ -0,0 +1,103 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Shares the terminal between the [`Reedline`](crate::Reedline) instances of a host,
+/// like the main prompt and a popup input opened from one of its callbacks
+///
+/// Each instance enables the raw mode when a read starts and disables it when the read
+/// ends. An instance reading while another one waits for it to return would leave the
+/// terminal in cooked mode for the rest of the outer read, and paint over the lines
+/// the outer prompt left. Instances sharing a manager only switch the raw mode for the
+/// outermost read, and an instance getting the terminal back paints its prompt again
+/// below whatever the other one printed.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{Reedline, TerminalManager};
+///
+/// let terminal = TerminalManager::default();
+/// let shell = Reedline::create()?.with_terminal_manager(terminal.clone());
+/// let popup = Reedline::create()?.with_terminal_manager(terminal.clone());
+/// assert!(!terminal.is_reading());
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TerminalManager {
+    state: Arc<Mutex<ManagerState>>,
+}
+
+#[derive(Debug, Default)]
+struct ManagerState {
+    // The instances reading, the one with the terminal last
+    readers: Vec<usize>,
+    next_id: usize,
+    // Counts the reads that ended, so the instances can tell they lost the terminal
+    handovers: u64,
+}
+
+impl TerminalManager {
+    /// Whether one of the instances is reading a line
+    pub fn is_reading(&self) -> bool {
+        !self.state().readers.is_empty()
+    }
+
+    /// A new id for an instance sharing the terminal
+    pub(crate) fn register(&self) -> usize {
+        let mut state = self.state();
+        state.next_id += 1;
+        state.next_id
+    }
+
+    /// The instance starts reading. Returns whether it is the first one, which has to
+    /// enable the raw mode
+    pub(crate) fn begin_read(&self, id: usize) -> bool {
+        let mut state = self.state();
+        state.readers.push(id);
+        state.readers.len() == 1
+    }
+
+    /// The instance stopped reading. Returns whether it was the last one, which has to
+    /// disable the raw mode
+    pub(crate) fn end_read(&self, id: usize) -> bool {
+        let mut state = self.state();
+        if let Some(index) = state.readers.iter().rposition(|&reader| reader == id) {
+            state.readers.remove(index);
+        }
+        state.handovers += 1;
+        state.readers.is_empty()
+    }
+
+    /// Changes each time a read ends, the instances compare it with the value seen when
+    /// they last painted
+    pub(crate) fn handovers(&self) -> u64 {
+        self.state().handovers
+    }
+
+    fn state(&self) -> MutexGuard<'_, ManagerState> {
+        // A reader that panicked still leaves a usable count
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_outermost_read_switches_the_raw_mode() {
+        let manager = TerminalManager::default();
+        let (shell, popup) = (manager.register(), manager.register());
+
+        assert!(manager.begin_read(shell));
+        assert!(!manager.begin_read(popup));
+        let handovers = manager.handovers();
+        assert!(!manager.end_read(popup));
+        assert_ne!(manager.handovers(), handovers);
+        assert!(manager.is_reading());
+
+        assert!(manager.end_read(shell));
+        assert!(!manager.is_reading());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::MenuRow;
+use nu_ansi_term::{Color, Style};
+
+/// Names of the built-in themes, for [`Theme::by_name`]
+pub const THEME_NAMES: &[&str] = &["high-contrast", "monochrome"];
+
+/// Styles of the parts the editor paints itself: the prompt, the hints and the menus
+///
+/// Without a theme the parts keep the colors of the prompt, the hinter and the menus.
+/// The built-in themes are for users that need more contrast or no colors at all, and
+/// are selected by name, for example from a setting of the host.
+///
+/// ## Example
+///
+/// ```rust
+/// use reedline::{Reedline, Theme};
+///
+/// let theme = Theme::by_name("monochrome").unwrap();
+/// let line_editor = Reedline::create()?.with_theme(theme);
+/// # Ok::<(), reedline::ReedlineError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Style of the prompt, in place of its own color
+    pub prompt: Style,
+    /// Style of the hints
+    pub hint: Style,
+    /// Style of the values of the menus
+    pub menu_text: Style,
+    /// Style of the value selected in a menu
+    pub menu_selected: Style,
+    /// Drops the colors of the highlighting of the buffer and keeps the other
+    /// attributes, like bold. Text on a colored background, like the matches of a
+    /// search, is shown in reverse video instead
+    pub monochrome: bool,
+}
+
+impl Theme {
+    /// Bright text on the default background, with the selection in black on yellow
+    pub fn high_contrast() -> Self {
+        Self {
+            prompt: Color::White.bold(),
+            hint: Color::LightCyan.normal(),
+            menu_text: Color::White.normal(),
+            menu_selected: Color::Black.on(Color::LightYellow).bold(),
+            monochrome: false,
+        }
+    }
+
+    /// No colors, only the attributes of the text. The selection is in reverse video
+    pub fn monochrome() -> Self {
+        Self {
+            prompt: Style::new().bold(),
+            hint: Style::new().italic(),
+            menu_text: Style::new(),
+            menu_selected: Style::new().reverse(),
+            monochrome: true,
+        }
+    }
+
+    /// The built-in theme with the given name, one of [`THEME_NAMES`]
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "high-contrast" => Some(Self::high_contrast()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Paints the parts of a menu row with the styles of the theme
+    pub(crate) fn restyle_menu_row(&self, row: &mut MenuRow<'_>) {
+        for (index, (style, _)) in row.content.iter_mut().enumerate() {
+            *style = if row.selected == Some(index) {
+                self.menu_selected
+            } else {
+                self.menu_text
+            };
+        }
+    }
+
+    /// The style of the buffer highlighting as the theme shows it
+    pub(crate) fn buffer_style(&self, style: Style) -> Style {
+        if !self.monochrome {
+            return style;
+        }
+
+        Style {
+            foreground: None,
+            background: None,
+            is_reverse: style.is_reverse || style.background.is_some(),
+            ..style
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn built_in_themes_are_found_by_name() {
+        for name in THEME_NAMES {
+            assert!(Theme::by_name(name).is_some());
+        }
+        assert_eq!(Theme::by_name("monochrome"), Some(Theme::monochrome()));
+        assert_eq!(Theme::by_name("solarized"), None);
+    }
+
+    #[test]
+    fn monochrome_keeps_the_attributes() {
+        let theme = Theme::monochrome();
+
+        assert_eq!(theme.buffer_style(Color::Green.bold()), Style::new().bold());
+        assert_eq!(
+            theme.buffer_style(Style::new().fg(Color::Black).on(Color::Yellow)),
+            Style::new().reverse()
+        );
+        assert_eq!(
+            Theme::high_contrast().buffer_style(Color::Green.bold()),
+            Color::Green.bold()
+        );
+    }
+
+    #[test]
+    fn menu_rows_get_the_theme_styles() {
+        let theme = Theme::monochrome();
+        let mut row = MenuRow::default();
+        row.push(Color::DarkGray.normal(), "help ");
+        row.push_selected(Color::Green.bold().reverse(), "hello");
+
+        theme.restyle_menu_row(&mut row);
+
+        assert_eq!(row.content[0].0, Style::new());
+        assert_eq!(row.content[1].0, Style::new().reverse());
+    }
+}
@@ -1,8 +1,40 @@
+use crate::Underline;
+
 /// The syntax validation trait. Implementers of this trait will check to see if the current input
 /// is incomplete and spans multiple lines
 pub trait Validator: Send {
     /// The action that will handle the current buffer as a line and return the corresponding validation
     fn validate(&self, line: &str) -> ValidationResult;
+
+    /// Problems found in the current buffer while typing, drawn as underlines below the offending text
+    ///
+    /// ```
+    /// use nu_ansi_term::Color;
+    /// use reedline::{Span, Underline, UnderlineStyle, ValidationResult, Validator};
+    ///
+    /// struct NoTabs;
+    ///
+    /// impl Validator for NoTabs {
+    ///     fn validate(&self, _line: &str) -> ValidationResult {
+    ///         ValidationResult::Complete
+    ///     }
+    ///
+    ///     fn diagnostics(&self, line: &str) -> Vec<Underline> {
+    ///         line.match_indices('\t')
+    ///             .map(|(idx, _)| {
+    ///                 Underline::new(Span::new(idx, idx + 1))
+    ///                     .with_style(UnderlineStyle::Curly)
+    ///                     .with_color(Color::Red)
+    ///             })
+    ///             .collect()
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(NoTabs.diagnostics("a\tb").len(), 1);
+    /// ```
+    fn diagnostics(&self, _line: &str) -> Vec<Underline> {
+        vec![]
+    }
 }
 
 /// Whether or not the validation shows the input was complete